@@ -43,6 +43,23 @@ pub const CODEC_DAG_PB: u64 = 0x70;
 /// - <https://github.com/multiformats/multicodec/blob/master/table.csv>
 pub const CODEC_RAW: u64 = 0x55;
 
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The hash functions CIDs can be computed with, see `HashingBlockStore`.
+///
+/// The hash function is recorded in the CID, so blocks hashed with different
+/// algorithms can coexist in a block store and all be loaded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    Sha2_256,
+    Sha3_256,
+    /// BLAKE3 with 256 bit digests, used by all block stores by default.
+    #[default]
+    Blake3,
+}
+
 //--------------------------------------------------------------------------------------------------
 // Traits
 //--------------------------------------------------------------------------------------------------
@@ -57,6 +74,28 @@ pub trait BlockStore: CondSync {
         cid: &Cid,
     ) -> impl Future<Output = Result<Bytes, BlockStoreError>> + CondSend;
 
+    /// Retrieve a block from this store like `get_block`, but only if its CID has given codec.
+    ///
+    /// Fails with `BlockStoreError::CodecMismatch` without fetching the block otherwise,
+    /// so callers get a clear error instead of a decoding failure.
+    fn get_block_as(
+        &self,
+        cid: &Cid,
+        expected_codec: u64,
+    ) -> impl Future<Output = Result<Bytes, BlockStoreError>> + CondSend {
+        async move {
+            if cid.codec() != expected_codec {
+                return Err(BlockStoreError::CodecMismatch {
+                    cid: *cid,
+                    expected: expected_codec,
+                    actual: cid.codec(),
+                });
+            }
+
+            self.get_block(cid).await
+        }
+    }
+
     /// Put some bytes into the blockstore. These bytes should be encoded with the given codec.
     ///
     /// E.g. `CODEC_RAW` for raw bytes blocks, `CODEC_DAG_CBOR` for dag-cbor, etc.
@@ -107,15 +146,37 @@ pub trait BlockStore: CondSync {
         cid: &Cid,
     ) -> impl Future<Output = Result<bool, BlockStoreError>> + CondSend;
 
-    // This should be the same in all implementations of BlockStore
+    // This should be the same in all implementations of BlockStore,
+    // except for ones configured to use another hash algorithm.
     fn create_cid(&self, bytes: &[u8], codec: u64) -> Result<Cid, BlockStoreError> {
+        HashAlgorithm::default().create_cid(bytes, codec)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl HashAlgorithm {
+    /// The multihash code of this hash algorithm.
+    pub fn code(self) -> Code {
+        match self {
+            Self::Sha2_256 => Code::Sha2_256,
+            Self::Sha3_256 => Code::Sha3_256,
+            Self::Blake3 => Code::Blake3_256,
+        }
+    }
+
+    /// Computes the V1 CID of given block with this hash algorithm.
+    ///
+    /// Fails with `BlockStoreError::MaximumBlockSizeExceeded` for blocks that are too big.
+    pub fn create_cid(self, bytes: &[u8], codec: u64) -> Result<Cid, BlockStoreError> {
         // If there are too many bytes, abandon this task
         if bytes.len() > MAX_BLOCK_SIZE {
             return Err(BlockStoreError::MaximumBlockSizeExceeded(bytes.len()));
         }
 
-        // Compute the Blake3 hash of the bytes
-        let hash = Code::Blake3_256.digest(bytes);
+        let hash = self.code().digest(bytes);
 
         // Represent the hash as a V1 CID
         let cid = Cid::new(Version::V1, codec, hash)?;
@@ -124,10 +185,6 @@ pub trait BlockStore: CondSync {
     }
 }
 
-//--------------------------------------------------------------------------------------------------
-// Implementations
-//--------------------------------------------------------------------------------------------------
-
 impl<B: BlockStore> BlockStore for &B {
     async fn get_block(&self, cid: &Cid) -> Result<Bytes, BlockStoreError> {
         (**self).get_block(cid).await
@@ -196,14 +253,60 @@ impl<B: BlockStore> BlockStore for Box<B> {
 pub struct MemoryBlockStore(
     #[serde(serialize_with = "crate::utils::serialize_cid_map")]
     #[serde(deserialize_with = "crate::utils::deserialize_cid_map")]
-    pub(crate) Arc<Mutex<HashMap<Cid, Bytes>>>,
+    pub(crate) SharedBlocks,
 );
 
+/// The blocks of a `MemoryBlockStore`, shared between its clones. The inner `Arc`
+/// is shared with snapshots and copied on write.
+pub(crate) type SharedBlocks = Arc<Mutex<Arc<HashMap<Cid, Bytes>>>>;
+
+/// The blocks of a `MemoryBlockStore` at some point in time, see `MemoryBlockStore::snapshot`.
+#[derive(Debug, Clone)]
+pub struct StoreSnapshot(Arc<HashMap<Cid, Bytes>>);
+
 impl MemoryBlockStore {
     /// Creates a new in-memory block store.
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Captures the blocks currently in this store, so the store can be reset to them
+    /// later via `restore`.
+    ///
+    /// Taking a snapshot is cheap, since the snapshot shares the blocks with the store.
+    /// The first put after taking a snapshot copies the block index, but not the blocks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs_common::{BlockStore, MemoryBlockStore, CODEC_RAW};
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> anyhow::Result<()> {
+    ///     let store = MemoryBlockStore::new();
+    ///     let kept = store.put_block(b"kept".to_vec(), CODEC_RAW).await?;
+    ///     let snapshot = store.snapshot();
+    ///
+    ///     let dropped = store.put_block(b"dropped".to_vec(), CODEC_RAW).await?;
+    ///     store.restore(&snapshot);
+    ///
+    ///     assert!(store.has_block(&kept).await?);
+    ///     assert!(!store.has_block(&dropped).await?);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn snapshot(&self) -> StoreSnapshot {
+        StoreSnapshot(Arc::clone(&self.0.lock()))
+    }
+
+    /// Resets this store to the blocks captured in given snapshot.
+    ///
+    /// Clones of this store share its blocks, so they get reset too.
+    /// The same snapshot can be restored any number of times.
+    pub fn restore(&self, snapshot: &StoreSnapshot) {
+        *self.0.lock() = Arc::clone(&snapshot.0);
+    }
 }
 
 impl BlockStore for MemoryBlockStore {
@@ -223,7 +326,7 @@ impl BlockStore for MemoryBlockStore {
         cid: Cid,
         bytes: impl Into<Bytes> + CondSend,
     ) -> Result<(), BlockStoreError> {
-        self.0.lock().insert(cid, bytes.into());
+        Arc::make_mut(&mut self.0.lock()).insert(cid, bytes.into());
 
         Ok(())
     }
@@ -233,6 +336,31 @@ impl BlockStore for MemoryBlockStore {
     }
 }
 
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Returns the name of given codec, if it's one of the codecs known to WNFS,
+/// otherwise its hexadecimal code.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::{codec_name, CODEC_DAG_CBOR};
+///
+/// assert_eq!(codec_name(CODEC_DAG_CBOR), "dag-cbor");
+/// assert_eq!(codec_name(0x0200), "0x200");
+/// ```
+pub fn codec_name(codec: u64) -> String {
+    match codec {
+        CODEC_DAG_JSON => "dag-json".into(),
+        CODEC_DAG_CBOR => "dag-cbor".into(),
+        CODEC_DAG_PB => "dag-pb".into(),
+        CODEC_RAW => "raw".into(),
+        _ => format!("{codec:#x}"),
+    }
+}
+
 //--------------------------------------------------------------------------------------------------
 // Tests
 //--------------------------------------------------------------------------------------------------
@@ -322,4 +450,55 @@ mod tests {
         bs_serialization_test::<MemoryBlockStore>(store).await?;
         Ok(())
     }
+
+    #[async_std::test]
+    async fn snapshots_can_be_restored_repeatedly() -> Result<()> {
+        let store = &MemoryBlockStore::new();
+        let base = store.put_block(b"base".to_vec(), CODEC_RAW).await?;
+        let snapshot = store.snapshot();
+
+        for i in 0..3u8 {
+            let clone = store.clone();
+            let what_if = clone.put_block(vec![i], CODEC_RAW).await?;
+            assert!(store.has_block(&what_if).await?);
+
+            store.restore(&snapshot);
+            assert!(!clone.has_block(&what_if).await?);
+            assert_eq!(store.get_block(&base).await?, b"base".to_vec());
+        }
+
+        // Blocks put after restoring don't leak into the snapshot
+        let later = store.put_block(b"later".to_vec(), CODEC_RAW).await?;
+        store.restore(&snapshot);
+        assert!(!store.has_block(&later).await?);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_block_as_checks_the_codec() -> Result<()> {
+        let store = &MemoryBlockStore::new();
+        let cid = store.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+
+        assert_eq!(
+            store.get_block_as(&cid, CODEC_RAW).await?,
+            b"Hello".to_vec()
+        );
+
+        let err = store.get_block_as(&cid, CODEC_DAG_CBOR).await.unwrap_err();
+        assert!(matches!(
+            err,
+            BlockStoreError::CodecMismatch {
+                expected: CODEC_DAG_CBOR,
+                actual: CODEC_RAW,
+                ..
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            format!("Expected dag-cbor codec, but got raw in CID {cid}")
+        );
+
+        Ok(())
+    }
 }
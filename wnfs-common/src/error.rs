@@ -1,6 +1,8 @@
 //! Errors
 
+use crate::codec_name;
 use libipld::Cid;
+use std::time::Duration;
 use thiserror::Error;
 
 //--------------------------------------------------------------------------------------------------
@@ -16,6 +18,26 @@ pub enum BlockStoreError {
     #[error("Cannot find specified CID in block store: {0}")]
     CIDNotFound(Cid),
 
+    #[error(
+        "Expected {} codec, but got {} in CID {cid}",
+        codec_name(*.expected),
+        codec_name(*.actual)
+    )]
+    CodecMismatch {
+        cid: Cid,
+        expected: u64,
+        actual: u64,
+    },
+
+    #[error("Unsupported codec {}", codec_name(*.0))]
+    UnsupportedCodec(u64),
+
+    #[error("Block doesn't match the hash in its CID: {0}")]
+    CorruptBlock(Cid),
+
+    #[error("Block store operation timed out after {0:?}")]
+    Timeout(Duration),
+
     #[error("CID error during blockstore operation: {0}")]
     CIDError(#[from] cid::Error),
 
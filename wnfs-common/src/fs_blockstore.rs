@@ -0,0 +1,229 @@
+//! A block store that keeps blocks in files on disk.
+
+use crate::{
+    utils::{self, CondSend},
+    BlockStore, BlockStoreError,
+};
+use anyhow::anyhow;
+use bytes::Bytes;
+use libipld::{
+    multihash::{Code, MultihashDigest},
+    Cid,
+};
+use std::{
+    fs::{self, File},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A block store that keeps each block in a file named after its CID, in a
+/// directory on disk. Blocks are only read from disk when they're requested,
+/// and can be read concurrently.
+///
+/// Blocks get written to a temporary file next to their final file first, which is
+/// then renamed to the final file. If the process crashes while storing a block,
+/// that leaves a temporary file behind, but never a partially written block.
+///
+/// File system calls run on a thread pool for blocking work, so they don't block
+/// the async executor.
+///
+/// By default, blocks are checked against the hash in their CID when they're read,
+/// and a `BlockStoreError::CorruptBlock` is returned if they don't match.
+/// See `FsBlockStore::with_verification`.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::{BlockStore, FsBlockStore, CODEC_RAW};
+///
+/// #[async_std::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let dir = tempfile::tempdir()?;
+///     let store = FsBlockStore::new(dir.path())?;
+///     let cid = store.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+///     drop(store);
+///
+///     let reopened = FsBlockStore::new(dir.path())?;
+///     assert_eq!(reopened.get_block(&cid).await?, b"Hello".to_vec());
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct FsBlockStore {
+    root: PathBuf,
+    verify: bool,
+    temp_counter: AtomicU64,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl FsBlockStore {
+    /// Creates a block store keeping its blocks in given directory, creating the
+    /// directory if it doesn't exist yet. Blocks already in it can be read right away.
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self, BlockStoreError> {
+        let root = root.into();
+        fs::create_dir_all(&root).map_err(anyhow::Error::from)?;
+
+        Ok(Self {
+            root,
+            verify: true,
+            temp_counter: AtomicU64::new(0),
+        })
+    }
+
+    /// Sets whether blocks get checked against their CID when they're read.
+    pub fn with_verification(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// The directory blocks are kept in.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn block_path(&self, cid: &Cid) -> PathBuf {
+        self.root.join(cid.to_string())
+    }
+
+    fn temp_path(&self, cid: &Cid) -> PathBuf {
+        // Unique, so concurrent puts of the same block don't write to the same file
+        let count = self.temp_counter.fetch_add(1, Ordering::SeqCst);
+        self.root
+            .join(format!("{cid}.{}-{count}.tmp", std::process::id()))
+    }
+}
+
+impl BlockStore for FsBlockStore {
+    async fn get_block(&self, cid: &Cid) -> Result<Bytes, BlockStoreError> {
+        let path = self.block_path(cid);
+        let bytes = match blocking::unblock(move || fs::read(path)).await {
+            Ok(bytes) => Bytes::from(bytes),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(BlockStoreError::CIDNotFound(*cid))
+            }
+            Err(e) => return Err(anyhow::Error::from(e).into()),
+        };
+
+        if self.verify {
+            let code = Code::try_from(cid.hash().code()).map_err(|_| {
+                anyhow!(
+                    "Unsupported hash function {:#x} in CID {cid}",
+                    cid.hash().code()
+                )
+            })?;
+            if code.digest(&bytes) != *cid.hash() {
+                return Err(BlockStoreError::CorruptBlock(*cid));
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    async fn put_block_keyed(
+        &self,
+        cid: Cid,
+        bytes: impl Into<Bytes> + CondSend,
+    ) -> Result<(), BlockStoreError> {
+        let path = self.block_path(&cid);
+        let temp_path = self.temp_path(&cid);
+        let bytes = bytes.into();
+        let write = move || -> std::io::Result<()> {
+            if path.exists() {
+                return Ok(());
+            }
+
+            let mut file = File::create(&temp_path)?;
+            let written = file
+                .write_all(&bytes)
+                .and_then(|_| file.sync_all())
+                .and_then(|_| fs::rename(&temp_path, &path));
+            if written.is_err() {
+                let _ = fs::remove_file(&temp_path);
+            }
+
+            written?;
+            utils::sync_parent_dir(&path)
+        };
+
+        blocking::unblock(write)
+            .await
+            .map_err(|e| anyhow::Error::from(e).into())
+    }
+
+    async fn has_block(&self, cid: &Cid) -> Result<bool, BlockStoreError> {
+        let path = self.block_path(cid);
+        Ok(blocking::unblock(move || path.exists()).await)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bs_duplication_test, bs_retrieval_test, CODEC_RAW};
+    use anyhow::Result;
+
+    #[async_std::test]
+    async fn fs_blockstore() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = &FsBlockStore::new(dir.path())?;
+        bs_retrieval_test::<FsBlockStore>(store).await?;
+        bs_duplication_test::<FsBlockStore>(store).await?;
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn blocks_survive_reopening_the_store() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = FsBlockStore::new(dir.path())?;
+        let first = store.put_block(b"first".to_vec(), CODEC_RAW).await?;
+        let second = store.put_block(b"second".to_vec(), CODEC_RAW).await?;
+        drop(store);
+
+        let store = FsBlockStore::new(dir.path())?;
+        assert_eq!(store.get_block(&first).await?, b"first".to_vec());
+        assert_eq!(store.get_block(&second).await?, b"second".to_vec());
+
+        // A crash mid-store leaves a temporary file, but not a partial block
+        let third = store.create_cid(b"third", CODEC_RAW)?;
+        fs::write(dir.path().join(format!("{third}.0-0.tmp")), b"thi")?;
+        assert!(!store.has_block(&third).await?);
+        assert!(matches!(
+            store.get_block(&third).await,
+            Err(BlockStoreError::CIDNotFound(_))
+        ));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn corrupt_blocks_are_detected_on_read() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = FsBlockStore::new(dir.path())?;
+        let cid = store.put_block(b"intact".to_vec(), CODEC_RAW).await?;
+        fs::write(dir.path().join(cid.to_string()), b"broken")?;
+
+        assert!(matches!(
+            store.get_block(&cid).await,
+            Err(BlockStoreError::CorruptBlock(corrupt)) if corrupt == cid
+        ));
+
+        let store = store.with_verification(false);
+        assert_eq!(store.get_block(&cid).await?, b"broken".to_vec());
+
+        Ok(())
+    }
+}
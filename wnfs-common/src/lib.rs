@@ -2,19 +2,27 @@
 pub mod blockstore;
 mod encoding;
 mod error;
+#[cfg(all(feature = "disk", not(target_arch = "wasm32")))]
+mod fs_blockstore;
 mod link;
 mod metadata;
 mod pathnodes;
+mod root_pointer;
 mod storable;
+mod stores;
 pub mod utils;
 
 pub use blockstore::*;
 pub use encoding::*;
 pub use error::*;
+#[cfg(all(feature = "disk", not(target_arch = "wasm32")))]
+pub use fs_blockstore::*;
 pub use link::*;
 pub use metadata::*;
 pub use pathnodes::*;
+pub use root_pointer::*;
 pub use storable::*;
+pub use stores::*;
 
 //--------------------------------------------------------------------------------------------------
 // Constants
@@ -2,7 +2,7 @@
 
 use anyhow::{bail, Result};
 use chrono::{DateTime, TimeZone, Utc};
-use libipld::{Ipld, Multihash};
+use libipld::{Cid, Ipld, Multihash};
 use multihash::{Code, MultihashDigest};
 use serde::{
     de::{DeserializeOwned, Error as DeError},
@@ -53,6 +53,24 @@ impl Display for NodeType {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Metadata(pub BTreeMap<String, Ipld>);
 
+/// How to combine the differing metadata of two concurrent versions of a node
+/// during a merge, see `Metadata::merge_with`.
+///
+/// All policies give the same result no matter which side merges the other.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MetadataMergePolicy {
+    /// Keep the metadata with the lower hash, see `Metadata::tie_break_with`.
+    /// Files whose versions only differ in metadata get reported as tie-breaks.
+    #[default]
+    TieBreak,
+    /// Keep the metadata with the later modification time. If both were modified
+    /// at the same time, the one with the lower hash is kept.
+    TakeNewer,
+    /// Keep the entries of both. Entries with different values on both sides are
+    /// taken from the newer metadata, as decided by `TakeNewer`.
+    Union,
+}
+
 //--------------------------------------------------------------------------------------------------
 // Implementations
 //--------------------------------------------------------------------------------------------------
@@ -146,6 +164,253 @@ impl Metadata {
         })
     }
 
+    /// Records the digest of the file content this metadata belongs to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs_common::Metadata;
+    /// use chrono::Utc;
+    ///
+    /// let mut metadata = Metadata::new(Utc::now());
+    /// metadata.upsert_content_digest([42; 32]);
+    ///
+    /// assert_eq!(metadata.get_content_digest(), Some([42; 32]));
+    /// ```
+    pub fn upsert_content_digest(&mut self, digest: [u8; 32]) {
+        self.0
+            .insert("contentDigest".into(), Ipld::Bytes(digest.to_vec()));
+    }
+
+    /// Returns the recorded content digest.
+    ///
+    /// Will return `None` if there's no content digest on the node
+    /// or if it's not a 32 byte long byte string.
+    pub fn get_content_digest(&self) -> Option<[u8; 32]> {
+        self.0.get("contentDigest").and_then(|ipld| match ipld {
+            Ipld::Bytes(bytes) => bytes.as_slice().try_into().ok(),
+            _ => None,
+        })
+    }
+
+    /// Removes the recorded content digest, e.g. because it's not known for new content.
+    pub fn delete_content_digest(&mut self) {
+        self.0.remove("contentDigest");
+    }
+
+    /// Records how the file content is encoded, e.g. `"gzip"` or `"zstd"`,
+    /// using the same tokens as the HTTP `Content-Encoding` header.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs_common::Metadata;
+    /// use chrono::Utc;
+    ///
+    /// let mut metadata = Metadata::new(Utc::now());
+    /// metadata.upsert_content_encoding("gzip");
+    ///
+    /// assert_eq!(metadata.get_content_encoding(), Some("gzip"));
+    /// ```
+    pub fn upsert_content_encoding(&mut self, encoding: &str) {
+        self.0
+            .insert("contentEncoding".into(), Ipld::String(encoding.into()));
+    }
+
+    /// Returns the recorded content encoding.
+    ///
+    /// Will return `None` if there's no content encoding on the node
+    /// or if it's not a string.
+    pub fn get_content_encoding(&self) -> Option<&str> {
+        self.0.get("contentEncoding").and_then(|ipld| match ipld {
+            Ipld::String(encoding) => Some(encoding.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Removes the recorded content encoding.
+    pub fn delete_content_encoding(&mut self) {
+        self.0.remove("contentEncoding");
+    }
+
+    /// Marks or unmarks the directory this metadata belongs to as a sync root,
+    /// i.e. the root of a subtree that gets synced on its own.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs_common::Metadata;
+    /// use chrono::Utc;
+    ///
+    /// let mut metadata = Metadata::new(Utc::now());
+    /// metadata.set_sync_root(true);
+    ///
+    /// assert!(metadata.is_sync_root());
+    /// ```
+    pub fn set_sync_root(&mut self, is_sync_root: bool) {
+        if is_sync_root {
+            self.0.insert("syncRoot".into(), Ipld::Bool(true));
+        } else {
+            self.0.remove("syncRoot");
+        }
+    }
+
+    /// Whether this metadata marks its directory as a sync root.
+    pub fn is_sync_root(&self) -> bool {
+        matches!(self.0.get("syncRoot"), Some(Ipld::Bool(true)))
+    }
+
+    /// Labels the revision this metadata belongs to, like a tag in git.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs_common::Metadata;
+    /// use chrono::Utc;
+    ///
+    /// let mut metadata = Metadata::new(Utc::now());
+    /// metadata.upsert_snapshot_label("v1.0");
+    ///
+    /// assert_eq!(metadata.get_snapshot_label(), Some("v1.0"));
+    /// ```
+    pub fn upsert_snapshot_label(&mut self, label: &str) {
+        self.0
+            .insert("snapshotLabel".into(), Ipld::String(label.into()));
+    }
+
+    /// Returns the snapshot label.
+    ///
+    /// Will return `None` if there's no label on the node or if it's not a string.
+    pub fn get_snapshot_label(&self) -> Option<&str> {
+        self.0.get("snapshotLabel").and_then(|ipld| match ipld {
+            Ipld::String(label) => Some(label.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Removes the snapshot label.
+    pub fn delete_snapshot_label(&mut self) {
+        self.0.remove("snapshotLabel");
+    }
+
+    /// Links a sidecar, i.e. an artifact derived from the node's content like a
+    /// thumbnail or a transcode, under given key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs_common::{BlockStore, Metadata, MemoryBlockStore, CODEC_RAW};
+    /// use chrono::Utc;
+    ///
+    /// let store = MemoryBlockStore::new();
+    /// let thumbnail = store.create_cid(b"tiny cat", CODEC_RAW).unwrap();
+    /// let mut metadata = Metadata::new(Utc::now());
+    /// metadata.upsert_sidecar("thumbnail", thumbnail);
+    ///
+    /// assert_eq!(metadata.get_sidecar("thumbnail"), Some(thumbnail));
+    /// ```
+    pub fn upsert_sidecar(&mut self, key: &str, cid: Cid) {
+        let mut sidecars = match self.0.remove("sidecars") {
+            Some(Ipld::Map(sidecars)) => sidecars,
+            _ => BTreeMap::new(),
+        };
+
+        sidecars.insert(key.into(), Ipld::Link(cid));
+        self.0.insert("sidecars".into(), Ipld::Map(sidecars));
+    }
+
+    /// Returns the sidecar linked under given key.
+    pub fn get_sidecar(&self, key: &str) -> Option<Cid> {
+        self.get_sidecars().remove(key)
+    }
+
+    /// Returns all linked sidecars by their keys.
+    ///
+    /// Entries that aren't links are skipped.
+    pub fn get_sidecars(&self) -> BTreeMap<String, Cid> {
+        match self.0.get("sidecars") {
+            Some(Ipld::Map(sidecars)) => sidecars
+                .iter()
+                .filter_map(|(key, ipld)| match ipld {
+                    Ipld::Link(cid) => Some((key.clone(), *cid)),
+                    _ => None,
+                })
+                .collect(),
+            _ => BTreeMap::new(),
+        }
+    }
+
+    /// Removes the sidecar linked under given key and returns it.
+    pub fn delete_sidecar(&mut self, key: &str) -> Option<Cid> {
+        let Some(Ipld::Map(sidecars)) = self.0.get_mut("sidecars") else {
+            return None;
+        };
+
+        let removed = sidecars.remove(key);
+        if sidecars.is_empty() {
+            self.0.remove("sidecars");
+        }
+
+        match removed {
+            Some(Ipld::Link(cid)) => Some(cid),
+            _ => None,
+        }
+    }
+
+    /// Sets an attribute, i.e. a user-defined tag like `author` or `content-type`,
+    /// and returns the value it had before.
+    ///
+    /// Attributes are kept in a map of their own, so they can't clash with the
+    /// entries WNFS itself keeps in the metadata. Metadata without attributes,
+    /// like the metadata of nodes written before attributes existed, simply has
+    /// no attributes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs_common::Metadata;
+    /// use chrono::Utc;
+    /// use libipld::Ipld;
+    ///
+    /// let mut metadata = Metadata::new(Utc::now());
+    /// metadata.set_attribute("author", Ipld::String("alice".into()));
+    ///
+    /// assert_eq!(metadata.get_attribute("author"), Some(&Ipld::String("alice".into())));
+    /// assert_eq!(metadata.get_attribute("created"), None);
+    /// ```
+    pub fn set_attribute(&mut self, key: &str, value: Ipld) -> Option<Ipld> {
+        let mut attributes = match self.0.remove("attributes") {
+            Some(Ipld::Map(attributes)) => attributes,
+            _ => BTreeMap::new(),
+        };
+
+        let previous = attributes.insert(key.into(), value);
+        self.0.insert("attributes".into(), Ipld::Map(attributes));
+        previous
+    }
+
+    /// Returns the attribute with given key, see `set_attribute`.
+    pub fn get_attribute(&self, key: &str) -> Option<&Ipld> {
+        match self.0.get("attributes") {
+            Some(Ipld::Map(attributes)) => attributes.get(key),
+            _ => None,
+        }
+    }
+
+    /// Removes the attribute with given key and returns its value.
+    pub fn remove_attribute(&mut self, key: &str) -> Option<Ipld> {
+        let Some(Ipld::Map(attributes)) = self.0.get_mut("attributes") else {
+            return None;
+        };
+
+        let removed = attributes.remove(key);
+        if attributes.is_empty() {
+            self.0.remove("attributes");
+        }
+
+        removed
+    }
+
     /// Inserts a key-value pair into the metadata.
     /// If the key already existed, the value is updated, and the old value is returned.
     ///
@@ -242,6 +507,55 @@ impl Metadata {
 
         Ok(())
     }
+
+    /// Combines this metadata with the metadata of a concurrent version of the same
+    /// node as given policy says. Used for conflict reconciliation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs_common::{Metadata, MetadataMergePolicy};
+    /// use chrono::{Duration, Utc};
+    /// use libipld::Ipld;
+    ///
+    /// let time = Utc::now();
+    /// let mut ours = Metadata::new(time);
+    /// ours.put("color", Ipld::String("red".into()));
+    /// let mut theirs = Metadata::new(time);
+    /// theirs.upsert_mtime(time + Duration::hours(1));
+    /// theirs.put("tag", Ipld::String("holiday".into()));
+    ///
+    /// ours.merge_with(&theirs, MetadataMergePolicy::Union).unwrap();
+    ///
+    /// assert_eq!(ours.get("color"), Some(&Ipld::String("red".into())));
+    /// assert_eq!(ours.get("tag"), Some(&Ipld::String("holiday".into())));
+    /// assert_eq!(ours.get_modified(), theirs.get_modified());
+    /// ```
+    pub fn merge_with(&mut self, other: &Self, policy: MetadataMergePolicy) -> Result<()> {
+        let other_is_newer = match self.get_modified().cmp(&other.get_modified()) {
+            std::cmp::Ordering::Less => true,
+            std::cmp::Ordering::Greater => false,
+            std::cmp::Ordering::Equal => self.hash()?.digest() > other.hash()?.digest(),
+        };
+
+        match policy {
+            MetadataMergePolicy::TieBreak => self.tie_break_with(other)?,
+            MetadataMergePolicy::TakeNewer => {
+                if other_is_newer {
+                    self.0 = other.0.clone();
+                }
+            }
+            MetadataMergePolicy::Union => {
+                for (key, value) in other.0.iter() {
+                    if other_is_newer || !self.0.contains_key(key) {
+                        self.0.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl TryFrom<&Ipld> for NodeType {
@@ -310,7 +624,8 @@ impl<'de> Deserialize<'de> for NodeType {
 mod tests {
     use crate::{decode, encode, Metadata};
     use chrono::Utc;
-    use libipld::cbor::DagCborCodec;
+    use libipld::{cbor::DagCborCodec, Ipld};
+    use std::collections::BTreeMap;
 
     #[async_std::test]
     async fn metadata_can_encode_decode_as_cbor() {
@@ -321,4 +636,29 @@ mod tests {
 
         assert_eq!(metadata, decoded_metadata);
     }
+
+    #[test]
+    fn metadata_without_attributes_decodes_with_none() {
+        // Metadata as it was written before attributes existed
+        let old = BTreeMap::from([
+            ("created".to_string(), Ipld::Integer(1_000)),
+            ("modified".to_string(), Ipld::Integer(2_000)),
+        ]);
+        let mut metadata: Metadata =
+            decode(&encode(&old, DagCborCodec).unwrap(), DagCborCodec).unwrap();
+        assert_eq!(metadata.get_attribute("author"), None);
+        assert_eq!(metadata.remove_attribute("author"), None);
+
+        metadata.set_attribute("author", Ipld::String("alice".into()));
+        let decoded: Metadata =
+            decode(&encode(&metadata, DagCborCodec).unwrap(), DagCborCodec).unwrap();
+        assert_eq!(
+            decoded.get_attribute("author"),
+            Some(&Ipld::String("alice".into()))
+        );
+        assert_eq!(decoded.get_modified(), metadata.get_modified());
+
+        metadata.remove_attribute("author");
+        assert_eq!(metadata.0, old);
+    }
 }
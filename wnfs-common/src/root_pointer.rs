@@ -0,0 +1,232 @@
+//! Durable pointers to the current root of a file system.
+
+use crate::{
+    utils::{CondSend, CondSync},
+    BlockStore,
+};
+use anyhow::{ensure, Result};
+use futures::Future;
+use libipld::Cid;
+use parking_lot::Mutex;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    fs::{self, File},
+    io::{ErrorKind, Write},
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Traits
+//--------------------------------------------------------------------------------------------------
+
+/// Holds the CID of the current root of a file system, which gets advanced on commits.
+///
+/// Committing a new root is meant to be the last step of persisting changes: First,
+/// all new blocks are put into the block store, then the pointer is swapped to the
+/// new root. If the process crashes before the swap, the pointer still refers to the
+/// previous, complete root.
+pub trait RootPointer: CondSync {
+    /// Returns the current root, or `None` if no root was committed yet.
+    fn current(&self) -> impl Future<Output = Result<Option<Cid>>> + CondSend;
+
+    /// Replaces the current root with given root in one atomic step.
+    ///
+    /// This doesn't check anything about the new root, see `commit` for that.
+    fn swap(&self, new_root: Cid) -> impl Future<Output = Result<()>> + CondSend;
+
+    /// Makes given root the current root, once its block is in given block store.
+    ///
+    /// Blocks put into a block store are expected to be durable once `put_block` returned,
+    /// so all blocks of the new root need to be stored before calling this.
+    /// Fails without changing the current root if the root block can't be found.
+    fn commit(
+        &self,
+        new_root: Cid,
+        store: &impl BlockStore,
+    ) -> impl Future<Output = Result<()>> + CondSend {
+        async move {
+            ensure!(
+                store.has_block(&new_root).await?,
+                "Cannot commit root {new_root}, its block isn't in the block store"
+            );
+
+            self.swap(new_root).await
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A root pointer that's only kept in memory, mostly useful for tests.
+#[derive(Debug, Default)]
+pub struct MemoryRootPointer(Mutex<Option<Cid>>);
+
+/// A root pointer that's kept in a small file on disk.
+///
+/// The file holds the root CID as a string. Swapping the root writes the new CID
+/// to a temporary file next to it first and then renames it over the pointer file,
+/// which is atomic on common file systems. File system calls run on a thread pool
+/// for blocking work, so they don't block the async executor.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::{BlockStore, FileRootPointer, MemoryBlockStore, RootPointer, CODEC_RAW};
+///
+/// #[async_std::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let dir = tempfile::tempdir()?;
+///     let store = &MemoryBlockStore::new();
+///     let pointer = FileRootPointer::new(dir.path().join("root"));
+///     assert_eq!(pointer.current().await?, None);
+///
+///     let root = store.put_block(b"root".to_vec(), CODEC_RAW).await?;
+///     pointer.commit(root, store).await?;
+///
+///     let reopened = FileRootPointer::new(dir.path().join("root"));
+///     assert_eq!(reopened.current().await?, Some(root));
+///
+///     Ok(())
+/// }
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct FileRootPointer {
+    path: PathBuf,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl MemoryRootPointer {
+    /// Creates a root pointer without a current root.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RootPointer for MemoryRootPointer {
+    async fn current(&self) -> Result<Option<Cid>> {
+        Ok(*self.0.lock())
+    }
+
+    async fn swap(&self, new_root: Cid) -> Result<()> {
+        *self.0.lock() = Some(new_root);
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileRootPointer {
+    /// Creates a root pointer kept at given file path. The file doesn't need to exist yet.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The path of the pointer file.
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl RootPointer for FileRootPointer {
+    async fn current(&self) -> Result<Option<Cid>> {
+        let path = self.path.clone();
+        match blocking::unblock(move || fs::read_to_string(path)).await {
+            Ok(content) => Ok(Some(content.trim().parse()?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn swap(&self, new_root: Cid) -> Result<()> {
+        // Unique, so concurrent swaps, also via clones, don't write to the same file
+        static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+        let count = TEMP_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let path = self.path.clone();
+        let mut temp_path = path.clone().into_os_string();
+        temp_path.push(format!(".{}-{count}.tmp", std::process::id()));
+
+        let write = move || -> std::io::Result<()> {
+            let mut file = File::create(&temp_path)?;
+            let written = file
+                .write_all(new_root.to_string().as_bytes())
+                .and_then(|_| file.sync_all())
+                .and_then(|_| fs::rename(&temp_path, &path));
+            if written.is_err() {
+                let _ = fs::remove_file(&temp_path);
+            }
+
+            written?;
+            // Make the rename itself durable
+            crate::utils::sync_parent_dir(&path)
+        };
+
+        Ok(blocking::unblock(write).await?)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryBlockStore, CODEC_RAW};
+
+    async fn root_pointer_test(pointer: &impl RootPointer) -> Result<()> {
+        let store = &MemoryBlockStore::new();
+        assert_eq!(pointer.current().await?, None);
+
+        let first = store.put_block(b"first".to_vec(), CODEC_RAW).await?;
+        pointer.commit(first, store).await?;
+        assert_eq!(pointer.current().await?, Some(first));
+
+        // A root whose block never made it into the store
+        let dangling = store.create_cid(b"dangling", CODEC_RAW)?;
+        assert!(pointer.commit(dangling, store).await.is_err());
+        assert_eq!(pointer.current().await?, Some(first));
+
+        let second = store.put_block(b"second".to_vec(), CODEC_RAW).await?;
+        pointer.commit(second, store).await?;
+        assert_eq!(pointer.current().await?, Some(second));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn memory_root_pointer() -> Result<()> {
+        root_pointer_test(&MemoryRootPointer::new()).await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[async_std::test]
+    async fn file_root_pointer() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let pointer = FileRootPointer::new(dir.path().join("root"));
+        root_pointer_test(&pointer).await?;
+
+        // A crash mid-swap leaves a partial temporary file, but not a partial pointer
+        fs::write(dir.path().join("root.tmp"), b"bafy")?;
+        let reopened = FileRootPointer::new(dir.path().join("root"));
+        assert_eq!(reopened.current().await?, pointer.current().await?);
+
+        // Concurrent swaps don't get in each other's way
+        let store = &MemoryBlockStore::new();
+        let mut roots = Vec::new();
+        for i in 0..10u8 {
+            roots.push(store.put_block(vec![i], CODEC_RAW).await?);
+        }
+        futures::future::try_join_all(roots.iter().map(|root| pointer.swap(*root))).await?;
+        assert!(roots.contains(&pointer.current().await?.unwrap()));
+        assert_eq!(fs::read_dir(dir.path())?.count(), 2);
+
+        Ok(())
+    }
+}
@@ -3,13 +3,13 @@
 //! `PublicNode`, `HamtForest` etc.
 use crate::{
     utils::{Arc, CondSend, CondSync},
-    BlockStore,
+    BlockStore, BlockStoreError, CODEC_DAG_CBOR, CODEC_DAG_JSON,
 };
 use anyhow::{bail, Result};
 use async_once_cell::OnceCell;
 use bytes::Bytes;
 use futures::Future;
-use libipld::{cbor::DagCborCodec, Cid};
+use libipld::{json::DagJsonCodec, Cid};
 use serde::{de::DeserializeOwned, Serialize};
 
 //--------------------------------------------------------------------------------------------------
@@ -39,6 +39,20 @@ macro_rules! impl_storable_from_serde {
 // Type Definitions
 //--------------------------------------------------------------------------------------------------
 
+/// The IPLD codecs serde-serializable types can be stored with, see
+/// `Storable::store_shallow_as`.
+///
+/// The codec is recorded in the CID of stored blocks, and loading dispatches on it,
+/// so blocks stored with either codec can be loaded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BlockCodec {
+    /// Compact binary encoding, used for everything WNFS stores by default.
+    #[default]
+    DagCbor,
+    /// Human-readable JSON encoding.
+    DagJson,
+}
+
 /// The trait that defines how to store something in a blockstore.
 ///
 /// This works via a two-tiered system, where the actual in-memory representation
@@ -84,6 +98,12 @@ pub trait Storable: Sized {
     /// Store this data type in a given `BlockStore`.
     ///
     /// This will short-circuit by using the `persisted_as` once-cell, if available.
+    ///
+    /// Types linking to other storables, like directories, store their children while
+    /// turning themselves into their at-rest representation. With `persisted_as` caches,
+    /// storing a whole tree thus serializes and hashes each node exactly once, children
+    /// before their parents, and the root's CID comes out of the last block put. Storing
+    /// the same tree again afterwards doesn't serialize or hash anything.
     fn store(&self, store: &impl BlockStore) -> impl Future<Output = Result<Cid>> + CondSend
     where
         Self: CondSync,
@@ -102,6 +122,44 @@ pub trait Storable: Sized {
         }
     }
 
+    /// Store this data type in a given `BlockStore`, with only its own block encoded
+    /// with given codec. This is a shallow encoding: Values it links to, like the
+    /// children of a directory, are stored as usual by `store`, so with DAG-CBOR.
+    ///
+    /// Unlike `store`, this doesn't use or populate the `persisted_as` cache,
+    /// since that holds the CID of the default encoding.
+    /// Types with a fixed encoding, like dag-pb file nodes, ignore the codec.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs_common::{BlockCodec, MemoryBlockStore, Storable, CODEC_DAG_JSON};
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> anyhow::Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let cid = "Hello".to_string().store_shallow_as(store, BlockCodec::DagJson).await?;
+    ///
+    ///     assert_eq!(cid.codec(), CODEC_DAG_JSON);
+    ///     assert_eq!(String::load(&cid, store).await?, "Hello");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    fn store_shallow_as(
+        &self,
+        store: &impl BlockStore,
+        codec: BlockCodec,
+    ) -> impl Future<Output = Result<Cid>> + CondSend
+    where
+        Self: CondSync,
+    {
+        async move {
+            let (bytes, codec) = self.to_serializable(store).await?.encode_ipld_as(codec)?;
+            Ok(store.put_block(bytes, codec).await?)
+        }
+    }
+
     /// Try to load a value of this type from a CID.
     ///
     /// This will pass on the CID to the `from_serializable` function so it can
@@ -117,6 +175,12 @@ pub trait Storable: Sized {
 
 pub trait StoreIpld {
     fn encode_ipld(&self) -> Result<(Bytes, u64)>;
+
+    /// Encodes this value with given codec. Types with a fixed encoding ignore
+    /// the codec, which is what this does by default.
+    fn encode_ipld_as(&self, _codec: BlockCodec) -> Result<(Bytes, u64)> {
+        self.encode_ipld()
+    }
 }
 
 pub trait LoadIpld: Sized {
@@ -125,19 +189,25 @@ pub trait LoadIpld: Sized {
 
 impl<T: Serialize> StoreIpld for T {
     fn encode_ipld(&self) -> Result<(Bytes, u64)> {
-        let bytes = serde_ipld_dagcbor::to_vec(self)?;
-        Ok((bytes.into(), DagCborCodec.into()))
+        self.encode_ipld_as(BlockCodec::DagCbor)
+    }
+
+    fn encode_ipld_as(&self, codec: BlockCodec) -> Result<(Bytes, u64)> {
+        let bytes = match codec {
+            BlockCodec::DagCbor => serde_ipld_dagcbor::to_vec(self)?,
+            BlockCodec::DagJson => crate::encode(self, DagJsonCodec)?,
+        };
+        Ok((bytes.into(), codec.code()))
     }
 }
 
 impl<T: DeserializeOwned + Sized> LoadIpld for T {
     fn decode_ipld(cid: &Cid, bytes: Bytes) -> Result<Self> {
-        let codec = cid.codec();
-        let dag_cbor: u64 = DagCborCodec.into();
-        if codec != dag_cbor {
-            bail!("Expected dag-cbor codec, but got {codec:X} in CID {cid}");
+        match BlockCodec::try_from(cid.codec()) {
+            Ok(BlockCodec::DagCbor) => Ok(serde_ipld_dagcbor::from_slice(bytes.as_ref())?),
+            Ok(BlockCodec::DagJson) => crate::decode(bytes.as_ref(), DagJsonCodec),
+            Err(_) => bail!(BlockStoreError::UnsupportedCodec(cid.codec())),
         }
-        Ok(serde_ipld_dagcbor::from_slice(bytes.as_ref())?)
     }
 }
 
@@ -164,6 +234,28 @@ impl<T: DeserializeOwned + Sized> LoadIpld for T {
 //     }
 // }
 
+impl BlockCodec {
+    /// The multicodec code of this codec, as recorded in CIDs.
+    pub fn code(self) -> u64 {
+        match self {
+            Self::DagCbor => CODEC_DAG_CBOR,
+            Self::DagJson => CODEC_DAG_JSON,
+        }
+    }
+}
+
+impl TryFrom<u64> for BlockCodec {
+    type Error = BlockStoreError;
+
+    fn try_from(code: u64) -> Result<Self, Self::Error> {
+        match code {
+            CODEC_DAG_CBOR => Ok(Self::DagCbor),
+            CODEC_DAG_JSON => Ok(Self::DagJson),
+            _ => Err(BlockStoreError::UnsupportedCodec(code)),
+        }
+    }
+}
+
 impl<T: Storable + CondSync> Storable for Arc<T> {
     type Serializable = T::Serializable;
 
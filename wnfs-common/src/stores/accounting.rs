@@ -0,0 +1,168 @@
+use crate::{utils::CondSend, BlockStore, BlockStoreError};
+use bytes::Bytes;
+use libipld::Cid;
+use parking_lot::Mutex;
+use std::collections::HashSet;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Statistics about the blocks written to a block store.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StoreStats {
+    /// The number of blocks that weren't in the store before.
+    pub new_blocks: u64,
+    /// The total size in bytes of the blocks that weren't in the store before.
+    pub new_bytes: u64,
+}
+
+/// A block store that wraps another one and keeps track of how many
+/// new blocks and bytes were written to it.
+///
+/// Blocks that are already present in the wrapped block store, as
+/// reported by `has_block`, don't count as new. Neither do blocks that were
+/// counted already, so concurrent puts of the same new block count once.
+/// For that, the CIDs of all counted blocks are kept in memory.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::{AccountingBlockStore, BlockStore, MemoryBlockStore, StoreStats, CODEC_RAW};
+///
+/// #[async_std::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let store = AccountingBlockStore::new(MemoryBlockStore::new());
+///
+///     store.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+///     store.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+///
+///     assert_eq!(store.stats(), StoreStats { new_blocks: 1, new_bytes: 5 });
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AccountingBlockStore<B> {
+    inner: B,
+    accounting: Mutex<Accounting>,
+}
+
+#[derive(Debug, Default)]
+struct Accounting {
+    stats: StoreStats,
+    counted: HashSet<Cid>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<B: BlockStore> AccountingBlockStore<B> {
+    /// Wraps given block store, starting with empty statistics.
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            accounting: Mutex::default(),
+        }
+    }
+
+    /// The statistics of all blocks written so far.
+    pub fn stats(&self) -> StoreStats {
+        self.accounting.lock().stats
+    }
+
+    /// Returns the statistics of all blocks written so far and resets them.
+    pub fn take_stats(&self) -> StoreStats {
+        std::mem::take(&mut self.accounting.lock().stats)
+    }
+
+    /// Returns the wrapped block store.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: BlockStore> BlockStore for AccountingBlockStore<B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Bytes, BlockStoreError> {
+        self.inner.get_block(cid).await
+    }
+
+    async fn put_block_keyed(
+        &self,
+        cid: Cid,
+        bytes: impl Into<Bytes> + CondSend,
+    ) -> Result<(), BlockStoreError> {
+        let bytes = bytes.into();
+        let size = bytes.len() as u64;
+        let is_new = !self.inner.has_block(&cid).await?;
+        self.inner.put_block_keyed(cid, bytes).await?;
+
+        let mut accounting = self.accounting.lock();
+        if is_new && accounting.counted.insert(cid) {
+            accounting.stats.new_blocks += 1;
+            accounting.stats.new_bytes += size;
+        }
+
+        Ok(())
+    }
+
+    async fn has_block(&self, cid: &Cid) -> Result<bool, BlockStoreError> {
+        self.inner.has_block(cid).await
+    }
+
+    fn create_cid(&self, bytes: &[u8], codec: u64) -> Result<Cid, BlockStoreError> {
+        self.inner.create_cid(bytes, codec)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bs_duplication_test, bs_retrieval_test, utils::InstrumentedBlockStore, MemoryBlockStore,
+        CODEC_RAW,
+    };
+    use anyhow::Result;
+    use std::time::Duration;
+
+    #[async_std::test]
+    async fn accounting_blockstore() -> Result<()> {
+        let store = &AccountingBlockStore::new(MemoryBlockStore::new());
+        bs_retrieval_test::<AccountingBlockStore<MemoryBlockStore>>(store).await?;
+        bs_duplication_test::<AccountingBlockStore<MemoryBlockStore>>(store).await?;
+
+        let stats = store.take_stats();
+        assert!(stats.new_blocks > 0);
+        assert_eq!(store.stats(), StoreStats::default());
+
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[async_std::test]
+    async fn concurrent_puts_of_a_new_block_count_once() -> Result<()> {
+        let inner = InstrumentedBlockStore::new(MemoryBlockStore::new())
+            .with_delay(Duration::from_millis(10));
+        let store = &AccountingBlockStore::new(inner);
+
+        futures::future::try_join_all(
+            (0..5).map(|_| store.put_block(b"Hello".to_vec(), CODEC_RAW)),
+        )
+        .await?;
+
+        assert_eq!(
+            store.stats(),
+            StoreStats {
+                new_blocks: 1,
+                new_bytes: 5
+            }
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{utils::CondSend, BlockStore, BlockStoreError};
+use bytes::Bytes;
+use libipld::Cid;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// How much a `CachingBlockStore` keeps in its cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheCapacity {
+    /// At most this many bytes of blocks. Blocks bigger than that aren't cached.
+    Bytes(usize),
+    /// At most this many blocks.
+    Entries(usize),
+}
+
+/// A block store that wraps another one and keeps the blocks that were fetched
+/// from or put into it most recently in memory, so getting them again doesn't
+/// go to the wrapped store.
+///
+/// Once the cache is full, the least recently used blocks get evicted.
+/// Storing a block and loading it back right after only goes to the wrapped store
+/// for storing it.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::{BlockStore, CacheCapacity, CachingBlockStore, MemoryBlockStore, CODEC_RAW};
+///
+/// #[async_std::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let store = CachingBlockStore::new(MemoryBlockStore::new(), CacheCapacity::Bytes(1024));
+///     let cid = store.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+///
+///     store.get_block(&cid).await?;
+///
+///     assert_eq!((store.hits(), store.misses()), (1, 0));
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct CachingBlockStore<B> {
+    inner: B,
+    capacity: CacheCapacity,
+    cache: Mutex<LruCache>,
+}
+
+/// The blocks in a `CachingBlockStore`'s cache together with their recency.
+#[derive(Debug, Default)]
+struct LruCache {
+    blocks: HashMap<Cid, (Bytes, u64)>,
+    /// The CIDs of all cached blocks keyed by when they were used last.
+    recency: BTreeMap<u64, Cid>,
+    clock: u64,
+    size: usize,
+    hits: u64,
+    misses: u64,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl CacheCapacity {
+    fn weight(&self, bytes: &Bytes) -> usize {
+        match self {
+            Self::Bytes(_) => bytes.len(),
+            Self::Entries(_) => 1,
+        }
+    }
+
+    fn limit(&self) -> usize {
+        match self {
+            Self::Bytes(limit) | Self::Entries(limit) => *limit,
+        }
+    }
+}
+
+impl<B: BlockStore> CachingBlockStore<B> {
+    /// Wraps given block store, caching up to given capacity.
+    pub fn new(inner: B, capacity: CacheCapacity) -> Self {
+        Self {
+            inner,
+            capacity,
+            cache: Mutex::new(LruCache::default()),
+        }
+    }
+
+    /// How many `get_block` calls were answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.cache.lock().hits
+    }
+
+    /// How many `get_block` calls had to go to the wrapped store.
+    pub fn misses(&self) -> u64 {
+        self.cache.lock().misses
+    }
+
+    /// Returns the wrapped block store.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    fn insert(&self, cid: Cid, bytes: Bytes) {
+        let weight = self.capacity.weight(&bytes);
+        let limit = self.capacity.limit();
+        if weight > limit {
+            return;
+        }
+
+        let cache = &mut *self.cache.lock();
+        cache.remove(&cid, &self.capacity);
+        while cache.size + weight > limit {
+            let Some((_, oldest)) = cache.recency.pop_first() else {
+                break;
+            };
+            if let Some((evicted, _)) = cache.blocks.remove(&oldest) {
+                cache.size -= self.capacity.weight(&evicted);
+            }
+        }
+
+        cache.clock += 1;
+        cache.recency.insert(cache.clock, cid);
+        cache.blocks.insert(cid, (bytes, cache.clock));
+        cache.size += weight;
+    }
+}
+
+impl LruCache {
+    /// Returns the cached block and marks it as used most recently.
+    fn touch(&mut self, cid: &Cid) -> Option<Bytes> {
+        self.clock += 1;
+        let (bytes, last_used) = self.blocks.get_mut(cid)?;
+        self.recency.remove(last_used);
+        self.recency.insert(self.clock, *cid);
+        *last_used = self.clock;
+        Some(bytes.clone())
+    }
+
+    fn remove(&mut self, cid: &Cid, capacity: &CacheCapacity) {
+        if let Some((bytes, last_used)) = self.blocks.remove(cid) {
+            self.recency.remove(&last_used);
+            self.size -= capacity.weight(&bytes);
+        }
+    }
+}
+
+impl<B: BlockStore> BlockStore for CachingBlockStore<B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Bytes, BlockStoreError> {
+        {
+            let cache = &mut *self.cache.lock();
+            if let Some(bytes) = cache.touch(cid) {
+                cache.hits += 1;
+                return Ok(bytes);
+            }
+
+            cache.misses += 1;
+        }
+
+        let bytes = self.inner.get_block(cid).await?;
+        self.insert(*cid, bytes.clone());
+        Ok(bytes)
+    }
+
+    async fn put_block_keyed(
+        &self,
+        cid: Cid,
+        bytes: impl Into<Bytes> + CondSend,
+    ) -> Result<(), BlockStoreError> {
+        let bytes = bytes.into();
+        self.inner.put_block_keyed(cid, bytes.clone()).await?;
+        self.insert(cid, bytes);
+        Ok(())
+    }
+
+    async fn has_block(&self, cid: &Cid) -> Result<bool, BlockStoreError> {
+        if self.cache.lock().blocks.contains_key(cid) {
+            return Ok(true);
+        }
+
+        self.inner.has_block(cid).await
+    }
+
+    fn create_cid(&self, bytes: &[u8], codec: u64) -> Result<Cid, BlockStoreError> {
+        self.inner.create_cid(bytes, codec)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bs_duplication_test, bs_retrieval_test, BlockOperation, MemoryBlockStore,
+        RecordingBlockStore, CODEC_RAW,
+    };
+    use anyhow::Result;
+
+    #[async_std::test]
+    async fn caching_blockstore() -> Result<()> {
+        let store = &CachingBlockStore::new(MemoryBlockStore::new(), CacheCapacity::Entries(8));
+        bs_retrieval_test::<CachingBlockStore<MemoryBlockStore>>(store).await?;
+        bs_duplication_test::<CachingBlockStore<MemoryBlockStore>>(store).await?;
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn repeated_loads_only_hit_the_backend_once() -> Result<()> {
+        let backend = MemoryBlockStore::new();
+        let cid = backend.put_block(b"Remote".to_vec(), CODEC_RAW).await?;
+        let store = CachingBlockStore::new(
+            RecordingBlockStore::new(backend),
+            CacheCapacity::Bytes(1024),
+        );
+
+        assert_eq!(store.get_block(&cid).await?, b"Remote".to_vec());
+        assert_eq!(store.get_block(&cid).await?, b"Remote".to_vec());
+        assert_eq!((store.hits(), store.misses()), (1, 1));
+
+        // Stored blocks are cached right away
+        let stored = store.put_block(b"Local".to_vec(), CODEC_RAW).await?;
+        store.get_block(&stored).await?;
+        assert_eq!(store.hits(), 2);
+
+        let gets = store
+            .into_inner()
+            .log()
+            .into_iter()
+            .filter(|access| matches!(access.operation, BlockOperation::Get(_)))
+            .count();
+        assert_eq!(gets, 1);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn least_recently_used_blocks_are_evicted() -> Result<()> {
+        let store = CachingBlockStore::new(MemoryBlockStore::new(), CacheCapacity::Bytes(10));
+        let a = store.put_block(b"aaaa".to_vec(), CODEC_RAW).await?;
+        let b = store.put_block(b"bbbb".to_vec(), CODEC_RAW).await?;
+        // Using `a` makes `b` the least recently used block
+        store.get_block(&a).await?;
+        let c = store.put_block(b"cccc".to_vec(), CODEC_RAW).await?;
+        let big = store.put_block(vec![0; 11], CODEC_RAW).await?;
+
+        for cid in [a, c, b, big] {
+            store.get_block(&cid).await?;
+        }
+        // `a` and `c` were cached, `b` was evicted and `big` never fit
+        assert_eq!((store.hits(), store.misses()), (3, 2));
+        assert!(store.cache.lock().size <= 10);
+
+        let store = CachingBlockStore::new(MemoryBlockStore::new(), CacheCapacity::Entries(1));
+        let a = store.put_block(b"a".to_vec(), CODEC_RAW).await?;
+        store.put_block(b"b".to_vec(), CODEC_RAW).await?;
+        store.get_block(&a).await?;
+        assert_eq!((store.hits(), store.misses()), (0, 1));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{
+    utils::{Arc, CondSend},
+    BlockStore, BlockStoreError,
+};
+use async_once_cell::OnceCell;
+use bytes::Bytes;
+use libipld::Cid;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A block store that wraps another one and merges concurrent `get_block`
+/// calls for the same CID into a single fetch from the wrapped store.
+///
+/// The first call for a CID starts the fetch, all calls for that CID arriving
+/// while it's in flight wait for it and get the same bytes. Once the fetch
+/// finished, the next call fetches again, so nothing gets cached beyond that.
+///
+/// If the shared fetch fails, the call that ran it gets the error and the
+/// waiting calls retry the wrapped store on their own.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::{BlockStore, CoalescingBlockStore, MemoryBlockStore, CODEC_RAW};
+///
+/// #[async_std::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let store = CoalescingBlockStore::new(MemoryBlockStore::new());
+///     let cid = store.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+///
+///     let (first, second) = futures::join!(store.get_block(&cid), store.get_block(&cid));
+///
+///     assert_eq!(first?, second?);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct CoalescingBlockStore<B> {
+    inner: B,
+    in_flight: Mutex<HashMap<Cid, Arc<OnceCell<Option<Bytes>>>>>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<B: BlockStore> CoalescingBlockStore<B> {
+    /// Wraps given block store.
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the wrapped block store.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: BlockStore> BlockStore for CoalescingBlockStore<B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Bytes, BlockStoreError> {
+        let fetch = Arc::clone(self.in_flight.lock().entry(*cid).or_default());
+
+        // Only the call that ends up running this fetch gets to see its error
+        let mut error = None;
+        let bytes = fetch
+            .get_or_init(async {
+                self.inner
+                    .get_block(cid)
+                    .await
+                    .map_err(|e| error = Some(e))
+                    .ok()
+            })
+            .await
+            .clone();
+
+        {
+            let mut in_flight = self.in_flight.lock();
+            if in_flight
+                .get(cid)
+                .is_some_and(|current| Arc::ptr_eq(current, &fetch))
+            {
+                in_flight.remove(cid);
+            }
+        }
+
+        match (bytes, error) {
+            (Some(bytes), _) => Ok(bytes),
+            (None, Some(error)) => Err(error),
+            (None, None) => self.inner.get_block(cid).await,
+        }
+    }
+
+    async fn put_block_keyed(
+        &self,
+        cid: Cid,
+        bytes: impl Into<Bytes> + CondSend,
+    ) -> Result<(), BlockStoreError> {
+        self.inner.put_block_keyed(cid, bytes).await
+    }
+
+    async fn has_block(&self, cid: &Cid) -> Result<bool, BlockStoreError> {
+        self.inner.has_block(cid).await
+    }
+
+    fn create_cid(&self, bytes: &[u8], codec: u64) -> Result<Cid, BlockStoreError> {
+        self.inner.create_cid(bytes, codec)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bs_duplication_test, bs_retrieval_test, utils::InstrumentedBlockStore, MemoryBlockStore,
+        CODEC_RAW,
+    };
+    use anyhow::Result;
+    use std::time::Duration;
+
+    #[async_std::test]
+    async fn coalescing_blockstore() -> Result<()> {
+        let store = &CoalescingBlockStore::new(MemoryBlockStore::new());
+        bs_retrieval_test::<CoalescingBlockStore<MemoryBlockStore>>(store).await?;
+        bs_duplication_test::<CoalescingBlockStore<MemoryBlockStore>>(store).await?;
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn concurrent_fetches_of_one_block_are_merged() -> Result<()> {
+        let store = std::sync::Arc::new(CoalescingBlockStore::new(
+            InstrumentedBlockStore::default().with_delay(Duration::from_millis(20)),
+        ));
+        let cid = store.put_block(b"Hot block".to_vec(), CODEC_RAW).await?;
+
+        let tasks = (0..50).map(|_| {
+            let store = std::sync::Arc::clone(&store);
+            async_std::task::spawn(async move { store.get_block(&cid).await })
+        });
+        for bytes in futures::future::join_all(tasks).await {
+            assert_eq!(bytes?, b"Hot block".to_vec());
+        }
+        assert_eq!(store.inner.gets(), 1);
+        assert!(store.in_flight.lock().is_empty());
+
+        // Fetches that don't overlap aren't merged
+        store.get_block(&cid).await?;
+        assert_eq!(store.inner.gets(), 2);
+
+        // Failures are reported to the waiting calls too
+        let missing = store.create_cid(b"Missing", CODEC_RAW)?;
+        let (first, second) = futures::join!(store.get_block(&missing), store.get_block(&missing));
+        assert!(matches!(first, Err(BlockStoreError::CIDNotFound(_))));
+        assert!(matches!(second, Err(BlockStoreError::CIDNotFound(_))));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+use crate::{utils::CondSend, BlockStore, BlockStoreError, MAX_BLOCK_SIZE};
+use anyhow::anyhow;
+use bytes::Bytes;
+use libipld::Cid;
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+const RAW_TAG: u8 = 0;
+const COMPRESSED_TAG: u8 = 1;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A block store that wraps another one and zstd-compresses blocks before putting
+/// them into the wrapped store, decompressing them again when they're fetched.
+///
+/// This pays off for compressible file content, like text, which is stored in
+/// large blocks. For lots of small blocks, like encoded directories, see
+/// `DictCompressingBlockStore`.
+///
+/// CIDs are still computed over the uncompressed bytes, so they're the same as
+/// with any other block store, and the wrapped store holds the compressed bytes
+/// under these CIDs. That way, only this block store can read blocks back from
+/// the wrapped store. Blocks that don't get smaller are stored uncompressed.
+///
+/// Since the blocks in the wrapped store don't match their CIDs, it must not
+/// check blocks against their CIDs, e.g. an `FsBlockStore` needs to be created
+/// `with_verification(false)`. Fetching a block from a wrapped store that does
+/// fails with an error saying so.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::{BlockStore, CompressingBlockStore, MemoryBlockStore, CODEC_RAW};
+///
+/// #[async_std::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let store = CompressingBlockStore::new(MemoryBlockStore::new());
+///     let text = "All work and no play makes Jack a dull boy. ".repeat(100);
+///     let cid = store.put_block(text.clone().into_bytes(), CODEC_RAW).await?;
+///
+///     assert_eq!(store.get_block(&cid).await?, text.into_bytes());
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct CompressingBlockStore<B> {
+    inner: B,
+    level: i32,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<B: BlockStore> CompressingBlockStore<B> {
+    /// Wraps given block store, compressing blocks with the default zstd level.
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            level: zstd::DEFAULT_COMPRESSION_LEVEL,
+        }
+    }
+
+    /// Sets the zstd compression level.
+    pub fn with_level(mut self, level: i32) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Returns the wrapped block store.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    fn encode(&self, bytes: &[u8]) -> Result<Vec<u8>, BlockStoreError> {
+        let compressed = zstd::bulk::compress(bytes, self.level).map_err(anyhow::Error::from)?;
+        if compressed.len() < bytes.len() {
+            Ok([&[COMPRESSED_TAG], &compressed[..]].concat())
+        } else {
+            Ok([&[RAW_TAG], bytes].concat())
+        }
+    }
+
+    fn decode(&self, cid: &Cid, encoded: Bytes) -> Result<Bytes, BlockStoreError> {
+        match encoded.first() {
+            Some(&RAW_TAG) => Ok(encoded.slice(1..)),
+            Some(&COMPRESSED_TAG) => Ok(zstd::bulk::decompress(&encoded[1..], MAX_BLOCK_SIZE)
+                .map_err(|e| anyhow!("Can't decompress block {cid}: {e}"))?
+                .into()),
+            _ => Err(anyhow!("Block {cid} wasn't put via a CompressingBlockStore").into()),
+        }
+    }
+}
+
+impl<B: BlockStore> BlockStore for CompressingBlockStore<B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Bytes, BlockStoreError> {
+        let encoded = self.inner.get_block(cid).await.map_err(|e| match e {
+            BlockStoreError::CorruptBlock(cid) => anyhow!(
+                "Block {cid} doesn't match its CID in the wrapped store, \
+                 which must not check blocks against their CIDs"
+            )
+            .into(),
+            e => e,
+        })?;
+        self.decode(cid, encoded)
+    }
+
+    async fn put_block_keyed(
+        &self,
+        cid: Cid,
+        bytes: impl Into<Bytes> + CondSend,
+    ) -> Result<(), BlockStoreError> {
+        let encoded = self.encode(&bytes.into())?;
+        self.inner.put_block_keyed(cid, encoded).await
+    }
+
+    async fn has_block(&self, cid: &Cid) -> Result<bool, BlockStoreError> {
+        self.inner.has_block(cid).await
+    }
+
+    fn create_cid(&self, bytes: &[u8], codec: u64) -> Result<Cid, BlockStoreError> {
+        self.inner.create_cid(bytes, codec)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bs_duplication_test, bs_retrieval_test, AccountingBlockStore, MemoryBlockStore, CODEC_RAW,
+    };
+    use anyhow::Result;
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+    #[async_std::test]
+    async fn compressing_blockstore() -> Result<()> {
+        let store = &CompressingBlockStore::new(MemoryBlockStore::new());
+        bs_retrieval_test::<CompressingBlockStore<MemoryBlockStore>>(store).await?;
+        bs_duplication_test::<CompressingBlockStore<MemoryBlockStore>>(store).await?;
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn blocks_round_trip_with_uncompressed_cids() -> Result<()> {
+        let plain = AccountingBlockStore::new(MemoryBlockStore::new());
+        let compressing =
+            CompressingBlockStore::new(AccountingBlockStore::new(MemoryBlockStore::new()));
+
+        let text = "All work and no play makes Jack a dull boy.\n"
+            .repeat(1000)
+            .into_bytes();
+        let mut noise = vec![0; 4096];
+        StdRng::seed_from_u64(0).fill_bytes(&mut noise);
+
+        for block in [text.clone(), noise.clone(), Vec::new()] {
+            let cid = plain.put_block(block.clone(), CODEC_RAW).await?;
+            assert_eq!(compressing.put_block(block.clone(), CODEC_RAW).await?, cid);
+            assert_eq!(compressing.get_block(&cid).await?, block);
+        }
+
+        let compressing = compressing.into_inner().stats();
+        let plain = plain.stats();
+        assert!(compressing.new_bytes < (noise.len() + text.len() / 10) as u64);
+        assert_eq!(plain.new_bytes, (noise.len() + text.len()) as u64);
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "disk", not(target_arch = "wasm32")))]
+    #[async_std::test]
+    async fn wrapped_stores_must_not_verify_blocks() -> Result<()> {
+        use crate::FsBlockStore;
+
+        let dir = tempfile::tempdir()?;
+        let text = "All work and no play makes Jack a dull boy.\n".repeat(100);
+
+        let store = CompressingBlockStore::new(FsBlockStore::new(dir.path())?);
+        let cid = store
+            .put_block(text.clone().into_bytes(), CODEC_RAW)
+            .await?;
+        let error = store.get_block(&cid).await.unwrap_err();
+        assert!(error.to_string().contains("must not check blocks"));
+
+        let store =
+            CompressingBlockStore::new(FsBlockStore::new(dir.path())?.with_verification(false));
+        assert_eq!(store.get_block(&cid).await?, text.into_bytes());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decompressed_blocks_are_bounded() -> Result<()> {
+        let inner = MemoryBlockStore::new();
+        let bomb = zstd::bulk::compress(&vec![0; MAX_BLOCK_SIZE + 1], 0)?;
+        let cid = inner.create_cid(b"bomb", CODEC_RAW)?;
+        inner
+            .put_block_keyed(cid, [&[COMPRESSED_TAG], &bomb[..]].concat())
+            .await?;
+
+        let store = CompressingBlockStore::new(inner);
+        assert!(store.get_block(&cid).await.is_err());
+
+        Ok(())
+    }
+}
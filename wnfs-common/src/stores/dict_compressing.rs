@@ -0,0 +1,286 @@
+use crate::{utils::CondSend, BlockStore, BlockStoreError, MAX_BLOCK_SIZE};
+use anyhow::anyhow;
+use bytes::Bytes;
+use libipld::Cid;
+use std::io::Read;
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// Blocks up to this size get compressed by a `DictCompressingBlockStore` by default.
+pub const DEFAULT_DICT_COMPRESSION_MAX_BLOCK_SIZE: usize = 16 * 1024;
+
+const RAW_TAG: u8 = 0;
+const COMPRESSED_TAG: u8 = 1;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A block store that wraps another one and compresses small blocks with
+/// a trained zstd dictionary before putting them into the wrapped store.
+///
+/// Compressing each block on its own doesn't get far for small blocks, since
+/// there's little repetition within them. With a dictionary trained on typical
+/// blocks, e.g. via `train_dictionary`, lots of similar small blocks, like
+/// encoded directories and metadata, compress well.
+///
+/// CIDs are still computed over the uncompressed bytes. The wrapped store holds
+/// an encoding of the blocks that only this block store, given the same
+/// dictionary, can read back. Blocks that are larger than the configured maximum,
+/// or that don't get smaller, are stored uncompressed.
+///
+/// Like with a `CompressingBlockStore`, the wrapped store must not check blocks
+/// against their CIDs, e.g. an `FsBlockStore` needs to be created
+/// `with_verification(false)`.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::{
+///     train_dictionary, BlockStore, DictCompressingBlockStore, MemoryBlockStore, CODEC_RAW,
+/// };
+///
+/// #[async_std::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let store = MemoryBlockStore::new();
+///
+///     let mut sample = Vec::new();
+///     for i in 0..200 {
+///         let block = format!(r#"{{"name":"file-{i}.txt","kind":"file","size":{i}}}"#);
+///         sample.push(store.put_block(block.into_bytes(), CODEC_RAW).await?);
+///     }
+///     let dictionary = train_dictionary(&store, &sample, 1024).await?;
+///
+///     let store = DictCompressingBlockStore::new(store, dictionary);
+///     let cid = store.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+///
+///     assert_eq!(store.get_block(&cid).await?, b"Hello".to_vec());
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DictCompressingBlockStore<B> {
+    inner: B,
+    dictionary: Vec<u8>,
+    level: i32,
+    max_block_size: usize,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<B: BlockStore> DictCompressingBlockStore<B> {
+    /// Wraps given block store, compressing small blocks with given zstd dictionary.
+    pub fn new(inner: B, dictionary: Vec<u8>) -> Self {
+        Self {
+            inner,
+            dictionary,
+            level: zstd::DEFAULT_COMPRESSION_LEVEL,
+            max_block_size: DEFAULT_DICT_COMPRESSION_MAX_BLOCK_SIZE,
+        }
+    }
+
+    /// Sets the zstd compression level.
+    pub fn with_level(mut self, level: i32) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Sets the size up to which blocks get compressed.
+    pub fn with_max_block_size(mut self, max_block_size: usize) -> Self {
+        self.max_block_size = max_block_size;
+        self
+    }
+
+    /// The dictionary blocks get compressed with.
+    pub fn dictionary(&self) -> &[u8] {
+        &self.dictionary
+    }
+
+    /// Returns the wrapped block store.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    fn encode(&self, bytes: &[u8]) -> Result<Vec<u8>, BlockStoreError> {
+        if bytes.len() <= self.max_block_size {
+            let compressed = zstd::bulk::Compressor::with_dictionary(self.level, &self.dictionary)
+                .and_then(|mut compressor| compressor.compress(bytes))
+                .map_err(anyhow::Error::from)?;
+
+            if compressed.len() < bytes.len() {
+                return Ok([&[COMPRESSED_TAG], &compressed[..]].concat());
+            }
+        }
+
+        Ok([&[RAW_TAG], bytes].concat())
+    }
+
+    fn decode(&self, cid: &Cid, encoded: Bytes) -> Result<Bytes, BlockStoreError> {
+        match encoded.first() {
+            Some(&RAW_TAG) => Ok(encoded.slice(1..)),
+            Some(&COMPRESSED_TAG) => {
+                // Reads one byte more than allowed, to tell whether the block is too large
+                let mut bytes = Vec::new();
+                zstd::stream::read::Decoder::with_dictionary(&encoded[1..], &self.dictionary)
+                    .and_then(|decoder| {
+                        decoder
+                            .take(MAX_BLOCK_SIZE as u64 + 1)
+                            .read_to_end(&mut bytes)
+                    })
+                    .map_err(|e| anyhow!("Can't decompress block {cid}: {e}"))?;
+                if bytes.len() > MAX_BLOCK_SIZE {
+                    return Err(anyhow!(
+                        "Block {cid} decompresses to more than {MAX_BLOCK_SIZE} bytes"
+                    )
+                    .into());
+                }
+
+                Ok(bytes.into())
+            }
+            _ => Err(anyhow!("Block {cid} wasn't put via a DictCompressingBlockStore").into()),
+        }
+    }
+}
+
+impl<B: BlockStore> BlockStore for DictCompressingBlockStore<B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Bytes, BlockStoreError> {
+        let encoded = self.inner.get_block(cid).await.map_err(|e| match e {
+            BlockStoreError::CorruptBlock(cid) => anyhow!(
+                "Block {cid} doesn't match its CID in the wrapped store, \
+                 which must not check blocks against their CIDs"
+            )
+            .into(),
+            e => e,
+        })?;
+        self.decode(cid, encoded)
+    }
+
+    async fn put_block_keyed(
+        &self,
+        cid: Cid,
+        bytes: impl Into<Bytes> + CondSend,
+    ) -> Result<(), BlockStoreError> {
+        let encoded = self.encode(&bytes.into())?;
+        self.inner.put_block_keyed(cid, encoded).await
+    }
+
+    async fn has_block(&self, cid: &Cid) -> Result<bool, BlockStoreError> {
+        self.inner.has_block(cid).await
+    }
+
+    fn create_cid(&self, bytes: &[u8], codec: u64) -> Result<Cid, BlockStoreError> {
+        self.inner.create_cid(bytes, codec)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Trains a zstd dictionary of at most `max_size` bytes on the blocks with given CIDs,
+/// for use with a `DictCompressingBlockStore`.
+///
+/// The sample should be representative for the blocks that are going to be stored.
+/// Training fails if the sample is too small, a few hundred blocks are a good start.
+pub async fn train_dictionary(
+    store: &impl BlockStore,
+    sample: &[Cid],
+    max_size: usize,
+) -> Result<Vec<u8>, BlockStoreError> {
+    let mut blocks = Vec::with_capacity(sample.len());
+    for cid in sample {
+        blocks.push(store.get_block(cid).await?);
+    }
+
+    Ok(zstd::dict::from_samples(&blocks, max_size).map_err(anyhow::Error::from)?)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bs_duplication_test, bs_retrieval_test, AccountingBlockStore, MemoryBlockStore, CODEC_RAW,
+    };
+    use anyhow::Result;
+
+    fn small_block(i: usize) -> Vec<u8> {
+        format!(
+            r#"{{"name":"file-{i}.txt","kind":"file","size":{},"modified":"2024-01-01T00:00:{:02}Z"}}"#,
+            i * 31,
+            i % 60
+        )
+        .into_bytes()
+    }
+
+    async fn dictionary() -> Result<Vec<u8>> {
+        let store = &MemoryBlockStore::new();
+        let mut sample = Vec::new();
+        for i in 0..500 {
+            sample.push(store.put_block(small_block(i), CODEC_RAW).await?);
+        }
+
+        Ok(train_dictionary(store, &sample, 4096).await?)
+    }
+
+    #[async_std::test]
+    async fn dict_compressing_blockstore() -> Result<()> {
+        let store = &DictCompressingBlockStore::new(MemoryBlockStore::new(), dictionary().await?);
+        bs_retrieval_test::<DictCompressingBlockStore<MemoryBlockStore>>(store).await?;
+        bs_duplication_test::<DictCompressingBlockStore<MemoryBlockStore>>(store).await?;
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn small_blocks_shrink_and_cids_stay_the_same() -> Result<()> {
+        let dictionary = dictionary().await?;
+        let plain = AccountingBlockStore::new(MemoryBlockStore::new());
+        let compressing = DictCompressingBlockStore::new(
+            AccountingBlockStore::new(MemoryBlockStore::new()),
+            dictionary,
+        );
+
+        for i in 1000..1100 {
+            let block = small_block(i);
+            let cid = plain.put_block(block.clone(), CODEC_RAW).await?;
+            assert_eq!(compressing.put_block(block.clone(), CODEC_RAW).await?, cid);
+            assert_eq!(compressing.get_block(&cid).await?, block);
+        }
+
+        let large = vec![7; DEFAULT_DICT_COMPRESSION_MAX_BLOCK_SIZE + 1];
+        let cid = compressing.put_block(large.clone(), CODEC_RAW).await?;
+        assert_eq!(compressing.get_block(&cid).await?, large);
+
+        let compressing = compressing.into_inner().stats();
+        let plain = plain.stats();
+        assert!((compressing.new_bytes - large.len() as u64 - 1) * 2 < plain.new_bytes);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decompressed_blocks_are_bounded() -> Result<()> {
+        let dictionary = dictionary().await?;
+        let inner = MemoryBlockStore::new();
+        let bomb = zstd::bulk::Compressor::with_dictionary(0, &dictionary)?
+            .compress(&vec![0; MAX_BLOCK_SIZE + 1])?;
+        let cid = inner.create_cid(b"bomb", CODEC_RAW)?;
+        inner
+            .put_block_keyed(cid, [&[COMPRESSED_TAG], &bomb[..]].concat())
+            .await?;
+
+        let store = DictCompressingBlockStore::new(inner, dictionary);
+        let error = store.get_block(&cid).await.unwrap_err();
+        assert!(error.to_string().contains("more than"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{utils::CondSend, BlockStore, BlockStoreError, HashAlgorithm};
+use bytes::Bytes;
+use libipld::Cid;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A block store that wraps another one and computes the CIDs of blocks put into
+/// it with a given hash algorithm, instead of the default BLAKE3.
+///
+/// Everything storing blocks via `put_block` picks up the hash algorithm, so using
+/// this as the store for a file system hashes both nodes and file content with it.
+/// Blocks are loaded by their CID as usual, no matter which algorithm it records.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::{BlockStore, HashAlgorithm, HashingBlockStore, MemoryBlockStore, CODEC_RAW};
+///
+/// #[async_std::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let store = HashingBlockStore::new(MemoryBlockStore::new(), HashAlgorithm::Sha2_256);
+///     let cid = store.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+///
+///     assert_eq!(cid.hash().code(), u64::from(HashAlgorithm::Sha2_256.code()));
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct HashingBlockStore<B> {
+    inner: B,
+    algorithm: HashAlgorithm,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<B: BlockStore> HashingBlockStore<B> {
+    /// Wraps given block store, computing CIDs with given hash algorithm.
+    pub fn new(inner: B, algorithm: HashAlgorithm) -> Self {
+        Self { inner, algorithm }
+    }
+
+    /// The hash algorithm CIDs get computed with.
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    /// Returns the wrapped block store.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: BlockStore> BlockStore for HashingBlockStore<B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Bytes, BlockStoreError> {
+        self.inner.get_block(cid).await
+    }
+
+    async fn put_block_keyed(
+        &self,
+        cid: Cid,
+        bytes: impl Into<Bytes> + CondSend,
+    ) -> Result<(), BlockStoreError> {
+        self.inner.put_block_keyed(cid, bytes).await
+    }
+
+    async fn has_block(&self, cid: &Cid) -> Result<bool, BlockStoreError> {
+        self.inner.has_block(cid).await
+    }
+
+    fn create_cid(&self, bytes: &[u8], codec: u64) -> Result<Cid, BlockStoreError> {
+        self.algorithm.create_cid(bytes, codec)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bs_duplication_test, bs_retrieval_test, MemoryBlockStore, CODEC_RAW};
+    use anyhow::Result;
+
+    #[async_std::test]
+    async fn hashing_blockstore() -> Result<()> {
+        let store = &HashingBlockStore::new(MemoryBlockStore::new(), HashAlgorithm::Sha3_256);
+        bs_retrieval_test::<HashingBlockStore<MemoryBlockStore>>(store).await?;
+        bs_duplication_test::<HashingBlockStore<MemoryBlockStore>>(store).await?;
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn differently_hashed_blocks_coexist() -> Result<()> {
+        let backend = MemoryBlockStore::new();
+        let mut cids = Vec::new();
+        for algorithm in [
+            HashAlgorithm::Sha2_256,
+            HashAlgorithm::Sha3_256,
+            HashAlgorithm::Blake3,
+        ] {
+            let store = HashingBlockStore::new(&backend, algorithm);
+            let cid = store.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+            assert_eq!(cid.hash().code(), u64::from(algorithm.code()));
+            cids.push(cid);
+        }
+
+        assert_eq!(cids[2], backend.create_cid(b"Hello", CODEC_RAW)?);
+        for cid in &cids {
+            assert_eq!(backend.get_block(cid).await?, b"Hello".to_vec());
+        }
+
+        Ok(())
+    }
+}
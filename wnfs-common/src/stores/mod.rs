@@ -0,0 +1,27 @@
+//! Block store implementations that wrap other block stores to add behavior.
+
+mod accounting;
+mod caching;
+mod coalescing;
+#[cfg(feature = "compress")]
+mod compressing;
+#[cfg(feature = "dict-compression")]
+mod dict_compressing;
+mod hashing;
+mod recording;
+mod retrying;
+mod timeout;
+mod transaction;
+
+pub use accounting::*;
+pub use caching::*;
+pub use coalescing::*;
+#[cfg(feature = "compress")]
+pub use compressing::*;
+#[cfg(feature = "dict-compression")]
+pub use dict_compressing::*;
+pub use hashing::*;
+pub use recording::*;
+pub use retrying::*;
+pub use timeout::*;
+pub use transaction::*;
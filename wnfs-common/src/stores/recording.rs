@@ -0,0 +1,316 @@
+use crate::{utils::CondSend, BlockStore, BlockStoreError};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use libipld::Cid;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A block store operation that was recorded by a `RecordingBlockStore`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockOperation {
+    /// A call to `get_block`. Has the bytes that were returned, if any.
+    Get(Option<Bytes>),
+    /// A call to `put_block` or `put_block_keyed` with the bytes that were put.
+    Put(Bytes),
+    /// A call to `has_block`. Has its result, if it didn't fail.
+    Has(Option<bool>),
+}
+
+/// A single entry in the access log of a `RecordingBlockStore`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockAccess {
+    /// The CID of the block that was accessed.
+    pub cid: Cid,
+    /// What kind of access it was.
+    pub operation: BlockOperation,
+    /// When the access happened.
+    pub timestamp: DateTime<Utc>,
+}
+
+/// A block store that wraps another one and records all block accesses
+/// going through it into an in-memory access log.
+///
+/// The log is serializable, so it can be persisted to a file and later
+/// be used to reproduce the exact same interactions via a `ReplayBlockStore`.
+///
+/// Recording can be toggled at runtime. When it's turned off, this block store
+/// just passes calls through to the wrapped block store.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::{BlockStore, MemoryBlockStore, RecordingBlockStore, CODEC_RAW};
+///
+/// #[async_std::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let store = RecordingBlockStore::new(MemoryBlockStore::new());
+///
+///     let cid = store.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+///     store.get_block(&cid).await?;
+///
+///     store.set_recording(false);
+///     store.get_block(&cid).await?;
+///
+///     assert_eq!(store.log().len(), 2);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RecordingBlockStore<B> {
+    inner: B,
+    recording: AtomicBool,
+    log: Mutex<Vec<BlockAccess>>,
+}
+
+/// A block store that serves blocks from the access log of a `RecordingBlockStore`.
+///
+/// Blocks that were returned by `get_block` calls or written via `put_block` calls
+/// in the recording can be read. `has_block` returns the recorded result, if there
+/// was one, otherwise whether the block's bytes are known.
+///
+/// Blocks put into this store during replay become readable too.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::{
+///     BlockStore, MemoryBlockStore, RecordingBlockStore, ReplayBlockStore, CODEC_RAW,
+/// };
+///
+/// #[async_std::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let store = RecordingBlockStore::new(MemoryBlockStore::new());
+///     let cid = store.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+///
+///     let replay = ReplayBlockStore::new(store.log());
+///
+///     assert_eq!(replay.get_block(&cid).await?, b"Hello".to_vec());
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct ReplayBlockStore {
+    log: Vec<BlockAccess>,
+    blocks: Mutex<HashMap<Cid, Bytes>>,
+    has_results: HashMap<Cid, bool>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<B: BlockStore> RecordingBlockStore<B> {
+    /// Wraps given block store, with recording turned on.
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            recording: AtomicBool::new(true),
+            log: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Turns recording on or off.
+    pub fn set_recording(&self, recording: bool) {
+        self.recording.store(recording, Ordering::Relaxed);
+    }
+
+    /// Whether block accesses are currently being recorded.
+    pub fn is_recording(&self) -> bool {
+        self.recording.load(Ordering::Relaxed)
+    }
+
+    /// Returns a copy of the access log recorded so far.
+    pub fn log(&self) -> Vec<BlockAccess> {
+        self.log.lock().clone()
+    }
+
+    /// Returns the access log recorded so far and clears it.
+    pub fn take_log(&self) -> Vec<BlockAccess> {
+        std::mem::take(&mut *self.log.lock())
+    }
+
+    /// Returns the wrapped block store.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    fn record(&self, cid: Cid, operation: impl FnOnce() -> BlockOperation) {
+        if self.is_recording() {
+            self.log.lock().push(BlockAccess {
+                cid,
+                operation: operation(),
+                timestamp: Utc::now(),
+            });
+        }
+    }
+}
+
+impl<B: BlockStore> BlockStore for RecordingBlockStore<B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Bytes, BlockStoreError> {
+        let result = self.inner.get_block(cid).await;
+        self.record(*cid, || BlockOperation::Get(result.as_ref().ok().cloned()));
+        result
+    }
+
+    async fn put_block(
+        &self,
+        bytes: impl Into<Bytes> + CondSend,
+        codec: u64,
+    ) -> Result<Cid, BlockStoreError> {
+        let bytes = bytes.into();
+        let cid = self.inner.put_block(bytes.clone(), codec).await?;
+        self.record(cid, || BlockOperation::Put(bytes));
+        Ok(cid)
+    }
+
+    async fn put_block_keyed(
+        &self,
+        cid: Cid,
+        bytes: impl Into<Bytes> + CondSend,
+    ) -> Result<(), BlockStoreError> {
+        let bytes = bytes.into();
+        self.inner.put_block_keyed(cid, bytes.clone()).await?;
+        self.record(cid, || BlockOperation::Put(bytes));
+        Ok(())
+    }
+
+    async fn has_block(&self, cid: &Cid) -> Result<bool, BlockStoreError> {
+        let result = self.inner.has_block(cid).await;
+        self.record(*cid, || BlockOperation::Has(result.as_ref().ok().copied()));
+        result
+    }
+
+    fn create_cid(&self, bytes: &[u8], codec: u64) -> Result<Cid, BlockStoreError> {
+        self.inner.create_cid(bytes, codec)
+    }
+}
+
+impl ReplayBlockStore {
+    /// Creates a block store serving blocks from given access log.
+    pub fn new(log: Vec<BlockAccess>) -> Self {
+        let mut blocks = HashMap::new();
+        let mut has_results = HashMap::new();
+        for access in log.iter() {
+            match &access.operation {
+                BlockOperation::Get(Some(bytes)) | BlockOperation::Put(bytes) => {
+                    blocks.entry(access.cid).or_insert_with(|| bytes.clone());
+                }
+                BlockOperation::Has(Some(result)) => {
+                    has_results.entry(access.cid).or_insert(*result);
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            log,
+            blocks: Mutex::new(blocks),
+            has_results,
+        }
+    }
+
+    /// The access log this block store is replaying.
+    pub fn log(&self) -> &[BlockAccess] {
+        &self.log
+    }
+}
+
+impl BlockStore for ReplayBlockStore {
+    async fn get_block(&self, cid: &Cid) -> Result<Bytes, BlockStoreError> {
+        self.blocks
+            .lock()
+            .get(cid)
+            .cloned()
+            .ok_or(BlockStoreError::CIDNotFound(*cid))
+    }
+
+    async fn put_block_keyed(
+        &self,
+        cid: Cid,
+        bytes: impl Into<Bytes> + CondSend,
+    ) -> Result<(), BlockStoreError> {
+        self.blocks.lock().insert(cid, bytes.into());
+        Ok(())
+    }
+
+    async fn has_block(&self, cid: &Cid) -> Result<bool, BlockStoreError> {
+        match self.has_results.get(cid) {
+            Some(result) => Ok(*result),
+            None => Ok(self.blocks.lock().contains_key(cid)),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bs_duplication_test, bs_retrieval_test, decode, encode, libipld::cbor::DagCborCodec,
+        MemoryBlockStore, CODEC_RAW,
+    };
+    use anyhow::Result;
+
+    #[async_std::test]
+    async fn recording_blockstore() -> Result<()> {
+        let store = &RecordingBlockStore::new(MemoryBlockStore::new());
+        bs_retrieval_test::<RecordingBlockStore<MemoryBlockStore>>(store).await?;
+        bs_duplication_test::<RecordingBlockStore<MemoryBlockStore>>(store).await?;
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn recorded_log_can_be_persisted_and_replayed() -> Result<()> {
+        let store = RecordingBlockStore::new(MemoryBlockStore::new());
+        let cid = store.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+        let missing = store.create_cid(b"Missing", CODEC_RAW)?;
+        assert!(store.get_block(&missing).await.is_err());
+        assert!(store.has_block(&cid).await?);
+
+        let log = store.take_log();
+        assert_eq!(
+            log.iter().map(|a| a.cid).collect::<Vec<_>>(),
+            vec![cid, missing, cid]
+        );
+        assert!(store.log().is_empty());
+
+        let persisted = encode(&log, DagCborCodec)?;
+        let replay = ReplayBlockStore::new(decode(&persisted, DagCborCodec)?);
+
+        assert_eq!(replay.log(), log.as_slice());
+        assert_eq!(replay.get_block(&cid).await?, b"Hello".to_vec());
+        assert!(replay.get_block(&missing).await.is_err());
+        assert!(replay.has_block(&cid).await?);
+        assert!(!replay.has_block(&missing).await?);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn recording_can_be_turned_off() -> Result<()> {
+        let store = RecordingBlockStore::new(MemoryBlockStore::new());
+        store.set_recording(false);
+        let cid = store.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+        store.get_block(&cid).await?;
+        assert!(store.log().is_empty());
+
+        store.set_recording(true);
+        store.get_block(&cid).await?;
+        assert_eq!(store.log().len(), 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,255 @@
+use crate::{
+    utils::{CondSend, CondSync},
+    BlockStore, BlockStoreError,
+};
+use bytes::Bytes;
+use futures::Future;
+use libipld::Cid;
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Waits for a while, for block stores that need timers, like `RetryingBlockStore`
+/// or `TimeoutBlockStore`.
+///
+/// This keeps block stores independent of the async runtime. Functions returning
+/// a future already implement it, like `async_std::task::sleep` or
+/// `tokio::time::sleep`.
+pub trait Sleep: CondSync {
+    /// Returns a future that completes after given duration.
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + CondSend;
+}
+
+/// A block store that wraps another one and retries failed calls to it, with
+/// exponentially growing delays between attempts.
+///
+/// Only errors the retry predicate deems transient are retried, by default
+/// `BlockStoreError::Custom`, which is what backends report I/O or network
+/// errors as, and `BlockStoreError::Timeout`, so this can wrap a
+/// `TimeoutBlockStore`. Other errors, like missing or corrupt blocks, are
+/// returned right away, as is the last error once all attempts failed.
+///
+/// Retries don't keep any state outside of the retried call, so dropping a call
+/// while it's waiting for its next attempt simply stops retrying.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use wnfs_common::{BlockStore, MemoryBlockStore, RetryingBlockStore, CODEC_RAW};
+///
+/// #[async_std::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let store = RetryingBlockStore::new(MemoryBlockStore::new(), async_std::task::sleep)
+///         .with_max_attempts(5)
+///         .with_backoff(Duration::from_millis(50), Duration::from_secs(2));
+///     let cid = store.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+///
+///     assert_eq!(store.get_block(&cid).await?, b"Hello".to_vec());
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RetryingBlockStore<B, S> {
+    inner: B,
+    sleep: S,
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    is_retryable: fn(&BlockStoreError) -> bool,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<F, Fut> Sleep for F
+where
+    F: Fn(Duration) -> Fut + CondSync,
+    Fut: Future<Output = ()> + CondSend,
+{
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + CondSend {
+        self(duration)
+    }
+}
+
+impl<B: BlockStore, S: Sleep> RetryingBlockStore<B, S> {
+    /// Wraps given block store, waiting between attempts with given sleep function.
+    ///
+    /// Calls are attempted 3 times, with delays starting at 100ms and growing up
+    /// to 5s between attempts.
+    pub fn new(inner: B, sleep: S) -> Self {
+        Self {
+            inner,
+            sleep,
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            is_retryable: is_transient,
+        }
+    }
+
+    /// Sets how often a call is attempted in total, at least once.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Sets the delay before the first retry, which doubles with every further
+    /// retry up to given maximum. Delays are randomly shortened by up to half,
+    /// so clients that failed together don't all retry at the same time.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Sets the predicate that decides which errors are worth retrying.
+    pub fn with_retryable(mut self, is_retryable: fn(&BlockStoreError) -> bool) -> Self {
+        self.is_retryable = is_retryable;
+        self
+    }
+
+    /// Returns the wrapped block store.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    async fn retry<T, Fut>(&self, attempt: impl Fn() -> Fut) -> Result<T, BlockStoreError>
+    where
+        Fut: Future<Output = Result<T, BlockStoreError>>,
+    {
+        let mut backoff = self.initial_backoff;
+        for _ in 1..self.max_attempts {
+            match attempt().await {
+                Err(e) if (self.is_retryable)(&e) => {
+                    self.sleep.sleep(jitter(backoff)).await;
+                    backoff = (backoff * 2).min(self.max_backoff);
+                }
+                result => return result,
+            }
+        }
+
+        attempt().await
+    }
+}
+
+impl<B: BlockStore, S: Sleep> BlockStore for RetryingBlockStore<B, S> {
+    async fn get_block(&self, cid: &Cid) -> Result<Bytes, BlockStoreError> {
+        self.retry(|| self.inner.get_block(cid)).await
+    }
+
+    async fn put_block_keyed(
+        &self,
+        cid: Cid,
+        bytes: impl Into<Bytes> + CondSend,
+    ) -> Result<(), BlockStoreError> {
+        let bytes = bytes.into();
+        self.retry(|| self.inner.put_block_keyed(cid, bytes.clone()))
+            .await
+    }
+
+    async fn has_block(&self, cid: &Cid) -> Result<bool, BlockStoreError> {
+        self.retry(|| self.inner.has_block(cid)).await
+    }
+
+    fn create_cid(&self, bytes: &[u8], codec: u64) -> Result<Cid, BlockStoreError> {
+        self.inner.create_cid(bytes, codec)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// The default retry predicate of `RetryingBlockStore`.
+fn is_transient(error: &BlockStoreError) -> bool {
+    matches!(
+        error,
+        BlockStoreError::Custom(_) | BlockStoreError::Timeout(_)
+    )
+}
+
+/// Shortens given delay by a random amount of up to half of it.
+fn jitter(delay: Duration) -> Duration {
+    // Randomly keyed, so this is random enough without depending on an RNG
+    let random = RandomState::new().build_hasher().finish();
+    delay.mul_f64(1.0 - (random as f64 / u64::MAX as f64) / 2.0)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bs_duplication_test, bs_retrieval_test, utils::InstrumentedBlockStore, MemoryBlockStore,
+        CODEC_RAW,
+    };
+    use anyhow::Result;
+    use futures::future::{self, Ready};
+    use std::sync::Mutex;
+
+    #[async_std::test]
+    async fn retrying_blockstore() -> Result<()> {
+        type Store = RetryingBlockStore<MemoryBlockStore, fn(Duration) -> Ready<()>>;
+        let store = &Store::new(MemoryBlockStore::new(), |_| future::ready(()));
+        bs_retrieval_test::<Store>(store).await?;
+        bs_duplication_test::<Store>(store).await?;
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn flaky_calls_succeed_on_the_third_attempt() -> Result<()> {
+        let delays = &Mutex::new(Vec::new());
+        let sleep = |delay| {
+            delays.lock().unwrap().push(delay);
+            async {}
+        };
+        let flaky = InstrumentedBlockStore::default();
+        flaky.fail_next(2);
+        let store = RetryingBlockStore::new(flaky, sleep)
+            .with_backoff(Duration::from_millis(100), Duration::from_secs(1));
+
+        let cid = store.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+        assert_eq!(store.inner.calls(), 3);
+
+        let delays = delays.lock().unwrap().clone();
+        assert_eq!(delays.len(), 2);
+        assert!((50..=100).contains(&delays[0].as_millis()));
+        assert!((100..=200).contains(&delays[1].as_millis()));
+
+        let store = store.into_inner();
+        assert_eq!(store.get_block(&cid).await?, b"Hello".to_vec());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn permanent_and_final_errors_are_returned() -> Result<()> {
+        let flaky = InstrumentedBlockStore::default();
+        flaky.fail_next(5);
+        let store = RetryingBlockStore::new(flaky, |_| async {});
+        let err = store.put_block(b"Hello".to_vec(), CODEC_RAW).await;
+        assert!(matches!(err, Err(BlockStoreError::Custom(_))));
+        assert_eq!(store.inner.calls(), 3);
+
+        let store = RetryingBlockStore::new(InstrumentedBlockStore::default(), |_| async {});
+        let cid = store.create_cid(b"Missing", CODEC_RAW)?;
+        let err = store.get_block(&cid).await;
+        assert!(matches!(err, Err(BlockStoreError::CIDNotFound(_))));
+        assert_eq!(store.inner.calls(), 1);
+
+        Ok(())
+    }
+}
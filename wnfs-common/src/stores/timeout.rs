@@ -0,0 +1,158 @@
+use crate::{utils::CondSend, BlockStore, BlockStoreError, Sleep};
+use bytes::Bytes;
+use futures::{
+    future::{self, Either},
+    Future,
+};
+use libipld::Cid;
+use std::{pin::pin, time::Duration};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A block store that wraps another one and fails calls to it with
+/// `BlockStoreError::Timeout` once they take longer than a given timeout.
+///
+/// The timeout applies to every call on its own, so a traversal loading many
+/// blocks can take as long as it needs, as long as no store call hangs. Calls
+/// that time out get dropped, it's up to the wrapped store to cancel them.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use wnfs_common::{BlockStore, MemoryBlockStore, TimeoutBlockStore, CODEC_RAW};
+///
+/// #[async_std::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let store = TimeoutBlockStore::new(
+///         MemoryBlockStore::new(),
+///         Duration::from_secs(10),
+///         async_std::task::sleep,
+///     );
+///     let cid = store.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+///
+///     assert_eq!(store.get_block(&cid).await?, b"Hello".to_vec());
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TimeoutBlockStore<B, S> {
+    inner: B,
+    timeout: Duration,
+    sleep: S,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<B: BlockStore, S: Sleep> TimeoutBlockStore<B, S> {
+    /// Wraps given block store, timing calls out after given duration, as measured
+    /// with given sleep function.
+    pub fn new(inner: B, timeout: Duration, sleep: S) -> Self {
+        Self {
+            inner,
+            timeout,
+            sleep,
+        }
+    }
+
+    /// The duration after which calls time out.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns the wrapped block store.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    async fn with_timeout<T>(
+        &self,
+        call: impl Future<Output = Result<T, BlockStoreError>>,
+    ) -> Result<T, BlockStoreError> {
+        match future::select(pin!(call), pin!(self.sleep.sleep(self.timeout))).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(BlockStoreError::Timeout(self.timeout)),
+        }
+    }
+}
+
+impl<B: BlockStore, S: Sleep> BlockStore for TimeoutBlockStore<B, S> {
+    async fn get_block(&self, cid: &Cid) -> Result<Bytes, BlockStoreError> {
+        self.with_timeout(self.inner.get_block(cid)).await
+    }
+
+    async fn put_block_keyed(
+        &self,
+        cid: Cid,
+        bytes: impl Into<Bytes> + CondSend,
+    ) -> Result<(), BlockStoreError> {
+        self.with_timeout(self.inner.put_block_keyed(cid, bytes))
+            .await
+    }
+
+    async fn has_block(&self, cid: &Cid) -> Result<bool, BlockStoreError> {
+        self.with_timeout(self.inner.has_block(cid)).await
+    }
+
+    fn create_cid(&self, bytes: &[u8], codec: u64) -> Result<Cid, BlockStoreError> {
+        self.inner.create_cid(bytes, codec)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bs_duplication_test, bs_retrieval_test, utils::InstrumentedBlockStore, MemoryBlockStore,
+        RetryingBlockStore, CODEC_RAW,
+    };
+    use anyhow::Result;
+    use futures::future::{self, Pending};
+
+    #[async_std::test]
+    async fn timeout_blockstore() -> Result<()> {
+        // A timer that never fires, so no call times out
+        type Store = TimeoutBlockStore<MemoryBlockStore, fn(Duration) -> Pending<()>>;
+        let store = &Store::new(MemoryBlockStore::new(), Duration::from_secs(10), |_| {
+            future::pending()
+        });
+        bs_retrieval_test::<Store>(store).await?;
+        bs_duplication_test::<Store>(store).await?;
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn hanging_calls_time_out() -> Result<()> {
+        let timeout = Duration::from_millis(20);
+        let store = TimeoutBlockStore::new(
+            InstrumentedBlockStore::default(),
+            timeout,
+            async_std::task::sleep,
+        );
+        let cid = store.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+
+        store.inner.stall_next_get();
+        let err = store.get_block(&cid).await;
+        assert!(matches!(err, Err(BlockStoreError::Timeout(t)) if t == timeout));
+
+        // Each call gets the whole timeout, no matter how long earlier ones took
+        assert_eq!(store.get_block(&cid).await?, b"Hello".to_vec());
+
+        // Timed out calls are retried by default
+        store.inner.stall_next_get();
+        let store = RetryingBlockStore::new(store, |_| async {});
+        assert_eq!(store.get_block(&cid).await?, b"Hello".to_vec());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{utils::CondSend, BlockStore, BlockStoreError};
+use bytes::Bytes;
+use libipld::Cid;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A block store that wraps another one and keeps all blocks put into it in
+/// memory, until they're written to the wrapped store with `commit`.
+///
+/// Blocks put into the transaction can be read back from it right away, all
+/// other blocks are read from the wrapped store. If a sequence of operations
+/// using the transaction as their store fails midway, the wrapped store stays
+/// untouched as long as `commit` isn't called.
+///
+/// Dropping a transaction or calling `rollback` discards the blocks that
+/// weren't committed.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::{BlockStore, MemoryBlockStore, Transaction, CODEC_RAW};
+///
+/// #[async_std::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let store = MemoryBlockStore::new();
+///     let transaction = Transaction::new(&store);
+///     let cid = transaction.put_block(b"Hello".to_vec(), CODEC_RAW).await?;
+///
+///     assert!(transaction.has_block(&cid).await?);
+///     assert!(!store.has_block(&cid).await?);
+///
+///     transaction.commit().await?;
+///
+///     assert!(store.has_block(&cid).await?);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Transaction<B> {
+    inner: B,
+    staged: Mutex<HashMap<Cid, Bytes>>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<B: BlockStore> Transaction<B> {
+    /// Starts a transaction on given block store.
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            staged: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Writes all blocks put into this transaction so far to the wrapped store.
+    ///
+    /// If writing a block fails, the blocks that weren't written yet stay in the
+    /// transaction, so committing can be retried. Blocks that got written before
+    /// the failure stay in the wrapped store, but aren't referenced by anything
+    /// already there.
+    pub async fn commit(&self) -> Result<(), BlockStoreError> {
+        let staged = self
+            .staged
+            .lock()
+            .iter()
+            .map(|(cid, bytes)| (*cid, bytes.clone()))
+            .collect::<Vec<_>>();
+
+        for (cid, bytes) in staged {
+            self.inner.put_block_keyed(cid, bytes).await?;
+            self.staged.lock().remove(&cid);
+        }
+
+        Ok(())
+    }
+
+    /// Discards all blocks put into this transaction that weren't committed.
+    pub fn rollback(&self) {
+        self.staged.lock().clear();
+    }
+
+    /// How many blocks were put into this transaction and not committed yet.
+    pub fn staged_len(&self) -> usize {
+        self.staged.lock().len()
+    }
+
+    /// Returns the wrapped block store, discarding the blocks that weren't committed.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: BlockStore> BlockStore for Transaction<B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Bytes, BlockStoreError> {
+        let staged = self.staged.lock().get(cid).cloned();
+        match staged {
+            Some(bytes) => Ok(bytes),
+            None => self.inner.get_block(cid).await,
+        }
+    }
+
+    async fn put_block_keyed(
+        &self,
+        cid: Cid,
+        bytes: impl Into<Bytes> + CondSend,
+    ) -> Result<(), BlockStoreError> {
+        self.staged.lock().insert(cid, bytes.into());
+        Ok(())
+    }
+
+    async fn has_block(&self, cid: &Cid) -> Result<bool, BlockStoreError> {
+        if self.staged.lock().contains_key(cid) {
+            return Ok(true);
+        }
+
+        self.inner.has_block(cid).await
+    }
+
+    fn create_cid(&self, bytes: &[u8], codec: u64) -> Result<Cid, BlockStoreError> {
+        self.inner.create_cid(bytes, codec)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bs_duplication_test, bs_retrieval_test, BlockOperation, MemoryBlockStore,
+        RecordingBlockStore, CODEC_RAW,
+    };
+    use anyhow::Result;
+
+    #[async_std::test]
+    async fn transaction_blockstore() -> Result<()> {
+        let store = &Transaction::new(MemoryBlockStore::new());
+        bs_retrieval_test::<Transaction<MemoryBlockStore>>(store).await?;
+        bs_duplication_test::<Transaction<MemoryBlockStore>>(store).await?;
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn aborted_transactions_write_nothing_to_the_backend() -> Result<()> {
+        let backend = RecordingBlockStore::new(MemoryBlockStore::new());
+        let existing = backend.put_block(b"existing".to_vec(), CODEC_RAW).await?;
+        let puts = |backend: &RecordingBlockStore<MemoryBlockStore>| {
+            backend
+                .log()
+                .into_iter()
+                .filter(|access| matches!(access.operation, BlockOperation::Put(_)))
+                .count()
+        };
+
+        let transaction = Transaction::new(&backend);
+        let staged = transaction.put_block(b"staged".to_vec(), CODEC_RAW).await?;
+        assert_eq!(transaction.get_block(&staged).await?, b"staged".to_vec());
+        assert_eq!(
+            transaction.get_block(&existing).await?,
+            b"existing".to_vec()
+        );
+        transaction.rollback();
+        assert!(!transaction.has_block(&staged).await?);
+
+        {
+            let dropped = Transaction::new(&backend);
+            dropped.put_block(b"dropped".to_vec(), CODEC_RAW).await?;
+        }
+        assert_eq!(puts(&backend), 1);
+
+        let committed = Transaction::new(&backend);
+        let cid = committed
+            .put_block(b"committed".to_vec(), CODEC_RAW)
+            .await?;
+        committed.commit().await?;
+        assert_eq!(committed.staged_len(), 0);
+        assert!(backend.has_block(&cid).await?);
+        assert_eq!(puts(&backend), 2);
+
+        Ok(())
+    }
+}
@@ -1,7 +1,6 @@
 use super::Arc;
-use crate::HashOutput;
+use crate::{blockstore::SharedBlocks, HashOutput};
 use anyhow::Result;
-use bytes::Bytes;
 use futures::{AsyncRead, AsyncReadExt};
 use libipld::{Cid, IpldCodec};
 use parking_lot::Mutex;
@@ -80,15 +79,93 @@ pub fn to_hash_output(bytes: &[u8]) -> HashOutput {
     nibbles
 }
 
+/// Returns the longest directory path shared by all given paths,
+/// i.e. the longest common prefix of the paths' parent directories.
+///
+/// This is the root of the smallest subtree that covers changes to all given paths.
+/// Returns an empty path for an empty list of paths.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::utils;
+///
+/// let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+///
+/// assert_eq!(
+///     utils::common_prefix(&[path("docs/drafts/a.md"), path("docs/drafts/b.md")]),
+///     path("docs/drafts")
+/// );
+/// assert_eq!(
+///     utils::common_prefix(&[path("docs/drafts/a.md"), path("docs/b.md")]),
+///     path("docs")
+/// );
+/// assert_eq!(utils::common_prefix(&[path("docs/a.md")]), path("docs"));
+/// assert!(utils::common_prefix(&[]).is_empty());
+/// ```
+pub fn common_prefix(paths: &[Vec<String>]) -> Vec<String> {
+    let mut parents = paths
+        .iter()
+        .map(|path| path.split_last().map_or(&path[..], |(_, parent)| parent));
+
+    let Some(first) = parents.next() else {
+        return Vec::new();
+    };
+
+    let length = parents.fold(first.len(), |length, parent| {
+        first
+            .iter()
+            .zip(parent)
+            .take(length)
+            .take_while(|(a, b)| a == b)
+            .count()
+    });
+
+    first[..length].to_vec()
+}
+
+/// Whether the first path is a strict ancestor of the second, i.e. a proper prefix of it.
+///
+/// A path isn't its own ancestor.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::utils;
+///
+/// let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+///
+/// assert!(utils::is_ancestor(&path("docs"), &path("docs/drafts/a.md")));
+/// assert!(utils::is_ancestor(&[], &path("docs")));
+/// assert!(!utils::is_ancestor(&path("docs"), &path("docs")));
+/// assert!(!utils::is_ancestor(&path("docs"), &path("documents/a.md")));
+/// ```
+pub fn is_ancestor(ancestor: &[String], descendant: &[String]) -> bool {
+    ancestor.len() < descendant.len() && descendant.starts_with(ancestor)
+}
+
 /// Tries to convert a u64 value to IPLD codec.
 pub fn u64_to_ipld(value: u64) -> Result<IpldCodec> {
     Ok(value.try_into()?)
 }
 
-pub(crate) fn serialize_cid_map<S>(
-    map: &Arc<Mutex<HashMap<Cid, Bytes>>>,
-    serializer: S,
-) -> Result<S::Ok, S::Error>
+/// Syncs the directory containing given path, which makes renaming a file to that
+/// path durable. Does nothing on platforms that can't open directories.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn sync_parent_dir(path: &std::path::Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        let parent = match parent.as_os_str().is_empty() {
+            true => std::path::Path::new("."),
+            false => parent,
+        };
+        std::fs::File::open(parent)?.sync_all()?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn serialize_cid_map<S>(map: &SharedBlocks, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
@@ -101,9 +178,7 @@ where
     map.serialize(serializer)
 }
 
-pub(crate) fn deserialize_cid_map<'de, D>(
-    deserializer: D,
-) -> Result<Arc<Mutex<HashMap<Cid, Bytes>>>, D::Error>
+pub(crate) fn deserialize_cid_map<'de, D>(deserializer: D) -> Result<SharedBlocks, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
@@ -116,5 +191,5 @@ where
         })
         .collect::<Result<_, _>>()?;
 
-    Ok(Arc::new(Mutex::new(map)))
+    Ok(Arc::new(Mutex::new(Arc::new(map))))
 }
@@ -0,0 +1,94 @@
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// Magic bytes of common formats, with the offset they're found at and their MIME type.
+const SIGNATURES: &[(usize, &[u8], &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, b"\xff\xd8\xff", "image/jpeg"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"\x1f\x8b", "application/gzip"),
+    (0, b"\x28\xb5\x2f\xfd", "application/zstd"),
+    (0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (257, b"ustar", "application/x-tar"),
+    (0, b"\0asm", "application/wasm"),
+    (0, b"SQLite format 3\0", "application/vnd.sqlite3"),
+    (0, b"ID3", "audio/mpeg"),
+    (0, b"OggS", "audio/ogg"),
+    (0, b"fLaC", "audio/flac"),
+    (4, b"ftyp", "video/mp4"),
+];
+
+/// RIFF containers, told apart by the format tag at offset 8.
+const RIFF_FORMATS: &[(&[u8], &str)] = &[
+    (b"WEBP", "image/webp"),
+    (b"WAVE", "audio/wav"),
+    (b"AVI ", "video/x-msvideo"),
+];
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Detects the MIME type of given content from the magic bytes at its start, for
+/// common image, audio, video, document and archive formats.
+///
+/// Returns `None` for content that isn't in any of the known formats, including
+/// plain text.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs_common::utils::detect_mime;
+///
+/// assert_eq!(detect_mime(b"%PDF-1.7\n").as_deref(), Some("application/pdf"));
+/// assert_eq!(detect_mime(b"Hello, World!"), None);
+/// ```
+pub fn detect_mime(bytes: &[u8]) -> Option<String> {
+    if let (Some(b"RIFF"), Some(format)) = (bytes.get(..4), bytes.get(8..12)) {
+        return RIFF_FORMATS
+            .iter()
+            .find(|(tag, _)| *tag == format)
+            .map(|(_, mime)| mime.to_string());
+    }
+
+    SIGNATURES
+        .iter()
+        .find(|(offset, magic, _)| bytes.get(*offset..*offset + magic.len()) == Some(*magic))
+        .map(|(_, _, mime)| mime.to_string())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn magic_bytes_are_detected() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        assert_eq!(detect_mime(png).as_deref(), Some("image/png"));
+        assert_eq!(
+            detect_mime(b"%PDF-1.4\n%").as_deref(),
+            Some("application/pdf")
+        );
+        assert_eq!(
+            detect_mime(b"RIFF\0\0\0\0WEBPVP8 ").as_deref(),
+            Some("image/webp")
+        );
+
+        let mut tar = vec![0; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(detect_mime(&tar).as_deref(), Some("application/x-tar"));
+
+        assert_eq!(detect_mime(b"Hello, World!"), None);
+        assert_eq!(detect_mime(b"\x89PN"), None);
+        assert_eq!(detect_mime(b"RIFF\0\0\0\0XXXX"), None);
+        assert_eq!(detect_mime(&[]), None);
+    }
+}
@@ -1,9 +1,13 @@
 mod common;
+#[cfg(feature = "mime")]
+mod mime;
 mod send_sync_poly;
 #[cfg(any(test, feature = "test_utils"))]
 mod test;
 
 pub use common::*;
+#[cfg(feature = "mime")]
+pub use mime::*;
 pub use send_sync_poly::*;
 #[cfg(any(test, feature = "test_utils"))]
 pub use test::*;
@@ -28,6 +28,16 @@ pub type BoxStream<'a, T> = futures::stream::BoxStream<'a, T>;
 #[cfg(target_arch = "wasm32")]
 pub type BoxStream<'a, T> = futures::stream::LocalBoxStream<'a, T>;
 
+/// A conditionally compiled boxed `AsyncRead`.
+/// Resolves to either a `Send` or a non-`Send` trait object, depending on the target.
+#[cfg(not(target_arch = "wasm32"))]
+pub type BoxAsyncRead<'a> = std::pin::Pin<Box<dyn futures::io::AsyncRead + Send + 'a>>;
+
+/// A conditionally compiled boxed `AsyncRead`.
+/// Resolves to either a `Send` or a non-`Send` trait object, depending on the target.
+#[cfg(target_arch = "wasm32")]
+pub type BoxAsyncRead<'a> = std::pin::Pin<Box<dyn futures::io::AsyncRead + 'a>>;
+
 /// A conditionally compiled trait indirection for `Send` bounds.
 /// This target makes it require `Send`.
 #[cfg(not(target_arch = "wasm32"))]
@@ -87,3 +97,10 @@ pub fn boxed_stream<'a, T>(
 ) -> BoxStream<'a, T> {
     futures::stream::StreamExt::boxed_local(stream)
 }
+
+/// Boxes given reader, which only needs to be `Send` on targets where `BoxAsyncRead` is.
+pub fn boxed_async_read<'a>(
+    reader: impl futures::io::AsyncRead + Sized + CondSend + 'a,
+) -> BoxAsyncRead<'a> {
+    Box::pin(reader)
+}
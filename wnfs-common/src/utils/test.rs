@@ -1,11 +1,13 @@
 use super::{Arc, CondSend, CondSync};
 use crate::{BlockStore, BlockStoreError, MemoryBlockStore, CODEC_DAG_CBOR, CODEC_RAW};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use base64_serde::base64_serde_type;
 use bytes::Bytes;
+use futures::Future;
 use libipld::{
     cbor::DagCborCodec,
     json::DagJsonCodec,
+    multihash::{Code, MultihashDigest},
     prelude::{Decode, Encode, References},
     Cid, Ipld, IpldCodec,
 };
@@ -19,6 +21,8 @@ use serde_json::Value;
 use std::{
     collections::{HashMap, HashSet, VecDeque},
     io::Cursor,
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    time::Duration,
 };
 
 //--------------------------------------------------------------------------------------------------
@@ -52,6 +56,27 @@ pub trait Sampleable {
     fn sample(&self, runner: &mut TestRunner) -> Self::Value;
 }
 
+/// A block store for tests that wraps another one, counts the calls to it, and
+/// can be set up to misbehave: to take a while for each call, to fail calls with
+/// a transient error, to hang on fetching a block, or to serve tampered blocks.
+/// Like a verifying store, it can also reject tampered blocks instead.
+#[derive(Debug)]
+pub struct InstrumentedBlockStore<B = MemoryBlockStore> {
+    inner: B,
+    delay: Duration,
+    verify: bool,
+    failures_left: AtomicUsize,
+    stall_next_get: AtomicBool,
+    overrides: Mutex<HashMap<Cid, Option<Bytes>>>,
+    calls: AtomicUsize,
+    gets: AtomicUsize,
+    puts: AtomicUsize,
+    fetched_bytes: AtomicU64,
+    cids_created: AtomicUsize,
+    in_flight: AtomicUsize,
+    max_in_flight: AtomicUsize,
+}
+
 //--------------------------------------------------------------------------------------------------
 // Implementations
 //--------------------------------------------------------------------------------------------------
@@ -141,6 +166,214 @@ impl BlockStore for SnapshotBlockStore {
     }
 }
 
+impl<B: BlockStore> InstrumentedBlockStore<B> {
+    /// Wraps given block store, without misbehaving yet.
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            delay: Duration::ZERO,
+            verify: false,
+            failures_left: AtomicUsize::new(0),
+            stall_next_get: AtomicBool::new(false),
+            overrides: Mutex::default(),
+            calls: AtomicUsize::new(0),
+            gets: AtomicUsize::new(0),
+            puts: AtomicUsize::new(0),
+            fetched_bytes: AtomicU64::new(0),
+            cids_created: AtomicUsize::new(0),
+            in_flight: AtomicUsize::new(0),
+            max_in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    /// Makes each call take at least given time. The wait blocks a thread of a
+    /// pool for blocking work, so it doesn't depend on an async runtime.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Makes fetching blocks that don't hash to their CID fail with
+    /// `BlockStoreError::CorruptBlock`, like a verifying store does.
+    pub fn with_verification(mut self) -> Self {
+        self.verify = true;
+        self
+    }
+
+    /// Makes the next given number of calls fail with a transient error.
+    pub fn fail_next(&self, calls: usize) {
+        self.failures_left.store(calls, Ordering::SeqCst);
+    }
+
+    /// Makes the next `get_block` call hang forever.
+    pub fn stall_next_get(&self) {
+        self.stall_next_get.store(true, Ordering::SeqCst);
+    }
+
+    /// Makes the block with given CID look missing.
+    pub fn drop_block(&self, cid: Cid) {
+        self.overrides.lock().insert(cid, None);
+    }
+
+    /// Makes the block with given CID have other bytes.
+    pub fn replace_block(&self, cid: Cid, bytes: impl Into<Bytes>) {
+        self.overrides.lock().insert(cid, Some(bytes.into()));
+    }
+
+    /// The number of calls to `get_block`, `put_block_keyed` and `has_block`,
+    /// including failed ones.
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    /// The number of calls to `get_block`.
+    pub fn gets(&self) -> usize {
+        self.gets.load(Ordering::SeqCst)
+    }
+
+    /// The number of calls to `put_block_keyed`.
+    pub fn puts(&self) -> usize {
+        self.puts.load(Ordering::SeqCst)
+    }
+
+    /// The number of bytes returned by `get_block`.
+    pub fn fetched_bytes(&self) -> u64 {
+        self.fetched_bytes.load(Ordering::SeqCst)
+    }
+
+    /// The number of CIDs computed via `create_cid`.
+    pub fn cids_created(&self) -> usize {
+        self.cids_created.load(Ordering::SeqCst)
+    }
+
+    /// The highest number of calls that were in flight at the same time.
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Resets all counts to zero.
+    pub fn reset_counts(&self) {
+        for count in [
+            &self.calls,
+            &self.gets,
+            &self.puts,
+            &self.cids_created,
+            &self.max_in_flight,
+        ] {
+            count.store(0, Ordering::SeqCst);
+        }
+        self.fetched_bytes.store(0, Ordering::SeqCst);
+    }
+
+    /// Returns the wrapped block store.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    async fn instrumented<T>(
+        &self,
+        call: impl Future<Output = Result<T, BlockStoreError>>,
+    ) -> Result<T, BlockStoreError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let _in_flight = InFlight::enter(&self.in_flight, &self.max_in_flight);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if !self.delay.is_zero() {
+            let delay = self.delay;
+            blocking::unblock(move || std::thread::sleep(delay)).await;
+        }
+
+        let failing = self
+            .failures_left
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+        match failing {
+            Ok(_) => Err(anyhow!("Connection reset").into()),
+            Err(_) => call.await,
+        }
+    }
+}
+
+/// Counts a call as in flight until it's done or dropped.
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl<'a> InFlight<'a> {
+    fn enter(in_flight: &'a AtomicUsize, max_in_flight: &AtomicUsize) -> Self {
+        let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        max_in_flight.fetch_max(now, Ordering::SeqCst);
+        Self(in_flight)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Default for InstrumentedBlockStore {
+    fn default() -> Self {
+        Self::new(MemoryBlockStore::new())
+    }
+}
+
+impl<B: BlockStore> BlockStore for InstrumentedBlockStore<B> {
+    async fn get_block(&self, cid: &Cid) -> Result<Bytes, BlockStoreError> {
+        self.gets.fetch_add(1, Ordering::SeqCst);
+        let bytes = self
+            .instrumented(async {
+                if self.stall_next_get.swap(false, Ordering::SeqCst) {
+                    futures::future::pending::<()>().await;
+                }
+
+                let overridden = self.overrides.lock().get(cid).cloned();
+                match overridden {
+                    Some(Some(bytes)) => Ok(bytes),
+                    Some(None) => Err(BlockStoreError::CIDNotFound(*cid)),
+                    None => self.inner.get_block(cid).await,
+                }
+            })
+            .await?;
+
+        if self.verify {
+            let code = Code::try_from(cid.hash().code()).map_err(anyhow::Error::from)?;
+            if code.digest(&bytes) != *cid.hash() {
+                return Err(BlockStoreError::CorruptBlock(*cid));
+            }
+        }
+
+        self.fetched_bytes
+            .fetch_add(bytes.len() as u64, Ordering::SeqCst);
+        Ok(bytes)
+    }
+
+    async fn put_block_keyed(
+        &self,
+        cid: Cid,
+        bytes: impl Into<Bytes> + CondSend,
+    ) -> Result<(), BlockStoreError> {
+        self.puts.fetch_add(1, Ordering::SeqCst);
+        self.instrumented(self.inner.put_block_keyed(cid, bytes))
+            .await
+    }
+
+    async fn has_block(&self, cid: &Cid) -> Result<bool, BlockStoreError> {
+        self.instrumented(async {
+            let overridden = self.overrides.lock().get(cid).map(Option::is_some);
+            match overridden {
+                Some(has_block) => Ok(has_block),
+                None => self.inner.has_block(cid).await,
+            }
+        })
+        .await
+    }
+
+    fn create_cid(&self, bytes: &[u8], codec: u64) -> Result<Cid, BlockStoreError> {
+        self.cids_created.fetch_add(1, Ordering::SeqCst);
+        self.inner.create_cid(bytes, codec)
+    }
+}
+
 impl<V, S> Sampleable for S
 where
     S: Strategy<Value = V>,
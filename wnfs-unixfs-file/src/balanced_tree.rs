@@ -1,12 +1,13 @@
 use crate::{
     builder::encode_unixfs_pb,
+    codecs::Codec,
     protobufs,
     types::Block,
     unixfs::{DataType, Node, UnixFsFile},
 };
-use anyhow::Result;
+use anyhow::{anyhow, ensure, Result};
 use async_stream::try_stream;
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use futures::{Stream, StreamExt};
 use libipld::Cid;
 use std::collections::VecDeque;
@@ -149,6 +150,219 @@ fn stream_balanced_tree<'a>(
     }
 }
 
+/// Builds a file DAG whose content is the concatenation of the content of the given
+/// file DAGs, by linking to their root nodes. None of their blocks get copied, only
+/// the new stem nodes on top of them get stored.
+///
+/// Empty files are skipped. If only one non-empty file is left, its root is returned.
+pub async fn concat_trees(roots: &[Cid], degree: usize, store: &impl BlockStore) -> Result<Cid> {
+    ensure!(degree > 1, "degree needs to be at least 2, but is {degree}");
+
+    let mut links = Vec::with_capacity(roots.len());
+    for root in roots {
+        let block = store.get_block(root).await?;
+        let encoded_len = block.len() as u64;
+        let link_info = match UnixFsFile::decode(root, block)? {
+            UnixFsFile::Raw(data) => LinkInfo {
+                raw_data_len: data.len() as u64,
+                encoded_len,
+            },
+            UnixFsFile::Node(node) => LinkInfo {
+                raw_data_len: node
+                    .filesize()
+                    .ok_or_else(|| anyhow!("missing filesize in {root}"))?,
+                encoded_len: encoded_len
+                    + node.outer.links.iter().filter_map(|l| l.tsize).sum::<u64>(),
+            },
+        };
+
+        if link_info.raw_data_len > 0 {
+            links.push((*root, link_info));
+        }
+    }
+
+    link_layers(links, degree, store).await
+}
+
+/// Builds a file DAG whose content is the content of the file DAG at given root,
+/// followed by given bytes.
+///
+/// The new DAG is laid out exactly like a balanced tree built from scratch out of
+/// the concatenated content, chunked into fixed size chunks of `chunk_size` bytes,
+/// so it has the same root CID. Only the stem nodes of the existing DAG get loaded
+/// to find its chunks. Chunks that already have `chunk_size` bytes are kept as they
+/// are, starting from the first one that doesn't, everything gets re-chunked
+/// together with the new bytes.
+pub async fn append_to_tree(
+    root: &Cid,
+    content: Bytes,
+    chunk_size: usize,
+    degree: usize,
+    store: &impl BlockStore,
+) -> Result<Cid> {
+    ensure!(degree > 1, "degree needs to be at least 2, but is {degree}");
+    ensure!(chunk_size > 0, "chunk size needs to be at least 1");
+
+    let (links, mut tail) = collect_chunks(root, None, chunk_size, store).await?;
+    tail.extend_from_slice(&content);
+    rechunk_tail(links, tail.freeze(), chunk_size, degree, store).await
+}
+
+/// Builds a file DAG whose content is the content of the file DAG at given root,
+/// cut off after `len` bytes, or extended with zero bytes up to `len` bytes.
+///
+/// Like with `append_to_tree`, the new DAG has the same root CID as a balanced
+/// tree built from scratch out of its content. Chunks past `len` don't get loaded.
+/// When extending, all chunks that only hold zero bytes link to the same block.
+pub async fn truncate_tree(
+    root: &Cid,
+    len: u64,
+    chunk_size: usize,
+    degree: usize,
+    store: &impl BlockStore,
+) -> Result<Cid> {
+    ensure!(degree > 1, "degree needs to be at least 2, but is {degree}");
+    ensure!(chunk_size > 0, "chunk size needs to be at least 1");
+
+    let (mut links, mut tail) = collect_chunks(root, Some(len), chunk_size, store).await?;
+    let tail_len = len - links.len() as u64 * chunk_size as u64;
+    let Some(zeros) = tail_len.checked_sub(tail.len() as u64) else {
+        tail.truncate(usize::try_from(tail_len)?);
+        return rechunk_tail(links, tail.freeze(), chunk_size, degree, store).await;
+    };
+
+    // Fill up the last chunk of the kept content, so the zeros after it start at a chunk boundary
+    let padding = zeros.min(((chunk_size - tail.len() % chunk_size) % chunk_size) as u64);
+    tail.resize(tail.len() + padding as usize, 0);
+    push_chunks(&mut links, tail.freeze(), chunk_size, store).await?;
+
+    let zeros = zeros - padding;
+    let full_chunks = usize::try_from(zeros / chunk_size as u64)?;
+    if full_chunks > 0 {
+        let (block, link_info) = TreeNode::Leaf(Bytes::from(vec![0; chunk_size])).encode()?;
+        let cid = block.store(store).await?;
+        links.extend(std::iter::repeat((cid, link_info)).take(full_chunks));
+    }
+
+    let rest = Bytes::from(vec![0; (zeros % chunk_size as u64) as usize]);
+    push_chunks(&mut links, rest, chunk_size, store).await?;
+    link_layers(links, degree, store).await
+}
+
+/// Walks the chunks of the file DAG at given root in content order, up to `limit`
+/// bytes if given. Returns the links to the leading chunks that have `chunk_size`
+/// bytes, and the content of the chunks after them, which may go past `limit`.
+async fn collect_chunks(
+    root: &Cid,
+    limit: Option<u64>,
+    chunk_size: usize,
+    store: &impl BlockStore,
+) -> Result<(Vec<(Cid, LinkInfo)>, BytesMut)> {
+    let mut links = Vec::new();
+    let mut tail = BytesMut::new();
+    let mut offset = 0;
+    // The nodes left to visit in content order, with their content size if known
+    let mut stack = vec![(*root, None)];
+    while let Some((cid, size)) = stack.pop() {
+        if limit.is_some_and(|limit| offset >= limit) {
+            break;
+        }
+
+        let is_raw = cid.codec() == Codec::Raw as u64;
+        let is_full_chunk = size == Some(chunk_size as u64);
+        let fits = limit.map_or(true, |limit| offset + chunk_size as u64 <= limit);
+        if is_raw && is_full_chunk && fits && tail.is_empty() {
+            let link_info = LinkInfo {
+                raw_data_len: chunk_size as u64,
+                encoded_len: chunk_size as u64,
+            };
+            links.push((cid, link_info));
+            offset += chunk_size as u64;
+            continue;
+        }
+
+        let data = match UnixFsFile::load(&cid, store).await? {
+            UnixFsFile::Raw(data) => data,
+            UnixFsFile::Node(node) if node.outer.links.is_empty() => {
+                node.data().unwrap_or_default()
+            }
+            UnixFsFile::Node(node) => {
+                let children = node
+                    .links()
+                    .zip(
+                        node.blocksizes()
+                            .iter()
+                            .map(Some)
+                            .chain(std::iter::repeat(None)),
+                    )
+                    .map(|(link, size)| Ok((link?.cid, size.copied())))
+                    .collect::<Result<Vec<_>>>()?;
+                stack.extend(children.into_iter().rev());
+                continue;
+            }
+        };
+
+        tail.extend_from_slice(&data);
+        offset += data.len() as u64;
+    }
+
+    Ok((links, tail))
+}
+
+/// Splits given tail content into chunks after given links, and builds a tree out of all of them.
+async fn rechunk_tail(
+    mut links: Vec<(Cid, LinkInfo)>,
+    tail: Bytes,
+    chunk_size: usize,
+    degree: usize,
+    store: &impl BlockStore,
+) -> Result<Cid> {
+    push_chunks(&mut links, tail, chunk_size, store).await?;
+    link_layers(links, degree, store).await
+}
+
+/// Splits given content into chunks, stores them and adds links to them.
+async fn push_chunks(
+    links: &mut Vec<(Cid, LinkInfo)>,
+    mut content: Bytes,
+    chunk_size: usize,
+    store: &impl BlockStore,
+) -> Result<()> {
+    while !content.is_empty() {
+        let chunk = content.split_to(chunk_size.min(content.len()));
+        let (block, link_info) = TreeNode::Leaf(chunk).encode()?;
+        links.push((block.store(store).await?, link_info));
+    }
+
+    Ok(())
+}
+
+/// Adds layers of stem nodes on top of given links until they fit into a single root node.
+async fn link_layers(
+    mut links: Vec<(Cid, LinkInfo)>,
+    degree: usize,
+    store: &impl BlockStore,
+) -> Result<Cid> {
+    match &links[..] {
+        [] => return UnixFsFile::empty().encode()?.store(store).await,
+        [(root, _)] => return Ok(*root),
+        _ => {}
+    }
+
+    loop {
+        let mut layer = Vec::with_capacity(links.len().div_ceil(degree));
+        for group in links.chunks(degree) {
+            let (block, link_info) = TreeNode::Stem(group.to_vec()).encode()?;
+            layer.push((block.store(store).await?, link_info));
+        }
+
+        match &layer[..] {
+            [(root, _)] => return Ok(*root),
+            _ => links = layer,
+        }
+    }
+}
+
 fn create_unixfs_node_from_links(links: Vec<(Cid, LinkInfo)>) -> Result<UnixFsFile> {
     let blocksizes: Vec<u64> = links.iter().map(|l| l.1.raw_data_len).collect();
     let filesize: u64 = blocksizes.iter().sum();
@@ -233,8 +447,8 @@ impl TreeNode {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use bytes::BytesMut;
-    use wnfs_common::MemoryBlockStore;
+    use crate::builder::FileBuilder;
+    use wnfs_common::{BlockOperation, MemoryBlockStore, RecordingBlockStore};
 
     // chunks are just a single usize integer
     const CHUNK_SIZE: u64 = std::mem::size_of::<usize>() as u64;
@@ -481,6 +695,89 @@ mod tests {
         assert_eq!(expected_tsize, got_tsize);
     }
 
+    #[tokio::test]
+    async fn appended_trees_equal_trees_built_at_once() {
+        let store = &MemoryBlockStore::new();
+        let content = (0..=255).collect::<Vec<u8>>();
+        let build = |content: &[u8]| {
+            FileBuilder::new()
+                .fixed_chunker(4)
+                .degree(3)
+                .content_bytes(content.to_vec())
+                .build()
+                .unwrap()
+                .store(store)
+        };
+
+        for split in [1, 4, 5, 36, 37, 255] {
+            let (existing, appended) = content.split_at(split);
+            let root = build(existing).await.unwrap();
+            let appended = append_to_tree(&root, appended.to_vec().into(), 4, 3, store)
+                .await
+                .unwrap();
+            assert_eq!(appended, build(&content).await.unwrap(), "split at {split}");
+        }
+
+        // Chunks of other sizes get re-chunked
+        let root = FileBuilder::new()
+            .fixed_chunker(3)
+            .content_bytes(content[..100].to_vec())
+            .build()
+            .unwrap()
+            .store(store)
+            .await
+            .unwrap();
+        let appended = append_to_tree(&root, content[100..].to_vec().into(), 4, 3, store)
+            .await
+            .unwrap();
+        assert_eq!(appended, build(&content).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn truncated_trees_equal_trees_built_at_once() {
+        let store = &MemoryBlockStore::new();
+        let content = (0..=255).collect::<Vec<u8>>();
+        let build = |content: &[u8]| {
+            FileBuilder::new()
+                .fixed_chunker(4)
+                .degree(3)
+                .content_bytes(content.to_vec())
+                .build()
+                .unwrap()
+                .store(store)
+        };
+
+        let root = build(&content).await.unwrap();
+        for len in [1, 4, 5, 36, 37, 255, 256, 300, 303] {
+            let mut expected = content[..len.min(256)].to_vec();
+            expected.resize(len, 0);
+            let truncated = truncate_tree(&root, len as u64, 4, 3, store).await.unwrap();
+            assert_eq!(truncated, build(&expected).await.unwrap(), "len {len}");
+        }
+
+        // Growing from a partial last chunk
+        let root = build(&content[..254]).await.unwrap();
+        let mut expected = content[..254].to_vec();
+        expected.resize(301, 0);
+        let recording = &RecordingBlockStore::new(store);
+        let grown = truncate_tree(&root, 301, 4, 3, recording).await.unwrap();
+        assert_eq!(grown, build(&expected).await.unwrap());
+        // The padded last chunk, the shared zero chunk and the short final chunk
+        let raw_puts = recording
+            .log()
+            .iter()
+            .filter(|access| matches!(access.operation, BlockOperation::Put(_)))
+            .filter(|access| access.cid.codec() == Codec::Raw as u64)
+            .count();
+        assert_eq!(raw_puts, 3);
+
+        let empty = truncate_tree(&root, 0, 4, 3, store).await.unwrap();
+        assert_eq!(
+            UnixFsFile::load(&empty, store).await.unwrap().filesize(),
+            Some(0)
+        );
+    }
+
     #[tokio::test]
     async fn balanced_tree_test_leaf() {
         let store = &MemoryBlockStore::new();
@@ -0,0 +1,405 @@
+//! Exchanging blocks with IPFS tooling via CARv1 files.
+//!
+//! See the [CARv1 specification](https://ipld.io/specs/transport/car/carv1/).
+
+use crate::error::CarError;
+use anyhow::{bail, Result};
+use futures::{io::BufReader, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use libipld_core::cid::Cid;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, io::Cursor};
+use wnfs_common::{
+    libipld::{
+        codec::Codec,
+        multihash::{Code, MultihashDigest},
+        Ipld, IpldCodec,
+    },
+    BlockStore,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The version of the CAR format written by `export_car`.
+pub const CAR_VERSION: u64 = 1;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The header at the start of each CAR file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CarHeader {
+    roots: Vec<Cid>,
+    version: u64,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Writes the block with given CID and all blocks it transitively links to as
+/// a CARv1 stream, with given CID as its only root.
+///
+/// Blocks are written depth-first, in the order of their links, and each block
+/// only once. Both links between file system nodes and links within file
+/// content DAGs are followed, as are links to previous revisions.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use chrono::Utc;
+/// use wnfs::{
+///     car::export_car,
+///     common::{MemoryBlockStore, Storable},
+///     public::PublicDirectory,
+/// };
+///
+/// #[async_std::main]
+/// async fn main() -> Result<()> {
+///     let store = &MemoryBlockStore::new();
+///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+///     dir.write(&["notes.txt".into()], b"Hello".to_vec(), Utc::now(), store).await?;
+///     let root = dir.store(store).await?;
+///
+///     let mut car = Vec::new();
+///     export_car(&root, store, &mut car).await?;
+///
+///     assert!(!car.is_empty());
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn export_car(
+    root: &Cid,
+    store: &impl BlockStore,
+    mut out: impl AsyncWrite + Unpin,
+) -> Result<()> {
+    let header = serde_ipld_dagcbor::to_vec(&CarHeader {
+        roots: vec![*root],
+        version: CAR_VERSION,
+    })?;
+    write_varint(&mut out, header.len() as u64).await?;
+    out.write_all(&header).await?;
+
+    let mut written = HashSet::new();
+    let mut stack = vec![*root];
+    while let Some(cid) = stack.pop() {
+        if !written.insert(cid) {
+            continue;
+        }
+
+        let bytes = store.get_block(&cid).await?;
+        let cid_bytes = cid.to_bytes();
+        write_varint(&mut out, (cid_bytes.len() + bytes.len()) as u64).await?;
+        out.write_all(&cid_bytes).await?;
+        out.write_all(&bytes).await?;
+
+        let codec = IpldCodec::try_from(cid.codec())?;
+        let mut links = Vec::new();
+        codec.references::<Ipld, _>(&bytes, &mut links)?;
+        stack.extend(links.into_iter().rev().filter(|cid| !written.contains(cid)));
+    }
+
+    out.flush().await?;
+    Ok(())
+}
+
+/// Reads a CARv1 stream, puts all of its blocks into given block store and
+/// returns the roots from its header.
+///
+/// Each block is checked to hash to its CID before it gets stored. Blocks that
+/// arrived before a malformed frame or a block with a wrong hash stay in the store.
+/// Fails with a `CarError` if the stream isn't a valid CARv1 stream.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use chrono::Utc;
+/// use wnfs::{
+///     car::{export_car, import_car},
+///     common::{MemoryBlockStore, Storable},
+///     public::PublicDirectory,
+/// };
+///
+/// #[async_std::main]
+/// async fn main() -> Result<()> {
+///     let store = &MemoryBlockStore::new();
+///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+///     dir.write(&["notes.txt".into()], b"Hello".to_vec(), Utc::now(), store).await?;
+///     let mut car = Vec::new();
+///     export_car(&dir.store(store).await?, store, &mut car).await?;
+///
+///     let other_store = &MemoryBlockStore::new();
+///     let roots = import_car(car.as_slice(), other_store).await?;
+///     let dir = PublicDirectory::load(&roots[0], other_store).await?;
+///
+///     assert_eq!(dir.read(&["notes.txt".into()], other_store).await?, b"Hello");
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn import_car(
+    reader: impl AsyncRead + Unpin,
+    store: &impl BlockStore,
+) -> Result<Vec<Cid>> {
+    let mut reader = BufReader::new(reader);
+
+    let Some(header) = read_frame(&mut reader).await? else {
+        bail!(CarError::InvalidHeader("Missing header".into()));
+    };
+    let header: CarHeader = serde_ipld_dagcbor::from_slice(&header)
+        .map_err(|e| CarError::InvalidHeader(e.to_string()))?;
+    if header.version != CAR_VERSION {
+        bail!(CarError::UnsupportedVersion(header.version));
+    }
+
+    while let Some(frame) = read_frame(&mut reader).await? {
+        let mut cursor = Cursor::new(frame.as_slice());
+        let cid = Cid::read_bytes(&mut cursor).map_err(|e| CarError::InvalidCid(e.to_string()))?;
+        let bytes = &frame[cursor.position() as usize..];
+
+        let Ok(code) = Code::try_from(cid.hash().code()) else {
+            bail!(CarError::UnsupportedHash(cid.hash().code(), cid));
+        };
+        if code.digest(bytes) != *cid.hash() {
+            bail!(CarError::HashMismatch(cid));
+        }
+
+        store.put_block_keyed(cid, bytes.to_vec()).await?;
+    }
+
+    Ok(header.roots)
+}
+
+/// Reads a varint length-prefixed frame. Returns `None` at the end of the stream.
+async fn read_frame(reader: &mut (impl AsyncRead + Unpin)) -> Result<Option<Vec<u8>>> {
+    let Some(len) = read_varint(reader).await? else {
+        return Ok(None);
+    };
+
+    let mut frame = Vec::new();
+    reader.take(len).read_to_end(&mut frame).await?;
+    if frame.len() as u64 != len {
+        bail!(CarError::Truncated);
+    }
+
+    Ok(Some(frame))
+}
+
+/// Reads an unsigned LEB128 varint. Returns `None` at the end of the stream.
+async fn read_varint(reader: &mut (impl AsyncRead + Unpin)) -> Result<Option<u64>> {
+    let mut value = 0;
+    for i in 0..10 {
+        let mut byte = [0];
+        if reader.read(&mut byte).await? == 0 {
+            if i == 0 {
+                return Ok(None);
+            }
+
+            bail!(CarError::Truncated);
+        }
+
+        value |= ((byte[0] & 0x7f) as u64) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+
+    bail!(CarError::InvalidVarint)
+}
+
+/// Writes given number as an unsigned LEB128 varint.
+async fn write_varint(out: &mut (impl AsyncWrite + Unpin), mut value: u64) -> Result<()> {
+    let mut bytes = Vec::new();
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            break;
+        }
+
+        bytes.push(byte | 0x80);
+    }
+
+    out.write_all(&bytes).await?;
+    Ok(())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::public::{golden::golden_content, PublicDirectory};
+    use chrono::Utc;
+    use testresult::TestResult;
+    use wnfs_common::{MemoryBlockStore, Storable};
+
+    /// Splits a CAR file into its header and its frames' CIDs.
+    fn parse(mut car: &[u8]) -> Result<(CarHeader, Vec<Cid>)> {
+        let read_varint = |car: &mut &[u8]| {
+            let (mut value, mut shift) = (0u64, 0);
+            loop {
+                let byte = car[0];
+                *car = &car[1..];
+                value |= ((byte & 0x7f) as u64) << shift;
+                shift += 7;
+                if byte & 0x80 == 0 {
+                    return value as usize;
+                }
+            }
+        };
+
+        let len = read_varint(&mut car);
+        let header = serde_ipld_dagcbor::from_slice(&car[..len])?;
+        car = &car[len..];
+
+        let mut cids = Vec::new();
+        while !car.is_empty() {
+            let len = read_varint(&mut car);
+            cids.push(Cid::read_bytes(&car[..len])?);
+            car = &car[len..];
+        }
+
+        Ok((header, cids))
+    }
+
+    #[async_std::test]
+    async fn exported_cars_have_every_reachable_block_once() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let dir = &mut PublicDirectory::new_rc(time);
+        dir.write(
+            &["pictures".into(), "big.png".into()],
+            golden_content(1_000_000),
+            time,
+            store,
+        )
+        .await?;
+        // The same content twice, so its blocks are linked twice
+        dir.write(&["a.txt".into()], b"Same".to_vec(), time, store)
+            .await?;
+        dir.write(&["b.txt".into()], b"Same".to_vec(), time, store)
+            .await?;
+        dir.store(store).await?;
+        dir.rm(&["b.txt".into()], store).await?;
+        let root = dir.store(store).await?;
+
+        // A block that isn't reachable from the root
+        store.put_block(b"Unrelated".to_vec(), 0x55).await?;
+
+        let mut car = Vec::new();
+        export_car(&root, store, &mut car).await?;
+        let (header, cids) = parse(&car)?;
+
+        assert_eq!(header.roots, vec![root]);
+        assert_eq!(header.version, 1);
+        assert_eq!(cids[0], root);
+
+        let unique = cids.iter().collect::<HashSet<_>>();
+        assert_eq!(unique.len(), cids.len());
+        // Previous revisions, file nodes, content roots and content chunks
+        assert!(cids.len() > 8);
+
+        for cid in &cids {
+            let bytes = store.get_block(cid).await?;
+            let mut links = Vec::new();
+            IpldCodec::try_from(cid.codec())?.references::<Ipld, _>(&bytes, &mut links)?;
+            assert!(links.iter().all(|link| unique.contains(link)));
+        }
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn imported_cars_round_trip_file_system_trees() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let dir = &mut PublicDirectory::new_rc(time);
+        let big = &["pictures".into(), "big.png".into()];
+        dir.write(big, golden_content(1_000_000), time, store)
+            .await?;
+        dir.store(store).await?;
+        dir.write(&["notes.txt".into()], b"Notes".to_vec(), time, store)
+            .await?;
+        let root = dir.store(store).await?;
+
+        let mut car = Vec::new();
+        export_car(&root, store, &mut car).await?;
+
+        let imported = &MemoryBlockStore::new();
+        assert_eq!(import_car(car.as_slice(), imported).await?, vec![root]);
+
+        let dir = PublicDirectory::load(&root, imported).await?;
+        assert_eq!(dir.read(big, imported).await?, golden_content(1_000_000));
+        assert_eq!(
+            dir.read(&["notes.txt".into()], imported).await?,
+            b"Notes".to_vec()
+        );
+        let (_, cids) = parse(&car)?;
+        for cid in cids {
+            assert!(imported.has_block(&cid).await?);
+        }
+
+        // Exporting again from the imported blocks gives the same CAR file
+        let mut again = Vec::new();
+        export_car(&root, imported, &mut again).await?;
+        assert_eq!(again, car);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn malformed_cars_fail_with_car_errors() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let dir = &mut PublicDirectory::new_rc(time);
+        dir.write(&["notes.txt".into()], b"Notes".to_vec(), time, store)
+            .await?;
+        let root = dir.store(store).await?;
+        let mut car = Vec::new();
+        export_car(&root, store, &mut car).await?;
+
+        let import = |car: Vec<u8>| async move {
+            let err = import_car(car.as_slice(), &MemoryBlockStore::new())
+                .await
+                .unwrap_err();
+            err.downcast::<CarError>()
+        };
+
+        // The last frame is the raw "Notes" content block
+        let mut tampered = car.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(import(tampered).await?, CarError::HashMismatch(_)));
+
+        let truncated = car[..car.len() - 1].to_vec();
+        assert!(matches!(import(truncated).await?, CarError::Truncated));
+
+        let bad_header = [vec![2, 0xa0], car.clone()].concat();
+        assert!(matches!(
+            import(bad_header).await?,
+            CarError::InvalidHeader(_)
+        ));
+
+        let v2_header = serde_ipld_dagcbor::to_vec(&CarHeader {
+            roots: vec![root],
+            version: 2,
+        })?;
+        let v2 = [vec![v2_header.len() as u8], v2_header].concat();
+        assert!(matches!(import(v2).await?, CarError::UnsupportedVersion(2)));
+
+        let overlong = [car.clone(), vec![0xff; 10]].concat();
+        assert!(matches!(import(overlong).await?, CarError::InvalidVarint));
+
+        assert!(matches!(import(vec![]).await?, CarError::InvalidHeader(_)));
+
+        Ok(())
+    }
+}
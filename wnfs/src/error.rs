@@ -1,5 +1,7 @@
 //! WNFS Errors
 
+use chrono::{DateTime, Utc};
+use libipld_core::cid::Cid;
 use semver::Version;
 use skip_ratchet::PreviousErr;
 use thiserror::Error;
@@ -15,14 +17,20 @@ pub enum FsError {
     #[error("Invalid WNFS path")]
     InvalidPath,
 
+    #[error("Invalid directory entry name: {0:?}")]
+    InvalidName(String),
+
     #[error("Expected a file")]
     NotAFile,
 
     #[error("Expected a directory")]
     NotADirectory,
 
-    #[error("Cannot find file or directory")]
-    NotFound,
+    #[error("Cannot find file or directory /{}", .path.join("/"))]
+    NotFound { path: Vec<String> },
+
+    #[error("Cannot find directory /{}", .path.join("/"))]
+    ParentNotFound { path: Vec<String> },
 
     #[error("File already exists")]
     FileAlreadyExists,
@@ -56,6 +64,79 @@ pub enum FsError {
 
     #[error("Cannot find the partition with this name")]
     PartitionNotFound,
+
+    #[error("Content root {0} doesn't decode as a file content DAG")]
+    InvalidContent(Cid),
+
+    #[error("File content doesn't match its recorded content digest")]
+    ContentDigestMismatch,
+
+    #[error("Invalid tar archive: {0}")]
+    InvalidTarArchive(String),
+
+    #[error("Unsupported content encoding: {0}")]
+    UnsupportedEncoding(String),
+
+    #[error("Cannot apply diff: {0}")]
+    InvalidDiff(String),
+
+    #[error("Content fan-out needs to be at least 2, but is {0}")]
+    InvalidContentFanout(usize),
+
+    #[error("Content chunk size needs to be between 1 byte and 1 MiB, but is {0}")]
+    InvalidChunkSize(usize),
+
+    #[error("No revision was modified at or before {0}")]
+    NoRevisionAt(DateTime<Utc>),
+
+    #[error("Revision has several previous revisions, pick one of them explicitly: {0:?}")]
+    AmbiguousPreviousRevision(Vec<Cid>),
+
+    #[error("Revision {0} isn't in the history of this revision")]
+    RevisionNotInHistory(Cid),
+
+    #[error("Invalid path proof: {0}")]
+    InvalidPathProof(String),
+
+    #[error("Range starts at byte {start}, but the file is only {size} bytes long")]
+    RangeNotSatisfiable { start: u64, size: u64 },
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl FsError {
+    /// The error for a missing node at given path.
+    pub(crate) fn not_found(path_segments: &[String]) -> Self {
+        Self::NotFound {
+            path: path_segments.to_vec(),
+        }
+    }
+
+    /// Fills in given path for a `NotFound` error of a node that was missing
+    /// while it was resolved without knowing its path. Other errors are kept.
+    pub(crate) fn at_path(error: anyhow::Error, path_segments: &[String]) -> anyhow::Error {
+        match error.downcast::<Self>() {
+            Ok(Self::NotFound { path }) if path.is_empty() => Self::not_found(path_segments).into(),
+            Ok(error) => error.into(),
+            Err(error) => error,
+        }
+    }
+
+    /// The error for a path that can't be followed past the segment at given depth.
+    ///
+    /// That's `NotFound` if it's the last segment, otherwise `ParentNotFound` with
+    /// the path of the directory that's missing.
+    pub(crate) fn missing_at(path_segments: &[String], depth: usize) -> Self {
+        if depth + 1 >= path_segments.len() {
+            Self::not_found(path_segments)
+        } else {
+            Self::ParentNotFound {
+                path: path_segments[..=depth].to_vec(),
+            }
+        }
+    }
 }
 
 /// Data sharing related errors
@@ -107,3 +188,28 @@ pub enum AccessKeyError {
     #[error("Snapshot access keys cannot be used to derive private refs")]
     UnsupportedSnapshotPrivateRefDerive,
 }
+
+/// Errors reading CAR files, see `wnfs::car::import_car`.
+#[derive(Debug, Error)]
+pub enum CarError {
+    #[error("Invalid CAR header: {0}")]
+    InvalidHeader(String),
+
+    #[error("Unsupported CAR version {0}")]
+    UnsupportedVersion(u64),
+
+    #[error("Invalid varint in CAR file")]
+    InvalidVarint,
+
+    #[error("CAR file ends in the middle of a frame")]
+    Truncated,
+
+    #[error("Invalid CID in CAR frame: {0}")]
+    InvalidCid(String),
+
+    #[error("Unsupported hash algorithm {0:#x} in CID {1}")]
+    UnsupportedHash(u64, Cid),
+
+    #[error("Block bytes don't hash to their CID {0}")]
+    HashMismatch(Cid),
+}
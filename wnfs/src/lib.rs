@@ -42,7 +42,7 @@
 //!
 //! Here we create a root directory `dir` and subsequently add a `/pictures/cats` subdirectory to it. As mentioned earlier, system-level operations like time are passed in from the API. In this case, we use the `Utc::now()` function from the [chrono][chrono-crate] crate to get the current time.
 //!
-//! `PublicDirectory` gets wrapped in `Rc` here because it lets us pass it around without worrying about ownership and lifetimes. Making the Rc `&mut` futher allows us to relinquish ownership to the interior `PublicDirectory` and point to a new one when needed (essentially for every write). This immutable way of handling changes has cool benefits like tracking and rolling back changes. It also makes collaborative editing easier to implement and reason about. You can find more examples in the [`wnfs/examples/`][wnfs-examples] folder.
+//! `PublicDirectory` gets wrapped in an `Arc` here because it lets us pass it around without worrying about ownership and lifetimes. Outside of WebAssembly, that's `std::sync::Arc`, so file system trees are `Send + Sync` and can be shared with other threads, e.g. in tokio tasks. On `wasm32`, where there's only a single thread, it's `std::rc::Rc` instead, which avoids the cost of atomic reference counting. See `wnfs::common::utils::Arc`. Making the Arc `&mut` futher allows us to relinquish ownership to the interior `PublicDirectory` and point to a new one when needed (essentially for every write). This immutable way of handling changes has cool benefits like tracking and rolling back changes. It also makes collaborative editing easier to implement and reason about. You can find more examples in the [`wnfs/examples/`][wnfs-examples] folder.
 //!
 //! That's the public filesystem, the private filesystem, on the other hand, is a bit more involved. The [Hash Array Mapped Trie (HAMT)][hamt-wiki] is where we store the private filesystem tree and some other information related to it. HAMT allows for effective storage and retrieval of encrypted and obfuscated filesystem trees and `PrivateForest` is basically a HAMT that can contain multiple file trees with hash for keys and CIDs for values.
 //!
@@ -123,7 +123,9 @@
 
 #![deny(unsafe_code)]
 
+pub mod car;
 pub mod error;
+mod path;
 pub mod private;
 pub mod public;
 #[doc(hidden)] // The API is in "prerelease" for now
@@ -131,6 +133,8 @@ pub mod root_tree;
 pub mod traits;
 mod utils;
 
+pub use path::*;
+
 pub mod rand_core {
     //! Re-exports of rand-core lib.
     pub use rand_core::CryptoRngCore;
@@ -155,10 +159,41 @@ pub mod nameaccumulator {
 /// The version of the WNFS data format that this library outputs
 pub const WNFS_VERSION: semver::Version = semver::Version::new(1, 0, 0);
 
+/// The maximum length in bytes of a directory entry name
+pub const MAX_NAME_LENGTH: usize = 255;
+
 //--------------------------------------------------------------------------------------------------
 // Types
 //--------------------------------------------------------------------------------------------------
 
+/// The kind of a file system node, see `PublicNode::kind` and `PrivateNode::kind`.
+///
+/// This enum isn't marked `#[non_exhaustive]` on purpose: It gains variants as
+/// node kinds get added, so exhaustive matches on it get flagged by the compiler.
+///
+/// [`PublicNode::kind`]: crate::public::PublicNode::kind
+/// [`PrivateNode::kind`]: crate::private::PrivateNode::kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    File,
+    Dir,
+}
+
+/// Why a name can't be used for a directory entry, see `PublicDirectory::validate_names`.
+///
+/// [`PublicDirectory::validate_names`]: crate::public::PublicDirectory::validate_names
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameViolation {
+    /// The name is empty.
+    Empty,
+    /// The name is `.` or `..`, which refer to directories in paths.
+    Reserved,
+    /// The name contains given character, which is either `/` or NUL.
+    InvalidCharacter(char),
+    /// The name is longer than `MAX_NAME_LENGTH` bytes. Has the name's length.
+    TooLong(usize),
+}
+
 /// The result of an basic get operation.
 pub(crate) enum SearchResult<T> {
     Missing(T, usize),
@@ -166,6 +201,26 @@ pub(crate) enum SearchResult<T> {
     Found(T),
 }
 
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<T> SearchResult<T> {
+    /// Returns the found directory, or the error for given path not leading to it:
+    /// `FsError::NotADirectory` if it runs into a file, otherwise `FsError::NotFound`
+    /// or `FsError::ParentNotFound`.
+    ///
+    /// Given path segments have to start with the ones that were searched for, so
+    /// the error can point at the segment that couldn't be followed.
+    pub(crate) fn into_found(self, path_segments: &[String]) -> Result<T, error::FsError> {
+        match self {
+            Self::Found(dir) => Ok(dir),
+            Self::NotADir(_, _) => Err(error::FsError::NotADirectory),
+            Self::Missing(_, depth) => Err(error::FsError::missing_at(path_segments, depth)),
+        }
+    }
+}
+
 //--------------------------------------------------------------------------------------------------
 // Functions
 //--------------------------------------------------------------------------------------------------
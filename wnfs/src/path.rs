@@ -0,0 +1,171 @@
+//! Validated file system paths.
+
+use crate::{error::FsError, utils};
+use anyhow::{bail, Result};
+use std::{fmt, ops::Deref, str::FromStr};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A path of directory entry names, relative to some directory.
+///
+/// Unlike plain `&[String]` path segments, each segment of a `Path` is a valid
+/// directory entry name, see `PublicDirectory::validate_names`, and `.` and `..`
+/// segments are resolved. Paths dereference to their segments, so they can be
+/// passed to everything that takes path segments.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use chrono::Utc;
+/// use wnfs::{common::MemoryBlockStore, public::PublicDirectory, Path};
+///
+/// #[async_std::main]
+/// async fn main() -> Result<()> {
+///     let store = &MemoryBlockStore::new();
+///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+///     let path = Path::parse("notes/drafts/../todo.md")?;
+///
+///     dir.write(&path, b"- [ ] Paths".to_vec(), Utc::now(), store).await?;
+///
+///     assert_eq!(path.to_string(), "notes/todo.md");
+///     assert!(dir.get_node(&path, store).await?.is_some());
+///     assert!(Path::parse("notes//todo.md").is_err());
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// [`PublicDirectory::validate_names`]: crate::public::PublicDirectory::validate_names
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Path(Vec<String>);
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl Path {
+    /// The empty path, referring to the directory a path is relative to.
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    /// Parses a path with segments separated by `/`. A leading `/` is ignored.
+    ///
+    /// Fails with `FsError::InvalidPath` if a segment is empty or if `..` segments
+    /// would go above the root, and with `FsError::InvalidName` for segments
+    /// that aren't valid directory entry names.
+    pub fn parse(path: &str) -> Result<Self> {
+        let path = path.strip_prefix('/').unwrap_or(path);
+        if path.is_empty() {
+            return Ok(Self::root());
+        }
+
+        Self::from_segments(path.split('/'))
+    }
+
+    /// Creates a path from given segments, validating and normalizing them like `Path::parse`.
+    /// Segments containing `/` are rejected instead of being split.
+    pub fn from_segments(segments: impl IntoIterator<Item = impl Into<String>>) -> Result<Self> {
+        let segments = segments.into_iter().map(Into::into).collect::<Vec<_>>();
+        for segment in &segments {
+            match segment.as_str() {
+                "" => bail!(FsError::InvalidPath),
+                "." | ".." => {}
+                name => utils::validate_name(name)?,
+            }
+        }
+
+        Ok(Self(utils::normalize_path(&segments)?))
+    }
+
+    /// Returns this path with given name appended.
+    pub fn join(&self, name: &str) -> Result<Self> {
+        Self::from_segments(self.0.iter().map(String::as_str).chain([name]))
+    }
+
+    /// Returns the segments of this path.
+    pub fn into_segments(self) -> Vec<String> {
+        self.0
+    }
+}
+
+impl Deref for Path {
+    type Target = [String];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<[String]> for Path {
+    fn as_ref(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl FromStr for Path {
+    type Err = anyhow::Error;
+
+    fn from_str(path: &str) -> Result<Self> {
+        Self::parse(path)
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.join("/"))
+    }
+}
+
+impl From<Path> for Vec<String> {
+    fn from(path: Path) -> Self {
+        path.0
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_validated_and_normalized() {
+        let is_invalid_path = |path: &str| {
+            matches!(
+                Path::parse(path).unwrap_err().downcast_ref(),
+                Some(FsError::InvalidPath)
+            )
+        };
+
+        assert_eq!(Path::parse("a/../b").unwrap().as_ref(), ["b"]);
+        assert_eq!(Path::parse("/a/./b/c/..").unwrap().as_ref(), ["a", "b"]);
+        assert_eq!(Path::parse("").unwrap(), Path::root());
+        assert_eq!(Path::parse("/").unwrap(), Path::root());
+        assert!(is_invalid_path("a//b"));
+        assert!(is_invalid_path("a/b/"));
+        assert!(is_invalid_path("a/../.."));
+        assert!(Path::parse("a/b\0c").is_err());
+
+        assert!(matches!(
+            Path::from_segments(["a", "b/c"]).unwrap_err().downcast_ref(),
+            Some(FsError::InvalidName(name)) if name == "b/c"
+        ));
+        assert_eq!(
+            Path::parse("a").unwrap().join("b").unwrap(),
+            Path::parse("a/b").unwrap()
+        );
+        assert_eq!(Path::parse("a/b").unwrap().to_string(), "a/b");
+
+        // Paths work wherever path segments do
+        let path = Path::parse("a/b/c").unwrap();
+        let (rest, last) = utils::split_last(&path).unwrap();
+        assert_eq!((rest, last.as_str()), (&path[..2], "c"));
+        assert!(utils::split_last(&Path::root()).is_err());
+    }
+}
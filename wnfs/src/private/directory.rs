@@ -15,7 +15,7 @@ use std::{
     fmt::Debug,
 };
 use wnfs_common::{
-    utils::{error, Arc, CondSend},
+    utils::{error, is_ancestor, Arc, CondSend},
     BlockStore, Metadata, PathNodes, PathNodesResult, CODEC_RAW,
 };
 use wnfs_nameaccumulator::{Name, NameSegment};
@@ -256,11 +256,18 @@ impl PrivateDirectory {
     ) -> Result<Option<PrivateNode>> {
         Ok(match self.content.entries.get(path_segment) {
             Some(private_link) => {
+                let not_found_at = |error| FsError::at_path(error, &[path_segment.to_string()]);
                 let private_node = private_link
                     .resolve_node(forest, store, Some(self.header.name.clone()))
-                    .await?;
+                    .await
+                    .map_err(not_found_at)?;
                 if search_latest {
-                    Some(private_node.search_latest_reconciled(forest, store).await?)
+                    Some(
+                        private_node
+                            .search_latest_reconciled(forest, store)
+                            .await
+                            .map_err(not_found_at)?,
+                    )
                 } else {
                     Some(private_node.clone())
                 }
@@ -279,11 +286,16 @@ impl PrivateDirectory {
     ) -> Result<Option<&'a mut PrivateNode>> {
         Ok(match self.content.entries.get_mut(path_segment) {
             Some(private_link) => {
+                let not_found_at = |error| FsError::at_path(error, &[path_segment.to_string()]);
                 let private_node = private_link
                     .resolve_node_mut(forest, store, Some(self.header.name.clone()))
-                    .await?;
+                    .await
+                    .map_err(not_found_at)?;
                 if search_latest {
-                    *private_node = private_node.search_latest_reconciled(forest, store).await?;
+                    *private_node = private_node
+                        .search_latest_reconciled(forest, store)
+                        .await
+                        .map_err(not_found_at)?;
                 }
 
                 Some(private_node)
@@ -375,6 +387,10 @@ impl PrivateDirectory {
         {
             SearchResult::Found(dir) => Ok(dir),
             SearchResult::Missing(mut dir, depth) => {
+                for segment in &path_segments[depth..] {
+                    crate::utils::validate_name(segment)?;
+                }
+
                 for segment in &path_segments[depth..] {
                     dir = Arc::make_mut(
                         dir.content
@@ -595,21 +611,17 @@ impl PrivateDirectory {
         store: &impl BlockStore,
     ) -> Result<Vec<u8>> {
         let (path, filename) = crate::utils::split_last(path_segments)?;
-        match self
+        let dir = self
             .get_leaf_dir(path, search_latest, forest, store)
             .await?
+            .into_found(path_segments)?;
+        match dir
+            .lookup_node(filename, search_latest, forest, store)
+            .await?
         {
-            SearchResult::Found(dir) => {
-                match dir
-                    .lookup_node(filename, search_latest, forest, store)
-                    .await?
-                {
-                    Some(PrivateNode::File(file)) => Ok(file.get_content(forest, store).await?),
-                    Some(_) => error(FsError::NotAFile),
-                    None => error(FsError::NotFound),
-                }
-            }
-            _ => error(FsError::NotFound),
+            Some(PrivateNode::File(file)) => Ok(file.get_content(forest, store).await?),
+            Some(_) => error(FsError::NotAFile),
+            None => error(FsError::not_found(path_segments)),
         }
     }
 
@@ -685,6 +697,7 @@ impl PrivateDirectory {
             .await?;
 
         if !dir.content.entries.contains_key(filename.as_str()) {
+            crate::utils::validate_name(filename)?;
             let link = PrivateLink::with_file(PrivateFile::new(&dir.header.name, time, rng));
             dir.content.entries.insert(filename.to_string(), link);
         }
@@ -774,6 +787,7 @@ impl PrivateDirectory {
             }
             Some(PrivateNode::Dir(_)) => bail!(FsError::DirectoryAlreadyExists),
             None => {
+                crate::utils::validate_name(filename)?;
                 let file =
                     PrivateFile::with_content(&dir.header.name, time, content, forest, store, rng)
                         .await?;
@@ -990,7 +1004,7 @@ impl PrivateDirectory {
                 Ok(result)
             }
             SearchResult::NotADir(_, _) => bail!(FsError::NotADirectory),
-            _ => bail!(FsError::NotFound),
+            SearchResult::Missing(_, depth) => bail!(FsError::missing_at(path_segments, depth)),
         }
     }
 
@@ -1066,19 +1080,17 @@ impl PrivateDirectory {
         store: &impl BlockStore,
     ) -> Result<PrivateNode> {
         let (path, node_name) = crate::utils::split_last(path_segments)?;
-        let SearchResult::Found(dir) = self
+        let dir = self
             .get_leaf_dir_mut(path, search_latest, forest, store)
             .await?
-        else {
-            bail!(FsError::NotFound)
-        };
+            .into_found(path_segments)?;
 
         let removed_node = match dir.content.entries.remove(node_name) {
             Some(link) => {
                 link.resolve_owned_node(forest, store, Some(dir.header.name.clone()))
                     .await?
             }
-            None => bail!(FsError::NotFound),
+            None => bail!(FsError::not_found(path_segments)),
         };
 
         Ok(removed_node)
@@ -1099,12 +1111,10 @@ impl PrivateDirectory {
         rng: &mut (impl CryptoRngCore + CondSend),
     ) -> Result<()> {
         let (path, node_name) = crate::utils::split_last(path_segments)?;
-        let SearchResult::Found(dir) = self
+        let dir = self
             .get_leaf_dir_mut(path, search_latest, forest, store)
             .await?
-        else {
-            bail!(FsError::NotFound);
-        };
+            .into_found(path_segments)?;
 
         ensure!(
             !dir.content.entries.contains_key(node_name),
@@ -1191,6 +1201,12 @@ impl PrivateDirectory {
         store: &impl BlockStore,
         rng: &mut (impl CryptoRngCore + CondSend),
     ) -> Result<()> {
+        let (_, node_name) = crate::utils::split_last(path_segments_to)?;
+        crate::utils::validate_name(node_name)?;
+        ensure!(
+            !is_ancestor(path_segments_from, path_segments_to),
+            FsError::InvalidPath
+        );
         let removed_node = self
             .rm(path_segments_from, search_latest, forest, store)
             .await?;
@@ -1282,7 +1298,7 @@ impl PrivateDirectory {
             .await?;
 
         self.attach(
-            result.ok_or(FsError::NotFound)?,
+            result.ok_or_else(|| FsError::not_found(path_segments_from))?,
             path_segments_to,
             search_latest,
             time,
@@ -1604,6 +1620,118 @@ mod tests {
     use testresult::TestResult;
     use wnfs_common::MemoryBlockStore;
 
+    #[test(async_std::test)]
+    async fn invalid_names_are_rejected() -> TestResult {
+        let rng = &mut ChaCha12Rng::seed_from_u64(0);
+        let store = &MemoryBlockStore::default();
+        let forest = &mut HamtForest::new_rsa_2048_rc(rng);
+        let root_dir = &mut PrivateDirectory::new_rc(&forest.empty_name(), Utc::now(), rng);
+
+        let is_invalid_name = |result: Result<()>| {
+            matches!(
+                result.unwrap_err().downcast_ref(),
+                Some(FsError::InvalidName(_))
+            )
+        };
+
+        let result = root_dir
+            .write(
+                &["a/b".into()],
+                true,
+                Utc::now(),
+                b"x".to_vec(),
+                forest,
+                store,
+                rng,
+            )
+            .await;
+        assert!(is_invalid_name(result));
+
+        let result = root_dir
+            .mkdir(&[String::new()], true, Utc::now(), forest, store, rng)
+            .await;
+        assert!(is_invalid_name(result));
+
+        assert!(root_dir.ls(&[], true, forest, store).await?.is_empty());
+
+        // Existing entries with such names can still be moved away or removed
+        let time = Utc::now();
+        for name in ["x", "y"] {
+            root_dir
+                .write(
+                    &[name.into()],
+                    true,
+                    time,
+                    b"x".to_vec(),
+                    forest,
+                    store,
+                    rng,
+                )
+                .await?;
+            let entries = &mut Arc::make_mut(root_dir).content.entries;
+            let link = entries.remove(name).unwrap();
+            entries.insert(format!("{name}/legacy"), link);
+        }
+
+        let result = root_dir
+            .basic_mv(
+                &["x/legacy".into()],
+                &["a/b".into()],
+                true,
+                time,
+                forest,
+                store,
+                rng,
+            )
+            .await;
+        assert!(is_invalid_name(result));
+        root_dir
+            .basic_mv(
+                &["x/legacy".into()],
+                &["x".into()],
+                true,
+                time,
+                forest,
+                store,
+                rng,
+            )
+            .await?;
+        root_dir
+            .rm(&["y/legacy".into()], true, forest, store)
+            .await?;
+        let names = root_dir.ls(&[], true, forest, store).await?;
+        assert_eq!(names.len(), 1);
+        assert_eq!(names[0].0, "x");
+
+        Ok(())
+    }
+
+    #[test(async_std::test)]
+    async fn nodes_missing_from_the_forest_are_reported_with_their_name() -> TestResult {
+        let rng = &mut ChaCha12Rng::seed_from_u64(0);
+        let store = &MemoryBlockStore::default();
+        let forest = &mut HamtForest::new_rsa_2048_rc(rng);
+        let root_dir = &mut PrivateDirectory::new_rc(&forest.empty_name(), Utc::now(), rng);
+        root_dir
+            .mkdir(&["docs".into()], true, Utc::now(), forest, store, rng)
+            .await?;
+        let access_key = root_dir.as_node().store(forest, store, rng).await?;
+        let root_dir = PrivateNode::load(&access_key, forest, store, None)
+            .await?
+            .as_dir()?;
+
+        let other_forest = &HamtForest::new_rsa_2048_rc(rng);
+        let result = root_dir
+            .lookup_node("docs", false, other_forest, store)
+            .await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::NotFound { path }) if path == &["docs".to_string()]
+        ));
+
+        Ok(())
+    }
+
     #[test(async_std::test)]
     async fn look_up_can_fetch_file_added_to_directory() {
         let rng = &mut ChaCha12Rng::seed_from_u64(0);
@@ -2167,7 +2295,23 @@ mod tests {
             )
             .await;
 
-        assert!(result.is_err());
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::InvalidPath)
+        ));
+
+        // The failed move left the tree as it was
+        let ghibli = &[
+            "videos".into(),
+            "movies".into(),
+            "anime".into(),
+            "ghibli".into(),
+        ];
+        assert!(root_dir
+            .get_node(ghibli, true, forest, store)
+            .await
+            .unwrap()
+            .is_some());
     }
 
     #[async_std::test]
@@ -296,3 +296,37 @@ impl PartialEq for PrivateNodeHeader {
         self.inumber == other.inumber && self.ratchet == other.ratchet
     }
 }
+
+//--------------------------------------------------------------------------------------------------
+// Proptests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod proptests {
+    use super::*;
+    use proptest::prop_assert_eq;
+    use test_strategy::proptest;
+
+    /// Catching up over many revisions, like `PrivateNode::search_latest` and
+    /// `PrivateNodeHistory` do, relies on ratchets skipping whole epochs giving
+    /// exactly the ratchets that stepping through all revisions gives.
+    #[proptest(cases = 32)]
+    fn ratchet_inc_by_is_equivalent_to_repeated_inc(
+        seed: [u8; 32],
+        inc_small: u8,
+        inc_medium: u8,
+        #[strategy(0usize..70_000)] n: usize,
+    ) {
+        let ratchet = Ratchet::from_seed(&seed, inc_small, inc_medium);
+
+        let mut jumped = ratchet.clone();
+        jumped.inc_by(n);
+
+        let mut stepped = ratchet;
+        for _ in 0..n {
+            stepped.inc();
+        }
+
+        prop_assert_eq!(jumped, stepped);
+    }
+}
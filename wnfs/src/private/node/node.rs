@@ -6,6 +6,7 @@ use crate::{
         PrivateDirectory, PrivateFile, PrivateNodeContentSerializable, PrivateRef,
     },
     traits::Id,
+    NodeKind,
 };
 use anyhow::{bail, Result};
 use async_once_cell::OnceCell;
@@ -313,6 +314,14 @@ impl PrivateNode {
         matches!(self, Self::File(_))
     }
 
+    /// Returns the kind of the underlying node, for matching exhaustively.
+    pub fn kind(&self) -> NodeKind {
+        match self {
+            Self::File(_) => NodeKind::File,
+            Self::Dir(_) => NodeKind::Dir,
+        }
+    }
+
     /// Gets the latest version of the node using exponential search.
     ///
     /// # Examples
@@ -377,7 +386,8 @@ impl PrivateNode {
             // We expect the latest revision to have found valid nodes.
             // otherwise it's a revision that's filled with other stuff
             // than PrivateNodes, which should be an error.
-            .ok_or(FsError::NotFound.into())
+            // Lookups fill in the path, see `FsError::at_path`.
+            .ok_or(FsError::not_found(&[]).into())
     }
 
     /// Go to the latest known node and do conflict reconciliation, if multiple
@@ -525,7 +535,8 @@ impl PrivateNode {
             .await?
         {
             Some(cids) if cids.contains(&private_ref.content_cid) => private_ref.content_cid,
-            _ => bail!(FsError::NotFound),
+            // Lookups fill in the path, see `FsError::at_path`
+            _ => bail!(FsError::not_found(&[])),
         };
 
         Self::from_cid(cid, &private_ref.temporal_key, forest, store, parent_name).await
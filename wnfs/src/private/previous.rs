@@ -282,7 +282,9 @@ impl<F: PrivateForest + Clone> PrivateNodeOnPathHistory<F> {
             .await?
         {
             PathNodesResult::Complete(path_nodes) => path_nodes,
-            PathNodesResult::MissingLink(_, _) => bail!(FsError::NotFound),
+            PathNodesResult::MissingLink(path_nodes, _) => bail!(FsError::ParentNotFound {
+                path: path_segments[..=path_nodes.path.len()].to_vec()
+            }),
             PathNodesResult::NotADirectory(_, _) => bail!(FsError::NotADirectory),
         };
 
@@ -290,7 +292,8 @@ impl<F: PrivateForest + Clone> PrivateNodeOnPathHistory<F> {
             .lookup_node(target_path_segment, false, &forest, store)
             .await?
         else {
-            bail!(FsError::NotFound);
+            let path = [path_segments, std::slice::from_ref(target_path_segment)].concat();
+            bail!(FsError::not_found(&path));
         };
 
         let target_latest = if search_latest {
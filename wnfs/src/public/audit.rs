@@ -0,0 +1,366 @@
+//! Append-only audit logs of operations on public directories.
+
+use super::{Op, PublicDirectory};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures::{lock::Mutex, AsyncWrite, AsyncWriteExt};
+use libipld_core::cid::Cid;
+use serde::{Deserialize, Serialize};
+use std::{
+    future::Future,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use wnfs_common::{
+    utils::{Arc, CondSend, CondSync},
+    BlockStore, Storable,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Receives an entry for every operation an [`AuditLog`] applied.
+pub trait AuditSink: CondSync {
+    /// Records given entry. Entries arrive in the order their operations were applied.
+    fn record(&self, entry: AuditEntry) -> impl Future<Output = Result<()>> + CondSend;
+}
+
+/// The kind of operation an [`AuditEntry`] describes, see [`Op`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OpKind {
+    Write,
+    Mkdir,
+    Rm,
+    Mv,
+    SetMetadata,
+}
+
+/// A record of one applied operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Who applied the operation, see `AuditLog::with_actor`.
+    pub actor: Option<String>,
+    /// What kind of operation was applied.
+    pub kind: OpKind,
+    /// The path the operation affected. For moves, this is the source path.
+    pub path: Vec<String>,
+    /// The destination path of moves.
+    pub to: Option<Vec<String>>,
+    /// The CID of the node at `path` before the operation, if there was one.
+    pub before: Option<Cid>,
+    /// The CID of the node at `path`, or at `to` for moves, after the operation,
+    /// if there is one.
+    pub after: Option<Cid>,
+    /// The CID of the root directory before the operation.
+    pub root_before: Cid,
+    /// The CID of the root directory after the operation.
+    pub root_after: Cid,
+    /// When the operation happened. This is the operation's own timestamp,
+    /// or the time it was applied for operations that don't carry one.
+    pub time: DateTime<Utc>,
+}
+
+/// Applies operations to public directories like [`OpRecorder`](super::OpRecorder),
+/// and records an [`AuditEntry`] for each of them in an [`AuditSink`].
+///
+/// By default, an operation is reverted and fails if recording its entry fails,
+/// so no change goes unrecorded. See `AuditLog::with_abort_on_failure`.
+///
+/// Entries refer to the trees before and after each operation by CID, so the
+/// directory gets stored before and after each operation. With that, each
+/// audited operation starts a new revision.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use chrono::Utc;
+/// use wnfs::{
+///     public::{AuditLog, CborAuditSink, Op, PublicDirectory},
+///     common::MemoryBlockStore,
+/// };
+///
+/// #[async_std::main]
+/// async fn main() -> Result<()> {
+///     let store = &MemoryBlockStore::new();
+///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+///
+///     let log = AuditLog::new(CborAuditSink::new(Vec::new())).with_actor("alice");
+///     log.apply(dir, &Op::Mkdir { path: vec!["pictures".into()], time: Utc::now() }, store)
+///         .await?;
+///
+///     let entries = log.into_sink().into_inner();
+///     assert!(!entries.is_empty());
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AuditLog<S> {
+    sink: S,
+    actor: Option<String>,
+    abort_on_failure: bool,
+    failed_records: AtomicUsize,
+}
+
+/// An [`AuditSink`] that appends each entry, encoded as DAG-CBOR, to a writer.
+///
+/// Encoded entries are self-delimiting, so the written log is a CBOR sequence.
+#[derive(Debug)]
+pub struct CborAuditSink<W> {
+    writer: Mutex<W>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<S: AuditSink> AuditLog<S> {
+    /// Creates an audit log recording entries in given sink.
+    pub fn new(sink: S) -> Self {
+        Self {
+            sink,
+            actor: None,
+            abort_on_failure: true,
+            failed_records: AtomicUsize::new(0),
+        }
+    }
+
+    /// Sets who's applying the operations, so entries tell who changed what.
+    pub fn with_actor(mut self, actor: impl Into<String>) -> Self {
+        self.actor = Some(actor.into());
+        self
+    }
+
+    /// Sets whether operations get reverted and fail when recording their entry fails.
+    /// Otherwise, such failures only get counted, see `AuditLog::failed_records`.
+    pub fn with_abort_on_failure(mut self, abort_on_failure: bool) -> Self {
+        self.abort_on_failure = abort_on_failure;
+        self
+    }
+
+    /// How many entries couldn't be recorded without aborting their operation.
+    pub fn failed_records(&self) -> usize {
+        self.failed_records.load(Ordering::SeqCst)
+    }
+
+    /// Returns the sink entries got recorded in.
+    pub fn into_sink(self) -> S {
+        self.sink
+    }
+
+    /// Applies given operation to given directory and records an entry for it.
+    ///
+    /// Operations that fail don't get recorded and leave the directory as it was.
+    pub async fn apply(
+        &self,
+        dir: &mut Arc<PublicDirectory>,
+        op: &Op,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let (kind, path, to, time) = match op {
+            Op::Write { path, time, .. } => (OpKind::Write, path, None, *time),
+            Op::Mkdir { path, time } => (OpKind::Mkdir, path, None, *time),
+            Op::Rm { path } => (OpKind::Rm, path, None, Utc::now()),
+            Op::Mv { from, to, time } => (OpKind::Mv, from, Some(to), *time),
+            Op::SetMetadata { path, .. } => (OpKind::SetMetadata, path, None, Utc::now()),
+        };
+
+        let original = Arc::clone(dir);
+        let root_before = dir.store(store).await?;
+        let before = node_cid(dir, path, store).await?;
+
+        if let Err(e) = op.apply(dir, store).await {
+            *dir = original;
+            return Err(e);
+        }
+
+        let root_after = dir.store(store).await?;
+        let after = node_cid(dir, to.unwrap_or(path), store).await?;
+        let entry = AuditEntry {
+            actor: self.actor.clone(),
+            kind,
+            path: path.clone(),
+            to: to.cloned(),
+            before,
+            after,
+            root_before,
+            root_after,
+            time,
+        };
+
+        if let Err(e) = self.sink.record(entry).await {
+            if self.abort_on_failure {
+                *dir = original;
+                return Err(e);
+            }
+
+            self.failed_records.fetch_add(1, Ordering::SeqCst);
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: AsyncWrite + Unpin + CondSend> CborAuditSink<W> {
+    /// Creates a sink appending to given writer.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    /// Returns the writer entries got appended to.
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+}
+
+impl<W: AsyncWrite + Unpin + CondSend> AuditSink for CborAuditSink<W> {
+    async fn record(&self, entry: AuditEntry) -> Result<()> {
+        let bytes = serde_ipld_dagcbor::to_vec(&entry)?;
+        let mut writer = self.writer.lock().await;
+        writer.write_all(&bytes).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// The CID of the node at given path, where the empty path refers to the directory itself.
+async fn node_cid(
+    dir: &PublicDirectory,
+    path_segments: &[String],
+    store: &impl BlockStore,
+) -> Result<Option<Cid>> {
+    if path_segments.is_empty() {
+        return Ok(Some(dir.store(store).await?));
+    }
+
+    dir.node_cid(path_segments, store).await
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::bail;
+    use chrono::TimeZone;
+    use libipld_core::ipld::Ipld;
+    use testresult::TestResult;
+    use wnfs_common::MemoryBlockStore;
+
+    /// A sink that fails to record anything.
+    struct FailingSink;
+
+    impl AuditSink for FailingSink {
+        async fn record(&self, _: AuditEntry) -> Result<()> {
+            bail!("Audit log unavailable")
+        }
+    }
+
+    #[async_std::test]
+    async fn audit_entries_describe_each_change() -> TestResult {
+        let time = Utc.timestamp_opt(1_000_000, 0).unwrap();
+        let store = &MemoryBlockStore::new();
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        let dir = &mut PublicDirectory::new_rc(time);
+
+        let log = AuditLog::new(CborAuditSink::new(Vec::new())).with_actor("alice");
+        let ops = [
+            Op::Write {
+                path: path("docs/a.md"),
+                content: b"# A".to_vec(),
+                time,
+            },
+            Op::Mv {
+                from: path("docs/a.md"),
+                to: path("a.md"),
+                time,
+            },
+            Op::SetMetadata {
+                path: path("a.md"),
+                key: "reviewed".into(),
+                value: Ipld::Bool(true),
+            },
+            Op::Rm { path: path("docs") },
+        ];
+
+        let mut roots_before = Vec::new();
+        for op in &ops {
+            let root_before = dir.store(store).await?;
+            log.apply(dir, op, store).await?;
+            roots_before.push(root_before);
+        }
+        assert!(log
+            .apply(dir, &Op::Rm { path: path("docs") }, store)
+            .await
+            .is_err());
+
+        // Entries are self-delimiting, so each one is the shortest prefix that decodes
+        let bytes = log.into_sink().into_inner();
+        let mut entries = Vec::new();
+        let mut rest = bytes.as_slice();
+        while !rest.is_empty() {
+            let len = (1..=rest.len())
+                .find(|&len| serde_ipld_dagcbor::from_slice::<AuditEntry>(&rest[..len]).is_ok())
+                .unwrap();
+            entries.push(serde_ipld_dagcbor::from_slice::<AuditEntry>(&rest[..len])?);
+            rest = &rest[len..];
+        }
+
+        let kinds = entries.iter().map(|e| e.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [OpKind::Write, OpKind::Mv, OpKind::SetMetadata, OpKind::Rm]
+        );
+        assert!(entries.iter().all(|e| e.actor.as_deref() == Some("alice")));
+        for (entry, root_before) in entries.iter().zip(&roots_before) {
+            assert_eq!(entry.root_before, *root_before);
+        }
+        for pair in entries.windows(2) {
+            assert_eq!(pair[0].root_after, pair[1].root_before);
+        }
+        assert_eq!(entries[3].root_after, dir.store(store).await?);
+
+        let [write, mv, set_metadata, rm] = &entries[..] else {
+            panic!("Expected four entries");
+        };
+        assert_eq!((write.before, write.time), (None, time));
+        assert_eq!(mv.to, Some(path("a.md")));
+        assert_eq!(mv.before, write.after);
+        assert_eq!(mv.after, write.after);
+        assert_ne!(set_metadata.after, set_metadata.before);
+        assert!(rm.before.is_some() && rm.after.is_none());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn recording_failures_abort_the_change_if_configured() -> TestResult {
+        let time = Utc.timestamp_opt(1_000_000, 0).unwrap();
+        let store = &MemoryBlockStore::new();
+        let dir = &mut PublicDirectory::new_rc(time);
+        let op = Op::Mkdir {
+            path: vec!["pictures".into()],
+            time,
+        };
+
+        let log = AuditLog::new(FailingSink);
+        assert!(log.apply(dir, &op, store).await.is_err());
+        assert!(dir.ls(&[], store).await?.is_empty());
+
+        let log = AuditLog::new(FailingSink).with_abort_on_failure(false);
+        log.apply(dir, &op, store).await?;
+        assert_eq!(dir.ls(&[], store).await?.len(), 1);
+        assert_eq!(log.failed_records(), 1);
+
+        Ok(())
+    }
+}
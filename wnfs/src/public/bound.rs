@@ -0,0 +1,217 @@
+//! Public directories bound to a block store.
+
+use super::{PublicDirectory, PublicNode};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use libipld_core::cid::Cid;
+use wnfs_common::{utils::Arc, BlockStore, Metadata, Storable};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A public directory together with the block store it's read from and written to.
+///
+/// This offers the common `PublicDirectory` operations without passing the store
+/// to each of them. Operations that change the directory consume the binding and
+/// return the updated one, so they can be chained. Create one via
+/// `PublicDirectory::with_store`.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use chrono::Utc;
+/// use wnfs::{
+///     public::PublicDirectory,
+///     common::MemoryBlockStore,
+/// };
+///
+/// #[async_std::main]
+/// async fn main() -> Result<()> {
+///     let store = &MemoryBlockStore::new();
+///     let time = Utc::now();
+///
+///     let dir = PublicDirectory::new_rc(time)
+///         .with_store(store)
+///         .mkdir(&["pictures".into()], time)
+///         .await?
+///         .write(&["notes.txt".into()], b"Hello".to_vec(), time)
+///         .await?;
+///
+///     assert_eq!(dir.read(&["notes.txt".into()]).await?, b"Hello");
+///     assert_eq!(dir.ls(&[]).await?.len(), 2);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BoundDirectory<'a, B: BlockStore> {
+    dir: Arc<PublicDirectory>,
+    store: &'a B,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl PublicDirectory {
+    /// Binds this directory to given block store, see `BoundDirectory`.
+    pub fn with_store<'a, B: BlockStore>(self: &Arc<Self>, store: &'a B) -> BoundDirectory<'a, B> {
+        BoundDirectory {
+            dir: Arc::clone(self),
+            store,
+        }
+    }
+}
+
+impl<'a, B: BlockStore> BoundDirectory<'a, B> {
+    /// The bound directory.
+    pub fn dir(&self) -> &Arc<PublicDirectory> {
+        &self.dir
+    }
+
+    /// The block store this directory is bound to.
+    pub fn store(&self) -> &'a B {
+        self.store
+    }
+
+    /// Gives up the binding, returning the directory.
+    pub fn into_inner(self) -> Arc<PublicDirectory> {
+        self.dir
+    }
+
+    /// See `PublicDirectory::get_node`.
+    pub async fn get_node(&self, path_segments: &[String]) -> Result<Option<&PublicNode>> {
+        self.dir.get_node(path_segments, self.store).await
+    }
+
+    /// See `PublicDirectory::read`.
+    pub async fn read(&self, path_segments: &[String]) -> Result<Vec<u8>> {
+        self.dir.read(path_segments, self.store).await
+    }
+
+    /// See `PublicDirectory::ls`.
+    pub async fn ls(&self, path_segments: &[String]) -> Result<Vec<(String, Metadata)>> {
+        self.dir.ls(path_segments, self.store).await
+    }
+
+    /// See `PublicDirectory::write`.
+    pub async fn write(
+        mut self,
+        path_segments: &[String],
+        content: Vec<u8>,
+        time: DateTime<Utc>,
+    ) -> Result<Self> {
+        self.dir
+            .write(path_segments, content, time, self.store)
+            .await?;
+        Ok(self)
+    }
+
+    /// See `PublicDirectory::mkdir`.
+    pub async fn mkdir(mut self, path_segments: &[String], time: DateTime<Utc>) -> Result<Self> {
+        self.dir.mkdir(path_segments, time, self.store).await?;
+        Ok(self)
+    }
+
+    /// See `PublicDirectory::rm`. Returns the removed node along with the updated directory.
+    pub async fn rm(mut self, path_segments: &[String]) -> Result<(Self, PublicNode)> {
+        let removed = self.dir.rm(path_segments, self.store).await?;
+        Ok((self, removed))
+    }
+
+    /// See `PublicDirectory::basic_mv`.
+    pub async fn basic_mv(
+        mut self,
+        path_segments_from: &[String],
+        path_segments_to: &[String],
+        time: DateTime<Utc>,
+    ) -> Result<Self> {
+        self.dir
+            .basic_mv(path_segments_from, path_segments_to, time, self.store)
+            .await?;
+        Ok(self)
+    }
+
+    /// See `PublicDirectory::mv`.
+    pub async fn mv(
+        mut self,
+        path_segments_from: &[String],
+        path_segments_to: &[String],
+        time: DateTime<Utc>,
+    ) -> Result<Self> {
+        self.dir
+            .mv(path_segments_from, path_segments_to, time, self.store)
+            .await?;
+        Ok(self)
+    }
+
+    /// See `PublicDirectory::cp`.
+    pub async fn cp(
+        mut self,
+        path_segments_from: &[String],
+        path_segments_to: &[String],
+        time: DateTime<Utc>,
+    ) -> Result<Self> {
+        self.dir
+            .cp(path_segments_from, path_segments_to, time, self.store)
+            .await?;
+        Ok(self)
+    }
+
+    /// Stores the directory in the bound block store and returns its CID.
+    pub async fn persist(&self) -> Result<Cid> {
+        self.dir.store(self.store).await
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use testresult::TestResult;
+    use wnfs_common::MemoryBlockStore;
+
+    #[async_std::test]
+    async fn bound_directories_match_explicit_store_calls() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let notes = &["docs".into(), "notes.txt".into()];
+        let copy = &["notes.txt".into()];
+        let moved = &["moved.txt".into()];
+
+        let explicit = &mut PublicDirectory::new_rc(time);
+        explicit
+            .write(notes, b"Hello".to_vec(), time, store)
+            .await?;
+        explicit.cp(notes, copy, time, store).await?;
+        explicit.basic_mv(copy, moved, time, store).await?;
+        explicit.mkdir(&["empty".into()], time, store).await?;
+        explicit.rm(&["empty".into()], store).await?;
+
+        let (bound, removed) = PublicDirectory::new_rc(time)
+            .with_store(store)
+            .write(notes, b"Hello".to_vec(), time)
+            .await?
+            .cp(notes, copy, time)
+            .await?
+            .basic_mv(copy, moved, time)
+            .await?
+            .mkdir(&["empty".into()], time)
+            .await?
+            .rm(&["empty".into()])
+            .await?;
+
+        assert!(removed.as_dir().is_ok());
+        assert_eq!(bound.read(moved).await?, b"Hello");
+        assert!(bound.get_node(copy).await?.is_none());
+        assert_eq!(bound.persist().await?, explicit.store(store).await?);
+
+        Ok(())
+    }
+}
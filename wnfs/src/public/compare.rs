@@ -0,0 +1,244 @@
+//! Comparing public file system trees with configurable notions of equality.
+
+use super::{PublicDirectory, PublicFile, PublicNode};
+use anyhow::Result;
+use async_recursion::async_recursion;
+use wnfs_common::{BlockStore, Metadata};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// Metadata entries holding timestamps.
+const TIMESTAMP_KEYS: &[&str] = &["created", "modified"];
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Which parts of nodes `PublicDirectory::compare` and `PublicNode::compare` take into account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EqMode {
+    /// Nodes are equal if they're identical, including their previous revisions,
+    /// i.e. if they have the same CID.
+    #[default]
+    Exact,
+    /// Like `Exact`, but previous revisions are ignored, on all nodes of the trees.
+    IgnoreHistory,
+    /// Only names, file content and metadata other than creation and modification
+    /// times are compared. Previous revisions are ignored as well.
+    ContentOnly,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl EqMode {
+    fn includes_history(self) -> bool {
+        self == Self::Exact
+    }
+
+    fn includes_timestamps(self) -> bool {
+        self != Self::ContentOnly
+    }
+}
+
+impl PublicDirectory {
+    /// Compares this directory tree with another one, taking into account what
+    /// given mode says.
+    ///
+    /// Subtrees with the same CID are equal in every mode, so they don't get
+    /// loaded. Nodes that weren't stored yet get stored to compute their CIDs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::{EqMode, PublicDirectory},
+    ///     common::{MemoryBlockStore, Storable},
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let time = Utc::now();
+    ///     let dir = &mut PublicDirectory::new_rc(time);
+    ///     dir.write(&["notes.txt".into()], b"Hello".to_vec(), time, store).await?;
+    ///
+    ///     // Same content, but with a history
+    ///     let other = &mut PublicDirectory::new_rc(time);
+    ///     other.write(&["notes.txt".into()], b"Draft".to_vec(), time, store).await?;
+    ///     other.store(store).await?;
+    ///     other.write(&["notes.txt".into()], b"Hello".to_vec(), time, store).await?;
+    ///
+    ///     assert!(!dir.compare(other, EqMode::Exact, store).await?);
+    ///     assert!(dir.compare(other, EqMode::IgnoreHistory, store).await?);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg_attr(not(target_arch = "wasm32"), async_recursion)]
+    #[cfg_attr(target_arch = "wasm32", async_recursion(?Send))]
+    pub async fn compare(
+        &self,
+        other: &PublicDirectory,
+        mode: EqMode,
+        store: &impl BlockStore,
+    ) -> Result<bool> {
+        if !metadata_eq(&self.metadata, &other.metadata, mode)
+            || (mode.includes_history() && self.previous != other.previous)
+            || !self.userland.keys().eq(other.userland.keys())
+        {
+            return Ok(false);
+        }
+
+        for (link, other_link) in self.userland.values().zip(other.userland.values()) {
+            if link.deep_eq(other_link, store).await? {
+                continue;
+            }
+
+            if mode == EqMode::Exact {
+                return Ok(false);
+            }
+
+            let node = link.resolve_value(store).await?;
+            let other_node = other_link.resolve_value(store).await?;
+            if !node.compare(other_node, mode, store).await? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl PublicFile {
+    /// Compares this file with another one, taking into account what given mode says.
+    /// See `PublicDirectory::compare`.
+    pub async fn compare(
+        &self,
+        other: &PublicFile,
+        mode: EqMode,
+        store: &impl BlockStore,
+    ) -> Result<bool> {
+        Ok(metadata_eq(&self.metadata, &other.metadata, mode)
+            && (!mode.includes_history() || self.previous == other.previous)
+            && self.userland.resolve_cid(store).await? == other.userland.resolve_cid(store).await?)
+    }
+}
+
+impl PublicNode {
+    /// Compares this node with another one, taking into account what given mode says.
+    /// Files never equal directories. See `PublicDirectory::compare`.
+    pub async fn compare(
+        &self,
+        other: &PublicNode,
+        mode: EqMode,
+        store: &impl BlockStore,
+    ) -> Result<bool> {
+        match (self, other) {
+            (Self::File(file), Self::File(other_file)) => {
+                file.compare(other_file, mode, store).await
+            }
+            (Self::Dir(dir), Self::Dir(other_dir)) => dir.compare(other_dir, mode, store).await,
+            _ => Ok(false),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+fn metadata_eq(metadata: &Metadata, other: &Metadata, mode: EqMode) -> bool {
+    if mode.includes_timestamps() {
+        return metadata == other;
+    }
+
+    let without_timestamps = |metadata: &Metadata| {
+        let mut metadata = metadata.0.clone();
+        metadata.retain(|key, _| !TIMESTAMP_KEYS.contains(&key.as_str()));
+        metadata
+    };
+
+    without_timestamps(metadata) == without_timestamps(other)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use testresult::TestResult;
+    use wnfs_common::{MemoryBlockStore, Storable};
+
+    #[async_std::test]
+    async fn eq_modes_ignore_history_and_timestamps_on_request() -> TestResult {
+        let time = Utc::now();
+        let later = time + Duration::seconds(60);
+        let store = &MemoryBlockStore::new();
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+
+        let dir = &mut PublicDirectory::new_rc(time);
+        dir.write(&path("docs/notes.txt"), b"Notes".to_vec(), time, store)
+            .await?;
+        dir.write(&path("todo.txt"), b"Todo".to_vec(), time, store)
+            .await?;
+
+        // The same tree, but with history below the root
+        let with_history = &mut PublicDirectory::new_rc(time);
+        with_history
+            .write(&path("docs/notes.txt"), b"Draft".to_vec(), time, store)
+            .await?;
+        with_history
+            .write(&path("todo.txt"), b"Todo".to_vec(), time, store)
+            .await?;
+        with_history.store(store).await?;
+        with_history
+            .write(&path("docs/notes.txt"), b"Notes".to_vec(), time, store)
+            .await?;
+
+        // The same tree, but written at another time
+        let written_later = &mut PublicDirectory::new_rc(later);
+        written_later
+            .write(&path("docs/notes.txt"), b"Notes".to_vec(), later, store)
+            .await?;
+        written_later
+            .write(&path("todo.txt"), b"Todo".to_vec(), later, store)
+            .await?;
+
+        // A tree with other content
+        let changed = &mut PublicDirectory::new_rc(time);
+        changed
+            .write(&path("docs/notes.txt"), b"Changed".to_vec(), time, store)
+            .await?;
+        changed
+            .write(&path("todo.txt"), b"Todo".to_vec(), time, store)
+            .await?;
+
+        let modes = [EqMode::Exact, EqMode::IgnoreHistory, EqMode::ContentOnly];
+        for (other, expected) in [
+            (&*dir, [true, true, true]),
+            (&*with_history, [false, true, true]),
+            (&*written_later, [false, false, true]),
+            (&*changed, [false, false, false]),
+        ] {
+            for (mode, expected) in modes.into_iter().zip(expected) {
+                assert_eq!(dir.compare(other, mode, store).await?, expected, "{mode:?}");
+            }
+        }
+
+        // Files never equal directories
+        let docs = dir.get_node(&path("docs"), store).await?.unwrap();
+        let todo = dir.get_node(&path("todo.txt"), store).await?.unwrap();
+        assert!(!docs.compare(todo, EqMode::ContentOnly, store).await?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+//! Configuration for how public file system data gets stored.
+
+use crate::error::FsError;
+use anyhow::{ensure, Result};
+use wnfs_unixfs_file::{
+    balanced_tree::DEFAULT_DEGREE,
+    chunker::{DEFAULT_CHUNKS_SIZE, DEFAULT_CHUNK_SIZE_LIMIT},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Configures how public file content gets laid out in the block store.
+///
+/// File content is split into fixed-size chunks, which are the leaves of a balanced
+/// tree of intermediate content nodes. The chunk size defaults to 256 KiB and can be
+/// at most 1 MiB, the block size limit common IPFS implementations enforce. The content
+/// fan-out is the maximum number of child links each intermediate node holds.
+///
+/// A smaller fan-out results in a deeper tree with smaller intermediate nodes.
+/// Seeking in a file, e.g. via `PublicFile::read_at`, loads one intermediate node per
+/// level of the tree before reaching the chunk at the requested offset, so a deeper tree
+/// means more sequential block loads, but less data per load. Proofs of a chunk are
+/// smaller with smaller fan-out too. A larger fan-out results in a shallower tree with
+/// bigger intermediate nodes.
+///
+/// The same content stored with the same configuration always results in the same
+/// content CID, but changing the chunk size or fan-out changes the CID.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use chrono::Utc;
+/// use wnfs::{
+///     public::{PublicFile, StoreConfig},
+///     common::MemoryBlockStore,
+/// };
+///
+/// #[async_std::main]
+/// async fn main() -> Result<()> {
+///     let store = &MemoryBlockStore::new();
+///     let config = &StoreConfig::default().with_content_fanout(16);
+///
+///     let mut file = PublicFile::new(Utc::now());
+///     file.set_content_with_config(vec![42; 10_000_000], Utc::now(), config, store)
+///         .await?;
+///
+///     assert_eq!(file.size(store).await?, 10_000_000);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreConfig {
+    /// The maximum number of child links of intermediate file content nodes.
+    pub content_fanout: usize,
+    /// The size in bytes of the chunks file content gets split into.
+    pub chunk_size: usize,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl StoreConfig {
+    /// Sets the maximum number of child links of intermediate file content nodes.
+    pub fn with_content_fanout(mut self, content_fanout: usize) -> Self {
+        self.content_fanout = content_fanout;
+        self
+    }
+
+    /// Sets the size in bytes of the chunks file content gets split into.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Checks that this configuration can be used for storing data.
+    pub(crate) fn validate(&self) -> Result<()> {
+        ensure!(
+            self.content_fanout > 1,
+            FsError::InvalidContentFanout(self.content_fanout)
+        );
+        ensure!(
+            (1..=DEFAULT_CHUNK_SIZE_LIMIT).contains(&self.chunk_size),
+            FsError::InvalidChunkSize(self.chunk_size)
+        );
+
+        Ok(())
+    }
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self {
+            content_fanout: DEFAULT_DEGREE,
+            chunk_size: DEFAULT_CHUNKS_SIZE,
+        }
+    }
+}
@@ -0,0 +1,1036 @@
+//! Computing the changes between two versions of a public directory tree.
+
+use super::{tombstone, PublicDirectory, PublicLink, PublicNode};
+use crate::{error::FsError, SearchResult};
+use anyhow::{bail, ensure, Result};
+use async_stream::try_stream;
+use futures::{Stream, TryStreamExt};
+use libipld_core::cid::Cid;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use wnfs_common::{utils::Arc, BlockStore, Storable, CODEC_RAW};
+use wnfs_unixfs_file::unixfs::UnixFsFile;
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// At most this many content chunks of a file are compared to detect similar
+/// renames, so the content DAGs of huge files don't need to be loaded fully.
+const MAX_RENAME_CHUNKS: usize = 4096;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A change between two versions of a public directory tree.
+///
+/// Nodes that only exist in one of the versions are reported once for the
+/// whole subtree rooted at them. All CIDs are the CIDs of public nodes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PathChange {
+    /// A node that only exists in the new version.
+    Added { path: Vec<String>, cid: Cid },
+    /// A node that only exists in the old version.
+    Removed { path: Vec<String>, cid: Cid },
+    /// A file that exists in both versions, but changed.
+    Modified {
+        path: Vec<String>,
+        old_cid: Cid,
+        new_cid: Cid,
+    },
+    /// A node that was moved to another path, possibly with slightly changed content.
+    Renamed {
+        from: Vec<String>,
+        to: Vec<String>,
+        old_cid: Cid,
+        new_cid: Cid,
+    },
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl PathChange {
+    /// The path this change applies to. For renames, that's the new path.
+    pub fn path(&self) -> &[String] {
+        match self {
+            Self::Added { path, .. } | Self::Removed { path, .. } | Self::Modified { path, .. } => {
+                path
+            }
+            Self::Renamed { to, .. } => to,
+        }
+    }
+}
+
+impl PublicDirectory {
+    /// Computes the changes needed to get from this directory to another one.
+    ///
+    /// Subtrees with equal CIDs get skipped without loading them, so diffing
+    /// large trees with few changes is fast. Changes are returned sorted by path.
+    ///
+    /// Moved nodes show up as a pair of `Removed` and `Added` changes,
+    /// see `diff_with_renames` for detecting these.
+    ///
+    /// Both directories get stored in given block store as part of the comparison.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::{PathChange, PublicDirectory},
+    ///     common::MemoryBlockStore,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let old = &mut PublicDirectory::new_rc(Utc::now());
+    ///     old.mkdir(&["pictures".into()], Utc::now(), store).await?;
+    ///
+    ///     let new = &mut old.clone();
+    ///     new.write(&["pictures".into(), "cat.jpg".into()], b"meow".to_vec(), Utc::now(), store)
+    ///         .await?;
+    ///
+    ///     let changes = old.diff(new, store).await?;
+    ///
+    ///     assert!(matches!(
+    ///         &changes[..],
+    ///         [PathChange::Added { path, .. }] if path == &["pictures", "cat.jpg"]
+    ///     ));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn diff(&self, other: &Self, store: &impl BlockStore) -> Result<Vec<PathChange>> {
+        self.diff_stream(other, store).try_collect().await
+    }
+
+    /// Computes the same changes as `diff`, but yields each change as soon as
+    /// it's found instead of collecting all of them.
+    ///
+    /// Memory use is bounded by the depth and width of the trees instead of the
+    /// number of changes, so huge diffs can be processed incrementally.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use futures::TryStreamExt;
+    /// use wnfs::{
+    ///     public::{PathChange, PublicDirectory},
+    ///     common::MemoryBlockStore,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let old = &mut PublicDirectory::new_rc(Utc::now());
+    ///
+    ///     let new = &mut old.clone();
+    ///     for i in 0..100 {
+    ///         new.write(&[format!("{i}.txt")], b"new".to_vec(), Utc::now(), store)
+    ///             .await?;
+    ///     }
+    ///
+    ///     let mut changes = std::pin::pin!(old.diff_stream(new, store));
+    ///     let mut added = 0;
+    ///     while let Some(change) = changes.try_next().await? {
+    ///         assert!(matches!(change, PathChange::Added { .. }));
+    ///         added += 1;
+    ///     }
+    ///
+    ///     assert_eq!(added, 100);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn diff_stream<'a>(
+        &'a self,
+        other: &'a Self,
+        store: &'a impl BlockStore,
+    ) -> impl Stream<Item = Result<PathChange>> + 'a {
+        try_stream! {
+            if self.store(store).await? != other.store(store).await? {
+                for await change in diff_entries(self, other, store) {
+                    yield change?;
+                }
+            }
+        }
+    }
+
+    /// Stores this directory and computes the changes since the directory
+    /// at given previous root CID, for feeding change logs.
+    ///
+    /// Without a previous root, all entries of this directory are reported as added.
+    /// Returns the CID this directory was stored as together with the changes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::{PathChange, PublicDirectory},
+    ///     common::MemoryBlockStore,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     dir.mkdir(&["pictures".into()], Utc::now(), store).await?;
+    ///
+    ///     let (root, changes) = dir.commit_and_diff(None, store).await?;
+    ///     assert_eq!(changes.len(), 1);
+    ///
+    ///     dir.mkdir(&["videos".into()], Utc::now(), store).await?;
+    ///
+    ///     let (_, changes) = dir.commit_and_diff(Some(root), store).await?;
+    ///     assert!(matches!(
+    ///         &changes[..],
+    ///         [PathChange::Added { path, .. }] if path == &["videos"]
+    ///     ));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn commit_and_diff(
+        &self,
+        previous_root: Option<Cid>,
+        store: &impl BlockStore,
+    ) -> Result<(Cid, Vec<PathChange>)> {
+        let root = self.store(store).await?;
+        let changes = match previous_root {
+            Some(previous_root) if previous_root == root => Vec::new(),
+            Some(previous_root) => {
+                let previous = PublicDirectory::load(&previous_root, store).await?;
+                previous.diff(self, store).await?
+            }
+            None => {
+                let empty = PublicDirectory::new(Default::default());
+                diff_entries(&empty, self, store).try_collect().await?
+            }
+        };
+
+        Ok((root, changes))
+    }
+
+    /// Computes the changes needed to get from this directory to another one,
+    /// like `diff`, but reports moved nodes as `Renamed` instead of pairs of
+    /// `Removed` and `Added` changes.
+    ///
+    /// A removed node and an added node are considered a rename if they're
+    /// directories with the same CID or files with the same content CID. The
+    /// content CID is part of the file node, so this doesn't load any content.
+    ///
+    /// If `similarity` is below `1.0`, files with differing content are matched
+    /// approximately too: Their similarity is the share of content chunks they
+    /// have in common, which requires loading the content DAG nodes above the
+    /// chunks. Only the first few thousand chunks of huge files are compared.
+    /// Each removed file is matched to the most similar added file that
+    /// reaches the threshold.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::{PathChange, PublicDirectory},
+    ///     common::MemoryBlockStore,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let old = &mut PublicDirectory::new_rc(Utc::now());
+    ///     old.write(&["draft.md".into()], b"# Notes".to_vec(), Utc::now(), store)
+    ///         .await?;
+    ///
+    ///     let new = &mut old.clone();
+    ///     new.basic_mv(&["draft.md".into()], &["notes.md".into()], Utc::now(), store)
+    ///         .await?;
+    ///
+    ///     let changes = old.diff_with_renames(new, 1.0, store).await?;
+    ///
+    ///     assert!(matches!(
+    ///         &changes[..],
+    ///         [PathChange::Renamed { from, to, .. }] if from == &["draft.md"] && to == &["notes.md"]
+    ///     ));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn diff_with_renames(
+        &self,
+        other: &Self,
+        similarity: f32,
+        store: &impl BlockStore,
+    ) -> Result<Vec<PathChange>> {
+        let changes = self.diff(other, store).await?;
+        let approximate = similarity < 1.0;
+
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        for (index, change) in changes.iter().enumerate() {
+            match change {
+                PathChange::Removed { cid, .. } => {
+                    removed.push((index, rename_key(cid, approximate, store).await?))
+                }
+                PathChange::Added { cid, .. } => {
+                    added.push((index, rename_key(cid, approximate, store).await?))
+                }
+                _ => {}
+            }
+        }
+
+        // Pairs of indices into `changes` of a removed and an added node
+        let mut renames = Vec::new();
+        let mut matched = BTreeSet::new();
+
+        for (removed_index, removed_key) in removed.iter() {
+            let exact = added.iter().find(|(added_index, added_key)| {
+                added_key.exact == removed_key.exact && !matched.contains(added_index)
+            });
+            if let Some((added_index, _)) = exact {
+                matched.insert(*added_index);
+                matched.insert(*removed_index);
+                renames.push((*removed_index, *added_index));
+            }
+        }
+
+        if approximate {
+            for (removed_index, removed_key) in removed.iter() {
+                if matched.contains(removed_index) || removed_key.chunks.is_empty() {
+                    continue;
+                }
+
+                let best = added
+                    .iter()
+                    .filter(|(added_index, added_key)| {
+                        !matched.contains(added_index) && !added_key.chunks.is_empty()
+                    })
+                    .map(|(added_index, added_key)| {
+                        (added_index, chunk_similarity(removed_key, added_key))
+                    })
+                    .filter(|(_, score)| *score >= similarity)
+                    .max_by(|(_, a), (_, b)| a.total_cmp(b));
+
+                if let Some((added_index, _)) = best {
+                    matched.insert(*added_index);
+                    matched.insert(*removed_index);
+                    renames.push((*removed_index, *added_index));
+                }
+            }
+        }
+
+        let mut result = changes
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !matched.contains(index))
+            .map(|(_, change)| change.clone())
+            .collect::<Vec<_>>();
+
+        for (removed_index, added_index) in renames {
+            let (
+                PathChange::Removed {
+                    path: from,
+                    cid: old_cid,
+                },
+                PathChange::Added {
+                    path: to,
+                    cid: new_cid,
+                },
+            ) = (&changes[removed_index], &changes[added_index])
+            else {
+                unreachable!("only removed and added changes get matched");
+            };
+
+            result.push(PathChange::Renamed {
+                from: from.clone(),
+                to: to.clone(),
+                old_cid: *old_cid,
+                new_cid: *new_cid,
+            });
+        }
+
+        result.sort_by(|a, b| a.path().cmp(b.path()));
+        Ok(result)
+    }
+
+    /// Applies changes computed by `diff` or `diff_with_renames` to this directory,
+    /// returning the updated directory. This directory stays as it is.
+    ///
+    /// Together with the blocks of added and modified nodes, that's enough to
+    /// reconstruct the directory a diff was computed against from the one it was
+    /// computed from. Entries of directories along the changed paths are the same as in
+    /// the other directory, but these directories become new revisions of this directory's
+    /// ones, so their metadata and previous links differ.
+    ///
+    /// Removals are applied before additions. Fails with `FsError::InvalidDiff` if
+    /// a change doesn't apply, e.g. because the removed or modified node at a path
+    /// has another CID, a node to be added already exists or its parent is missing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let old = &mut PublicDirectory::new_rc(Utc::now());
+    ///     old.mkdir(&["pictures".into()], Utc::now(), store).await?;
+    ///
+    ///     let new = &mut old.clone();
+    ///     new.write(&["pictures".into(), "cat.jpg".into()], b"meow".to_vec(), Utc::now(), store)
+    ///         .await?;
+    ///
+    ///     let changes = old.diff(new, store).await?;
+    ///     let rebuilt = old.apply_diff(&changes, store).await?;
+    ///
+    ///     assert!(rebuilt.diff(new, store).await?.is_empty());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn apply_diff(
+        &self,
+        changes: &[PathChange],
+        store: &impl BlockStore,
+    ) -> Result<Arc<Self>> {
+        let dir = &mut Arc::new(self.clone());
+
+        for change in changes {
+            match change {
+                PathChange::Removed { path, cid } => {
+                    Self::take_node(dir, path, cid, store).await?;
+                }
+                PathChange::Renamed { from, old_cid, .. } => {
+                    Self::take_node(dir, from, old_cid, store).await?;
+                }
+                PathChange::Modified { path, old_cid, .. } => {
+                    Self::take_node(dir, path, old_cid, store).await?;
+                }
+                PathChange::Added { .. } => {}
+            }
+        }
+
+        for change in changes {
+            match change {
+                PathChange::Added { path, cid }
+                | PathChange::Modified {
+                    path, new_cid: cid, ..
+                } => {
+                    Self::put_node(dir, path, cid, store).await?;
+                }
+                PathChange::Renamed { to, new_cid, .. } => {
+                    Self::put_node(dir, to, new_cid, store).await?;
+                }
+                PathChange::Removed { .. } => {}
+            }
+        }
+
+        Ok(Arc::clone(dir))
+    }
+
+    /// Removes the node at given path, making sure it's stored as given CID.
+    async fn take_node(
+        dir: &mut Arc<Self>,
+        path_segments: &[String],
+        expected: &Cid,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let invalid = || FsError::InvalidDiff(format!("{} doesn't exist", path_segments.join("/")));
+        let (name, path) = path_segments.split_last().ok_or_else(invalid)?;
+        let SearchResult::Found(parent) = dir.get_leaf_dir_mut(path, store).await? else {
+            bail!(invalid());
+        };
+        let link = parent.userland.get(name).ok_or_else(invalid)?;
+
+        ensure!(
+            &link.resolve_cid(store).await? == expected,
+            FsError::InvalidDiff(format!("{} isn't {expected}", path_segments.join("/")))
+        );
+
+        parent.userland.remove(name);
+        Ok(())
+    }
+
+    /// Inserts the node with given CID at given path, which must be free.
+    async fn put_node(
+        dir: &mut Arc<Self>,
+        path_segments: &[String],
+        cid: &Cid,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let (name, path) = path_segments
+            .split_last()
+            .ok_or_else(|| FsError::InvalidDiff("Cannot add the root directory".into()))?;
+        let SearchResult::Found(parent) = dir.get_leaf_dir_mut(path, store).await? else {
+            bail!(FsError::InvalidDiff(format!(
+                "Parent of {} doesn't exist",
+                path_segments.join("/")
+            )));
+        };
+
+        ensure!(
+            !parent.userland.contains_key(name),
+            FsError::InvalidDiff(format!("{} already exists", path_segments.join("/")))
+        );
+
+        let node = PublicNode::load(cid, store).await?;
+        tombstone::clear_tombstone(&mut parent.metadata, name);
+        parent.userland.insert(name.clone(), PublicLink::new(node));
+        Ok(())
+    }
+}
+
+/// What we compare removed & added nodes by to detect renames.
+struct RenameKey {
+    /// The node CID for directories, the content CID for files.
+    exact: Cid,
+    /// The CIDs of a file's first `MAX_RENAME_CHUNKS` content chunks. Empty for
+    /// directories, and unless chunks were asked for.
+    chunks: HashSet<Cid>,
+}
+
+/// Loads the node with given CID to find what it can be matched by as a rename.
+/// Only loads a file's content DAG if `with_chunks` is set.
+async fn rename_key(cid: &Cid, with_chunks: bool, store: &impl BlockStore) -> Result<RenameKey> {
+    Ok(match PublicNode::load(cid, store).await? {
+        PublicNode::Dir(_) => RenameKey {
+            exact: *cid,
+            chunks: HashSet::new(),
+        },
+        PublicNode::File(file) => {
+            let content_cid = file.userland.resolve_cid(store).await?;
+            let chunks = if !with_chunks {
+                HashSet::new()
+            } else {
+                content_chunks(content_cid, store).await?
+            };
+
+            RenameKey {
+                exact: content_cid,
+                chunks,
+            }
+        }
+    })
+}
+
+/// Collects the CIDs of the leaves of given content DAG, in content order, up to
+/// `MAX_RENAME_CHUNKS` of them. Raw leaves don't get loaded.
+async fn content_chunks(content_cid: Cid, store: &impl BlockStore) -> Result<HashSet<Cid>> {
+    let mut chunks = HashSet::new();
+    // Leaves are all at the same depth, so visiting the DAG breadth-first
+    // finds them in content order
+    let mut queue = VecDeque::from([content_cid]);
+    while let Some(cid) = queue.pop_front() {
+        if chunks.len() >= MAX_RENAME_CHUNKS {
+            break;
+        }
+
+        if cid.codec() == CODEC_RAW {
+            chunks.insert(cid);
+            continue;
+        }
+
+        let links = UnixFsFile::load(&cid, store).await?.links_owned()?;
+        if links.is_empty() {
+            chunks.insert(cid);
+        }
+        queue.extend(links.into_iter().map(|link| link.cid));
+    }
+
+    Ok(chunks)
+}
+
+fn chunk_similarity(a: &RenameKey, b: &RenameKey) -> f32 {
+    let shared = a.chunks.intersection(&b.chunks).count();
+    shared as f32 / a.chunks.len().max(b.chunks.len()) as f32
+}
+
+/// Yields the changes between the entries of two directories, depth-first and
+/// sorted by path. Only the entries still to be compared, i.e. the pending siblings
+/// along the current path, are kept in memory.
+fn diff_entries<'a>(
+    old: &'a PublicDirectory,
+    new: &'a PublicDirectory,
+    store: &'a impl BlockStore,
+) -> impl Stream<Item = Result<PathChange>> + 'a {
+    try_stream! {
+        let mut pending = Vec::new();
+        push_entries(&mut pending, old, new, &[]);
+
+        while let Some((path, old_link, new_link)) = pending.pop() {
+            match (old_link, new_link) {
+                (Some(old_link), None) => yield PathChange::Removed {
+                    path,
+                    cid: old_link.resolve_cid(store).await?,
+                },
+                (None, Some(new_link)) => yield PathChange::Added {
+                    path,
+                    cid: new_link.resolve_cid(store).await?,
+                },
+                (Some(old_link), Some(new_link)) => {
+                    let old_cid = old_link.resolve_cid(store).await?;
+                    let new_cid = new_link.resolve_cid(store).await?;
+                    if old_cid == new_cid {
+                        continue;
+                    }
+
+                    match (
+                        old_link.resolve_value(store).await?,
+                        new_link.resolve_value(store).await?,
+                    ) {
+                        (PublicNode::Dir(old_dir), PublicNode::Dir(new_dir)) => {
+                            push_entries(&mut pending, old_dir, new_dir, &path);
+                        }
+                        (PublicNode::File(_), PublicNode::File(_)) => {
+                            yield PathChange::Modified {
+                                path,
+                                old_cid,
+                                new_cid,
+                            };
+                        }
+                        _ => {
+                            yield PathChange::Removed {
+                                path: path.clone(),
+                                cid: old_cid,
+                            };
+                            yield PathChange::Added {
+                                path,
+                                cid: new_cid,
+                            };
+                        }
+                    }
+                }
+                (None, None) => unreachable!(),
+            }
+        }
+    }
+}
+
+/// Pushes the entries of two directories onto given stack, so they get popped in name order.
+fn push_entries(
+    pending: &mut Vec<(Vec<String>, Option<PublicLink>, Option<PublicLink>)>,
+    old: &PublicDirectory,
+    new: &PublicDirectory,
+    path: &[String],
+) {
+    let names = old
+        .userland
+        .keys()
+        .chain(new.userland.keys())
+        .collect::<BTreeSet<_>>();
+
+    for name in names.into_iter().rev() {
+        pending.push((
+            [path, std::slice::from_ref(name)].concat(),
+            old.userland.get(name).cloned(),
+            new.userland.get(name).cloned(),
+        ));
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::public::{golden::golden_content, StoreConfig};
+    use chrono::Utc;
+    use futures::StreamExt;
+    use testresult::TestResult;
+    use wnfs_common::{BlockOperation, MemoryBlockStore, RecordingBlockStore, CODEC_DAG_CBOR};
+
+    fn path(segments: &[&str]) -> Vec<String> {
+        segments.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[async_std::test]
+    async fn diff_reports_added_removed_and_modified_nodes() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let old = &mut PublicDirectory::new_rc(time);
+        old.write(&path(&["a", "b", "c.txt"]), b"c".to_vec(), time, store)
+            .await?;
+        old.write(&path(&["a", "d.txt"]), b"d".to_vec(), time, store)
+            .await?;
+        old.write(&path(&["gone", "e.txt"]), b"e".to_vec(), time, store)
+            .await?;
+        old.store(store).await?;
+
+        let new = &mut Arc::clone(old);
+        new.write(&path(&["a", "b", "c.txt"]), b"C".to_vec(), time, store)
+            .await?;
+        new.write(
+            &path(&["a", "b", "x", "y", "new.txt"]),
+            b"new".to_vec(),
+            time,
+            store,
+        )
+        .await?;
+        new.rm(&path(&["gone"]), store).await?;
+
+        let changes = old.diff(new, store).await?;
+        let kinds = changes
+            .iter()
+            .map(|change| match change {
+                PathChange::Added { path, .. } => ("added", path.join("/")),
+                PathChange::Removed { path, .. } => ("removed", path.join("/")),
+                PathChange::Modified { path, .. } => ("modified", path.join("/")),
+                PathChange::Renamed { to, .. } => ("renamed", to.join("/")),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            kinds,
+            vec![
+                ("modified", "a/b/c.txt".into()),
+                ("added", "a/b/x".into()),
+                ("removed", "gone".into()),
+            ]
+        );
+        assert!(new.diff(new, store).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn diff_only_loads_changed_subtrees() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let old = &mut PublicDirectory::new_rc(time);
+        for i in 0..20 {
+            for j in 0..5 {
+                old.write(
+                    &path(&[&format!("dir{i}"), &format!("{j}.txt")]),
+                    vec![i, j],
+                    time,
+                    store,
+                )
+                .await?;
+            }
+        }
+        old.write(
+            &path(&["deep", "a", "b", "c.txt"]),
+            b"c".to_vec(),
+            time,
+            store,
+        )
+        .await?;
+        old.write(
+            &path(&["gone", "x", "y", "z.txt"]),
+            b"z".to_vec(),
+            time,
+            store,
+        )
+        .await?;
+        old.write(&path(&["gone", "w.txt"]), b"w".to_vec(), time, store)
+            .await?;
+        let old_cid = old.store(store).await?;
+
+        let new = &mut Arc::clone(old);
+        new.write(
+            &path(&["deep", "a", "b", "c.txt"]),
+            b"C".to_vec(),
+            time,
+            store,
+        )
+        .await?;
+        new.write(
+            &path(&["deep", "a", "b", "new", "n.txt"]),
+            b"n".to_vec(),
+            time,
+            store,
+        )
+        .await?;
+        new.rm(&path(&["gone"]), store).await?;
+        let new_cid = new.store(store).await?;
+
+        let unchanged_cid = old
+            .get_node(&path(&["dir0"]), store)
+            .await?
+            .unwrap()
+            .store(store)
+            .await?;
+        let gone_cid = old
+            .get_node(&path(&["gone"]), store)
+            .await?
+            .unwrap()
+            .store(store)
+            .await?;
+
+        let recording = &RecordingBlockStore::new(store);
+        let old = PublicDirectory::load(&old_cid, recording).await?;
+        let new = PublicDirectory::load(&new_cid, recording).await?;
+        let changes = old.diff(&new, recording).await?;
+
+        // The deleted subtree and the added one are reported once, as a whole
+        assert_eq!(
+            changes.iter().map(PathChange::path).collect::<Vec<_>>(),
+            vec![
+                path(&["deep", "a", "b", "c.txt"]),
+                path(&["deep", "a", "b", "new"]),
+                path(&["gone"]),
+            ]
+        );
+        assert!(matches!(
+            &changes[2],
+            PathChange::Removed { cid, .. } if *cid == gone_cid
+        ));
+
+        // Only the directories along the changed paths were loaded
+        let fetched = recording
+            .log()
+            .into_iter()
+            .filter(|access| matches!(access.operation, BlockOperation::Get(_)))
+            .map(|access| access.cid)
+            .collect::<BTreeSet<_>>();
+        assert!(!fetched.contains(&unchanged_cid));
+        assert!(!fetched.contains(&gone_cid));
+        assert!(fetched.len() <= 10, "{}", fetched.len());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn diff_streams_yield_the_same_changes_incrementally() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let old = &mut PublicDirectory::new_rc(time);
+        for i in 0..20 {
+            old.write(&path(&["kept", &format!("{i}.txt")]), vec![i], time, store)
+                .await?;
+            old.write(&path(&["old", &format!("{i}.txt")]), vec![i], time, store)
+                .await?;
+        }
+        old.store(store).await?;
+
+        let new = &mut Arc::clone(old);
+        new.rm(&path(&["old"]), store).await?;
+        for i in 0..20 {
+            new.write(
+                &path(&["kept", &format!("{i}.txt")]),
+                vec![i + 1],
+                time,
+                store,
+            )
+            .await?;
+            new.write(
+                &path(&["new", &format!("{i}"), "a.txt"]),
+                vec![i],
+                time,
+                store,
+            )
+            .await?;
+        }
+
+        let streamed = old.diff_stream(new, store).try_collect::<Vec<_>>().await?;
+        assert_eq!(streamed, old.diff(new, store).await?);
+        assert_eq!(streamed.len(), 22);
+
+        // Stopping early only computes what was consumed
+        let first = old
+            .diff_stream(new, store)
+            .take(3)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(first, streamed[..3]);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn commit_and_diff_feeds_changes_since_the_last_commit() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let dir = &mut PublicDirectory::new_rc(time);
+        dir.write(&path(&["a.txt"]), b"a".to_vec(), time, store)
+            .await?;
+        dir.write(&path(&["b", "c.txt"]), b"c".to_vec(), time, store)
+            .await?;
+
+        let (first, changes) = dir.commit_and_diff(None, store).await?;
+        assert_eq!(first, dir.store(store).await?);
+        assert_eq!(
+            changes
+                .iter()
+                .map(|c| c.path().to_vec())
+                .collect::<Vec<_>>(),
+            vec![path(&["a.txt"]), path(&["b"])]
+        );
+        assert!(changes
+            .iter()
+            .all(|c| matches!(c, PathChange::Added { .. })));
+
+        let (second, changes) = dir.commit_and_diff(Some(first), store).await?;
+        assert_eq!(second, first);
+        assert!(changes.is_empty());
+
+        dir.rm(&path(&["a.txt"]), store).await?;
+        let (third, changes) = dir.commit_and_diff(Some(second), store).await?;
+        assert_ne!(third, second);
+        assert!(matches!(
+            &changes[..],
+            [PathChange::Removed { path, .. }] if path == &["a.txt"]
+        ));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn diff_with_renames_detects_exact_and_similar_moves() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let big = golden_content(1_000_000);
+        let old = &mut PublicDirectory::new_rc(time);
+        old.write(&path(&["small.txt"]), b"small".to_vec(), time, store)
+            .await?;
+        old.write(&path(&["big.bin"]), big.clone(), time, store)
+            .await?;
+        old.mkdir(&path(&["dir", "sub"]), time, store).await?;
+        old.store(store).await?;
+
+        let mut edited = big;
+        edited.extend_from_slice(b"appended");
+        let new = &mut Arc::clone(old);
+        new.basic_mv(&path(&["small.txt"]), &path(&["moved.txt"]), time, store)
+            .await?;
+        new.rm(&path(&["big.bin"]), store).await?;
+        new.write(&path(&["edited.bin"]), edited, time, store)
+            .await?;
+        new.basic_mv(&path(&["dir"]), &path(&["folder"]), time, store)
+            .await?;
+
+        let recording = &RecordingBlockStore::new(store);
+        let old = &Arc::new(PublicDirectory::load(&old.store(store).await?, recording).await?);
+        let exact = old.diff_with_renames(new, 1.0, recording).await?;
+        // Only nodes get loaded, no file content
+        assert!(recording
+            .take_log()
+            .iter()
+            .all(|access| access.cid.codec() == CODEC_DAG_CBOR));
+        let renamed = |changes: &[PathChange]| {
+            changes
+                .iter()
+                .filter_map(|change| match change {
+                    PathChange::Renamed { from, to, .. } => Some((from.join("/"), to.join("/"))),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(exact.len(), 4);
+        assert_eq!(
+            renamed(&exact),
+            vec![
+                ("dir".into(), "folder".into()),
+                ("small.txt".into(), "moved.txt".into())
+            ]
+        );
+
+        let approximate = old.diff_with_renames(new, 0.5, store).await?;
+        assert_eq!(
+            renamed(&approximate),
+            vec![
+                ("big.bin".into(), "edited.bin".into()),
+                ("dir".into(), "folder".into()),
+                ("small.txt".into(), "moved.txt".into())
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn similar_renames_compare_all_content_chunks() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        // 64 chunks, six levels deep
+        let config = StoreConfig::default()
+            .with_content_fanout(2)
+            .with_chunk_size(1024);
+        let content = golden_content(64 * 1024);
+        let mut edited = content.clone();
+        edited[30_000] ^= 1;
+
+        let old = &mut PublicDirectory::new_rc(time);
+        old.open_file_mut(&path(&["a.bin"]), time, store)
+            .await?
+            .set_content_with_config(content, time, &config, store)
+            .await?;
+        old.store(store).await?;
+        let new = &mut PublicDirectory::new_rc(time);
+        new.open_file_mut(&path(&["b.bin"]), time, store)
+            .await?
+            .set_content_with_config(edited, time, &config, store)
+            .await?;
+
+        // One of the two top-level subtrees differs, but only one of the 64 chunks does
+        let changes = old.diff_with_renames(new, 0.9, store).await?;
+        assert!(matches!(
+            &changes[..],
+            [PathChange::Renamed { from, to, .. }] if from == &path(&["a.bin"]) && to == &path(&["b.bin"])
+        ));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn applying_a_diff_reconstructs_the_other_tree() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let old = &mut PublicDirectory::new_rc(time);
+        old.write(&path(&["docs", "a.md"]), b"a".to_vec(), time, store)
+            .await?;
+        old.write(&path(&["docs", "b.md"]), b"b".to_vec(), time, store)
+            .await?;
+        old.write(&path(&["tmp", "x", "y.txt"]), b"y".to_vec(), time, store)
+            .await?;
+        old.store(store).await?;
+
+        let new = &mut Arc::clone(old);
+        new.write(&path(&["docs", "a.md"]), b"a2".to_vec(), time, store)
+            .await?;
+        new.basic_mv(&path(&["docs", "b.md"]), &path(&["b.md"]), time, store)
+            .await?;
+        new.rm(&path(&["tmp", "x"]), store).await?;
+        new.write(&path(&["new", "deep", "c.md"]), b"c".to_vec(), time, store)
+            .await?;
+
+        for changes in [
+            old.diff(new, store).await?,
+            old.diff_with_renames(new, 1.0, store).await?,
+        ] {
+            let rebuilt = old.apply_diff(&changes, store).await?;
+            assert!(rebuilt.diff(new, store).await?.is_empty());
+
+            // Applying it again doesn't work, since the old nodes are gone
+            let result = rebuilt.apply_diff(&changes, store).await;
+            assert!(matches!(
+                result.unwrap_err().downcast_ref(),
+                Some(FsError::InvalidDiff(_))
+            ));
+        }
+
+        // The original directory is left as it is
+        assert_eq!(old.read(&path(&["docs", "a.md"]), store).await?, b"a");
+
+        Ok(())
+    }
+}
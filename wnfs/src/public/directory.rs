@@ -1,27 +1,32 @@
 //! Public fs directory node.
 
 use super::{
-    PublicDirectorySerializable, PublicFile, PublicLink, PublicNode, PublicNodeSerializable,
+    tombstone, MergeConflict, PublicDirectorySerializable, PublicFile, PublicLink, PublicNode,
+    PublicNodeSerializable, Resolution, TwoWayMerge,
 };
 use crate::{
     error::FsError,
     is_readable_wnfs_version,
     traits::Id,
     utils::{self, OnceCellDebug},
-    SearchResult, WNFS_VERSION,
+    NameViolation, SearchResult, WNFS_VERSION,
 };
 use anyhow::{bail, ensure, Result};
 use async_once_cell::OnceCell;
 use async_recursion::async_recursion;
+use async_stream::try_stream;
 use chrono::{DateTime, Utc};
+use futures::{Stream, TryStreamExt};
 use libipld_core::cid::Cid;
 use std::{
     cmp::Ordering,
-    collections::{btree_map::Entry, BTreeMap, BTreeSet},
+    collections::{btree_map::Entry, BTreeMap, BTreeSet, VecDeque},
+    ops::Bound,
 };
 use wnfs_common::{
-    utils::{boxed_fut, error, Arc},
-    BlockStore, Metadata, NodeType, Storable,
+    utils::{boxed_fut, error, is_ancestor, Arc, BoxAsyncRead, CondSync},
+    AccountingBlockStore, BlockStore, Metadata, MetadataMergePolicy, NodeType, Storable,
+    StoreStats,
 };
 
 //--------------------------------------------------------------------------------------------------
@@ -42,11 +47,22 @@ use wnfs_common::{
 /// ```
 pub struct PublicDirectory {
     persisted_as: OnceCell<Cid>,
+    /// The result of `size_recursive`, only valid while `persisted_as` is set.
+    size_recursive: OnceCell<u64>,
     pub(crate) metadata: Metadata,
     pub(crate) userland: BTreeMap<String, PublicLink>,
     pub(crate) previous: BTreeSet<Cid>,
 }
 
+/// Where `PublicDirectory::find_by_cid` found a CID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CidMatch {
+    /// The node at this path has the CID.
+    Node(Vec<String>),
+    /// The content root of the file at this path has the CID.
+    Content(Vec<String>),
+}
+
 /// Different types of reconciliation results we can detect
 #[derive(Debug, Clone)]
 pub enum Reconciliation {
@@ -82,6 +98,7 @@ impl PublicDirectory {
     pub fn new(time: DateTime<Utc>) -> Self {
         Self {
             persisted_as: OnceCell::new(),
+            size_recursive: OnceCell::new(),
             metadata: Metadata::new(time),
             userland: BTreeMap::new(),
             previous: BTreeSet::new(),
@@ -141,7 +158,10 @@ impl PublicDirectory {
     }
 
     /// Returns a mutable reference to this directory's metadata.
+    ///
+    /// This forgets the CID the directory was stored as, since it's about to change.
     pub fn get_metadata_mut(&mut self) -> &mut Metadata {
+        self.persisted_as = OnceCell::new();
         &mut self.metadata
     }
 
@@ -161,7 +181,10 @@ impl PublicDirectory {
 
         let cloned = Arc::make_mut(self);
         cloned.persisted_as = OnceCell::new();
+        cloned.size_recursive = OnceCell::new();
         cloned.previous = [previous_cid].into_iter().collect();
+        // Labels belong to the labeled revision only
+        cloned.metadata.delete_snapshot_label();
         cloned
     }
 
@@ -177,6 +200,7 @@ impl PublicDirectory {
             // This is a merge node
             let cloned = Arc::make_mut(self);
             cloned.persisted_as = OnceCell::new();
+            cloned.size_recursive = OnceCell::new();
             return Ok(cloned);
         }
 
@@ -187,11 +211,13 @@ impl PublicDirectory {
         let previous_cid = self.store(store).await?;
         let cloned = Arc::make_mut(self);
         cloned.persisted_as = OnceCell::new();
+        cloned.size_recursive = OnceCell::new();
         cloned.previous = BTreeSet::from([previous_cid]);
+        cloned.metadata.delete_snapshot_label();
         Ok(cloned)
     }
 
-    async fn get_leaf_dir<'a>(
+    pub(crate) async fn get_leaf_dir<'a>(
         &'a self,
         path_segments: &[String],
         store: &impl BlockStore,
@@ -210,7 +236,7 @@ impl PublicDirectory {
         Ok(SearchResult::Found(working_dir))
     }
 
-    async fn get_leaf_dir_mut<'a>(
+    pub(crate) async fn get_leaf_dir_mut<'a>(
         self: &'a mut Arc<Self>,
         path_segments: &[String],
         store: &impl BlockStore,
@@ -240,7 +266,7 @@ impl PublicDirectory {
         Ok(SearchResult::Found(working_dir))
     }
 
-    async fn get_or_create_leaf_dir_mut<'a>(
+    pub(crate) async fn get_or_create_leaf_dir_mut<'a>(
         self: &'a mut Arc<Self>,
         path_segments: &[String],
         time: DateTime<Utc>,
@@ -250,10 +276,17 @@ impl PublicDirectory {
             SearchResult::Found(dir) => Ok(dir),
             SearchResult::Missing(mut dir, depth) => {
                 for segment in &path_segments[depth..] {
+                    utils::validate_name(segment)?;
+                }
+
+                for segment in &path_segments[depth..] {
+                    let name = dir.entry_name(segment).into_owned();
+                    let child = dir.new_child_dir(time);
+                    tombstone::clear_tombstone(&mut dir.metadata, &name);
                     dir = Arc::make_mut(
                         dir.userland
-                            .entry(segment.to_string())
-                            .or_insert_with(|| PublicLink::with_dir(Self::new(time)))
+                            .entry(name)
+                            .or_insert_with(|| PublicLink::with_dir(child))
                             .resolve_value_mut(store)
                             .await
                             .unwrap()
@@ -313,6 +346,145 @@ impl PublicDirectory {
         dir.lookup_node(tail, store).await
     }
 
+    /// Follows a path relative to a base path and fetches the node at the end of it.
+    ///
+    /// Unlike `get_node`, this resolves `.` and `..` segments in both paths.
+    /// Fails with `FsError::InvalidPath` if the path goes above this directory via `..`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     dir.mkdir(&["pictures".into(), "cats".into()], Utc::now(), store).await?;
+    ///     dir.mkdir(&["pictures".into(), "dogs".into()], Utc::now(), store).await?;
+    ///
+    ///     let result = dir
+    ///         .get_node_relative(
+    ///             &["pictures".into(), "cats".into()],
+    ///             &["..".into(), "dogs".into()],
+    ///             store,
+    ///         )
+    ///         .await?;
+    ///
+    ///     assert!(result.is_some());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_node_relative<'a>(
+        &'a self,
+        base: &[String],
+        relative: &[String],
+        store: &impl BlockStore,
+    ) -> Result<Option<&'a PublicNode>> {
+        let path_segments = utils::normalize_path(base.iter().chain(relative))?;
+        self.get_node(&path_segments, store).await
+    }
+
+    /// Follows a batch of paths and fetches the nodes at the end of each of them.
+    ///
+    /// Paths sharing a common prefix walk the directories along that prefix
+    /// only once. The results are returned in the same order as the given paths,
+    /// with `None` for paths that don't exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    /// use chrono::Utc;
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///
+    ///     dir.mkdir(&["pictures".into(), "cats".into()], Utc::now(), store).await?;
+    ///     dir.mkdir(&["pictures".into(), "dogs".into()], Utc::now(), store).await?;
+    ///
+    ///     let nodes = dir
+    ///         .get_many(
+    ///             &[
+    ///                 vec!["pictures".into(), "dogs".into()],
+    ///                 vec!["pictures".into(), "birds".into()],
+    ///                 vec!["pictures".into(), "cats".into()],
+    ///             ],
+    ///             store,
+    ///         )
+    ///         .await?;
+    ///
+    ///     assert!(nodes[0].is_some());
+    ///     assert!(nodes[1].is_none());
+    ///     assert!(nodes[2].is_some());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_many(
+        &self,
+        paths: &[Vec<String>],
+        store: &impl BlockStore,
+    ) -> Result<Vec<Option<PublicNode>>> {
+        let mut results = vec![None; paths.len()];
+        let indices = (0..paths.len()).collect();
+        self.get_many_helper(paths, indices, 0, &mut results, store)
+            .await?;
+        Ok(results)
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), async_recursion)]
+    #[cfg_attr(target_arch = "wasm32", async_recursion(?Send))]
+    async fn get_many_helper(
+        &self,
+        paths: &[Vec<String>],
+        indices: Vec<usize>,
+        depth: usize,
+        results: &mut [Option<PublicNode>],
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        // Group the remaining paths by their segment at this depth
+        let mut groups: BTreeMap<&String, Vec<usize>> = BTreeMap::new();
+        for index in indices {
+            if let Some(segment) = paths[index].get(depth) {
+                groups.entry(segment).or_default().push(index);
+            }
+        }
+
+        for (segment, group) in groups {
+            let Some(node) = self.lookup_node(segment, store).await? else {
+                continue;
+            };
+
+            let (ending, continuing): (Vec<_>, Vec<_>) = group
+                .into_iter()
+                .partition(|&index| paths[index].len() == depth + 1);
+
+            for index in ending {
+                results[index] = Some(node.clone());
+            }
+
+            if let (PublicNode::Dir(dir), false) = (node, continuing.is_empty()) {
+                dir.get_many_helper(paths, continuing, depth + 1, results, store)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Opens a file at given path, or creates a new one if it was missing.
     /// Also creates the intermediate directories if they didn't exist before.
     /// Updates the modification time for everything on the path.
@@ -356,13 +528,17 @@ impl PublicDirectory {
         store: &'a impl BlockStore,
     ) -> Result<&'a mut PublicFile> {
         let (path, filename) = utils::split_last(path_segments)?;
+        let dir = self.get_or_create_leaf_dir_mut(path, time, store).await?;
+        let name = dir.entry_name(filename).into_owned();
+        if !dir.userland.contains_key(&name) {
+            utils::validate_name(filename)?;
+            tombstone::clear_tombstone(&mut dir.metadata, &name);
+        }
 
         // Resolve the path to an entry
-        let file_ref = self
-            .get_or_create_leaf_dir_mut(path, time, store)
-            .await?
+        let file_ref = dir
             .userland
-            .entry(filename.clone())
+            .entry(name)
             // Create a file, if it doesn't exist yet
             .or_insert_with(|| PublicLink::with_file(PublicFile::new(time)))
             // Get a mutable ref out of the directory entry
@@ -408,19 +584,22 @@ impl PublicDirectory {
         path_segment: &str,
         store: &impl BlockStore,
     ) -> Result<Option<&'a PublicNode>> {
-        Ok(match self.userland.get(path_segment) {
-            Some(link) => Some(link.resolve_value(store).await?),
-            None => None,
-        })
+        Ok(
+            match self.userland.get(self.entry_name(path_segment).as_ref()) {
+                Some(link) => Some(link.resolve_value(store).await?),
+                None => None,
+            },
+        )
     }
 
     /// Looks up a node by its path name in the current directory.
-    async fn lookup_node_mut<'a>(
+    pub(crate) async fn lookup_node_mut<'a>(
         &'a mut self,
         path_segment: &str,
         store: &impl BlockStore,
     ) -> Result<Option<&'a mut PublicNode>> {
-        Ok(match self.userland.get_mut(path_segment) {
+        let name = self.entry_name(path_segment).into_owned();
+        Ok(match self.userland.get_mut(&name) {
             Some(link) => Some(link.resolve_value_mut(store).await?),
             None => None,
         })
@@ -463,181 +642,261 @@ impl PublicDirectory {
     /// ```
     pub async fn read(&self, path_segments: &[String], store: &impl BlockStore) -> Result<Vec<u8>> {
         let (path, filename) = utils::split_last(path_segments)?;
-        match self.get_leaf_dir(path, store).await? {
-            SearchResult::Found(dir) => match dir.lookup_node(filename, store).await? {
-                Some(PublicNode::File(file)) => Ok(file.read_at(0, None, store).await?),
-                Some(_) => error(FsError::NotAFile),
-                None => error(FsError::NotFound),
-            },
-            _ => error(FsError::NotFound),
+        let dir = self
+            .get_leaf_dir(path, store)
+            .await?
+            .into_found(path_segments)?;
+        match dir.lookup_node(filename, store).await? {
+            Some(PublicNode::File(file)) => Ok(file.read_at(0, None, store).await?),
+            Some(_) => error(FsError::NotAFile),
+            None => error(FsError::not_found(path_segments)),
         }
     }
 
-    /// Writes a file to the directory.
+    /// Reads bytes `start` up to, but excluding, `end` of the file at given path,
+    /// e.g. for answering HTTP range requests.
+    ///
+    /// Only the content blocks overlapping the range get loaded. A range ending
+    /// after the end of the file is cut off there, and one starting after it fails
+    /// with `FsError::RangeNotSatisfiable`.
     ///
     /// # Examples
     ///
     /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
     /// use wnfs::{
     ///     public::PublicDirectory,
     ///     common::MemoryBlockStore
     /// };
-    /// use chrono::Utc;
-    /// use anyhow::Result;
     ///
     /// #[async_std::main]
     /// async fn main() -> Result<()> {
     ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
     ///     let store = &MemoryBlockStore::new();
+    ///     let path = &["notes.txt".into()];
+    ///     dir.write(path, b"Hello, World!".to_vec(), Utc::now(), store).await?;
     ///
-    ///     dir
-    ///         .write(
-    ///             &["pictures".into(), "cats".into(), "tabby.png".into()],
-    ///             b"Hello, World!".to_vec(),
-    ///             Utc::now(),
-    ///             store
-    ///         )
-    ///         .await?;
+    ///     assert_eq!(dir.read_range(path, 7, 12, store).await?, b"World");
+    ///     assert_eq!(dir.read_range(path, 7, 100, store).await?, b"World!");
     ///
     ///     Ok(())
     /// }
     /// ```
-    pub async fn write(
-        self: &mut Arc<Self>,
+    pub async fn read_range(
+        &self,
         path_segments: &[String],
-        content: Vec<u8>,
-        time: DateTime<Utc>,
+        start: u64,
+        end: u64,
         store: &impl BlockStore,
-    ) -> Result<()> {
+    ) -> Result<Vec<u8>> {
         let (path, filename) = utils::split_last(path_segments)?;
-        let dir = self.get_or_create_leaf_dir_mut(path, time, store).await?;
-
-        match dir.lookup_node_mut(filename, store).await? {
-            Some(PublicNode::File(file)) => {
-                file.prepare_next_revision()
-                    .set_content(content, time, store)
-                    .await?
-            }
-            Some(PublicNode::Dir(_)) => bail!(FsError::DirectoryAlreadyExists),
-            None => {
-                dir.userland.insert(
-                    filename.to_string(),
-                    PublicLink::with_file(PublicFile::with_content(time, content, store).await?),
-                );
-            }
-        }
+        let dir = self
+            .get_leaf_dir(path, store)
+            .await?
+            .into_found(path_segments)?;
+        let file = match dir.lookup_node(filename, store).await? {
+            Some(PublicNode::File(file)) => file,
+            Some(_) => bail!(FsError::NotAFile),
+            None => bail!(FsError::not_found(path_segments)),
+        };
 
-        Ok(())
+        let size = file.size(store).await?;
+        ensure!(start <= size, FsError::RangeNotSatisfiable { start, size });
+        let len = end.min(size).saturating_sub(start);
+        file.read_at(start, Some(len as usize), store).await
     }
 
-    /// Creates a new directory at the specified path.
+    /// Reads the content of the file at given path like `read`, but as a stream
+    /// that's decoded according to the content encoding in the file's metadata.
+    ///
+    /// Fails with `FsError::UnsupportedEncoding` if the file's content encoding
+    /// is neither `"identity"` nor, with the `compress` feature, `"gzip"` or
+    /// `"zstd"`.
     ///
     /// # Examples
     ///
     /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use futures::AsyncReadExt;
     /// use wnfs::{
     ///     public::PublicDirectory,
-    ///     traits::Id,
     ///     common::MemoryBlockStore
     /// };
-    /// use chrono::Utc;
     ///
     /// #[async_std::main]
-    /// async fn main() {
+    /// async fn main() -> Result<()> {
     ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
-    ///     let store = MemoryBlockStore::default();
+    ///     let store = &MemoryBlockStore::new();
+    ///     let path = &["notes.txt".into()];
     ///
-    ///     dir
-    ///         .mkdir(&["pictures".into(), "cats".into()], Utc::now(), &store)
-    ///         .await
-    ///         .unwrap();
+    ///     dir.write(path, b"Hello".to_vec(), Utc::now(), store).await?;
     ///
-    ///     let result = dir
-    ///         .ls(&["pictures".into()], &store)
-    ///         .await
-    ///         .unwrap();
+    ///     let mut content = Vec::new();
+    ///     dir.read_decoded(path, store)
+    ///         .await?
+    ///         .read_to_end(&mut content)
+    ///         .await?;
     ///
-    ///     assert_eq!(result.len(), 1);
-    ///     assert_eq!(result[0].0, "cats");
+    ///     assert_eq!(content, b"Hello");
+    ///
+    ///     Ok(())
     /// }
     /// ```
+    pub async fn read_decoded<'a>(
+        &'a self,
+        path_segments: &[String],
+        store: &'a impl BlockStore,
+    ) -> Result<BoxAsyncRead<'a>> {
+        let (path, filename) = utils::split_last(path_segments)?;
+        let dir = self
+            .get_leaf_dir(path, store)
+            .await?
+            .into_found(path_segments)?;
+        match dir.lookup_node(filename, store).await? {
+            Some(PublicNode::File(file)) => file.stream_decoded_content(store).await,
+            Some(_) => error(FsError::NotAFile),
+            None => error(FsError::not_found(path_segments)),
+        }
+    }
+
+    /// Sets the content encoding recorded in the metadata of the file at given path,
+    /// e.g. after writing compressed content, so `read_decoded` can decode it.
+    /// Passing `None` clears the content encoding.
+    ///
+    /// This only changes the file's metadata, its modification time stays the same.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
     ///
-    /// This method acts like `mkdir -p` in Unix because it creates intermediate directories if they do not exist.
-    pub async fn mkdir(
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     let path = &["archive.tar.zst".into()];
+    ///
+    ///     dir.write(path, vec![], Utc::now(), store).await?;
+    ///     dir.set_content_encoding(path, Some("zstd".into()), store).await?;
+    ///
+    ///     let file = dir.get_node(path, store).await?.unwrap().as_file()?;
+    ///     assert_eq!(file.get_metadata().get_content_encoding(), Some("zstd"));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn set_content_encoding(
         self: &mut Arc<Self>,
         path_segments: &[String],
-        time: DateTime<Utc>,
+        encoding: Option<String>,
         store: &impl BlockStore,
     ) -> Result<()> {
-        let _ = self
-            .get_or_create_leaf_dir_mut(path_segments, time, store)
-            .await?;
+        let (path, filename) = utils::split_last(path_segments)?;
+        let dir = self
+            .get_leaf_dir_mut(path, store)
+            .await?
+            .into_found(path_segments)?;
+
+        let metadata = match dir.lookup_node_mut(filename, store).await? {
+            Some(PublicNode::File(file)) => file.get_metadata_mut_rc(),
+            Some(_) => bail!(FsError::NotAFile),
+            None => bail!(FsError::not_found(path_segments)),
+        };
+
+        match encoding {
+            Some(encoding) => metadata.upsert_content_encoding(&encoding),
+            None => metadata.delete_content_encoding(),
+        }
 
         Ok(())
     }
 
-    /// Returns names and metadata of directory's immediate children.
+    /// Links a sidecar, i.e. a block or file content DAG derived from the file at
+    /// given path, like a thumbnail or a transcode, into its metadata under given key.
+    /// Passing `None` unlinks the sidecar.
+    ///
+    /// Sidecars aren't directory entries, but they're reachable from the file, so
+    /// e.g. `verify_import` and `deep_clone_to_store` include them. They're expected
+    /// to be file content, i.e. raw blocks or UnixFS file DAGs. Sidecars are kept
+    /// when the file's content changes.
     ///
     /// # Examples
     ///
     /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
     /// use wnfs::{
     ///     public::PublicDirectory,
-    ///     common::MemoryBlockStore
+    ///     common::{BlockStore, MemoryBlockStore, CODEC_RAW},
     /// };
-    /// use libipld_core::cid::Cid;
-    /// use chrono::Utc;
     ///
     /// #[async_std::main]
-    /// async fn main() {
+    /// async fn main() -> Result<()> {
     ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
-    ///     let store = MemoryBlockStore::default();
+    ///     let store = &MemoryBlockStore::new();
+    ///     let path = &["cat.png".into()];
+    ///     dir.write(path, b"A big cat".to_vec(), Utc::now(), store).await?;
     ///
-    ///     dir
-    ///         .write(
-    ///             &["pictures".into(), "cats".into(), "tabby.png".into()],
-    ///             b"Hello, world!".to_vec(),
-    ///             Utc::now(),
-    ///             &store
-    ///         )
-    ///         .await
-    ///         .unwrap();
+    ///     let thumbnail = store.put_block(b"A tiny cat".to_vec(), CODEC_RAW).await?;
+    ///     dir.set_sidecar(path, "thumbnail", Some(thumbnail), store).await?;
     ///
-    ///     let result = dir
-    ///         .ls(&["pictures".into(), "cats".into()], &store)
-    ///         .await
-    ///         .unwrap();
+    ///     assert_eq!(dir.get_sidecar(path, "thumbnail", store).await?, Some(thumbnail));
+    ///     assert_eq!(dir.ls(&[], store).await?.len(), 1);
     ///
-    ///     assert_eq!(result.len(), 1);
-    ///     assert_eq!(result[0].0, "tabby.png");
+    ///     Ok(())
     /// }
     /// ```
-    pub async fn ls(
-        &self,
+    pub async fn set_sidecar(
+        self: &mut Arc<Self>,
         path_segments: &[String],
+        key: &str,
+        cid: Option<Cid>,
         store: &impl BlockStore,
-    ) -> Result<Vec<(String, Metadata)>> {
-        match self.get_leaf_dir(path_segments, store).await? {
-            SearchResult::Found(dir) => {
-                let mut result = vec![];
-                for (name, link) in dir.userland.iter() {
-                    match link.resolve_value(store).await? {
-                        PublicNode::File(file) => {
-                            result.push((name.clone(), file.metadata.clone()));
-                        }
-                        PublicNode::Dir(dir) => {
-                            result.push((name.clone(), dir.metadata.clone()));
-                        }
-                    }
-                }
-                Ok(result)
+    ) -> Result<()> {
+        let (path, filename) = utils::split_last(path_segments)?;
+        let dir = self
+            .get_leaf_dir_mut(path, store)
+            .await?
+            .into_found(path_segments)?;
+
+        let metadata = match dir.lookup_node_mut(filename, store).await? {
+            Some(PublicNode::File(file)) => file.get_metadata_mut_rc(),
+            Some(_) => bail!(FsError::NotAFile),
+            None => bail!(FsError::not_found(path_segments)),
+        };
+
+        match cid {
+            Some(cid) => metadata.upsert_sidecar(key, cid),
+            None => {
+                metadata.delete_sidecar(key);
             }
-            SearchResult::NotADir(_, _) => bail!(FsError::NotADirectory),
-            _ => bail!(FsError::NotFound),
+        }
+
+        Ok(())
+    }
+
+    /// Returns the sidecar linked under given key of the file at given path, see `set_sidecar`.
+    pub async fn get_sidecar(
+        &self,
+        path_segments: &[String],
+        key: &str,
+        store: &impl BlockStore,
+    ) -> Result<Option<Cid>> {
+        match self.get_node(path_segments, store).await? {
+            Some(PublicNode::File(file)) => Ok(file.get_metadata().get_sidecar(key)),
+            Some(_) => bail!(FsError::NotAFile),
+            None => bail!(FsError::not_found(path_segments)),
         }
     }
 
-    /// Removes a file or directory from the directory.
+    /// Writes a file to the directory.
     ///
     /// # Examples
     ///
@@ -663,48 +922,26 @@ impl PublicDirectory {
     ///         )
     ///         .await?;
     ///
-    ///     let result = dir
-    ///         .ls(&["pictures".into()], store)
-    ///         .await?;
-    ///
-    ///     assert_eq!(result.len(), 1);
-    ///
-    ///     dir
-    ///         .rm(&["pictures".into(), "cats".into()], store)
-    ///         .await?;
-    ///
-    ///     let result = dir
-    ///         .ls(&["pictures".into()], store)
-    ///         .await?;
-    ///
-    ///     assert_eq!(result.len(), 0);
-    ///
     ///     Ok(())
     /// }
     /// ```
-    pub async fn rm(
+    pub async fn write(
         self: &mut Arc<Self>,
         path_segments: &[String],
+        content: Vec<u8>,
+        time: DateTime<Utc>,
         store: &impl BlockStore,
-    ) -> Result<PublicNode> {
-        // TODO(matheus23) set modification time
-        let (path, node_name) = utils::split_last(path_segments)?;
-
-        let SearchResult::Found(dir) = self.get_leaf_dir_mut(path, store).await? else {
-            bail!(FsError::NotFound)
-        };
-
-        let removed_node = match dir.userland.remove(node_name) {
-            Some(link) => link.resolve_owned_value(store).await?,
-            None => bail!(FsError::NotFound),
-        };
+    ) -> Result<()> {
+        self.write_returning(path_segments, content, time, store)
+            .await?;
 
-        Ok(removed_node)
+        Ok(())
     }
 
-    /// Moves a file or directory from one path to another.
+    /// Writes a file to the directory like `write` does, and returns the written file.
     ///
-    /// This function requires stating the destination name explicitly.
+    /// The root is this directory, which is updated in place, so this saves
+    /// looking up the file again after writing it.
     ///
     /// # Examples
     ///
@@ -713,715 +950,4437 @@ impl PublicDirectory {
     ///     public::PublicDirectory,
     ///     common::MemoryBlockStore
     /// };
-    /// use libipld_core::cid::Cid;
     /// use chrono::Utc;
+    /// use anyhow::Result;
     ///
     /// #[async_std::main]
-    /// async fn main() {
+    /// async fn main() -> Result<()> {
     ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
-    ///     let store = MemoryBlockStore::default();
+    ///     let store = &MemoryBlockStore::new();
     ///
-    ///     dir
-    ///         .write(
+    ///     let file = dir
+    ///         .write_returning(
     ///             &["pictures".into(), "cats".into(), "tabby.png".into()],
     ///             b"Hello, World!".to_vec(),
     ///             Utc::now(),
-    ///             &store
-    ///         )
-    ///         .await
-    ///         .unwrap();
-    ///
-    ///     dir
-    ///         .basic_mv(
-    ///             &["pictures".into(), "cats".into()],
-    ///             &["cats".into()],
-    ///             Utc::now(),
-    ///             &store
+    ///             store
     ///         )
-    ///         .await
-    ///         .unwrap();
+    ///         .await?;
     ///
-    ///     let result = dir
-    ///         .ls(&[], &store)
-    ///         .await
-    ///         .unwrap();
+    ///     assert_eq!(file.size(store).await?, 13);
     ///
-    ///     assert_eq!(result.len(), 2);
+    ///     Ok(())
     /// }
     /// ```
-    pub async fn basic_mv(
-        self: &mut Arc<Self>,
-        path_segments_from: &[String],
-        path_segments_to: &[String],
+    pub async fn write_returning<'a>(
+        self: &'a mut Arc<Self>,
+        path_segments: &[String],
+        content: Vec<u8>,
         time: DateTime<Utc>,
         store: &impl BlockStore,
-    ) -> Result<()> {
-        let (path, filename) = utils::split_last(path_segments_to)?;
-        let mut removed_node = self.rm(path_segments_from, store).await?;
-
-        let SearchResult::Found(dir) = self.get_leaf_dir_mut(path, store).await? else {
-            bail!(FsError::NotFound);
-        };
-
-        ensure!(
-            !dir.userland.contains_key(filename),
-            FsError::FileAlreadyExists
-        );
-
-        removed_node.upsert_mtime(time);
-
-        dir.userland
-            .insert(filename.clone(), PublicLink::new(removed_node));
+    ) -> Result<&'a mut PublicFile> {
+        let (path, filename) = utils::split_last(path_segments)?;
+        let dir = self.get_or_create_leaf_dir_mut(path, time, store).await?;
 
-        Ok(())
+        match dir.userland.entry(dir.entry_name(filename).into_owned()) {
+            Entry::Occupied(entry) => match entry.into_mut().resolve_value_mut(store).await? {
+                PublicNode::File(file) => {
+                    let file = file.prepare_next_revision();
+                    file.set_content(content, time, store).await?;
+                    Ok(file)
+                }
+                PublicNode::Dir(_) => bail!(FsError::DirectoryAlreadyExists),
+            },
+            Entry::Vacant(entry) => {
+                utils::validate_name(filename)?;
+                tombstone::clear_tombstone(&mut dir.metadata, entry.key());
+                let file = PublicFile::with_content(time, content, store).await?;
+                let node = entry
+                    .insert(PublicLink::with_file(file))
+                    .resolve_value_mut(store)
+                    .await?;
+                Ok(Arc::make_mut(node.as_file_mut()?))
+            }
+        }
     }
 
-    /// Copies a file or directory from one path to another.
+    /// Writes a file to the directory like `write` does, and records the MIME type
+    /// detected from its content in the `content-type` attribute of the file.
+    ///
+    /// See `wnfs::common::utils::detect_mime` for the formats that get detected.
+    /// If the content isn't in any of them, a previous `content-type` attribute
+    /// gets removed, since it doesn't describe the new content anymore.
     ///
     /// # Examples
     ///
     /// ```
-    /// use anyhow::Result;
-    /// use libipld_core::cid::Cid;
-    /// use chrono::Utc;
     /// use wnfs::{
     ///     public::PublicDirectory,
-    ///     common::{BlockStore, MemoryBlockStore},
+    ///     common::MemoryBlockStore
     /// };
+    /// use chrono::Utc;
+    /// use libipld_core::ipld::Ipld;
+    /// use anyhow::Result;
     ///
     /// #[async_std::main]
     /// async fn main() -> Result<()> {
     ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
     ///     let store = &MemoryBlockStore::new();
+    ///     let path = &["report.pdf".into()];
     ///
-    ///     dir
-    ///         .write(
-    ///             &["code".into(), "python".into(), "hello.py".into()],
-    ///             b"Hello, world!".to_vec(),
-    ///             Utc::now(),
-    ///             store
-    ///         )
-    ///         .await?;
-    ///
-    ///     dir
-    ///         .cp(
-    ///             &["code".into(), "python".into(), "hello.py".into()],
-    ///             &["code".into(), "hello.py".into()],
-    ///             Utc::now(),
-    ///             store
-    ///         )
-    ///         .await?;
-    ///
-    ///     let result = dir
-    ///         .ls(&["code".into()], store)
-    ///         .await?;
+    ///     dir.write_with_mime(path, b"%PDF-1.7\n".to_vec(), Utc::now(), store).await?;
     ///
-    ///     assert_eq!(result.len(), 2);
+    ///     let node = dir.get_node(path, store).await?.unwrap();
+    ///     assert_eq!(
+    ///         node.get_attribute("content-type"),
+    ///         Some(&Ipld::String("application/pdf".into()))
+    ///     );
     ///
     ///     Ok(())
     /// }
     /// ```
-    pub async fn cp(
+    #[cfg(feature = "mime")]
+    pub async fn write_with_mime(
         self: &mut Arc<Self>,
-        path_segments_from: &[String],
-        path_segments_to: &[String],
+        path_segments: &[String],
+        content: Vec<u8>,
         time: DateTime<Utc>,
         store: &impl BlockStore,
     ) -> Result<()> {
-        let (path, filename) = utils::split_last(path_segments_to)?;
-        let Some(mut node) = self.get_node(path_segments_from, store).await?.cloned() else {
-            bail!(FsError::NotFound);
-        };
+        let mime = wnfs_common::utils::detect_mime(&content);
+        let file = self
+            .write_returning(path_segments, content, time, store)
+            .await?;
 
-        let SearchResult::Found(dir) = self.get_leaf_dir_mut(path, store).await? else {
-            bail!(FsError::NotFound);
+        match mime {
+            Some(mime) => file.metadata.set_attribute("content-type", mime.into()),
+            None => file.metadata.remove_attribute("content-type"),
         };
 
-        ensure!(
-            !dir.userland.contains_key(filename),
-            FsError::FileAlreadyExists
-        );
-
-        node.upsert_mtime(time);
-
-        dir.userland.insert(filename.clone(), PublicLink::new(node));
-
         Ok(())
     }
 
-    /// Comparing the merkle clocks of this directory to the other directory
-    pub async fn causal_compare(
-        self: Arc<Self>,
-        other: Arc<Self>,
-        store: &impl BlockStore,
-    ) -> Result<Option<Ordering>> {
-        let causal_order = PublicNode::Dir(self)
-            .causal_compare(&PublicNode::Dir(other), store)
-            .await?;
-        Ok(causal_order)
-    }
-
-    /// Reconcile this node with another node.
+    /// Appends bytes to the file at given path, see `PublicFile::append_content`.
     ///
-    /// Use this function when you're informed about another version of this
-    /// public WNFS directory and want to merge any possibly new changes into
-    /// this directory.
+    /// If there's no file at that path yet, it gets created with given bytes as
+    /// its content, like `write` does. Otherwise, the file moves on to its next
+    /// revision.
     ///
-    /// The return value can give information about what exactly happened.
-    /// See the documentation for the `Reconciliation` enum for more information.
-    pub async fn reconcile(
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    /// use chrono::Utc;
+    /// use anyhow::Result;
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     let path = &["logs".into(), "app.log".into()];
+    ///
+    ///     dir.append(path, b"started\n", Utc::now(), store).await?;
+    ///     dir.append(path, b"stopped\n", Utc::now(), store).await?;
+    ///
+    ///     let content = dir.read(path, store).await?;
+    ///     assert_eq!(content, b"started\nstopped\n");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn append(
         self: &mut Arc<Self>,
-        other: &Arc<Self>,
+        path_segments: &[String],
+        content: &[u8],
+        time: DateTime<Utc>,
         store: &impl BlockStore,
-    ) -> Result<Reconciliation> {
-        let causal_order = self.clone().causal_compare(other.clone(), store).await?;
+    ) -> Result<()> {
+        let (path, filename) = utils::split_last(path_segments)?;
+        let dir = self.get_or_create_leaf_dir_mut(path, time, store).await?;
 
-        Ok(match causal_order {
-            Some(Ordering::Equal) => Reconciliation::AlreadyAhead,
-            Some(Ordering::Greater) => Reconciliation::AlreadyAhead,
-            Some(Ordering::Less) => {
-                self.clone_from(other);
-                Reconciliation::FastForward
-            }
-            None => {
-                let mut file_tie_breaks = BTreeSet::new();
-                self.reconcile_helper(other, store, &[], &mut file_tie_breaks)
-                    .await?;
-                Reconciliation::Merged { file_tie_breaks }
+        match dir.userland.entry(dir.entry_name(filename).into_owned()) {
+            Entry::Occupied(entry) => match entry.into_mut().resolve_value_mut(store).await? {
+                PublicNode::File(file) => {
+                    let file = file.prepare_next_revision();
+                    file.append_content(content, time, store).await
+                }
+                PublicNode::Dir(_) => bail!(FsError::DirectoryAlreadyExists),
+            },
+            Entry::Vacant(entry) => {
+                utils::validate_name(filename)?;
+                tombstone::clear_tombstone(&mut dir.metadata, entry.key());
+                let file = PublicFile::with_content(time, content.to_vec(), store).await?;
+                entry.insert(PublicLink::with_file(file));
+                Ok(())
             }
-        })
+        }
     }
 
-    #[cfg_attr(not(target_arch = "wasm32"), async_recursion)]
-    #[cfg_attr(target_arch = "wasm32", async_recursion(?Send))]
-    async fn reconcile_helper<'a>(
-        self: &'a mut Arc<Self>,
-        other: &'a Arc<Self>,
-        store: &'a impl BlockStore,
-        current_path: &[String],
-        file_tie_breaks: &mut BTreeSet<Vec<String>>,
+    /// Truncates or extends the file at given path to `len` bytes, see `PublicFile::truncate`.
+    /// The file moves on to its next revision.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    /// use chrono::Utc;
+    /// use anyhow::Result;
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     let path = &["logs".into(), "app.log".into()];
+    ///
+    ///     dir.write(path, b"started\nstopped\n".to_vec(), Utc::now(), store).await?;
+    ///     dir.truncate(path, 8, Utc::now(), store).await?;
+    ///
+    ///     let content = dir.read(path, store).await?;
+    ///     assert_eq!(content, b"started\n");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn truncate(
+        self: &mut Arc<Self>,
+        path_segments: &[String],
+        len: u64,
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
     ) -> Result<()> {
-        let our_cid = self.store(store).await?;
-        let other_cid = other.store(store).await?;
-        if our_cid == other_cid {
-            // We don't have to merge
-            return Ok(());
-        }
+        let (path, filename) = utils::split_last(path_segments)?;
+        let file = self
+            .get_leaf_dir_mut(path, store)
+            .await?
+            .into_found(path)?
+            .lookup_node_mut(filename, store)
+            .await?
+            .ok_or_else(|| FsError::not_found(path_segments))?
+            .as_file_mut()?
+            .prepare_next_revision();
 
-        let dir = self.prepare_next_merge(store).await?;
-        if other.previous.len() > 1 {
-            // The other node is a merge node, we should merge the merge nodes directly:
-            dir.previous.extend(other.previous.iter().cloned());
-        } else {
-            // The other node is a 'normal' node - we need to merge it normally
-            dir.previous.insert(other.store(store).await?);
-        }
-        dir.metadata.tie_break_with(&other.metadata)?;
+        file.truncate(len, time, store).await
+    }
 
-        for (name, other_link) in other.userland.iter() {
-            let other_node = other_link.resolve_value(store).await?;
-            match dir.userland.entry(name.clone()) {
-                Entry::Vacant(vacant) => {
-                    vacant.insert(other_link.clone());
-                }
-                Entry::Occupied(mut occupied) => {
-                    let our_node = occupied.get_mut().resolve_value_mut(store).await?;
+    /// Creates a new directory at the specified path.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     traits::Id,
+    ///     common::MemoryBlockStore
+    /// };
+    /// use chrono::Utc;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = MemoryBlockStore::default();
+    ///
+    ///     dir
+    ///         .mkdir(&["pictures".into(), "cats".into()], Utc::now(), &store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let result = dir
+    ///         .ls(&["pictures".into()], &store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(result.len(), 1);
+    ///     assert_eq!(result[0].0, "cats");
+    /// }
+    /// ```
+    ///
+    /// This method acts like `mkdir -p` in Unix because it creates intermediate directories if they do not exist.
+    pub async fn mkdir(
+        self: &mut Arc<Self>,
+        path_segments: &[String],
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        self.mkdir_returning(path_segments, time, store).await?;
 
-                    match our_node.causal_compare(other_node, store).await? {
-                        Some(Ordering::Equal) => {
-                            continue;
-                        }
-                        Some(Ordering::Less) => {
-                            our_node.clone_from(other_node);
-                            continue;
-                        }
-                        Some(Ordering::Greater) => {
-                            continue;
-                        }
-                        None => {}
-                    };
+        Ok(())
+    }
 
-                    match (our_node, other_node) {
-                        (PublicNode::File(our_file), PublicNode::File(other_file)) => {
-                            if our_file.merge(other_file, store).await? {
-                                let mut path = current_path.to_vec();
-                                path.push(name.clone());
-                                file_tie_breaks.insert(path);
-                            }
-                        }
-                        (node @ PublicNode::File(_), PublicNode::Dir(other_dir)) => {
-                            // directories have priority
-                            // we don't add previous links
-                            *node = PublicNode::Dir(other_dir.clone());
-                        }
-                        (PublicNode::Dir(_), PublicNode::File(_)) => {
-                            // directories have priority, no changes necessary
+    /// Creates a new directory at the specified path like `mkdir` does,
+    /// and returns the created directory.
+    ///
+    /// The root is this directory, which is updated in place, so this saves
+    /// looking up the directory again after creating it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    /// use chrono::Utc;
+    /// use anyhow::Result;
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///
+    ///     let cats = dir
+    ///         .mkdir_returning(&["pictures".into(), "cats".into()], Utc::now(), store)
+    ///         .await?;
+    ///
+    ///     cats.get_metadata_mut().put("favorite", "tabby".into());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn mkdir_returning<'a>(
+        self: &'a mut Arc<Self>,
+        path_segments: &[String],
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<&'a mut Self> {
+        self.get_or_create_leaf_dir_mut(path_segments, time, store)
+            .await
+    }
+
+    /// Returns names and metadata of directory's immediate children.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    /// use libipld_core::cid::Cid;
+    /// use chrono::Utc;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = MemoryBlockStore::default();
+    ///
+    ///     dir
+    ///         .write(
+    ///             &["pictures".into(), "cats".into(), "tabby.png".into()],
+    ///             b"Hello, world!".to_vec(),
+    ///             Utc::now(),
+    ///             &store
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let result = dir
+    ///         .ls(&["pictures".into(), "cats".into()], &store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(result.len(), 1);
+    ///     assert_eq!(result[0].0, "tabby.png");
+    /// }
+    /// ```
+    pub async fn ls(
+        &self,
+        path_segments: &[String],
+        store: &impl BlockStore,
+    ) -> Result<Vec<(String, Metadata)>> {
+        match self.get_leaf_dir(path_segments, store).await? {
+            SearchResult::Found(dir) => {
+                let mut result = vec![];
+                for (name, link) in dir.userland.iter() {
+                    match link.resolve_value(store).await? {
+                        PublicNode::File(file) => {
+                            result.push((name.clone(), file.metadata.clone()));
                         }
-                        (PublicNode::Dir(dir), PublicNode::Dir(other_dir)) => {
-                            let mut path = current_path.to_vec();
-                            path.push(name.clone());
-                            dir.reconcile_helper(other_dir, store, &path, file_tie_breaks)
-                                .await?;
+                        PublicNode::Dir(dir) => {
+                            result.push((name.clone(), dir.metadata.clone()));
                         }
                     }
                 }
+                Ok(result)
+            }
+            SearchResult::NotADir(_, _) => bail!(FsError::NotADirectory),
+            SearchResult::Missing(_, depth) => bail!(FsError::missing_at(path_segments, depth)),
+        }
+    }
+
+    /// Returns names and metadata of up to `limit` immediate children of the
+    /// directory at given path, sorted by name, starting after the `cursor` name.
+    /// Pages have at least one entry, unless there are no more entries.
+    ///
+    /// Pass `None` as the cursor to start with the first entry. Next to the entries,
+    /// this returns the cursor for the next page, which is the last returned name,
+    /// or `None` if there are no more entries. Names are compared by their Unicode
+    /// code points, so paging visits each entry exactly once, and the cursor stays
+    /// valid when entries get added or removed between pages.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     for name in ["a", "b", "c"] {
+    ///         dir.mkdir(&["docs".into(), name.into()], Utc::now(), store).await?;
+    ///     }
+    ///
+    ///     let (page, cursor) = dir.ls_paginated(&["docs".into()], None, 2, store).await?;
+    ///     assert_eq!(page.len(), 2);
+    ///     assert_eq!(cursor.as_deref(), Some("b"));
+    ///
+    ///     let (page, cursor) = dir.ls_paginated(&["docs".into()], cursor, 2, store).await?;
+    ///     assert_eq!(page[0].0, "c");
+    ///     assert_eq!(cursor, None);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn ls_paginated(
+        &self,
+        path_segments: &[String],
+        cursor: Option<String>,
+        limit: usize,
+        store: &impl BlockStore,
+    ) -> Result<(Vec<(String, Metadata)>, Option<String>)> {
+        let dir = self
+            .get_leaf_dir(path_segments, store)
+            .await?
+            .into_found(path_segments)?;
+
+        let mut entries = match &cursor {
+            Some(cursor) => dir
+                .userland
+                .range::<String, _>((Bound::Excluded(cursor), Bound::Unbounded)),
+            None => dir.userland.range::<String, _>(..),
+        }
+        .peekable();
+
+        let mut result = vec![];
+        while result.len() < limit.max(1) {
+            let Some((name, link)) = entries.next() else {
+                break;
+            };
+
+            let metadata = match link.resolve_value(store).await? {
+                PublicNode::File(file) => file.metadata.clone(),
+                PublicNode::Dir(dir) => dir.metadata.clone(),
+            };
+            result.push((name.clone(), metadata));
+        }
+
+        let next_cursor = match entries.peek() {
+            Some(_) => result.last().map(|(name, _)| name.clone()).or(cursor),
+            None => None,
+        };
+
+        Ok((result, next_cursor))
+    }
+
+    /// Returns the metadata of the directory at given path, like its creation and
+    /// modification times.
+    ///
+    /// This only loads the directories along the path, none of the target directory's
+    /// children, so it's cheap even for large directories.
+    ///
+    /// Fails with `FsError::NotADirectory` if the path leads to a file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     let time = Utc::now();
+    ///     dir.mkdir(&["pictures".into()], time, store).await?;
+    ///
+    ///     let metadata = dir.dir_metadata(&["pictures".into()], store).await?;
+    ///
+    ///     assert!(metadata.get_created().is_some());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn dir_metadata(
+        &self,
+        path_segments: &[String],
+        store: &impl BlockStore,
+    ) -> Result<Metadata> {
+        let dir = self
+            .get_leaf_dir(path_segments, store)
+            .await?
+            .into_found(path_segments)?;
+        Ok(dir.metadata.clone())
+    }
+
+    /// Loads all nodes along given path, so following it later doesn't need to
+    /// fetch any of them from the block store anymore.
+    ///
+    /// Loaded nodes are kept in memory by this directory, so subsequent `get_node`
+    /// or `read` calls on it skip those fetches. Call this before latency-sensitive
+    /// operations. Loading stops where the path stops existing, without an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::{MemoryBlockStore, Storable},
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let path = &["pictures".into(), "cats".into(), "tabby.png".into()];
+    ///     dir.write(path, b"Tabby".to_vec(), Utc::now(), store).await?;
+    ///     let cid = dir.store(store).await?;
+    ///
+    ///     let loaded = PublicDirectory::load(&cid, store).await?;
+    ///     loaded.prefetch_path(path, store).await?;
+    ///
+    ///     assert_eq!(loaded.read(path, store).await?, b"Tabby");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn prefetch_path(
+        &self,
+        path_segments: &[String],
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        self.get_node(path_segments, store).await?;
+        Ok(())
+    }
+
+    /// Sums up the content sizes of all files in this directory tree, in bytes.
+    ///
+    /// Directories themselves don't add to the size. The size of each stored
+    /// subdirectory is remembered, so calling this again after changing only some
+    /// part of the tree doesn't walk the unchanged subtrees again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     dir.write(&["notes.txt".into()], b"Hello".to_vec(), Utc::now(), store).await?;
+    ///     dir.write(&["docs".into(), "todo.md".into()], b"- [ ] Backup".to_vec(), Utc::now(), store)
+    ///         .await?;
+    ///
+    ///     assert_eq!(dir.size_recursive(store).await?, 17);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg_attr(not(target_arch = "wasm32"), async_recursion)]
+    #[cfg_attr(target_arch = "wasm32", async_recursion(?Send))]
+    pub async fn size_recursive(&self, store: &impl BlockStore) -> Result<u64> {
+        let cached = self.persisted_as.get().and(self.size_recursive.get());
+        if let Some(size) = cached {
+            return Ok(*size);
+        }
+
+        let mut size = 0;
+        for link in self.userland.values() {
+            size += match link.resolve_value(store).await? {
+                PublicNode::File(file) => file.size(store).await?,
+                PublicNode::Dir(dir) => dir.size_recursive(store).await?,
+            };
+        }
+
+        if self.persisted_as.get().is_some() {
+            self.size_recursive.get_or_init(async { size }).await;
+        }
+
+        Ok(size)
+    }
+
+    /// Finds the first node below given directory path whose name matches given
+    /// pattern, where `*` matches any sequence of characters and `?` matches
+    /// any single character.
+    ///
+    /// The search is breadth-first: Matches closer to the root path are found first,
+    /// matches within the same depth in the order of their paths. It stops at the
+    /// first match, so the rest of the tree isn't loaded from the block store.
+    /// Names are matched before loading the nodes they point to.
+    ///
+    /// Returns the path of the match, including the root path, together with the node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     let path = &["code".into(), "app".into(), "config.toml".into()];
+    ///     dir.write(path, b"debug = true".to_vec(), Utc::now(), store).await?;
+    ///
+    ///     let (found, node) = dir.find_first(&[], "*.toml", store).await?.unwrap();
+    ///
+    ///     assert_eq!(&found, path);
+    ///     assert!(node.is_file());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn find_first(
+        &self,
+        root_path: &[String],
+        pattern: &str,
+        store: &impl BlockStore,
+    ) -> Result<Option<(Vec<String>, PublicNode)>> {
+        let root = self
+            .get_leaf_dir(root_path, store)
+            .await?
+            .into_found(root_path)?;
+
+        let mut queue = VecDeque::from([(root_path.to_vec(), root)]);
+        while let Some((mut path, dir)) = queue.pop_front() {
+            if let Some((name, link)) = dir
+                .userland
+                .iter()
+                .find(|(name, _)| utils::matches_pattern(name, pattern))
+            {
+                path.push(name.clone());
+                return Ok(Some((path, link.resolve_value(store).await?.clone())));
+            }
+
+            for (name, link) in dir.userland.iter() {
+                if let PublicNode::Dir(child) = link.resolve_value(store).await? {
+                    let mut child_path = path.clone();
+                    child_path.push(name.clone());
+                    queue.push_back((child_path, child.as_ref()));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Finds all nodes below this directory whose paths match given glob pattern,
+    /// sorted by path.
+    ///
+    /// The pattern is matched segment by segment, with segments separated by `/`.
+    /// Within a segment, `*` matches any sequence of characters and `?` any single
+    /// character. A segment that's just `**` matches any number of segments,
+    /// including zero, so `docs/**/*.md` matches both `docs/a.md` and `docs/x/y/b.md`.
+    /// Matches never include this directory itself.
+    ///
+    /// Names are matched before loading the nodes they point to, so only directories
+    /// whose paths can still lead to a match get loaded from the block store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+    ///     dir.write(&path("docs/intro.md"), b"# Intro".to_vec(), Utc::now(), store).await?;
+    ///     dir.write(&path("docs/guides/setup.md"), b"# Setup".to_vec(), Utc::now(), store).await?;
+    ///     dir.write(&path("docs/logo.png"), b"...".to_vec(), Utc::now(), store).await?;
+    ///
+    ///     let found = dir.glob("docs/**/*.md", store).await?;
+    ///
+    ///     let paths = found.iter().map(|(path, _)| path.join("/")).collect::<Vec<_>>();
+    ///     assert_eq!(paths, ["docs/guides/setup.md", "docs/intro.md"]);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn glob(
+        &self,
+        pattern: &str,
+        store: &impl BlockStore,
+    ) -> Result<Vec<(Vec<String>, PublicNode)>> {
+        let segments = pattern
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect::<Vec<_>>();
+
+        let mut matches = BTreeMap::new();
+        self.glob_into(&[], &segments, &mut matches, store).await?;
+        Ok(matches.into_iter().collect())
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), async_recursion)]
+    #[cfg_attr(target_arch = "wasm32", async_recursion(?Send))]
+    async fn glob_into(
+        &self,
+        path: &[String],
+        pattern: &[&str],
+        matches: &mut BTreeMap<Vec<String>, PublicNode>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let Some((&segment, rest)) = pattern.split_first() else {
+            return Ok(());
+        };
+
+        if segment == "**" {
+            // Matching zero segments
+            self.glob_into(path, rest, matches, store).await?;
+        }
+
+        for (name, link) in self.userland.iter() {
+            if segment != "**" && !utils::matches_pattern(name, segment) {
+                continue;
+            }
+
+            let mut child_path = path.to_vec();
+            child_path.push(name.clone());
+            let node = link.resolve_value(store).await?;
+
+            // `**` keeps matching further segments below
+            let child_pattern = if segment == "**" { pattern } else { rest };
+            if rest.is_empty() {
+                matches.insert(child_path.clone(), node.clone());
+            }
+            match node {
+                PublicNode::Dir(child) if !child_pattern.is_empty() => {
+                    child
+                        .glob_into(&child_path, child_pattern, matches, store)
+                        .await?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Walks all nodes below this directory depth-first, in the order of their paths,
+    /// and calls `visit` with the path and node of each of them.
+    ///
+    /// Before an entry is loaded, `should_visit` is called with its path. If it returns
+    /// false, the entry and everything below it are skipped, without loading any of them
+    /// from the block store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+    ///     dir.write(&path("src/main.rs"), b"fn main() {}".to_vec(), Utc::now(), store).await?;
+    ///     dir.write(&path(".cache/blob"), b"...".to_vec(), Utc::now(), store).await?;
+    ///
+    ///     let mut visited = Vec::new();
+    ///     dir.walk_filtered(
+    ///         |path| path.last().map_or(true, |name| name != ".cache"),
+    ///         |path, _| visited.push(path.join("/")),
+    ///         store,
+    ///     )
+    ///     .await?;
+    ///
+    ///     assert_eq!(visited, ["src", "src/main.rs"]);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn walk_filtered(
+        &self,
+        should_visit: impl Fn(&[String]) -> bool,
+        mut visit: impl FnMut(&[String], &PublicNode),
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let mut stack = Vec::new();
+        Self::push_links(self, &[], &mut stack);
+
+        while let Some((path, link)) = stack.pop() {
+            if !should_visit(&path) {
+                continue;
+            }
+
+            let node = link.resolve_value(store).await?;
+            visit(&path, node);
+            if let PublicNode::Dir(dir) = node {
+                Self::push_links(dir, &path, &mut stack);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the content CID and size in bytes of every file below this directory,
+    /// keyed by their paths joined with `/`.
+    ///
+    /// Comparing the indices of two revisions tells which files have new content.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     dir.write(&["docs".into(), "notes.txt".into()], b"Hello".to_vec(), Utc::now(), store)
+    ///         .await?;
+    ///
+    ///     let index = dir.content_index(store).await?;
+    ///
+    ///     let (_, size) = index["docs/notes.txt"];
+    ///     assert_eq!(size, 5);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn content_index(
+        &self,
+        store: &impl BlockStore,
+    ) -> Result<BTreeMap<String, (Cid, u64)>> {
+        let mut files = Vec::new();
+        self.walk_filtered(
+            |_| true,
+            |path, node| {
+                if let PublicNode::File(file) = node {
+                    files.push((path.join("/"), Arc::clone(file)));
+                }
+            },
+            store,
+        )
+        .await?;
+
+        let mut index = BTreeMap::new();
+        for (path, file) in files {
+            let cid = file.userland.resolve_cid(store).await?;
+            index.insert(path, (cid, file.size(store).await?));
+        }
+
+        Ok(index)
+    }
+
+    /// Finds all nodes below this directory that match given predicate, which is
+    /// called with the path and node of every entry in the tree.
+    ///
+    /// The tree is walked lazily and depth-first, yielding matches sorted by path.
+    /// Nodes get loaded from the block store only once the walk reaches them, so
+    /// dropping the stream early leaves the rest of the tree unloaded. Matches never
+    /// include this directory itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use futures::TryStreamExt;
+    /// use wnfs::{
+    ///     public::{PublicDirectory, PublicNode},
+    ///     common::MemoryBlockStore
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+    ///     dir.write(&path("docs/notes.txt"), b"Hello".to_vec(), Utc::now(), store).await?;
+    ///     dir.write(&path("docs/logo.png"), b"...".to_vec(), Utc::now(), store).await?;
+    ///     dir.mkdir(&path("docs/archive.txt"), Utc::now(), store).await?;
+    ///
+    ///     let is_text_file = |path: &[String], node: &PublicNode| {
+    ///         node.is_file() && path.last().is_some_and(|name| name.ends_with(".txt"))
+    ///     };
+    ///     let found = dir
+    ///         .find(is_text_file, store)
+    ///         .map_ok(|(path, _)| path.join("/"))
+    ///         .try_collect::<Vec<_>>()
+    ///         .await?;
+    ///
+    ///     assert_eq!(found, ["docs/notes.txt"]);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn find<'a, F>(
+        &'a self,
+        predicate: F,
+        store: &'a impl BlockStore,
+    ) -> impl Stream<Item = Result<(Vec<String>, PublicNode)>> + 'a
+    where
+        F: Fn(&[String], &PublicNode) -> bool + 'a,
+    {
+        try_stream! {
+            let mut stack = Vec::new();
+            Self::push_links(self, &[], &mut stack);
+
+            while let Some((path, link)) = stack.pop() {
+                let node = link.resolve_value(store).await?;
+                if predicate(&path, node) {
+                    yield (path.clone(), node.clone());
+                }
+                if let PublicNode::Dir(dir) = node {
+                    Self::push_links(dir, &path, &mut stack);
+                }
+            }
+        }
+    }
+
+    /// Finds a node in this tree whose CID is given CID, or a file whose content
+    /// root has given CID, and returns where it was found. An empty path refers
+    /// to this directory itself.
+    ///
+    /// If the CID occurs more than once, the first match in depth-first order
+    /// of paths is returned. Nodes that weren't stored yet get stored to compute
+    /// their CIDs. If given CID isn't in the block store at all, nothing is walked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::{CidMatch, PublicDirectory},
+    ///     common::{MemoryBlockStore, Storable},
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     let path = vec!["docs".to_string(), "notes.txt".into()];
+    ///     dir.write(&path, b"Hello".to_vec(), Utc::now(), store).await?;
+    ///
+    ///     let file = dir.get_node(&path, store).await?.unwrap().as_file()?;
+    ///     let cid = file.store(store).await?;
+    ///
+    ///     assert_eq!(dir.find_by_cid(&cid, store).await?, Some(CidMatch::Node(path)));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn find_by_cid(
+        &self,
+        target: &Cid,
+        store: &impl BlockStore,
+    ) -> Result<Option<CidMatch>> {
+        if !store.has_block(target).await? {
+            return Ok(None);
+        }
+
+        if self.store(store).await? == *target {
+            return Ok(Some(CidMatch::Node(Vec::new())));
+        }
+
+        fn entries<'a>(
+            dir: &'a PublicDirectory,
+            path: &[String],
+        ) -> Vec<(Vec<String>, &'a PublicLink)> {
+            dir.userland
+                .iter()
+                .rev()
+                .map(|(name, link)| {
+                    let mut entry_path = path.to_vec();
+                    entry_path.push(name.clone());
+                    (entry_path, link)
+                })
+                .collect()
+        }
+
+        let mut stack = entries(self, &[]);
+        while let Some((path, link)) = stack.pop() {
+            if link.resolve_cid(store).await? == *target {
+                return Ok(Some(CidMatch::Node(path)));
+            }
+
+            match link.resolve_value(store).await? {
+                PublicNode::File(file) => {
+                    if file.userland.resolve_cid(store).await? == *target {
+                        return Ok(Some(CidMatch::Content(path)));
+                    }
+                }
+                PublicNode::Dir(dir) => stack.extend(entries(dir, &path)),
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Pushes the entries of given directory onto a depth-first traversal stack,
+    /// so they get popped in order. The entries aren't loaded.
+    fn push_links<'a>(
+        dir: &'a Self,
+        path: &[String],
+        stack: &mut Vec<(Vec<String>, &'a PublicLink)>,
+    ) {
+        for (name, link) in dir.userland.iter().rev() {
+            let mut entry_path = path.to_vec();
+            entry_path.push(name.clone());
+            stack.push((entry_path, link));
+        }
+    }
+
+    /// Marks or unmarks the directory at given path as a sync root, i.e. the root
+    /// of a subtree that sync tooling handles on its own. An empty path marks this
+    /// directory itself.
+    ///
+    /// The marker is a flag in the directory's metadata, see `find_sync_roots`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+    ///     dir.mkdir(&path("shared/team-a"), Utc::now(), store).await?;
+    ///     dir.mkdir(&path("shared/team-b"), Utc::now(), store).await?;
+    ///
+    ///     dir.set_sync_root(&path("shared/team-b"), true, store).await?;
+    ///
+    ///     assert_eq!(dir.find_sync_roots(store).await?, [path("shared/team-b")]);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn set_sync_root(
+        self: &mut Arc<Self>,
+        path_segments: &[String],
+        is_sync_root: bool,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let dir = self
+            .get_leaf_dir_mut(path_segments, store)
+            .await?
+            .into_found(path_segments)?;
+
+        dir.get_metadata_mut().set_sync_root(is_sync_root);
+
+        Ok(())
+    }
+
+    /// Returns the paths of all directories in this tree that are marked
+    /// as sync roots via `set_sync_root`, in order.
+    ///
+    /// This includes sync roots nested in other sync roots, and an empty
+    /// path if this directory itself is marked.
+    pub async fn find_sync_roots(&self, store: &impl BlockStore) -> Result<Vec<Vec<String>>> {
+        let mut roots = Vec::new();
+        if self.metadata.is_sync_root() {
+            roots.push(Vec::new());
+        }
+
+        self.walk_filtered(
+            |_| true,
+            |path, node| {
+                if let PublicNode::Dir(dir) = node {
+                    if dir.metadata.is_sync_root() {
+                        roots.push(path.to_vec());
+                    }
+                }
+            },
+            store,
+        )
+        .await?;
+
+        Ok(roots)
+    }
+
+    /// Returns the paths of all entries in this tree whose names don't follow the
+    /// current naming rules, together with what's wrong with each name.
+    ///
+    /// New entries always get checked, but trees written by older versions may
+    /// still contain such names. See `repair_names` for fixing them.
+    pub async fn validate_names(
+        &self,
+        store: &impl BlockStore,
+    ) -> Result<Vec<(Vec<String>, NameViolation)>> {
+        let mut violations = Vec::new();
+        self.walk_filtered(
+            |_| true,
+            |path, _| {
+                let violation = path.last().and_then(|name| utils::name_violation(name));
+                if let Some(violation) = violation {
+                    violations.push((path.to_vec(), violation));
+                }
+            },
+            store,
+        )
+        .await?;
+
+        Ok(violations)
+    }
+
+    /// Returns a copy of this tree with all entries reported by `validate_names`
+    /// renamed to whatever given function returns for their old names.
+    ///
+    /// Renamed nodes are kept as they are, so their content and previous revisions
+    /// stay intact. Directories containing renamed entries get new revisions linking
+    /// back to their old ones, with given modification time.
+    ///
+    /// Fails with `FsError::InvalidName` if a new name isn't valid either, or with
+    /// `FsError::FileAlreadyExists` if it's already taken.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     dir.mkdir(&["pictures".into()], Utc::now(), store).await?;
+    ///
+    ///     let repaired = dir
+    ///         .repair_names(|name| name.replace('/', "_"), Utc::now(), store)
+    ///         .await?;
+    ///
+    ///     assert!(repaired.validate_names(store).await?.is_empty());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn repair_names(
+        &self,
+        sanitize: impl Fn(&str) -> String + CondSync,
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<Arc<Self>> {
+        let mut root = Arc::new(self.clone());
+        Self::repair_names_helper(&mut root, &sanitize, time, store).await?;
+        Ok(root)
+    }
+
+    /// Renames invalid entries in given directory and below it.
+    /// Returns whether anything was renamed.
+    #[cfg_attr(not(target_arch = "wasm32"), async_recursion)]
+    #[cfg_attr(target_arch = "wasm32", async_recursion(?Send))]
+    async fn repair_names_helper(
+        dir: &mut Arc<Self>,
+        sanitize: &(impl Fn(&str) -> String + CondSync),
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<bool> {
+        let mut changed = false;
+        let names = dir.userland.keys().cloned().collect::<Vec<_>>();
+        for name in names {
+            if let PublicNode::Dir(child) = dir.userland[&name].resolve_value(store).await? {
+                let mut child = Arc::clone(child);
+                if Self::repair_names_helper(&mut child, sanitize, time, store).await? {
+                    let link = PublicLink::new(PublicNode::Dir(child));
+                    dir.prepare_next_revision()
+                        .userland
+                        .insert(name.clone(), link);
+                    changed = true;
+                }
+            }
+
+            if utils::name_violation(&name).is_some() {
+                let new_name = sanitize(&name);
+                utils::validate_name(&new_name)?;
+
+                let dir = dir.prepare_next_revision();
+                let new_name = dir.entry_name(&new_name).into_owned();
+                ensure!(
+                    !dir.userland.contains_key(&new_name),
+                    FsError::FileAlreadyExists
+                );
+                if let Some(link) = dir.userland.remove(&name) {
+                    tombstone::clear_tombstone(&mut dir.metadata, &new_name);
+                    dir.userland.insert(new_name, link);
+                }
+                changed = true;
+            }
+        }
+
+        if changed {
+            dir.prepare_next_revision().metadata.upsert_mtime(time);
+        }
+
+        Ok(changed)
+    }
+
+    /// Removes a file or directory from the directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    /// use chrono::Utc;
+    /// use anyhow::Result;
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///
+    ///     dir
+    ///         .write(
+    ///             &["pictures".into(), "cats".into(), "tabby.png".into()],
+    ///             b"Hello, World!".to_vec(),
+    ///             Utc::now(),
+    ///             store
+    ///         )
+    ///         .await?;
+    ///
+    ///     let result = dir
+    ///         .ls(&["pictures".into()], store)
+    ///         .await?;
+    ///
+    ///     assert_eq!(result.len(), 1);
+    ///
+    ///     dir
+    ///         .rm(&["pictures".into(), "cats".into()], store)
+    ///         .await?;
+    ///
+    ///     let result = dir
+    ///         .ls(&["pictures".into()], store)
+    ///         .await?;
+    ///
+    ///     assert_eq!(result.len(), 0);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn rm(
+        self: &mut Arc<Self>,
+        path_segments: &[String],
+        store: &impl BlockStore,
+    ) -> Result<PublicNode> {
+        // TODO(matheus23) set modification time
+        let (path, node_name) = utils::split_last(path_segments)?;
+
+        let dir = self
+            .get_leaf_dir_mut(path, store)
+            .await?
+            .into_found(path_segments)?;
+
+        let node_name = dir.entry_name(node_name).into_owned();
+        let removed_node = match dir.userland.remove(&node_name) {
+            Some(link) => link.resolve_owned_value(store).await?,
+            None => bail!(FsError::not_found(path_segments)),
+        };
+
+        Ok(removed_node)
+    }
+
+    /// Moves a file or directory from one path to another.
+    ///
+    /// This function requires stating the destination name explicitly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    /// use libipld_core::cid::Cid;
+    /// use chrono::Utc;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = MemoryBlockStore::default();
+    ///
+    ///     dir
+    ///         .write(
+    ///             &["pictures".into(), "cats".into(), "tabby.png".into()],
+    ///             b"Hello, World!".to_vec(),
+    ///             Utc::now(),
+    ///             &store
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     dir
+    ///         .basic_mv(
+    ///             &["pictures".into(), "cats".into()],
+    ///             &["cats".into()],
+    ///             Utc::now(),
+    ///             &store
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let result = dir
+    ///         .ls(&[], &store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(result.len(), 2);
+    /// }
+    /// ```
+    pub async fn basic_mv(
+        self: &mut Arc<Self>,
+        path_segments_from: &[String],
+        path_segments_to: &[String],
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let (path, filename) = utils::split_last(path_segments_to)?;
+        // Check the destination first, so a failing move doesn't remove the source
+        self.check_move(path_segments_from, path_segments_to, store)
+            .await?;
+        let mut removed_node = self.rm(path_segments_from, store).await?;
+
+        let dir = self
+            .get_leaf_dir_mut(path, store)
+            .await?
+            .into_found(path_segments_to)?;
+        let name = dir.entry_name(filename).into_owned();
+
+        removed_node.upsert_mtime(time);
+
+        tombstone::clear_tombstone(&mut dir.metadata, &name);
+        dir.userland.insert(name, PublicLink::new(removed_node));
+
+        Ok(())
+    }
+
+    /// Moves a file or directory from one path to another, keeping its history.
+    ///
+    /// Unlike `basic_mv`, the moved node gets a new revision whose previous revision
+    /// is the stored node before the move, so its history can be followed across the
+    /// move.
+    ///
+    /// Moving a directory into itself or one of its descendants fails with
+    /// `FsError::InvalidPath`. Moves never overwrite: If there's a node at the
+    /// destination already, this fails with `FsError::FileAlreadyExists` and leaves
+    /// the source where it is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::{MemoryBlockStore, Storable},
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     dir.write(&["draft.md".into()], b"# Notes".to_vec(), Utc::now(), store)
+    ///         .await?;
+    ///     let draft = dir.get_node(&["draft.md".into()], store).await?.unwrap().store(store).await?;
+    ///
+    ///     dir.mv(&["draft.md".into()], &["notes.md".into()], Utc::now(), store)
+    ///         .await?;
+    ///
+    ///     let notes = dir.get_node(&["notes.md".into()], store).await?.unwrap();
+    ///     assert!(notes.get_previous().contains(&draft));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn mv(
+        self: &mut Arc<Self>,
+        path_segments_from: &[String],
+        path_segments_to: &[String],
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let (path, filename) = utils::split_last(path_segments_to)?;
+        // Check the destination first, so a failing move doesn't remove the source
+        self.check_move(path_segments_from, path_segments_to, store)
+            .await?;
+
+        let mut moved_node = self.rm(path_segments_from, store).await?;
+        moved_node.upsert_mtime_in_next_revision(time);
+
+        let dir = self
+            .get_leaf_dir_mut(path, store)
+            .await?
+            .into_found(path_segments_to)?;
+        let name = dir.entry_name(filename).into_owned();
+        tombstone::clear_tombstone(&mut dir.metadata, &name);
+        dir.userland.insert(name, PublicLink::new(moved_node));
+
+        Ok(())
+    }
+
+    /// Checks that the node at one path can be moved to the other one: The
+    /// destination must be a valid, free name in an existing directory, and not
+    /// inside the moved node, however both paths are spelled.
+    async fn check_move(
+        &self,
+        path_segments_from: &[String],
+        path_segments_to: &[String],
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let (path, filename) = utils::split_last(path_segments_to)?;
+        utils::validate_name(filename)?;
+        ensure!(
+            !is_ancestor(
+                &self.resolve_names(path_segments_from, store).await?,
+                &self.resolve_names(path_segments_to, store).await?
+            ),
+            FsError::InvalidPath
+        );
+
+        let dir = self
+            .get_leaf_dir(path, store)
+            .await?
+            .into_found(path_segments_to)?;
+        ensure!(
+            !dir.userland.contains_key(dir.entry_name(filename).as_ref()),
+            FsError::FileAlreadyExists
+        );
+
+        Ok(())
+    }
+
+    /// Returns the names of the entries given path leads to, as far as they
+    /// exist, so differently spelled paths into normalizing directories compare
+    /// equal. Segments past a missing entry or a file are kept as they are.
+    async fn resolve_names(
+        &self,
+        path_segments: &[String],
+        store: &impl BlockStore,
+    ) -> Result<Vec<String>> {
+        let mut names = Vec::with_capacity(path_segments.len());
+        let mut dir = Some(self);
+        for segment in path_segments {
+            let Some(current) = dir else {
+                names.push(segment.clone());
+                continue;
+            };
+
+            let name = current.entry_name(segment).into_owned();
+            dir = match current.userland.get(&name) {
+                Some(link) => match link.resolve_value(store).await? {
+                    PublicNode::Dir(child) => Some(child.as_ref()),
+                    PublicNode::File(_) => None,
+                },
+                None => None,
+            };
+            names.push(name);
+        }
+
+        Ok(names)
+    }
+
+    /// Copies a file or directory from one path to another.
+    ///
+    /// Copies are cheap, the copy links to the same children and file content as
+    /// the original. Only the copied node itself gets the new modification time,
+    /// and its previous revision is the original.
+    ///
+    /// Fails with `FsError::FileAlreadyExists` if there's a node at the destination
+    /// already, and doesn't create missing parent directories of the destination.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use libipld_core::cid::Cid;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::{BlockStore, MemoryBlockStore},
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///
+    ///     dir
+    ///         .write(
+    ///             &["code".into(), "python".into(), "hello.py".into()],
+    ///             b"Hello, world!".to_vec(),
+    ///             Utc::now(),
+    ///             store
+    ///         )
+    ///         .await?;
+    ///
+    ///     dir
+    ///         .cp(
+    ///             &["code".into(), "python".into(), "hello.py".into()],
+    ///             &["code".into(), "hello.py".into()],
+    ///             Utc::now(),
+    ///             store
+    ///         )
+    ///         .await?;
+    ///
+    ///     let result = dir
+    ///         .ls(&["code".into()], store)
+    ///         .await?;
+    ///
+    ///     assert_eq!(result.len(), 2);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn cp(
+        self: &mut Arc<Self>,
+        path_segments_from: &[String],
+        path_segments_to: &[String],
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let (path, filename) = utils::split_last(path_segments_to)?;
+        utils::validate_name(filename)?;
+        let (from_path, from_name) = utils::split_last(path_segments_from)?;
+        let from_dir = self
+            .get_leaf_dir(from_path, store)
+            .await?
+            .into_found(path_segments_from)?;
+        let Some(mut node) = from_dir.lookup_node(from_name, store).await?.cloned() else {
+            bail!(FsError::not_found(path_segments_from));
+        };
+
+        let dir = self
+            .get_leaf_dir_mut(path, store)
+            .await?
+            .into_found(path_segments_to)?;
+
+        let name = dir.entry_name(filename).into_owned();
+        ensure!(
+            !dir.userland.contains_key(&name),
+            FsError::FileAlreadyExists
+        );
+
+        // The copy starts a new revision, so storing it doesn't reuse the CID
+        // of the original. Its content is shared with the original.
+        node.upsert_mtime_in_next_revision(time);
+
+        tombstone::clear_tombstone(&mut dir.metadata, &name);
+        dir.userland.insert(name, PublicLink::new(node));
+
+        Ok(())
+    }
+
+    /// Sets the modification time of the node at given path and of all nodes
+    /// below it to given time. An empty path touches this whole directory.
+    ///
+    /// Every node in the subtree gets a new revision, so their CIDs change and
+    /// their previous links point to their old revisions. File content blocks
+    /// are reused as-is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::{Duration, Utc};
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     let path = &["pictures".into(), "cats".into(), "tabby.png".into()];
+    ///
+    ///     dir.write(path, b"meow".to_vec(), Utc::now(), store).await?;
+    ///
+    ///     let later = Utc::now() + Duration::days(1);
+    ///     dir.touch_tree(&["pictures".into()], later, store).await?;
+    ///
+    ///     let file = dir.get_node(path, store).await?.unwrap().as_file()?;
+    ///     assert_eq!(
+    ///         file.get_metadata().get_modified().unwrap().timestamp(),
+    ///         later.timestamp()
+    ///     );
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn touch_tree(
+        self: &mut Arc<Self>,
+        path_segments: &[String],
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let Some((filename, path)) = path_segments.split_last() else {
+            return Self::touch_helper(self, time, store).await;
+        };
+
+        let dir = self
+            .get_leaf_dir_mut(path, store)
+            .await?
+            .into_found(path_segments)?;
+
+        match dir.lookup_node_mut(filename, store).await? {
+            Some(PublicNode::Dir(dir)) => Self::touch_helper(dir, time, store).await,
+            Some(PublicNode::File(file)) => {
+                file.prepare_next_revision().metadata.upsert_mtime(time);
+                Ok(())
+            }
+            None => bail!(FsError::not_found(path_segments)),
+        }
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), async_recursion)]
+    #[cfg_attr(target_arch = "wasm32", async_recursion(?Send))]
+    async fn touch_helper(
+        dir: &mut Arc<Self>,
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let dir = dir.prepare_next_revision();
+        dir.metadata.upsert_mtime(time);
+
+        for link in dir.userland.values_mut() {
+            match link.resolve_value_mut(store).await? {
+                PublicNode::Dir(dir) => Self::touch_helper(dir, time, store).await?,
+                PublicNode::File(file) => file.prepare_next_revision().metadata.upsert_mtime(time),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stores this directory like `store` does, and additionally reports how many
+    /// blocks and bytes were new to the block store.
+    ///
+    /// Blocks that were already present in the store don't count, so this is the
+    /// amount of data a sync would have to upload for this store.
+    ///
+    /// File content is put into the block store when writing, not when storing.
+    /// To account for content blocks as well, wrap the block store in an
+    /// [`AccountingBlockStore`] for all operations instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///
+    ///     dir.mkdir(&["pictures".into()], Utc::now(), store).await?;
+    ///     let (_, stats) = dir.store_accounted(store).await?;
+    ///     assert_eq!(stats.new_blocks, 2);
+    ///
+    ///     let (_, stats) = dir.store_accounted(store).await?;
+    ///     assert_eq!(stats.new_blocks, 0);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn store_accounted(&self, store: &impl BlockStore) -> Result<(Cid, StoreStats)> {
+        let store = AccountingBlockStore::new(store);
+        let cid = self.store(&store).await?;
+        Ok((cid, store.stats()))
+    }
+
+    /// Returns the paths of all nodes in this tree that have changes that weren't
+    /// stored yet, see `PublicNode::is_dirty`, in depth-first order. This directory
+    /// itself is included as the empty path if it's dirty.
+    ///
+    /// Only nodes that are in memory are visited, nodes that would have to be loaded
+    /// from the block store can't have unstored changes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::{MemoryBlockStore, Storable},
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     dir.write(&["docs".into(), "a.md".into()], b"A".to_vec(), Utc::now(), store).await?;
+    ///     dir.write(&["b.md".into()], b"B".to_vec(), Utc::now(), store).await?;
+    ///     dir.store(store).await?;
+    ///
+    ///     dir.write(&["docs".into(), "a.md".into()], b"AA".to_vec(), Utc::now(), store).await?;
+    ///
+    ///     assert_eq!(
+    ///         dir.dirty_paths(),
+    ///         [vec![], vec!["docs".to_string()], vec!["docs".into(), "a.md".into()]]
+    ///     );
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn dirty_paths(&self) -> Vec<Vec<String>> {
+        let mut dirty = Vec::new();
+        if self.persisted_as.get().is_none() {
+            dirty.push(Vec::new());
+        }
+
+        self.collect_dirty_paths(&mut Vec::new(), &mut dirty);
+        dirty
+    }
+
+    fn collect_dirty_paths(&self, path: &mut Vec<String>, dirty: &mut Vec<Vec<String>>) {
+        for (name, link) in &self.userland {
+            let Some(node) = link.get_value().filter(|node| node.is_dirty()) else {
+                continue;
+            };
+
+            path.push(name.clone());
+            dirty.push(path.clone());
+            if let PublicNode::Dir(dir) = node {
+                dir.collect_dirty_paths(path, dirty);
+            }
+            path.pop();
+        }
+    }
+
+    /// Stores this directory as a new revision labeled with given label, like a
+    /// tag in git, and returns its CID. See `find_snapshot` for finding it again.
+    ///
+    /// The label is recorded in the metadata of the stored revision only, later
+    /// revisions don't inherit it. Labels don't need to be unique.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     dir.write(&["notes.txt".into()], b"Draft".to_vec(), Utc::now(), store).await?;
+    ///     let release = dir.snapshot_labeled("v1.0", store).await?;
+    ///
+    ///     dir.write(&["notes.txt".into()], b"Edited".to_vec(), Utc::now(), store).await?;
+    ///
+    ///     assert_eq!(dir.find_snapshot("v1.0", store).await?, Some(release));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn snapshot_labeled(
+        self: &mut Arc<Self>,
+        label: &str,
+        store: &impl BlockStore,
+    ) -> Result<Cid> {
+        self.prepare_next_revision()
+            .metadata
+            .upsert_snapshot_label(label);
+        self.store(store).await
+    }
+
+    /// Finds the most recent revision in this directory's history, including this
+    /// revision itself, that's labeled with given label via `snapshot_labeled`.
+    ///
+    /// Revisions are searched breadth-first along their previous links, so a
+    /// revision fewer steps back in history counts as more recent.
+    pub async fn find_snapshot(&self, label: &str, store: &impl BlockStore) -> Result<Option<Cid>> {
+        if self.metadata.get_snapshot_label() == Some(label) {
+            return Ok(Some(self.store(store).await?));
+        }
+
+        let history = PublicNode::history_from(&self.previous, store);
+        futures::pin_mut!(history);
+        while let Some(revision) = history.try_next().await? {
+            let revision = revision.as_dir()?;
+            if revision.metadata.get_snapshot_label() == Some(label) {
+                return Ok(Some(revision.store(store).await?));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the newest revision of this directory, going back along its previous
+    /// links, that was last modified at or before given time. That's this revision
+    /// itself if nothing in it was modified since.
+    ///
+    /// Directories don't get new modification times when nodes below them change,
+    /// so a revision counts as modified when the directory itself or any node below
+    /// it was. That needs the whole tree of each revision, though subtrees that
+    /// revisions share are only loaded once.
+    ///
+    /// Each branch of merged histories is followed back until a revision that's old
+    /// enough. Of these, the one modified most recently gets returned.
+    /// Fails with `FsError::NoRevisionAt` if even the oldest revisions are newer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::{Duration, Utc};
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::{MemoryBlockStore, Storable},
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let last_week = Utc::now() - Duration::days(7);
+    ///     let dir = &mut PublicDirectory::new_rc(last_week);
+    ///     dir.write(&["notes.txt".into()], b"Old".to_vec(), last_week, store).await?;
+    ///     dir.store(store).await?;
+    ///     dir.write(&["notes.txt".into()], b"New".to_vec(), Utc::now(), store).await?;
+    ///
+    ///     let old = dir.at_time(last_week + Duration::days(1), store).await?;
+    ///
+    ///     assert_eq!(old.read(&["notes.txt".into()], store).await?, b"Old");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn at_time(&self, time: DateTime<Utc>, store: &impl BlockStore) -> Result<Arc<Self>> {
+        let mut known = BTreeMap::new();
+        let is_old_enough = |modified: Option<DateTime<Utc>>| modified.is_some_and(|m| m <= time);
+        if is_old_enough(self.latest_modified(&mut known, store).await?) {
+            return Ok(Arc::new(self.clone()));
+        }
+
+        // The revisions still to check, skipping those only reached through ones
+        // that are old enough, since these are older still
+        let mut pending = self.previous.clone();
+        let mut newest = None;
+        let history = PublicNode::history_from(&self.previous, store);
+        futures::pin_mut!(history);
+        while !pending.is_empty() {
+            let Some(revision) = history.try_next().await? else {
+                break;
+            };
+            let revision = revision.as_dir()?;
+            if !pending.remove(&revision.store(store).await?) {
+                continue;
+            }
+
+            let modified = revision.latest_modified(&mut known, store).await?;
+            if !is_old_enough(modified) {
+                pending.extend(revision.previous.iter().copied());
+                continue;
+            }
+
+            if newest
+                .as_ref()
+                .map_or(true, |(newest, _)| *newest < modified)
+            {
+                newest = Some((modified, revision));
+            }
+        }
+
+        match newest {
+            Some((_, revision)) => Ok(revision),
+            None => bail!(FsError::NoRevisionAt(time)),
+        }
+    }
+
+    /// Returns the revision this directory was derived from, with its own history
+    /// intact, so it can be made the new head. Returns `None` for directories
+    /// without a previous revision.
+    ///
+    /// Fails with `FsError::AmbiguousPreviousRevision` if this revision was merged
+    /// from several previous revisions. Use `rollback_to` to pick one of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::{MemoryBlockStore, Storable},
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     dir.write(&["notes.txt".into()], b"Old".to_vec(), Utc::now(), store).await?;
+    ///     dir.store(store).await?;
+    ///     dir.write(&["notes.txt".into()], b"New".to_vec(), Utc::now(), store).await?;
+    ///
+    ///     let previous = dir.rollback(store).await?.unwrap();
+    ///
+    ///     assert_eq!(previous.read(&["notes.txt".into()], store).await?, b"Old");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn rollback(&self, store: &impl BlockStore) -> Result<Option<Arc<Self>>> {
+        let mut previous = self.previous.iter();
+        match (previous.next(), previous.next()) {
+            (None, _) => Ok(None),
+            (Some(cid), None) => Ok(Some(Arc::new(Self::load(cid, store).await?))),
+            _ => bail!(FsError::AmbiguousPreviousRevision(
+                self.previous.iter().copied().collect()
+            )),
+        }
+    }
+
+    /// Returns the revision with given CID from the history of this directory, with
+    /// its own history intact, so it can be made the new head.
+    ///
+    /// Fails with `FsError::RevisionNotInHistory` if given CID can't be reached by
+    /// following previous links from this revision.
+    pub async fn rollback_to(&self, cid: &Cid, store: &impl BlockStore) -> Result<Arc<Self>> {
+        let history = PublicNode::history_from(&self.previous, store);
+        futures::pin_mut!(history);
+        while let Some(revision) = history.try_next().await? {
+            if revision.store(store).await? == *cid {
+                return revision.as_dir();
+            }
+        }
+
+        bail!(FsError::RevisionNotInHistory(*cid))
+    }
+
+    /// The latest modification time of this directory and all nodes below it.
+    /// Remembers the times of subtrees by their CIDs in `known`, so they're
+    /// only computed once.
+    #[cfg_attr(not(target_arch = "wasm32"), async_recursion)]
+    #[cfg_attr(target_arch = "wasm32", async_recursion(?Send))]
+    async fn latest_modified(
+        &self,
+        known: &mut BTreeMap<Cid, Option<DateTime<Utc>>>,
+        store: &impl BlockStore,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let mut latest = self.metadata.get_modified();
+        for link in self.userland.values() {
+            let cid = link.get_cid().copied();
+            if let Some(modified) = cid.and_then(|cid| known.get(&cid)) {
+                latest = latest.max(*modified);
+                continue;
+            }
+
+            let modified = match link.resolve_value(store).await? {
+                PublicNode::File(file) => file.metadata.get_modified(),
+                PublicNode::Dir(dir) => dir.latest_modified(known, store).await?,
+            };
+            if let Some(cid) = cid {
+                known.insert(cid, modified);
+            }
+
+            latest = latest.max(modified);
+        }
+
+        Ok(latest)
+    }
+
+    /// Returns a copy of this directory whose history only reaches back `keep` revisions.
+    ///
+    /// Ancestors more than `keep` revisions back are unlinked: Each ancestor within
+    /// the limit gets rewritten and stored with its own previous links pruned
+    /// accordingly, with an ancestor at the limit ending up without previous links.
+    /// When there are several previous links, they're walked in their recorded order,
+    /// so pruning the same history always results in the same CIDs.
+    ///
+    /// Neither this directory's content nor its ancestors' content changes. Blocks
+    /// of dropped revisions aren't deleted from the block store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::{MemoryBlockStore, Storable},
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///
+    ///     for i in 0..5 {
+    ///         dir.write(&["log.txt".into()], vec![i], Utc::now(), store).await?;
+    ///         dir.store(store).await?;
+    ///     }
+    ///
+    ///     let pruned = dir.gc_history(0, store).await?;
+    ///     assert!(pruned.get_previous().is_empty());
+    ///     assert_eq!(pruned.read(&["log.txt".into()], store).await?, vec![4]);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn gc_history(&self, keep: usize, store: &impl BlockStore) -> Result<Arc<Self>> {
+        let mut pruned = BTreeMap::new();
+        let mut dir = self.clone();
+        dir.persisted_as = OnceCell::new();
+        dir.previous = Self::prune_previous(&self.previous, keep, &mut pruned, store).await?;
+        Ok(Arc::new(dir))
+    }
+
+    /// Rewrites given previous links so they reach back at most `keep` revisions.
+    /// Remembers already rewritten ancestors in `pruned`, keyed by their CID and
+    /// how many revisions they may still reach back.
+    #[cfg_attr(not(target_arch = "wasm32"), async_recursion)]
+    #[cfg_attr(target_arch = "wasm32", async_recursion(?Send))]
+    async fn prune_previous(
+        previous: &BTreeSet<Cid>,
+        keep: usize,
+        pruned: &mut BTreeMap<(Cid, usize), Cid>,
+        store: &impl BlockStore,
+    ) -> Result<BTreeSet<Cid>> {
+        let Some(remaining) = keep.checked_sub(1) else {
+            return Ok(BTreeSet::new());
+        };
+
+        let mut rewritten = BTreeSet::new();
+        for cid in previous {
+            if let Some(cid) = pruned.get(&(*cid, remaining)) {
+                rewritten.insert(*cid);
+                continue;
+            }
+
+            let mut ancestor = Self::load(cid, store).await?;
+            let ancestor_previous =
+                Self::prune_previous(&ancestor.previous, remaining, pruned, store).await?;
+            let new_cid = if ancestor_previous == ancestor.previous {
+                *cid
+            } else {
+                ancestor.persisted_as = OnceCell::new();
+                ancestor.previous = ancestor_previous;
+                ancestor.store(store).await?
+            };
+
+            pruned.insert((*cid, remaining), new_cid);
+            rewritten.insert(new_cid);
+        }
+
+        Ok(rewritten)
+    }
+
+    /// Comparing the merkle clocks of this directory to the other directory
+    pub async fn causal_compare(
+        self: Arc<Self>,
+        other: Arc<Self>,
+        store: &impl BlockStore,
+    ) -> Result<Option<Ordering>> {
+        let causal_order = PublicNode::Dir(self)
+            .causal_compare(&PublicNode::Dir(other), store)
+            .await?;
+        Ok(causal_order)
+    }
+
+    /// Reconcile this node with another node.
+    ///
+    /// Use this function when you're informed about another version of this
+    /// public WNFS directory and want to merge any possibly new changes into
+    /// this directory.
+    ///
+    /// The return value can give information about what exactly happened.
+    /// See the documentation for the `Reconciliation` enum for more information.
+    pub async fn reconcile(
+        self: &mut Arc<Self>,
+        other: &Arc<Self>,
+        store: &impl BlockStore,
+    ) -> Result<Reconciliation> {
+        self.reconcile_with_policy(other, MetadataMergePolicy::default(), store)
+            .await
+    }
+
+    /// Reconcile this node with another node like `reconcile`, combining the
+    /// metadata of concurrently changed nodes as given policy says.
+    ///
+    /// With `MetadataMergePolicy::TakeNewer` or `MetadataMergePolicy::Union`,
+    /// files whose versions only differ in metadata get merged without being
+    /// reported in `Reconciliation::Merged`. Files with differing content still
+    /// need a tie-break and get reported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::{Duration, Utc};
+    /// use wnfs::{
+    ///     public::{PublicDirectory, Reconciliation},
+    ///     common::{MemoryBlockStore, MetadataMergePolicy, Storable},
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let time = Utc::now();
+    ///     let path = ["notes.txt".into()];
+    ///     let dir = &mut PublicDirectory::new_rc(time);
+    ///     dir.write(&path, b"Notes".to_vec(), time, store).await?;
+    ///     dir.store(store).await?;
+    ///
+    ///     let fork = &mut dir.clone();
+    ///     dir.touch_tree(&path, time + Duration::hours(1), store).await?;
+    ///     fork.touch_tree(&path, time + Duration::hours(2), store).await?;
+    ///
+    ///     let result = dir
+    ///         .reconcile_with_policy(fork, MetadataMergePolicy::TakeNewer, store)
+    ///         .await?;
+    ///
+    ///     assert!(matches!(
+    ///         result,
+    ///         Reconciliation::Merged { file_tie_breaks } if file_tie_breaks.is_empty()
+    ///     ));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn reconcile_with_policy(
+        self: &mut Arc<Self>,
+        other: &Arc<Self>,
+        policy: MetadataMergePolicy,
+        store: &impl BlockStore,
+    ) -> Result<Reconciliation> {
+        let causal_order = self.clone().causal_compare(other.clone(), store).await?;
+
+        Ok(match causal_order {
+            Some(Ordering::Equal) => Reconciliation::AlreadyAhead,
+            Some(Ordering::Greater) => Reconciliation::AlreadyAhead,
+            Some(Ordering::Less) => {
+                self.clone_from(other);
+                Reconciliation::FastForward
+            }
+            None => {
+                let mut file_tie_breaks = BTreeSet::new();
+                self.reconcile_helper(other, policy, None, store, &[], &mut file_tie_breaks)
+                    .await?;
+                Reconciliation::Merged { file_tie_breaks }
+            }
+        })
+    }
+
+    /// Merges the other directory into this one, recursively. Nodes that are
+    /// concurrent, so neither is an ancestor of the other, get merged as given
+    /// two-way merge settles them, or by tie-breaks without one.
+    #[cfg_attr(not(target_arch = "wasm32"), async_recursion)]
+    #[cfg_attr(target_arch = "wasm32", async_recursion(?Send))]
+    pub(crate) async fn reconcile_helper<'a>(
+        self: &'a mut Arc<Self>,
+        other: &'a Arc<Self>,
+        policy: MetadataMergePolicy,
+        two_way: Option<TwoWayMerge<'a>>,
+        store: &'a impl BlockStore,
+        current_path: &[String],
+        file_tie_breaks: &mut BTreeSet<Vec<String>>,
+    ) -> Result<()> {
+        let our_cid = self.store(store).await?;
+        let other_cid = other.store(store).await?;
+        if our_cid == other_cid {
+            // We don't have to merge
+            return Ok(());
+        }
+
+        let dir = self.prepare_next_merge(store).await?;
+        if other.previous.len() > 1 {
+            // The other node is a merge node, we should merge the merge nodes directly:
+            dir.previous.extend(other.previous.iter().cloned());
+        } else {
+            // The other node is a 'normal' node - we need to merge it normally
+            dir.previous.insert(other.store(store).await?);
+        }
+        let our_tombstones = tombstone::tombstones(&dir.metadata);
+        dir.metadata.merge_with(&other.metadata, policy)?;
+        // Labels belong to the labeled revision only, not ones merged from it
+        dir.metadata.delete_snapshot_label();
+        if let Some(two_way) = two_way {
+            dir.metadata.upsert_mtime(two_way.time);
+        }
+
+        for (name, other_link) in other.userland.iter() {
+            let other_node = other_link.resolve_value(store).await?;
+            match dir.userland.entry(name.clone()) {
+                Entry::Vacant(vacant) => {
+                    vacant.insert(other_link.clone());
+                }
+                Entry::Occupied(mut occupied) => {
+                    let our_node = occupied.get_mut().resolve_value_mut(store).await?;
+
+                    match our_node.causal_compare(other_node, store).await? {
+                        Some(Ordering::Equal) => {
+                            continue;
+                        }
+                        Some(Ordering::Less) => {
+                            our_node.clone_from(other_node);
+                            continue;
+                        }
+                        Some(Ordering::Greater) => {
+                            continue;
+                        }
+                        None => {}
+                    };
+
+                    let mut path = current_path.to_vec();
+                    path.push(name.clone());
+                    if let Some(two_way) = two_way {
+                        if !matches!(
+                            (&*our_node, other_node),
+                            (PublicNode::Dir(_), PublicNode::Dir(_))
+                        ) {
+                            let conflict = MergeConflict {
+                                path: &path,
+                                ours: our_node,
+                                theirs: other_node,
+                            };
+                            if two_way.resolver.resolve(&conflict) == Resolution::Theirs {
+                                our_node.clone_from(other_node);
+                            }
+                            continue;
+                        }
+                    }
+
+                    match (our_node, other_node) {
+                        (PublicNode::File(our_file), PublicNode::File(other_file)) => {
+                            if our_file
+                                .merge_with_policy(other_file, policy, store)
+                                .await?
+                            {
+                                file_tie_breaks.insert(path);
+                            }
+                        }
+                        (node @ PublicNode::File(_), PublicNode::Dir(other_dir)) => {
+                            // directories have priority
+                            // we don't add previous links
+                            *node = PublicNode::Dir(other_dir.clone());
+                        }
+                        (PublicNode::Dir(_), PublicNode::File(_)) => {
+                            // directories have priority, no changes necessary
+                        }
+                        (PublicNode::Dir(dir), PublicNode::Dir(other_dir)) => {
+                            dir.reconcile_helper(
+                                other_dir,
+                                policy,
+                                two_way,
+                                store,
+                                &path,
+                                file_tie_breaks,
+                            )
+                            .await?;
+                        }
+                    }
+                }
+            }
+        }
+
+        dir.merge_tombstones(our_tombstones, &other.metadata);
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for PublicDirectory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PublicDirectory")
+            .field(
+                "persisted_as",
+                &OnceCellDebug(self.persisted_as.get().map(|cid| format!("{cid}"))),
+            )
+            .field("metadata", &self.metadata)
+            .field("userland", &self.userland)
+            .field(
+                "previous",
+                &self
+                    .previous
+                    .iter()
+                    .map(|cid| format!("{cid}"))
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl Id for PublicDirectory {
+    fn get_id(&self) -> String {
+        format!("{:p}", &self.metadata)
+    }
+}
+
+impl PartialEq for PublicDirectory {
+    fn eq(&self, other: &Self) -> bool {
+        self.metadata == other.metadata
+            && self.userland == other.userland
+            && self.previous == other.previous
+    }
+}
+
+impl Clone for PublicDirectory {
+    fn clone(&self) -> Self {
+        Self {
+            persisted_as: self
+                .persisted_as
+                .get()
+                .cloned()
+                .map(OnceCell::new_with)
+                .unwrap_or_default(),
+            size_recursive: self
+                .size_recursive
+                .get()
+                .cloned()
+                .map(OnceCell::new_with)
+                .unwrap_or_default(),
+            metadata: self.metadata.clone(),
+            userland: self.userland.clone(),
+            previous: self.previous.clone(),
+        }
+    }
+}
+
+impl Storable for PublicDirectory {
+    type Serializable = PublicNodeSerializable;
+
+    async fn to_serializable(&self, store: &impl BlockStore) -> Result<Self::Serializable> {
+        let userland = {
+            let mut map = BTreeMap::new();
+            for (name, link) in self.userland.iter() {
+                // Boxing the future due to recursion
+                map.insert(name.clone(), boxed_fut(link.resolve_cid(store)).await?);
+            }
+            map
+        };
+
+        Ok(PublicNodeSerializable::Dir(PublicDirectorySerializable {
+            version: WNFS_VERSION,
+            metadata: self.metadata.clone(),
+            userland,
+            previous: self.previous.iter().cloned().collect(),
+        }))
+    }
+
+    async fn from_serializable(
+        cid: Option<&Cid>,
+        serializable: Self::Serializable,
+    ) -> Result<Self> {
+        let PublicNodeSerializable::Dir(serializable) = serializable else {
+            bail!(FsError::UnexpectedNodeType(NodeType::PublicFile));
+        };
+
+        if !is_readable_wnfs_version(&serializable.version) {
+            bail!(FsError::UnexpectedVersion(serializable.version))
+        }
+
+        let userland = serializable
+            .userland
+            .into_iter()
+            .map(|(name, cid)| (name, PublicLink::from_cid(cid)))
+            .collect();
+
+        Ok(Self {
+            persisted_as: cid.cloned().map(OnceCell::new_with).unwrap_or_default(),
+            size_recursive: OnceCell::new(),
+            metadata: serializable.metadata,
+            userland,
+            previous: serializable.previous.iter().cloned().collect(),
+        })
+    }
+
+    fn persisted_as(&self) -> Option<&OnceCell<Cid>> {
+        Some(&self.persisted_as)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MAX_NAME_LENGTH;
+    use libipld_core::ipld::Ipld;
+    use testresult::TestResult;
+    use wnfs_common::{decode, libipld::cbor::DagCborCodec, MemoryBlockStore, RecordingBlockStore};
+    use wnfs_unixfs_file::builder::FileBuilder;
+
+    #[async_std::test]
+    async fn look_up_can_fetch_file_added_to_directory() -> TestResult {
+        let root_dir = &mut PublicDirectory::new_rc(Utc::now());
+        let store = &MemoryBlockStore::new();
+        let time = Utc::now();
+
+        root_dir
+            .write(&["text.txt".into()], b"Hello World!".to_vec(), time, store)
+            .await?;
+
+        let node = root_dir.lookup_node("text.txt", store).await?;
+
+        assert_eq!(
+            node,
+            Some(&PublicNode::File(
+                PublicFile::with_content_rc(time, b"Hello World!".to_vec(), store).await?
+            ))
+        );
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn look_up_cannot_fetch_file_not_added_to_directory() {
+        let root = PublicDirectory::new(Utc::now());
+        let store = MemoryBlockStore::default();
+
+        let node = root.lookup_node("Unknown", &store).await;
+
+        assert!(node.is_ok());
+
+        assert_eq!(node.unwrap(), None);
+    }
+
+    #[async_std::test]
+    async fn get_node_can_fetch_node_from_root_dir() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root_dir = &mut PublicDirectory::new_rc(time);
+
+        root_dir
+            .mkdir(&["pictures".into(), "dogs".into()], time, store)
+            .await?;
+
+        root_dir
+            .write(
+                &["pictures".into(), "cats".into(), "tabby.jpg".into()],
+                b"Hello".to_vec(),
+                time,
+                store,
+            )
+            .await?;
+
+        assert!(root_dir
+            .get_node(
+                &["pictures".into(), "cats".into(), "tabby.jpg".into()],
+                store
+            )
+            .await?
+            .is_some());
+
+        assert!(root_dir
+            .get_node(
+                &["pictures".into(), "cats".into(), "tabby.jpeg".into()],
+                store
+            )
+            .await?
+            .is_none());
+
+        assert!(root_dir
+            .get_node(
+                &["images".into(), "parrots".into(), "coco.png".into()],
+                store
+            )
+            .await?
+            .is_none());
+
+        assert!(root_dir
+            .get_node(
+                &["pictures".into(), "dogs".into(), "bingo.jpg".into()],
+                store
+            )
+            .await?
+            .is_none());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_node_relative_resolves_dot_segments() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::default();
+        let root = &mut PublicDirectory::new_rc(time);
+        root.write(
+            &["a".into(), "b".into(), "c.txt".into()],
+            b"c".to_vec(),
+            time,
+            store,
+        )
+        .await?;
+        root.write(&["d.txt".into()], b"d".to_vec(), time, store)
+            .await?;
+
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        let base = path("a/b");
+
+        let c = root
+            .get_node_relative(&base, &path("./c.txt"), store)
+            .await?;
+        assert!(c.unwrap().is_file());
+
+        let d = root
+            .get_node_relative(&base, &path("../../d.txt"), store)
+            .await?;
+        assert!(d.unwrap().is_file());
+
+        let b = root
+            .get_node_relative(&base, &path("c.txt/.."), store)
+            .await?;
+        assert!(b.unwrap().is_dir());
+
+        let result = root
+            .get_node_relative(&base, &path("../../../d.txt"), store)
+            .await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::InvalidPath)
+        ));
+
+        assert!(root.get_node(&path("a/../d.txt"), store).await?.is_none());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_many_resolves_paths_in_input_order() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root_dir = &mut PublicDirectory::new_rc(time);
+
+        root_dir
+            .write(
+                &["pictures".into(), "cats".into(), "tabby.jpg".into()],
+                b"Hello".to_vec(),
+                time,
+                store,
+            )
+            .await?;
+        root_dir
+            .mkdir(&["pictures".into(), "dogs".into()], time, store)
+            .await?;
+
+        let paths = vec![
+            vec!["pictures".into(), "cats".into(), "tabby.jpg".into()],
+            vec![
+                "pictures".into(),
+                "cats".into(),
+                "tabby.jpg".into(),
+                "x".into(),
+            ],
+            vec!["images".into()],
+            vec![],
+            vec!["pictures".into(), "dogs".into()],
+            vec!["pictures".into()],
+        ];
+
+        let nodes = root_dir.get_many(&paths, store).await?;
+
+        assert_eq!(nodes.len(), paths.len());
+        for (path, node) in paths.iter().zip(nodes) {
+            assert_eq!(node.as_ref(), root_dir.get_node(path, store).await?);
+        }
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn mkdir_can_create_new_directory() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root_dir = &mut PublicDirectory::new_rc(time);
+
+        root_dir
+            .mkdir(&["tamedun".into(), "pictures".into()], time, store)
+            .await?;
+
+        let result = root_dir
+            .get_node(&["tamedun".into(), "pictures".into()], store)
+            .await?;
+
+        assert!(result.is_some());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn ls_can_list_children_under_directory() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root_dir = &mut PublicDirectory::new_rc(time);
+
+        root_dir
+            .mkdir(&["tamedun".into(), "pictures".into()], time, store)
+            .await?;
+
+        root_dir
+            .write(
+                &["tamedun".into(), "pictures".into(), "puppy.jpg".into()],
+                b"Hello".to_vec(),
+                time,
+                store,
+            )
+            .await?;
+
+        root_dir
+            .mkdir(
+                &["tamedun".into(), "pictures".into(), "cats".into()],
+                time,
+                store,
+            )
+            .await?;
+
+        let result = root_dir
+            .ls(&["tamedun".into(), "pictures".into()], store)
+            .await?;
+
+        assert_eq!(result.len(), 2);
+
+        assert_eq!(result[0].0, String::from("cats"));
+
+        assert_eq!(result[1].0, String::from("puppy.jpg"));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn rm_can_remove_children_from_directory() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let mut root_dir = PublicDirectory::new_rc(time);
+
+        root_dir
+            .mkdir(&["tamedun".into(), "pictures".into()], time, store)
+            .await?;
+
+        root_dir
+            .write(
+                &["tamedun".into(), "pictures".into(), "puppy.jpg".into()],
+                b"Hello".to_vec(),
+                time,
+                store,
+            )
+            .await?;
+
+        root_dir
+            .mkdir(
+                &["tamedun".into(), "pictures".into(), "cats".into()],
+                time,
+                store,
+            )
+            .await?;
+
+        let result = root_dir
+            .rm(&["tamedun".into(), "pictures".into()], store)
+            .await;
+
+        assert!(result.is_ok());
+
+        let result = root_dir
+            .rm(&["tamedun".into(), "pictures".into()], store)
+            .await;
+
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn read_can_fetch_userland_of_file_added_to_directory() -> TestResult {
+        let store = &MemoryBlockStore::new();
+        let time = Utc::now();
+        let content = b"Hello".to_vec();
+        let mut root_dir = PublicDirectory::new_rc(time);
+
+        root_dir
+            .write(&["text.txt".into()], content.clone(), time, store)
+            .await?;
+
+        let result = root_dir.read(&["text.txt".into()], store).await?;
+
+        assert_eq!(result, content);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn ranges_across_chunk_boundaries_load_only_overlapping_chunks() -> TestResult {
+        let store = &MemoryBlockStore::new();
+        let time = Utc::now();
+        let root_dir = &mut PublicDirectory::new_rc(time);
+        let path = &["video.mp4".into()];
+        let content = (0..3 * 256 * 1024)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        root_dir.write(path, content.clone(), time, store).await?;
+        let cid = root_dir.store(store).await?;
+
+        let boundary = 256 * 1024;
+        let recording = &RecordingBlockStore::new(store);
+        let loaded = PublicDirectory::load(&cid, recording).await?;
+        let range = loaded
+            .read_range(path, boundary - 100, boundary + 100, recording)
+            .await?;
+        assert_eq!(
+            range,
+            &content[boundary as usize - 100..boundary as usize + 100]
+        );
+
+        // The directory, the file, its content root and the two chunks
+        let loads = recording.log();
+        assert_eq!(loads.len(), 5);
+
+        let size = content.len() as u64;
+        let tail = root_dir
+            .read_range(path, size - 10, size + 10, store)
+            .await?;
+        assert_eq!(tail, &content[content.len() - 10..]);
+        assert!(root_dir
+            .read_range(path, size, size + 10, store)
+            .await?
+            .is_empty());
+        let err = root_dir.read_range(path, size + 1, size + 2, store).await;
+        assert!(matches!(
+            err.unwrap_err().downcast_ref(),
+            Some(FsError::RangeNotSatisfiable { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn missing_parents_and_missing_leaves_are_told_apart() -> TestResult {
+        let store = &MemoryBlockStore::new();
+        let time = Utc::now();
+        let root_dir = &mut PublicDirectory::new_rc(time);
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        root_dir
+            .write(&path("docs/notes.txt"), b"Notes".to_vec(), time, store)
+            .await?;
+
+        let missing_leaf = root_dir.read(&path("docs/todo.txt"), store).await;
+        match missing_leaf.unwrap_err().downcast_ref() {
+            Some(FsError::NotFound { path: missing }) => {
+                assert_eq!(missing, &path("docs/todo.txt"))
+            }
+            other => panic!("Unexpected error {other:?}"),
+        }
+
+        let missing_parent = root_dir.rm(&path("docs/old/2020/todo.txt"), store).await;
+        match missing_parent.unwrap_err().downcast_ref() {
+            Some(FsError::ParentNotFound { path: missing }) => {
+                assert_eq!(missing, &path("docs/old"))
+            }
+            other => panic!("Unexpected error {other:?}"),
+        }
+
+        let missing_parent = root_dir
+            .cp(
+                &path("docs/notes.txt"),
+                &path("backup/notes.txt"),
+                time,
+                store,
+            )
+            .await;
+        assert!(matches!(
+            missing_parent.unwrap_err().downcast_ref(),
+            Some(FsError::ParentNotFound { path }) if path == &["backup"]
+        ));
+
+        let missing_dir = root_dir.ls(&path("docs/old"), store).await;
+        assert_eq!(
+            missing_dir.unwrap_err().to_string(),
+            "Cannot find file or directory /docs/old"
+        );
+
+        for result in [
+            root_dir
+                .rm(&path("docs/notes.txt/todo.txt"), store)
+                .await
+                .map(|_| ()),
+            root_dir
+                .ls_paginated(&path("docs/notes.txt"), None, 10, store)
+                .await
+                .map(|_| ()),
+            root_dir
+                .prove_path(&path("docs/notes.txt/todo.txt"), store)
+                .await
+                .map(|_| ()),
+        ] {
+            assert!(matches!(
+                result.unwrap_err().downcast_ref(),
+                Some(FsError::NotADirectory)
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn mv_can_move_sub_directory_to_another_valid_location() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let mut root_dir = PublicDirectory::new_rc(time);
+
+        root_dir
+            .write(
+                &["pictures".into(), "cats".into(), "tabby.jpg".into()],
+                b"Hello".to_vec(),
+                time,
+                store,
+            )
+            .await?;
+
+        root_dir
+            .write(
+                &["pictures".into(), "cats".into(), "luna.png".into()],
+                b"Hello".to_vec(),
+                time,
+                store,
+            )
+            .await?;
+
+        root_dir.mkdir(&["images".into()], time, store).await?;
+
+        root_dir
+            .basic_mv(
+                &["pictures".into(), "cats".into()],
+                &["images".into(), "cats".into()],
+                Utc::now(),
+                store,
+            )
+            .await?;
+
+        let result = root_dir.ls(&["images".into()], store).await?;
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].0, String::from("cats"));
+
+        let result = root_dir.ls(&["pictures".into()], store).await?;
+
+        assert_eq!(result.len(), 0);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn mv_cannot_move_sub_directory_to_invalid_location() {
+        let time = Utc::now();
+        let store = MemoryBlockStore::default();
+        let mut root_dir = PublicDirectory::new_rc(time);
+
+        root_dir
+            .mkdir(
+                &[
+                    "videos".into(),
+                    "movies".into(),
+                    "anime".into(),
+                    "ghibli".into(),
+                ],
+                time,
+                &store,
+            )
+            .await
+            .unwrap();
+
+        let result = root_dir
+            .basic_mv(
+                &["videos".into(), "movies".into()],
+                &["videos".into(), "movies".into(), "anime".into()],
+                Utc::now(),
+                &store,
+            )
+            .await;
+
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::InvalidPath)
+        ));
+
+        // The failed move left the tree as it was
+        let ghibli = &[
+            "videos".into(),
+            "movies".into(),
+            "anime".into(),
+            "ghibli".into(),
+        ];
+        assert!(root_dir.get_node(ghibli, &store).await.unwrap().is_some());
+    }
+
+    #[async_std::test]
+    async fn cp_copies_subtrees_sharing_their_content() -> TestResult {
+        let time = Utc::now();
+        let later = time + chrono::Duration::seconds(60);
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        root.write(&path("pictures/cats/tabby.png"), vec![1; 1000], time, store)
+            .await?;
+        root.write(&path("pictures/dog.png"), vec![2; 1000], time, store)
+            .await?;
+        root.mkdir(&path("backup/pictures"), time, store).await?;
+        root.store(store).await?;
+
+        root.cp(&path("pictures"), &path("archive"), later, store)
+            .await?;
+        let cid = root.store(store).await?;
+        let loaded = PublicDirectory::load(&cid, store).await?;
+
+        let original = loaded.get_node(&path("pictures"), store).await?.unwrap();
+        let copy = loaded.get_node(&path("archive"), store).await?.unwrap();
+        let mtime = |node: &PublicNode| node.as_dir().unwrap().metadata.get_modified();
+        assert_eq!(mtime(copy).unwrap().timestamp(), later.timestamp());
+        assert_eq!(mtime(original).unwrap().timestamp(), time.timestamp());
+        let original_cid = original.as_dir()?.store(store).await?;
+        assert_eq!(copy.get_previous(), &BTreeSet::from([original_cid]));
+        assert_eq!(
+            copy.as_dir()?.userland.get("cats"),
+            original.as_dir()?.userland.get("cats")
+        );
+        assert_eq!(
+            loaded.read(&path("archive/cats/tabby.png"), store).await?,
+            vec![1; 1000]
+        );
+
+        let onto_existing = root
+            .cp(&path("pictures"), &path("backup/pictures"), later, store)
+            .await;
+        assert!(matches!(
+            onto_existing.unwrap_err().downcast_ref(),
+            Some(FsError::FileAlreadyExists)
+        ));
+
+        let missing_source = root
+            .cp(&path("pictures/bird.png"), &path("bird.png"), later, store)
+            .await;
+        assert!(matches!(
+            missing_source.unwrap_err().downcast_ref(),
+            Some(FsError::NotFound { .. })
+        ));
+
+        let into_file = root
+            .cp(
+                &path("pictures/cats"),
+                &path("pictures/dog.png/cats"),
+                later,
+                store,
+            )
+            .await;
+        assert!(matches!(
+            into_file.unwrap_err().downcast_ref(),
+            Some(FsError::NotADirectory)
+        ));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn mv_keeps_the_history_of_moved_nodes() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        root.write(&path("drafts/notes.md"), b"Notes".to_vec(), time, store)
+            .await?;
+        root.write(&path("todo.md"), b"Todo".to_vec(), time, store)
+            .await?;
+        root.store(store).await?;
+        let drafts = root.get_node(&path("drafts"), store).await?.unwrap();
+        let drafts_cid = drafts.store(store).await?;
+
+        root.mv(&path("drafts"), &path("docs"), time, store).await?;
+        let cid = root.store(store).await?;
+        let loaded = PublicDirectory::load(&cid, store).await?;
+
+        let docs = loaded.get_node(&path("docs"), store).await?.unwrap();
+        assert_eq!(docs.get_previous(), &BTreeSet::from([drafts_cid]));
+        assert_eq!(loaded.read(&path("docs/notes.md"), store).await?, b"Notes");
+        assert!(loaded.get_node(&path("drafts"), store).await?.is_none());
+
+        let into_itself = root
+            .mv(&path("docs"), &path("docs/archive/docs"), time, store)
+            .await;
+        assert!(matches!(
+            into_itself.unwrap_err().downcast_ref(),
+            Some(FsError::InvalidPath)
+        ));
+
+        let onto_existing = root
+            .mv(&path("todo.md"), &path("docs/notes.md"), time, store)
+            .await;
+        assert!(matches!(
+            onto_existing.unwrap_err().downcast_ref(),
+            Some(FsError::FileAlreadyExists)
+        ));
+        assert_eq!(root.read(&path("todo.md"), store).await?, b"Todo");
+        assert_eq!(root.read(&path("docs/notes.md"), store).await?, b"Notes");
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn mv_can_rename_directories() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root_dir = &mut PublicDirectory::new_rc(time);
+
+        root_dir
+            .write(&["file.txt".into()], b"Hello".to_vec(), time, store)
+            .await?;
+
+        root_dir
+            .basic_mv(
+                &["file.txt".into()],
+                &["renamed.txt".into()],
+                Utc::now(),
+                store,
+            )
+            .await?;
+
+        let result = root_dir.read(&["renamed.txt".into()], store).await?;
+
+        assert_eq!(result, b"Hello".to_vec());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn mv_fails_moving_directories_to_files() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root_dir = &mut PublicDirectory::new_rc(time);
+
+        root_dir
+            .mkdir(&["movies".into(), "ghibli".into()], time, store)
+            .await?;
+
+        root_dir
+            .write(&["file.txt".into()], b"Hello".to_vec(), time, store)
+            .await?;
+
+        let result = root_dir
+            .basic_mv(
+                &["movies".into(), "ghibli".into()],
+                &["file.txt".into()],
+                Utc::now(),
+                store,
+            )
+            .await;
+
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn sidecars_are_reachable_but_not_listed() -> TestResult {
+        use crate::public::verify_import;
+        use wnfs_common::CODEC_RAW;
+
+        let time = Utc::now();
+        let store = &MemoryBlockStore::default();
+        let root = &mut PublicDirectory::new_rc(time);
+        let path = &["pictures".into(), "cat.png".into()];
+        root.write(path, b"A big cat".to_vec(), time, store).await?;
+
+        let thumbnail = store.put_block(b"A tiny cat".to_vec(), CODEC_RAW).await?;
+        root.set_sidecar(path, "thumbnail", Some(thumbnail), store)
+            .await?;
+        assert_eq!(
+            root.get_sidecar(path, "thumbnail", store).await?,
+            Some(thumbnail)
+        );
+        assert_eq!(root.get_sidecar(path, "transcode", store).await?, None);
+        assert_eq!(root.ls(&["pictures".into()], store).await?.len(), 1);
+
+        let other = &MemoryBlockStore::default();
+        let migrated = root.deep_clone_to_store(store, other).await?;
+        assert_eq!(other.get_block(&thumbnail).await?, b"A tiny cat".to_vec());
+
+        // A sidecar that never made it into the store makes the tree incomplete
+        let missing = store.create_cid(b"A lost cat", CODEC_RAW)?;
+        root.set_sidecar(path, "transcode", Some(missing), store)
+            .await?;
+        let report = verify_import(&root.store(store).await?, store).await?;
+        assert_eq!(report.missing, vec![missing]);
+
+        root.set_sidecar(path, "transcode", None, store).await?;
+        assert_eq!(root.get_sidecar(path, "transcode", store).await?, None);
+
+        let result = root
+            .set_sidecar(&["pictures".into()], "thumbnail", Some(thumbnail), store)
+            .await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::NotAFile)
+        ));
+        assert_eq!(
+            migrated.get_sidecar(path, "thumbnail", other).await?,
+            Some(thumbnail)
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "compress")]
+    #[async_std::test]
+    async fn set_content_encoding_pairs_with_read_decoded() -> TestResult {
+        use async_compression::futures::bufread::GzipEncoder;
+        use futures::AsyncReadExt;
+
+        let time = Utc::now();
+        let store = &MemoryBlockStore::default();
+        let root = &mut PublicDirectory::new_rc(time);
+        let path = &["docs".into(), "notes.txt.gz".into()];
+        let mut compressed = Vec::new();
+        GzipEncoder::new(&b"Hello, World!"[..])
+            .read_to_end(&mut compressed)
+            .await?;
+        root.write(path, compressed.clone(), time, store).await?;
+
+        root.set_content_encoding(path, Some("gzip".into()), store)
+            .await?;
+        let mut content = Vec::new();
+        root.read_decoded(path, store)
+            .await?
+            .read_to_end(&mut content)
+            .await?;
+        assert_eq!(content, b"Hello, World!");
+
+        root.set_content_encoding(path, None, store).await?;
+        assert_eq!(root.read(path, store).await?, compressed);
+        let file = root.get_node(path, store).await?.unwrap().as_file()?;
+        assert_eq!(file.get_metadata().get_content_encoding(), None);
+
+        let result = root
+            .set_content_encoding(&["docs".into()], Some("gzip".into()), store)
+            .await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::NotAFile)
+        ));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn touch_tree_updates_mtimes_and_keeps_content() -> TestResult {
+        let time = Utc::now();
+        let later = time + chrono::Duration::days(1);
+        let store = &MemoryBlockStore::default();
+        let root = &mut PublicDirectory::new_rc(time);
+        root.write(
+            &["a".into(), "b".into(), "c.txt".into()],
+            b"c".to_vec(),
+            time,
+            store,
+        )
+        .await?;
+        root.write(&["d.txt".into()], b"d".to_vec(), time, store)
+            .await?;
+        let old_a_cid = root
+            .get_node(&["a".into()], store)
+            .await?
+            .unwrap()
+            .store(store)
+            .await?;
+        let old_content_cid = root
+            .get_node(&["a".into(), "b".into(), "c.txt".into()], store)
+            .await?
+            .unwrap()
+            .as_file()?
+            .userland
+            .resolve_cid(store)
+            .await?;
+
+        root.touch_tree(&["a".into()], later, store).await?;
+
+        let mtime = |node: &PublicNode| match node {
+            PublicNode::Dir(dir) => dir.metadata.get_modified(),
+            PublicNode::File(file) => file.metadata.get_modified(),
+        };
+
+        for path in [vec!["a"], vec!["a", "b"], vec!["a", "b", "c.txt"]] {
+            let path = path.into_iter().map(String::from).collect::<Vec<_>>();
+            let node = root.get_node(&path, store).await?.unwrap();
+            assert_eq!(mtime(node).unwrap().timestamp(), later.timestamp());
+        }
+
+        let untouched = root.get_node(&["d.txt".into()], store).await?.unwrap();
+        assert_eq!(mtime(untouched).unwrap().timestamp(), time.timestamp());
+
+        let a = root.get_node(&["a".into()], store).await?.unwrap();
+        assert_eq!(a.get_previous(), &BTreeSet::from([old_a_cid]));
+
+        let file = root
+            .get_node(&["a".into(), "b".into(), "c.txt".into()], store)
+            .await?
+            .unwrap()
+            .as_file()?;
+        assert_eq!(file.userland.resolve_cid(store).await?, old_content_cid);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn invalid_names_are_rejected_for_new_entries_only() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        root.write(&["a.txt".into()], b"a".to_vec(), time, store)
+            .await?;
+
+        let is_invalid_name = |result: Result<()>| {
+            matches!(
+                result.unwrap_err().downcast_ref(),
+                Some(FsError::InvalidName(_))
+            )
+        };
+
+        let bad = "a/b".to_string();
+        assert!(is_invalid_name(
+            root.write(std::slice::from_ref(&bad), b"x".to_vec(), time, store)
+                .await
+        ));
+        assert!(is_invalid_name(
+            root.mkdir(&["ok".into(), String::new()], time, store).await
+        ));
+        assert!(is_invalid_name(
+            root.basic_mv(&["a.txt".into()], &["b\0".into()], time, store)
+                .await
+        ));
+        assert!(is_invalid_name(
+            root.cp(&["a.txt".into()], &["c".repeat(256)], time, store)
+                .await
+        ));
+
+        // Nothing was created or lost along the way
+        assert_eq!(root.ls(&[], store).await?.len(), 1);
+
+        // Trees that already contain bad names still load and stay usable
+        Arc::make_mut(root).userland.insert(
+            bad.clone(),
+            PublicLink::with_dir(PublicDirectory::new(time)),
+        );
+        let cid = root.store(store).await?;
+        let loaded = &mut Arc::new(PublicDirectory::load(&cid, store).await?);
+        loaded
+            .write(&[bad.clone(), "c.txt".into()], b"c".to_vec(), time, store)
+            .await?;
+        assert!(loaded
+            .get_node(&[bad, "c.txt".into()], store)
+            .await?
+            .is_some());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn returning_variants_hand_out_the_target_in_the_tree() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let path = &["a".into(), "b.txt".into()];
+
+        let file = root
+            .write_returning(path, b"Hello".to_vec(), time, store)
+            .await?;
+        file.get_metadata_mut().put("tag", "first".into());
+        let first_cid = root
+            .get_node(path, store)
+            .await?
+            .unwrap()
+            .store(store)
+            .await?;
+
+        let file = root
+            .write_returning(path, b"Hello again".to_vec(), time, store)
+            .await?;
+        assert_eq!(file.get_previous(), &BTreeSet::from([first_cid]));
+        assert_eq!(file.get_content(store).await?, b"Hello again");
+
+        let dir = root.mkdir_returning(&["a".into()], time, store).await?;
+        dir.get_metadata_mut().put("tag", "dir".into());
+
+        let file = root.get_node(path, store).await?.unwrap().as_file()?;
+        assert_eq!(file.get_metadata().get("tag"), Some(&Ipld::from("first")));
+        let dir = root
+            .get_node(&["a".into()], store)
+            .await?
+            .unwrap()
+            .as_dir()?;
+        assert_eq!(dir.metadata.get("tag"), Some(&Ipld::from("dir")));
+        assert_eq!(dir.ls(&[], store).await?.len(), 1);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn find_first_stops_loading_at_the_shallowest_match() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        let deep = path("a/deep/x/config.toml");
+        root.write(&deep, b"deep".to_vec(), time, store).await?;
+        root.write(&path("b/config.toml"), b"shallow".to_vec(), time, store)
+            .await?;
+        let cid = root.store(store).await?;
+        let deep_cid = root
+            .get_node(&deep, store)
+            .await?
+            .unwrap()
+            .store(store)
+            .await?;
+
+        let recording = &RecordingBlockStore::new(store);
+        let loaded = PublicDirectory::load(&cid, recording).await?;
+        let (found, node) = loaded
+            .find_first(&[], "config.*", recording)
+            .await?
+            .unwrap();
+
+        assert_eq!(found, path("b/config.toml"));
+        assert_eq!(node.as_file()?.get_content(store).await?, b"shallow");
+        assert!(!recording.log().is_empty());
+        assert!(recording.log().iter().all(|access| access.cid != deep_cid));
+
+        let (found, _) = loaded
+            .find_first(&path("a"), "*.toml", store)
+            .await?
+            .unwrap();
+        assert_eq!(found, deep);
+        assert!(loaded.find_first(&[], "*.json", store).await?.is_none());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn find_streams_files_modified_after_a_time_lazily() -> TestResult {
+        use futures::{StreamExt, TryStreamExt};
+
+        let time = Utc::now();
+        let later = time + chrono::Duration::days(1);
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        root.write(&path("a/old.txt"), b"old".to_vec(), time, store)
+            .await?;
+        root.write(&path("a/new.txt"), b"new".to_vec(), later, store)
+            .await?;
+        root.write(&path("b/c/new.txt"), b"new".to_vec(), later, store)
+            .await?;
+        root.write(&path("z/latest.txt"), b"new".to_vec(), later, store)
+            .await?;
+        let cid = root.store(store).await?;
+        let z_cid = root
+            .get_node(&path("z"), store)
+            .await?
+            .unwrap()
+            .store(store)
+            .await?;
+
+        let recording = &RecordingBlockStore::new(store);
+        let loaded = PublicDirectory::load(&cid, recording).await?;
+        let modified_after = |cutoff: DateTime<Utc>| {
+            move |_: &[String], node: &PublicNode| match node {
+                PublicNode::File(file) => file
+                    .metadata
+                    .get_modified()
+                    .is_some_and(|mtime| mtime.timestamp() > cutoff.timestamp()),
+                PublicNode::Dir(_) => false,
             }
-        }
+        };
+
+        let found = loaded
+            .find(modified_after(time), recording)
+            .map_ok(|(path, _)| path.join("/"))
+            .take(2)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(found, ["a/new.txt", "b/c/new.txt"]);
+        assert!(recording.log().iter().all(|access| access.cid != z_cid));
+
+        let all = loaded
+            .find(|_, _| true, store)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(all.len(), 8);
+        let none = loaded.find(modified_after(later), store);
+        assert!(none.try_collect::<Vec<_>>().await?.is_empty());
 
         Ok(())
     }
-}
 
-impl std::fmt::Debug for PublicDirectory {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("PublicDirectory")
-            .field(
-                "persisted_as",
-                &OnceCellDebug(self.persisted_as.get().map(|cid| format!("{cid}"))),
-            )
-            .field("metadata", &self.metadata)
-            .field("userland", &self.userland)
-            .field(
-                "previous",
-                &self
-                    .previous
-                    .iter()
-                    .map(|cid| format!("{cid}"))
+    #[async_std::test]
+    async fn glob_matches_segments_and_skips_unmatched_subtrees() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        for file in [
+            "docs/a.md",
+            "docs/b.txt",
+            "docs/guides/c.md",
+            "docs/guides/deep/d.md",
+            "src/e.md",
+            "src/f1.rs",
+            "src/f22.rs",
+        ] {
+            root.write(&path(file), b"...".to_vec(), time, store)
+                .await?;
+        }
+        let cid = root.store(store).await?;
+        let src_cid = root
+            .get_node(&path("src"), store)
+            .await?
+            .unwrap()
+            .store(store)
+            .await?;
+
+        let recording = &RecordingBlockStore::new(store);
+        let loaded = &PublicDirectory::load(&cid, recording).await?;
+        let glob = |pattern: &'static str| async move {
+            let found = loaded.glob(pattern, recording).await?;
+            anyhow::Ok(
+                found
+                    .into_iter()
+                    .map(|(path, _)| path.join("/"))
                     .collect::<Vec<_>>(),
             )
-            .finish()
-    }
-}
+        };
 
-impl Id for PublicDirectory {
-    fn get_id(&self) -> String {
-        format!("{:p}", &self.metadata)
-    }
-}
+        assert_eq!(
+            glob("docs/**/*.md").await?,
+            ["docs/a.md", "docs/guides/c.md", "docs/guides/deep/d.md"]
+        );
+        assert!(recording.log().iter().all(|access| access.cid != src_cid));
 
-impl PartialEq for PublicDirectory {
-    fn eq(&self, other: &Self) -> bool {
-        self.metadata == other.metadata
-            && self.userland == other.userland
-            && self.previous == other.previous
+        assert_eq!(glob("src/f?.rs").await?, ["src/f1.rs"]);
+        assert_eq!(glob("*/*.md").await?, ["docs/a.md", "src/e.md"]);
+        assert_eq!(
+            glob("docs/guides/**").await?,
+            [
+                "docs/guides/c.md",
+                "docs/guides/deep",
+                "docs/guides/deep/d.md"
+            ]
+        );
+        // Overlapping `**` segments don't report matches twice
+        assert_eq!(glob("**/**/d.md").await?, ["docs/guides/deep/d.md"]);
+        assert!(glob("missing/**").await?.is_empty());
+        assert!(glob("").await?.is_empty());
+
+        Ok(())
     }
-}
 
-impl Clone for PublicDirectory {
-    fn clone(&self) -> Self {
-        Self {
-            persisted_as: self
-                .persisted_as
-                .get()
-                .cloned()
-                .map(OnceCell::new_with)
-                .unwrap_or_default(),
-            metadata: self.metadata.clone(),
-            userland: self.userland.clone(),
-            previous: self.previous.clone(),
+    #[async_std::test]
+    async fn dir_metadata_doesnt_load_children() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        for name in ["a.png", "b.png", "c.png"] {
+            root.write(
+                &["pictures".into(), name.into()],
+                name.as_bytes().to_vec(),
+                time,
+                store,
+            )
+            .await?;
         }
-    }
-}
+        root.mkdir(&["pictures".into(), "cats".into()], time, store)
+            .await?;
+        let cid = root.store(store).await?;
 
-impl Storable for PublicDirectory {
-    type Serializable = PublicNodeSerializable;
+        let recording = &RecordingBlockStore::new(store);
+        let loaded = PublicDirectory::load(&cid, recording).await?;
+        recording.take_log();
 
-    async fn to_serializable(&self, store: &impl BlockStore) -> Result<Self::Serializable> {
-        let userland = {
-            let mut map = BTreeMap::new();
-            for (name, link) in self.userland.iter() {
-                // Boxing the future due to recursion
-                map.insert(name.clone(), boxed_fut(link.resolve_cid(store)).await?);
-            }
-            map
-        };
+        let metadata = loaded.dir_metadata(&["pictures".into()], recording).await?;
+        let pictures = loaded.get_node(&["pictures".into()], store).await?;
+        assert_eq!(metadata, pictures.unwrap().as_dir()?.metadata);
+        assert_eq!(recording.log().len(), 1);
+        assert!(loaded.dir_metadata(&[], store).await.is_ok());
 
-        Ok(PublicNodeSerializable::Dir(PublicDirectorySerializable {
-            version: WNFS_VERSION,
-            metadata: self.metadata.clone(),
-            userland,
-            previous: self.previous.iter().cloned().collect(),
-        }))
+        let result = loaded
+            .dir_metadata(&["pictures".into(), "a.png".into()], store)
+            .await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::NotADirectory)
+        ));
+        let result = loaded.dir_metadata(&["videos".into()], store).await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::NotFound { .. })
+        ));
+
+        Ok(())
     }
 
-    async fn from_serializable(
-        cid: Option<&Cid>,
-        serializable: Self::Serializable,
-    ) -> Result<Self> {
-        let PublicNodeSerializable::Dir(serializable) = serializable else {
-            bail!(FsError::UnexpectedNodeType(NodeType::PublicFile));
-        };
+    #[async_std::test]
+    async fn prefetched_paths_dont_fetch_again() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let path = &["pictures".into(), "cats".into(), "tabby.png".into()];
+        root.write(path, b"Tabby".to_vec(), time, store).await?;
+        root.write(&["notes.txt".into()], b"Notes".to_vec(), time, store)
+            .await?;
+        let cid = root.store(store).await?;
 
-        if !is_readable_wnfs_version(&serializable.version) {
-            bail!(FsError::UnexpectedVersion(serializable.version))
-        }
+        let recording = &RecordingBlockStore::new(store);
+        let loaded = PublicDirectory::load(&cid, recording).await?;
+        recording.take_log();
 
-        let userland = serializable
-            .userland
-            .into_iter()
-            .map(|(name, cid)| (name, PublicLink::from_cid(cid)))
-            .collect();
+        loaded.prefetch_path(path, recording).await?;
+        assert_eq!(recording.take_log().len(), 3);
 
-        Ok(Self {
-            persisted_as: cid.cloned().map(OnceCell::new_with).unwrap_or_default(),
-            metadata: serializable.metadata,
-            userland,
-            previous: serializable.previous.iter().cloned().collect(),
-        })
-    }
+        assert!(loaded.get_node(path, recording).await?.is_some());
+        assert!(recording.take_log().is_empty());
 
-    fn persisted_as(&self) -> Option<&OnceCell<Cid>> {
-        Some(&self.persisted_as)
+        // Missing paths get loaded as far as they exist
+        let missing = &["pictures".into(), "dogs".into(), "rex.png".into()];
+        loaded.prefetch_path(missing, recording).await?;
+        assert!(recording.take_log().is_empty());
+
+        Ok(())
     }
-}
 
-//--------------------------------------------------------------------------------------------------
-// Tests
-//--------------------------------------------------------------------------------------------------
+    #[async_std::test]
+    async fn recursive_sizes_are_cached_for_stored_subtrees() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        root.write(&path("pictures/big.png"), vec![0; 1_000_000], time, store)
+            .await?;
+        root.write(&path("pictures/cats/tabby.png"), vec![1; 100], time, store)
+            .await?;
+        root.write(&path("notes.txt"), vec![2; 10], time, store)
+            .await?;
+        root.mkdir(&path("empty"), time, store).await?;
+        assert_eq!(root.size_recursive(store).await?, 1_000_110);
+
+        // Sizes are only remembered for stored directories
+        let pictures = root.get_node(&path("pictures"), store).await?.unwrap();
+        assert!(pictures.as_dir()?.size_recursive.get().is_none());
+
+        let cid = root.store(store).await?;
+        let loaded = &mut Arc::new(PublicDirectory::load(&cid, store).await?);
+        assert_eq!(loaded.size_recursive(store).await?, 1_000_110);
+        let pictures = loaded.get_node(&path("pictures"), store).await?.unwrap();
+        assert_eq!(pictures.as_dir()?.size_recursive.get(), Some(&1_000_100));
+
+        // Changes invalidate the remembered sizes along their path only
+        loaded
+            .write(&path("pictures/cats/tom.png"), vec![3; 5], time, store)
+            .await?;
+        loaded.rm(&path("notes.txt"), store).await?;
+        assert_eq!(loaded.size_recursive(store).await?, 1_000_105);
+        let pictures = loaded.get_node(&path("pictures"), store).await?.unwrap();
+        assert!(pictures.as_dir()?.size_recursive.get().is_none());
+        let empty = loaded.get_node(&path("empty"), store).await?.unwrap();
+        assert_eq!(empty.as_dir()?.size_recursive.get(), Some(&0));
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use libipld_core::ipld::Ipld;
-    use testresult::TestResult;
-    use wnfs_common::{decode, libipld::cbor::DagCborCodec, MemoryBlockStore};
+        Ok(())
+    }
 
     #[async_std::test]
-    async fn look_up_can_fetch_file_added_to_directory() -> TestResult {
-        let root_dir = &mut PublicDirectory::new_rc(Utc::now());
-        let store = &MemoryBlockStore::new();
+    async fn legacy_names_get_reported_and_repaired() -> TestResult {
         let time = Utc::now();
-
-        root_dir
-            .write(&["text.txt".into()], b"Hello World!".to_vec(), time, store)
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        root.write(
+            &["docs".into(), "ok.txt".into()],
+            b"ok".to_vec(),
+            time,
+            store,
+        )
+        .await?;
+
+        // Entries that older versions might have written
+        let file = |content: &[u8]| {
+            let content = content.to_vec();
+            async move {
+                let file = PublicFile::with_content_rc(time, content, store).await?;
+                Ok::<_, anyhow::Error>(PublicLink::new(PublicNode::File(file)))
+            }
+        };
+        let long = "a".repeat(MAX_NAME_LENGTH + 1);
+        let docs = root
+            .get_node(&["docs".into()], store)
+            .await?
+            .unwrap()
+            .as_dir()?;
+        let mut docs = (*docs).clone();
+        docs.userland
+            .insert("a/b.txt".into(), file(b"slash").await?);
+        docs.userland.insert(long.clone(), file(b"long").await?);
+        let mut legacy = PublicDirectory::new(time);
+        legacy.userland.insert("x\0y".into(), file(b"nul").await?);
+        let dir = Arc::make_mut(root);
+        dir.userland
+            .insert("docs".into(), PublicLink::with_dir(docs));
+        dir.userland.insert("".into(), PublicLink::with_dir(legacy));
+        let old_cid = root.store(store).await?;
+        let slash_cid = root
+            .get_node(&["docs".into(), "a/b.txt".into()], store)
+            .await?
+            .unwrap()
+            .store(store)
             .await?;
 
-        let node = root_dir.lookup_node("text.txt", store).await?;
+        let mut violations = root.validate_names(store).await?;
+        violations.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            violations,
+            vec![
+                (vec!["".into()], NameViolation::Empty),
+                (
+                    vec!["".into(), "x\0y".into()],
+                    NameViolation::InvalidCharacter('\0')
+                ),
+                (
+                    vec!["docs".into(), "a/b.txt".into()],
+                    NameViolation::InvalidCharacter('/')
+                ),
+                (
+                    vec!["docs".into(), long.clone()],
+                    NameViolation::TooLong(MAX_NAME_LENGTH + 1)
+                ),
+            ]
+        );
 
+        let sanitize = |name: &str| match name {
+            "" => "unnamed".to_string(),
+            name => name.replace(['/', '\0'], "_").chars().take(100).collect(),
+        };
+        let repaired = root.repair_names(sanitize, time, store).await?;
+        assert!(repaired.validate_names(store).await?.is_empty());
+        assert_eq!(repaired.get_previous(), &BTreeSet::from([old_cid]));
         assert_eq!(
-            node,
-            Some(&PublicNode::File(
-                PublicFile::with_content_rc(time, b"Hello World!".to_vec(), store).await?
-            ))
+            repaired
+                .read(&["unnamed".into(), "x_y".into()], store)
+                .await?,
+            b"nul"
+        );
+        assert_eq!(
+            repaired
+                .read(&["docs".into(), "a".repeat(100)], store)
+                .await?,
+            b"long"
         );
+        let slash = repaired
+            .get_node(&["docs".into(), "a_b.txt".into()], store)
+            .await?
+            .unwrap();
+        assert_eq!(slash.store(store).await?, slash_cid);
+
+        // Clashing or still invalid names get rejected
+        let result = root.repair_names(|_| "ok.txt".into(), time, store).await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::FileAlreadyExists)
+        ));
+        let result = root.repair_names(|_| String::new(), time, store).await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::InvalidName(_))
+        ));
 
         Ok(())
     }
 
     #[async_std::test]
-    async fn look_up_cannot_fetch_file_not_added_to_directory() {
-        let root = PublicDirectory::new(Utc::now());
-        let store = MemoryBlockStore::default();
-
-        let node = root.lookup_node("Unknown", &store).await;
+    async fn storing_hashes_each_node_once() -> TestResult {
+        use wnfs_common::{utils::InstrumentedBlockStore, BlockOperation, RecordingBlockStore};
 
-        assert!(node.is_ok());
+        let time = Utc::now();
+        let store = &InstrumentedBlockStore::new(RecordingBlockStore::new(MemoryBlockStore::new()));
+        let root = &mut PublicDirectory::new_rc(time);
+        for file in ["a/b/c/deep.txt", "a/b/mid.txt", "a/top.txt", "root.txt"] {
+            let path = file.split('/').map(String::from).collect::<Vec<_>>();
+            root.write(&path, file.as_bytes().to_vec(), time, store)
+                .await?;
+        }
+        store.reset_counts();
+        store.inner().take_log();
+
+        // 4 directories and 4 files, each serialized and hashed once, the root last
+        let cid = root.store(store).await?;
+        let puts = store
+            .inner()
+            .log()
+            .into_iter()
+            .filter(|access| matches!(access.operation, BlockOperation::Put(_)))
+            .map(|access| access.cid)
+            .collect::<Vec<_>>();
+        assert_eq!(puts.len(), 8);
+        assert_eq!(store.puts(), 8);
+        assert_eq!(store.cids_created(), 8);
+        assert_eq!(puts.last(), Some(&cid));
+
+        // All CIDs are cached now, so storing again doesn't hash anything
+        assert_eq!(root.store(store).await?, cid);
+        assert_eq!(store.cids_created(), 8);
+        let deep = root
+            .get_node(&["a".into(), "b".into(), "c".into()], store)
+            .await?
+            .unwrap();
+        assert!(deep.as_dir()?.persisted_as.get().is_some());
 
-        assert_eq!(node.unwrap(), None);
+        Ok(())
     }
 
     #[async_std::test]
-    async fn get_node_can_fetch_node_from_root_dir() -> TestResult {
+    async fn metadata_merge_policies_resolve_metadata_only_divergences() -> TestResult {
         let time = Utc::now();
+        let later = time + chrono::Duration::hours(1);
         let store = &MemoryBlockStore::new();
-        let root_dir = &mut PublicDirectory::new_rc(time);
+        let notes = &["notes.txt".into()];
+        let base = &mut PublicDirectory::new_rc(time);
+        base.write(notes, b"Notes".to_vec(), time, store).await?;
+        base.store(store).await?;
+
+        // One side touches the file, the other one adds a sidecar
+        let touched = &mut Arc::clone(base);
+        touched.touch_tree(notes, later, store).await?;
+        let annotated = &mut Arc::clone(base);
+        let thumbnail = store
+            .put_block(b"Thumbnail".to_vec(), wnfs_common::CODEC_RAW)
+            .await?;
+        annotated
+            .set_sidecar(notes, "thumbnail", Some(thumbnail), store)
+            .await?;
 
-        root_dir
-            .mkdir(&["pictures".into(), "dogs".into()], time, store)
+        let merge = |ours: &Arc<PublicDirectory>, theirs: &Arc<PublicDirectory>, policy| {
+            let mut ours = Arc::clone(ours);
+            let theirs = Arc::clone(theirs);
+            async move {
+                let result = ours.reconcile_with_policy(&theirs, policy, store).await?;
+                let Reconciliation::Merged { file_tie_breaks } = result else {
+                    panic!("Expected a merge");
+                };
+                let file = ours.get_node(notes, store).await?.unwrap().as_file()?;
+                Ok::<_, anyhow::Error>((file_tie_breaks, file.metadata.clone()))
+            }
+        };
+
+        let (tie_breaks, _) = merge(touched, annotated, MetadataMergePolicy::TieBreak).await?;
+        assert_eq!(tie_breaks, BTreeSet::from([notes.to_vec()]));
+
+        let (tie_breaks, metadata) =
+            merge(touched, annotated, MetadataMergePolicy::TakeNewer).await?;
+        assert!(tie_breaks.is_empty());
+        assert_eq!(
+            metadata.get_modified().map(|t| t.timestamp()),
+            Some(later.timestamp())
+        );
+        assert_eq!(metadata.get_sidecar("thumbnail"), None);
+
+        for (ours, theirs) in [(&*touched, &*annotated), (&*annotated, &*touched)] {
+            let (tie_breaks, metadata) = merge(ours, theirs, MetadataMergePolicy::Union).await?;
+            assert!(tie_breaks.is_empty());
+            assert_eq!(
+                metadata.get_modified().map(|t| t.timestamp()),
+                Some(later.timestamp())
+            );
+            assert_eq!(metadata.get_sidecar("thumbnail"), Some(thumbnail));
+        }
+
+        // Content conflicts still need a tie-break
+        let rewritten = &mut Arc::clone(base);
+        rewritten
+            .write(notes, b"Other notes".to_vec(), time, store)
             .await?;
+        let (tie_breaks, _) = merge(touched, rewritten, MetadataMergePolicy::Union).await?;
+        assert_eq!(tie_breaks, BTreeSet::from([notes.to_vec()]));
 
-        root_dir
-            .write(
-                &["pictures".into(), "cats".into(), "tabby.jpg".into()],
-                b"Hello".to_vec(),
-                time,
-                store,
-            )
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn sync_roots_survive_storing_and_loading() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        let root = &mut PublicDirectory::new_rc(time);
+        root.mkdir(&path("shared/team-a/nested"), time, store)
             .await?;
+        root.write(&path("shared/notes.txt"), b"Notes".to_vec(), time, store)
+            .await?;
+        root.mkdir(&path("shared/team-b"), time, store).await?;
 
-        assert!(root_dir
-            .get_node(
-                &["pictures".into(), "cats".into(), "tabby.jpg".into()],
-                store
-            )
-            .await?
-            .is_some());
+        for marked in ["shared/team-a", "shared/team-a/nested", "shared/team-b"] {
+            root.set_sync_root(&path(marked), true, store).await?;
+        }
+        root.set_sync_root(&path("shared/team-b"), false, store)
+            .await?;
+        root.set_sync_root(&[], true, store).await?;
 
-        assert!(root_dir
-            .get_node(
-                &["pictures".into(), "cats".into(), "tabby.jpeg".into()],
-                store
-            )
-            .await?
-            .is_none());
+        let loaded = PublicDirectory::load(&root.store(store).await?, store).await?;
+        assert_eq!(
+            loaded.find_sync_roots(store).await?,
+            [vec![], path("shared/team-a"), path("shared/team-a/nested")]
+        );
 
-        assert!(root_dir
-            .get_node(
-                &["images".into(), "parrots".into(), "coco.png".into()],
-                store
-            )
-            .await?
-            .is_none());
+        let result = root
+            .set_sync_root(&path("shared/notes.txt"), true, store)
+            .await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::NotADirectory)
+        ));
 
-        assert!(root_dir
-            .get_node(
-                &["pictures".into(), "dogs".into(), "bingo.jpg".into()],
-                store
-            )
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn walk_filtered_doesnt_load_pruned_subtrees() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        for file in ["a/x.txt", "a/.cache/deep/blob", "b.txt", "c/.cache/y"] {
+            root.write(&path(file), b"...".to_vec(), time, store)
+                .await?;
+        }
+        let cid = root.store(store).await?;
+        let cache_cid = root
+            .get_node(&path("a/.cache"), store)
             .await?
-            .is_none());
+            .unwrap()
+            .store(store)
+            .await?;
+
+        let recording = &RecordingBlockStore::new(store);
+        let loaded = PublicDirectory::load(&cid, recording).await?;
+        let mut visited = Vec::new();
+        loaded
+            .walk_filtered(
+                |path| path.last().is_some_and(|name| name != ".cache"),
+                |path, node| visited.push((path.join("/"), node.is_dir())),
+                recording,
+            )
+            .await?;
+
+        let visited = visited
+            .iter()
+            .map(|(p, d)| (p.as_str(), *d))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            visited,
+            [
+                ("a", true),
+                ("a/x.txt", false),
+                ("b.txt", false),
+                ("c", true)
+            ]
+        );
+        assert!(recording.log().iter().all(|access| access.cid != cache_cid));
 
         Ok(())
     }
 
     #[async_std::test]
-    async fn mkdir_can_create_new_directory() -> TestResult {
+    async fn content_indices_tell_which_files_changed() -> TestResult {
         let time = Utc::now();
         let store = &MemoryBlockStore::new();
-        let root_dir = &mut PublicDirectory::new_rc(time);
-
-        root_dir
-            .mkdir(&["tamedun".into(), "pictures".into()], time, store)
+        let root = &mut PublicDirectory::new_rc(time);
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        root.write(&path("docs/notes.txt"), b"Notes".to_vec(), time, store)
+            .await?;
+        root.write(&path("docs/todo.txt"), b"Todo".to_vec(), time, store)
+            .await?;
+        root.write(&path("big.bin"), vec![7; 1_000_000], time, store)
             .await?;
+        root.mkdir(&path("empty"), time, store).await?;
 
-        let result = root_dir
-            .get_node(&["tamedun".into(), "pictures".into()], store)
+        let before = root.content_index(store).await?;
+        assert_eq!(
+            before.keys().collect::<Vec<_>>(),
+            ["big.bin", "docs/notes.txt", "docs/todo.txt"]
+        );
+        assert_eq!(before["big.bin"].1, 1_000_000);
+        assert_eq!(before["docs/todo.txt"].1, 4);
+
+        root.write(&path("docs/todo.txt"), b"Done".to_vec(), time, store)
+            .await?;
+        root.touch_tree(&path("docs/notes.txt"), time, store)
             .await?;
+        let after = root.content_index(store).await?;
 
-        assert!(result.is_some());
+        let changed = after
+            .iter()
+            .filter(|(path, entry)| before.get(*path) != Some(entry))
+            .map(|(path, _)| path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(changed, ["docs/todo.txt"]);
 
         Ok(())
     }
 
     #[async_std::test]
-    async fn ls_can_list_children_under_directory() -> TestResult {
+    async fn store_accounted_only_counts_new_blocks() -> TestResult {
         let time = Utc::now();
         let store = &MemoryBlockStore::new();
-        let root_dir = &mut PublicDirectory::new_rc(time);
-
-        root_dir
-            .mkdir(&["tamedun".into(), "pictures".into()], time, store)
+        let root = &mut PublicDirectory::new_rc(time);
+        let path = &["a".into(), "b".into(), "c.txt".into()];
+        root.write(path, b"Hello".to_vec(), time, store).await?;
+        root.write(&["d.txt".into()], b"World".to_vec(), time, store)
             .await?;
 
-        root_dir
-            .write(
-                &["tamedun".into(), "pictures".into(), "puppy.jpg".into()],
-                b"Hello".to_vec(),
-                time,
-                store,
-            )
-            .await?;
+        let (cid, stats) = root.store_accounted(store).await?;
+        assert_eq!(stats.new_blocks, 5);
+        assert!(stats.new_bytes > 0);
+        assert_eq!(cid, root.store(store).await?);
 
-        root_dir
-            .mkdir(
-                &["tamedun".into(), "pictures".into(), "cats".into()],
-                time,
-                store,
-            )
+        root.write(path, b"Hello again".to_vec(), time, store)
             .await?;
 
-        let result = root_dir
-            .ls(&["tamedun".into(), "pictures".into()], store)
-            .await?;
+        // Only the spine down to the changed file is new, not d.txt
+        let (_, stats) = root.store_accounted(store).await?;
+        assert_eq!(stats.new_blocks, 4);
 
-        assert_eq!(result.len(), 2);
+        Ok(())
+    }
 
-        assert_eq!(result[0].0, String::from("cats"));
+    #[async_std::test]
+    async fn gc_history_keeps_only_recent_revisions() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let log = &["log.txt".into()];
+        for i in 0..5 {
+            root.write(log, vec![i], time, store).await?;
+            root.store(store).await?;
+        }
 
-        assert_eq!(result[1].0, String::from("puppy.jpg"));
+        let pruned = root.gc_history(2, store).await?;
+        assert_eq!(pruned.read(log, store).await?, vec![4]);
+
+        let mut contents = Vec::new();
+        let mut current = pruned.clone();
+        while let Some(previous) = current.get_previous().iter().next() {
+            current = PublicDirectory::load(previous, store).await?.into();
+            contents.push(current.read(log, store).await?);
+        }
+        assert_eq!(contents, vec![vec![3], vec![2]]);
+
+        // Pruning is deterministic and leaves short enough histories alone
+        let again = root.gc_history(2, store).await?;
+        assert_eq!(again.store(store).await?, pruned.store(store).await?);
+        let untouched = root.gc_history(10, store).await?;
+        assert_eq!(untouched.get_previous(), root.get_previous());
+        assert!(root.gc_history(0, store).await?.get_previous().is_empty());
 
         Ok(())
     }
 
     #[async_std::test]
-    async fn rm_can_remove_children_from_directory() -> TestResult {
+    async fn snapshot_labels_resolve_to_their_most_recent_revision() -> TestResult {
         let time = Utc::now();
         let store = &MemoryBlockStore::new();
-        let mut root_dir = PublicDirectory::new_rc(time);
+        let root = &mut PublicDirectory::new_rc(time);
+        let notes = &["notes.txt".into()];
+
+        root.write(notes, b"One".to_vec(), time, store).await?;
+        let first_nightly = root.snapshot_labeled("nightly", store).await?;
+        root.write(notes, b"Two".to_vec(), time, store).await?;
+        let release = root.snapshot_labeled("v1.0", store).await?;
+        root.write(notes, b"Three".to_vec(), time, store).await?;
+        let nightly = root.snapshot_labeled("nightly", store).await?;
+
+        assert_ne!(first_nightly, nightly);
+        assert_eq!(root.find_snapshot("nightly", store).await?, Some(nightly));
+        assert_eq!(root.find_snapshot("v1.0", store).await?, Some(release));
+        assert_eq!(root.find_snapshot("v2.0", store).await?, None);
+
+        // Later revisions don't inherit the label
+        root.write(notes, b"Four".to_vec(), time, store).await?;
+        assert_eq!(root.get_metadata().get_snapshot_label(), None);
+        assert_eq!(root.find_snapshot("nightly", store).await?, Some(nightly));
+
+        let release = PublicDirectory::load(&release, store).await?;
+        assert_eq!(release.read(notes, store).await?, b"Two");
+        assert_eq!(
+            release.find_snapshot("nightly", store).await?,
+            Some(first_nightly)
+        );
 
-        root_dir
-            .mkdir(&["tamedun".into(), "pictures".into()], time, store)
-            .await?;
+        Ok(())
+    }
 
-        root_dir
-            .write(
-                &["tamedun".into(), "pictures".into(), "puppy.jpg".into()],
-                b"Hello".to_vec(),
-                time,
-                store,
-            )
-            .await?;
+    #[async_std::test]
+    async fn at_time_selects_the_newest_revision_old_enough() -> TestResult {
+        let days = chrono::Duration::days;
+        let monday = Utc::now() - days(14);
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(monday);
+        let notes = &["notes.txt".into()];
 
-        root_dir
-            .mkdir(
-                &["tamedun".into(), "pictures".into(), "cats".into()],
-                time,
-                store,
-            )
+        root.write(notes, b"Monday".to_vec(), monday, store).await?;
+        root.store(store).await?;
+        root.write(notes, b"Tuesday".to_vec(), monday + days(1), store)
+            .await?;
+        root.store(store).await?;
+        root.write(notes, b"Friday".to_vec(), monday + days(4), store)
             .await?;
 
-        let result = root_dir
-            .rm(&["tamedun".into(), "pictures".into()], store)
-            .await;
+        for (time, expected) in [
+            (monday, "Monday"),
+            (monday + days(1), "Tuesday"),
+            (monday + days(3), "Tuesday"),
+            (monday + days(4), "Friday"),
+            (Utc::now(), "Friday"),
+        ] {
+            let revision = root.at_time(time, store).await?;
+            assert_eq!(revision.read(notes, store).await?, expected.as_bytes());
+        }
 
-        assert!(result.is_ok());
+        let err = root.at_time(monday - days(1), store).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(FsError::NoRevisionAt(_))));
 
-        let result = root_dir
-            .rm(&["tamedun".into(), "pictures".into()], store)
-            .await;
+        Ok(())
+    }
 
-        assert!(result.is_err());
+    #[async_std::test]
+    async fn directories_stored_as_dag_json_load_back_equal() -> TestResult {
+        use wnfs_common::{BlockCodec, CODEC_DAG_JSON};
+
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let notes = &["docs".into(), "notes.txt".into()];
+        root.write(notes, b"Notes".to_vec(), time, store).await?;
+        root.store(store).await?;
+        root.mkdir(&["pictures".into()], time, store).await?;
+
+        let cid = root.store_shallow_as(store, BlockCodec::DagJson).await?;
+        assert_eq!(cid.codec(), CODEC_DAG_JSON);
+        assert_ne!(cid, root.store(store).await?);
+        let bytes = store.get_block(&cid).await?;
+        assert!(serde_json::from_slice::<serde_json::Value>(&bytes).is_ok());
+
+        // Its children are still DAG-CBOR, so loading goes through both codecs
+        let loaded = PublicDirectory::load(&cid, store).await?;
+        assert_eq!(loaded, **root);
+        assert_eq!(loaded.read(notes, store).await?, b"Notes");
 
         Ok(())
     }
 
     #[async_std::test]
-    async fn read_can_fetch_userland_of_file_added_to_directory() -> TestResult {
-        let store = &MemoryBlockStore::new();
+    async fn directories_can_be_hashed_with_other_algorithms() -> TestResult {
+        use wnfs_common::{HashAlgorithm, HashingBlockStore};
+
         let time = Utc::now();
-        let content = b"Hello".to_vec();
-        let mut root_dir = PublicDirectory::new_rc(time);
+        let backend = &MemoryBlockStore::new();
+        let notes = &["docs".into(), "notes.txt".into()];
+        let mut cids = Vec::new();
+        for algorithm in [HashAlgorithm::Sha2_256, HashAlgorithm::Sha3_256] {
+            let store = &HashingBlockStore::new(backend, algorithm);
+            let root = &mut PublicDirectory::new_rc(time);
+            root.write(notes, b"Notes".to_vec(), time, store).await?;
+            let cid = root.store(store).await?;
+
+            let code = u64::from(algorithm.code());
+            let file = root.get_node(notes, store).await?.unwrap();
+            assert_eq!(cid.hash().code(), code);
+            assert_eq!(
+                file.as_file()?
+                    .userland
+                    .resolve_cid(store)
+                    .await?
+                    .hash()
+                    .code(),
+                code
+            );
+            cids.push(cid);
+        }
 
-        root_dir
-            .write(&["text.txt".into()], content.clone(), time, store)
-            .await?;
+        assert_ne!(cids[0], cids[1]);
+        for cid in &cids {
+            let loaded = PublicDirectory::load(cid, backend).await?;
+            assert_eq!(loaded.read(notes, backend).await?, b"Notes");
+        }
 
-        let result = root_dir.read(&["text.txt".into()], store).await?;
+        Ok(())
+    }
 
-        assert_eq!(result, content);
+    #[async_std::test]
+    async fn paging_visits_each_entry_exactly_once() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let names = (0..100)
+            .map(|i| match i % 4 {
+                0 => format!("file-{i}"),
+                1 => format!("Datei-ä-{i}"),
+                2 => format!("文件-{i}"),
+                _ => format!("🗂️-{i}"),
+            })
+            .collect::<BTreeSet<_>>();
+        for name in &names {
+            root.write(std::slice::from_ref(name), b"".to_vec(), time, store)
+                .await?;
+        }
+
+        let mut visited = vec![];
+        let mut cursor = None;
+        loop {
+            let (page, next) = root.ls_paginated(&[], cursor, 10, store).await?;
+            assert!(page.len() <= 10);
+            visited.extend(page.into_iter().map(|(name, _)| name));
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(visited, names.into_iter().collect::<Vec<_>>());
+
+        let (page, next) = root.ls_paginated(&[], None, 0, store).await?;
+        assert_eq!(page.len(), 1);
+        assert_eq!(next.as_ref(), visited.first());
+        let err = root
+            .ls_paginated(&["missing".into()], None, 10, store)
+            .await;
+        assert!(matches!(
+            err.unwrap_err().downcast_ref(),
+            Some(FsError::NotFound { .. })
+        ));
 
         Ok(())
     }
 
     #[async_std::test]
-    async fn mv_can_move_sub_directory_to_another_valid_location() -> TestResult {
+    async fn rollbacks_return_revisions_with_their_history() -> TestResult {
         let time = Utc::now();
         let store = &MemoryBlockStore::new();
-        let mut root_dir = PublicDirectory::new_rc(time);
+        let root = &mut PublicDirectory::new_rc(time);
+        let notes = &["notes.txt".into()];
+
+        assert!(root.rollback(store).await?.is_none());
+        root.write(notes, b"First".to_vec(), time, store).await?;
+        let first = root.store(store).await?;
+        root.write(notes, b"Second".to_vec(), time, store).await?;
+        let second = root.store(store).await?;
+        root.write(notes, b"Third".to_vec(), time, store).await?;
+
+        let previous = root.rollback(store).await?.unwrap();
+        assert_eq!(previous.read(notes, store).await?, b"Second");
+        assert_eq!(previous.store(store).await?, second);
+        assert_eq!(previous.previous, BTreeSet::from([first]));
+
+        let oldest = root.rollback_to(&first, store).await?;
+        assert_eq!(oldest.read(notes, store).await?, b"First");
+        let err = previous.rollback_to(&second, store).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(FsError::RevisionNotInHistory(cid)) if *cid == second
+        ));
+
+        // Merged revisions need an explicit choice
+        let merged = &mut Arc::clone(root);
+        Arc::make_mut(merged).previous = BTreeSet::from([first, second]);
+        let err = merged.rollback(store).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(FsError::AmbiguousPreviousRevision(cids)) if cids.len() == 2
+        ));
+        assert_eq!(
+            merged
+                .rollback_to(&second, store)
+                .await?
+                .store(store)
+                .await?,
+            second
+        );
 
-        root_dir
-            .write(
-                &["pictures".into(), "cats".into(), "tabby.jpg".into()],
-                b"Hello".to_vec(),
-                time,
-                store,
-            )
-            .await?;
+        Ok(())
+    }
 
-        root_dir
-            .write(
-                &["pictures".into(), "cats".into(), "luna.png".into()],
-                b"Hello".to_vec(),
-                time,
-                store,
-            )
+    #[async_std::test]
+    async fn appends_match_a_fresh_write_of_the_whole_content() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let log = &["app.log".into()];
+        // Spans a few chunks, with a last chunk that isn't full
+        let first = vec![1; 600 * 1024];
+        let second = vec![2; 300 * 1024];
+
+        root.append(log, &first, time, store).await?;
+        let before = root
+            .get_node(log, store)
+            .await?
+            .unwrap()
+            .store(store)
             .await?;
+        root.append(log, &second, time, store).await?;
+        root.append(log, &[], time, store).await?;
 
-        root_dir.mkdir(&["images".into()], time, store).await?;
+        let appended = root.get_node(log, store).await?.unwrap().as_file()?;
+        let written = PublicFile::with_content(time, [first, second].concat(), store).await?;
+        assert_eq!(appended.size(store).await?, 900 * 1024);
+        assert_eq!(
+            appended.userland.resolve_cid(store).await?,
+            written.userland.resolve_cid(store).await?
+        );
+        assert_eq!(appended.previous, BTreeSet::from([before]));
 
-        root_dir
-            .basic_mv(
-                &["pictures".into(), "cats".into()],
-                &["images".into(), "cats".into()],
-                Utc::now(),
-                store,
-            )
-            .await?;
+        let err = root.append(&[], b"", time, store).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(FsError::InvalidPath)));
+        root.mkdir(&["logs".into()], time, store).await?;
+        let err = root.append(&["logs".into()], b"", time, store).await;
+        assert!(matches!(
+            err.unwrap_err().downcast_ref(),
+            Some(FsError::DirectoryAlreadyExists)
+        ));
 
-        let result = root_dir.ls(&["images".into()], store).await?;
+        Ok(())
+    }
 
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].0, String::from("cats"));
+    #[async_std::test]
+    async fn truncating_matches_a_fresh_write_of_the_remaining_content() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let data = &["data.bin".into()];
+        let chunk = 256 * 1024;
+        let content = (0..3 * chunk).map(|i| i as u8).collect::<Vec<_>>();
+
+        root.write(data, content.clone(), time, store).await?;
+        let mut expected = content;
+        // On a chunk boundary, in the middle of a chunk, and past the end
+        for len in [2 * chunk, chunk + 100, chunk + 200] {
+            let before = root
+                .get_node(data, store)
+                .await?
+                .unwrap()
+                .store(store)
+                .await?;
+            root.truncate(data, len as u64, time, store).await?;
+
+            expected.resize(len, 0);
+            let truncated = root.get_node(data, store).await?.unwrap().as_file()?;
+            let written = PublicFile::with_content(time, expected.clone(), store).await?;
+            assert_eq!(truncated.size(store).await?, len as u64);
+            assert_eq!(truncated.get_content(store).await?, expected);
+            assert_eq!(
+                truncated.userland.resolve_cid(store).await?,
+                written.userland.resolve_cid(store).await?
+            );
+            assert_eq!(truncated.previous, BTreeSet::from([before]));
+        }
 
-        let result = root_dir.ls(&["pictures".into()], store).await?;
+        root.truncate(data, 0, time, store).await?;
+        assert!(root.read(data, store).await?.is_empty());
 
-        assert_eq!(result.len(), 0);
+        let err = root.truncate(&["missing".into()], 0, time, store).await;
+        assert!(matches!(
+            err.unwrap_err().downcast_ref(),
+            Some(FsError::NotFound { .. })
+        ));
 
         Ok(())
     }
 
+    #[cfg(feature = "mime")]
     #[async_std::test]
-    async fn mv_cannot_move_sub_directory_to_invalid_location() {
+    async fn write_with_mime_records_the_detected_content_type() -> TestResult {
         let time = Utc::now();
-        let store = MemoryBlockStore::default();
-        let mut root_dir = PublicDirectory::new_rc(time);
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let path = &["upload".into()];
+        let content_type = |root: &Arc<PublicDirectory>| {
+            let root = Arc::clone(root);
+            async move {
+                let node = root.get_node(path, store).await?.unwrap();
+                anyhow::Ok(node.get_attribute("content-type").cloned())
+            }
+        };
 
-        root_dir
-            .mkdir(
-                &[
-                    "videos".into(),
-                    "movies".into(),
-                    "anime".into(),
-                    "ghibli".into(),
-                ],
-                time,
-                &store,
-            )
-            .await
-            .unwrap();
+        root.write_with_mime(path, b"\x89PNG\r\n\x1a\n".to_vec(), time, store)
+            .await?;
+        assert_eq!(
+            content_type(root).await?,
+            Some(Ipld::String("image/png".into()))
+        );
 
-        let result = root_dir
-            .basic_mv(
-                &["videos".into(), "movies".into()],
-                &["videos".into(), "movies".into(), "anime".into()],
-                Utc::now(),
-                &store,
-            )
-            .await;
+        root.write_with_mime(path, b"plain text".to_vec(), time, store)
+            .await?;
+        assert_eq!(content_type(root).await?, None);
 
-        assert!(result.is_err());
+        Ok(())
     }
 
     #[async_std::test]
-    async fn mv_can_rename_directories() -> TestResult {
+    async fn changes_make_nodes_dirty_until_stored() -> TestResult {
         let time = Utc::now();
         let store = &MemoryBlockStore::new();
-        let root_dir = &mut PublicDirectory::new_rc(time);
-
-        root_dir
-            .write(&["file.txt".into()], b"Hello".to_vec(), time, store)
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        let root = &mut PublicDirectory::new_rc(time);
+        root.write(&path("docs/a.md"), b"A".to_vec(), time, store)
             .await?;
-
-        root_dir
-            .basic_mv(
-                &["file.txt".into()],
-                &["renamed.txt".into()],
-                Utc::now(),
-                store,
-            )
+        root.write(&path("pics/cat.png"), b"Cat".to_vec(), time, store)
             .await?;
+        assert_eq!(root.dirty_paths().len(), 5);
 
-        let result = root_dir.read(&["renamed.txt".into()], store).await?;
+        let stored = root.store(store).await?;
+        assert!(root.dirty_paths().is_empty());
 
-        assert_eq!(result, b"Hello".to_vec());
+        root.mkdir(&path("pics/dogs"), time, store).await?;
+        assert_eq!(
+            root.dirty_paths(),
+            [vec![], path("pics"), path("pics/dogs")]
+        );
+        root.store(store).await?;
+
+        // Changing metadata in place forgets the stored CID, so the change gets stored
+        let node = &mut PublicNode::Dir(Arc::clone(root));
+        let before = node.store(store).await?;
+        node.upsert_mtime(time + chrono::Duration::days(1));
+        assert!(node.is_dirty());
+        let after = node.store(store).await?;
+        assert_ne!(after, before);
+        assert_ne!(after, stored);
+        let loaded = PublicNode::load(&after, store).await?;
+        assert_eq!(
+            loaded.as_dir()?.get_metadata().get_modified(),
+            node.as_dir()?.get_metadata().get_modified()
+        );
+
+        // So does changing a file's content in place
+        let node = &mut PublicNode::File(PublicFile::new_rc(time));
+        let blob_root = FileBuilder::new()
+            .content_bytes(b"Blob".to_vec())
+            .build()?
+            .store(store)
+            .await?;
+        let mut cids = BTreeSet::from([node.store(store).await?]);
+        for step in 0..5 {
+            let file = Arc::make_mut(node.as_file_mut()?);
+            match step {
+                0 => file.set_content(b"Set".to_vec(), time, store).await?,
+                1 => file.append_content(b" more", time, store).await?,
+                2 => file.truncate(3, time, store).await?,
+                3 => {
+                    file.set_content_from_blob_cid(blob_root, time, store)
+                        .await?
+                }
+                _ => file.copy_content_from(&PublicFile::new(time), time),
+            }
+            assert!(node.is_dirty());
+            assert!(cids.insert(node.store(store).await?));
+        }
 
         Ok(())
     }
 
     #[async_std::test]
-    async fn mv_fails_moving_directories_to_files() -> TestResult {
+    async fn cids_are_found_as_nodes_or_content() -> TestResult {
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
         let time = Utc::now();
         let store = &MemoryBlockStore::new();
-        let root_dir = &mut PublicDirectory::new_rc(time);
-
-        root_dir
-            .mkdir(&["movies".into(), "ghibli".into()], time, store)
+        let root = &mut PublicDirectory::new_rc(time);
+        root.write(&path("a/one.txt"), b"one".to_vec(), time, store)
             .await?;
-
-        root_dir
-            .write(&["file.txt".into()], b"Hello".to_vec(), time, store)
+        root.write(&path("b/c/two.txt"), b"two".to_vec(), time, store)
             .await?;
+        root.write(&path("b/copy.txt"), b"two".to_vec(), time, store)
+            .await?;
+        root.store(store).await?;
 
-        let result = root_dir
-            .basic_mv(
-                &["movies".into(), "ghibli".into()],
-                &["file.txt".into()],
-                Utc::now(),
-                store,
-            )
-            .await;
+        let dir = root.get_node(&path("b/c"), store).await?.unwrap();
+        let dir_cid = dir.store(store).await?;
+        assert_eq!(
+            root.find_by_cid(&dir_cid, store).await?,
+            Some(CidMatch::Node(path("b/c")))
+        );
 
-        assert!(result.is_err());
+        // The first of several identical contents in depth-first order is found
+        let file = root.get_node(&path("b/copy.txt"), store).await?.unwrap();
+        let content_cid = file.as_file()?.userland.resolve_cid(store).await?;
+        assert_eq!(
+            root.find_by_cid(&content_cid, store).await?,
+            Some(CidMatch::Content(path("b/c/two.txt")))
+        );
+
+        let root_cid = root.store(store).await?;
+        assert_eq!(
+            root.find_by_cid(&root_cid, store).await?,
+            Some(CidMatch::Node(vec![]))
+        );
+
+        use wnfs_common::CODEC_RAW;
+        let unknown = store.create_cid(b"unknown", CODEC_RAW)?;
+        assert_eq!(root.find_by_cid(&unknown, store).await?, None);
+        let stored = store.put_block(b"unrelated".to_vec(), CODEC_RAW).await?;
+        assert_eq!(root.find_by_cid(&stored, store).await?, None);
 
         Ok(())
     }
@@ -1,22 +1,34 @@
 //! Public fs file node.
 
-use super::{PublicFileSerializable, PublicNodeSerializable};
+use super::{PublicFileSerializable, PublicNodeSerializable, StoreConfig};
 use crate::{
     error::FsError, is_readable_wnfs_version, traits::Id, utils::OnceCellDebug, WNFS_VERSION,
 };
 use anyhow::{anyhow, bail, Result};
+#[cfg(feature = "compress")]
+use async_compression::futures::bufread::{GzipDecoder, ZstdDecoder};
 use async_once_cell::OnceCell;
+use bytes::Bytes;
 use chrono::{DateTime, Utc};
-use futures::{AsyncRead, AsyncReadExt};
+use futures::{io::BufReader, AsyncRead, AsyncReadExt};
 use libipld_core::cid::Cid;
-use std::{cmp::Ordering, collections::BTreeSet, io::SeekFrom};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, VecDeque},
+    io::SeekFrom,
+};
 use tokio::io::AsyncSeekExt;
 use tokio_util::compat::{FuturesAsyncReadCompatExt, TokioAsyncReadCompatExt};
 use wnfs_common::{
-    utils::{Arc, CondSend},
-    BlockStore, Link, Metadata, NodeType, Storable,
+    libipld::multihash::{Code, MultihashDigest},
+    utils::{boxed_async_read, Arc, BoxAsyncRead, CondSend},
+    BlockStore, Link, Metadata, MetadataMergePolicy, NodeType, Storable, CODEC_RAW,
+};
+use wnfs_unixfs_file::{
+    balanced_tree::{self, DEFAULT_DEGREE},
+    builder::FileBuilder,
+    unixfs::UnixFsFile,
 };
-use wnfs_unixfs_file::{builder::FileBuilder, unixfs::UnixFsFile};
 
 /// A file in the WNFS public file system.
 ///
@@ -37,6 +49,36 @@ pub struct PublicFile {
     pub(crate) previous: BTreeSet<Cid>,
 }
 
+/// The shape of a file's content DAG, see `PublicFile::content_stat`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentStat {
+    /// How the content is laid out.
+    pub layout: ContentLayout,
+    /// The number of blocks in the content DAG.
+    pub blocks: usize,
+    /// The number of blocks on the longest path from the root to a chunk, including both.
+    pub depth: usize,
+    /// The number of leaf blocks holding content bytes.
+    pub chunks: usize,
+    /// The size in bytes of the smallest chunk.
+    pub min_chunk_size: u64,
+    /// The size in bytes of the largest chunk.
+    pub max_chunk_size: u64,
+    /// The average chunk size in bytes, rounded down.
+    pub average_chunk_size: u64,
+}
+
+/// How a file's content is laid out in the block store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentLayout {
+    /// The content is inlined in a single UnixFS node without links.
+    Inline,
+    /// The content is a single raw block.
+    Single,
+    /// The content is split into chunks linked from a tree of UnixFS nodes.
+    Chunked,
+}
+
 //--------------------------------------------------------------------------------------------------
 // Implementations
 //--------------------------------------------------------------------------------------------------
@@ -68,6 +110,26 @@ impl PublicFile {
         Arc::new(Self::new(time))
     }
 
+    /// Creates a new, empty file.
+    ///
+    /// All ways of creating files without content, like `new`, `with_content`
+    /// with no bytes or `with_content_streaming` from an empty reader, result in
+    /// the same canonical empty content block. See `is_empty`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::public::PublicFile;
+    /// use chrono::Utc;
+    ///
+    /// let file = PublicFile::empty(Utc::now());
+    ///
+    /// assert!(file.is_empty());
+    /// ```
+    pub fn empty(time: DateTime<Utc>) -> Self {
+        Self::new(time)
+    }
+
     /// Creates a file with given content bytes.
     ///
     /// # Examples
@@ -93,16 +155,23 @@ impl PublicFile {
         content: Vec<u8>,
         store: &impl BlockStore,
     ) -> Result<Self> {
-        let content_cid = FileBuilder::new()
-            .content_bytes(content)
-            .build()?
-            .store(store)
-            .await?;
+        Self::with_content_and_config(time, content, &StoreConfig::default(), store).await
+    }
+
+    /// Creates a file with given content like `with_content` does,
+    /// laying out the content as configured.
+    pub async fn with_content_and_config(
+        time: DateTime<Utc>,
+        content: Vec<u8>,
+        config: &StoreConfig,
+        store: &impl BlockStore,
+    ) -> Result<Self> {
+        let userland = store_content_bytes(content, config, store).await?;
 
         Ok(Self {
             persisted_as: OnceCell::new(),
             metadata: Metadata::new(time),
-            userland: Link::from_cid(content_cid),
+            userland,
             previous: BTreeSet::new(),
         })
     }
@@ -153,7 +222,25 @@ impl PublicFile {
         content: impl AsyncRead + CondSend + 'a,
         store: &'a impl BlockStore,
     ) -> Result<Self> {
+        Self::with_content_streaming_and_config(time, content, &StoreConfig::default(), store).await
+    }
+
+    /// Creates a file from streamed content like `with_content_streaming` does,
+    /// laying out the content as configured.
+    ///
+    /// Identical content stored with the same configuration results in the same
+    /// content CID as `with_content_and_config`.
+    pub async fn with_content_streaming_and_config<'a>(
+        time: DateTime<Utc>,
+        content: impl AsyncRead + CondSend + 'a,
+        config: &StoreConfig,
+        store: &'a impl BlockStore,
+    ) -> Result<Self> {
+        config.validate()?;
+
         let content_cid = FileBuilder::new()
+            .fixed_chunker(config.chunk_size)
+            .degree(config.content_fanout)
             .content_reader(FuturesAsyncReadCompatExt::compat(content))
             .build()?
             .store(store)
@@ -162,7 +249,7 @@ impl PublicFile {
         Ok(Self {
             persisted_as: OnceCell::new(),
             metadata: Metadata::new(time),
-            userland: Link::from_cid(content_cid),
+            userland: canonicalize_content(content_cid, store).await?,
             previous: BTreeSet::new(),
         })
     }
@@ -217,7 +304,15 @@ impl PublicFile {
     /// }
     /// ```
     pub fn copy_content_from(&mut self, other: &Self, time: DateTime<Utc>) {
-        self.metadata.upsert_mtime(time);
+        self.content_changed(time);
+        match other.metadata.get_content_digest() {
+            Some(digest) => self.metadata.upsert_content_digest(digest),
+            None => self.metadata.delete_content_digest(),
+        }
+        match other.metadata.get_content_encoding() {
+            Some(encoding) => self.metadata.upsert_content_encoding(encoding),
+            None => self.metadata.delete_content_encoding(),
+        }
         self.userland = other.userland.clone();
     }
 
@@ -263,6 +358,52 @@ impl PublicFile {
         Ok(TokioAsyncReadCompatExt::compat(reader))
     }
 
+    /// Stream out the content of this file, decoded according to the
+    /// content encoding recorded in its metadata.
+    ///
+    /// Supports the `"identity"` encoding, and `"gzip"` and `"zstd"` with the
+    /// `compress` feature. Content without a recorded encoding is streamed out
+    /// as-is, other encodings fail with `FsError::UnsupportedEncoding`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use futures::AsyncReadExt;
+    /// use wnfs::{public::PublicFile, common::MemoryBlockStore};
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let file = PublicFile::with_content(Utc::now(), b"Hello".to_vec(), store).await?;
+    ///
+    ///     let mut content = Vec::new();
+    ///     file.stream_decoded_content(store)
+    ///         .await?
+    ///         .read_to_end(&mut content)
+    ///         .await?;
+    ///
+    ///     assert_eq!(content, b"Hello");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn stream_decoded_content<'a>(
+        &'a self,
+        store: &'a impl BlockStore,
+    ) -> Result<BoxAsyncRead<'a>> {
+        let content = BufReader::new(self.stream_content(0, store).await?);
+        Ok(match self.metadata.get_content_encoding() {
+            None | Some("identity") => boxed_async_read(content),
+            #[cfg(feature = "compress")]
+            Some("gzip") => boxed_async_read(GzipDecoder::new(content)),
+            #[cfg(feature = "compress")]
+            Some("zstd") => boxed_async_read(ZstdDecoder::new(content)),
+            Some(encoding) => bail!(FsError::UnsupportedEncoding(encoding.to_string())),
+        })
+    }
+
     /// Read the contents of this file.
     /// You can provide a byte offset from which to start reading,
     /// and you can provide a maximum amount of bytes you want to read.
@@ -301,7 +442,7 @@ impl PublicFile {
         let size = self.size(store).await?;
         let mut reader = self.stream_content(byte_offset, store).await?;
         if let Some(len) = len_limit {
-            let len = std::cmp::min(len as u64, size - byte_offset) as usize;
+            let len = std::cmp::min(len as u64, size.saturating_sub(byte_offset)) as usize;
             let mut buffer = vec![0; len];
             reader.read_exact(&mut buffer).await?;
             Ok(buffer)
@@ -353,6 +494,106 @@ impl PublicFile {
             .ok_or_else(|| anyhow!("Missing size on dag-pb node"))
     }
 
+    /// Reports the shape of this file's content DAG, e.g. for checking how content
+    /// got chunked. This loads each block of the content DAG once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{public::{ContentLayout, PublicFile}, common::MemoryBlockStore};
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let file = PublicFile::with_content(Utc::now(), vec![42; 1_000_000], store).await?;
+    ///
+    ///     let stat = file.content_stat(store).await?;
+    ///
+    ///     assert_eq!(stat.layout, ContentLayout::Chunked);
+    ///     assert_eq!(stat.depth, 2);
+    ///     assert_eq!(stat.max_chunk_size, 256 * 1024);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn content_stat(&self, store: &impl BlockStore) -> Result<ContentStat> {
+        let root = self.userland.resolve_value(store).await?;
+        let layout = match root {
+            UnixFsFile::Raw(_) => ContentLayout::Single,
+            UnixFsFile::Node(_) if root.links_owned()?.is_empty() => ContentLayout::Inline,
+            UnixFsFile::Node(_) => ContentLayout::Chunked,
+        };
+
+        let mut stat = ContentStat {
+            layout,
+            blocks: 0,
+            depth: 0,
+            chunks: 0,
+            min_chunk_size: u64::MAX,
+            max_chunk_size: 0,
+            average_chunk_size: 0,
+        };
+        let mut total_chunk_size = 0;
+        let mut queue = VecDeque::new();
+        let mut next = Some((root.clone(), 1));
+        while let Some((node, depth)) = next {
+            stat.blocks += 1;
+            stat.depth = stat.depth.max(depth);
+
+            let links = node.links_owned()?;
+            if links.is_empty() {
+                let size = match &node {
+                    UnixFsFile::Raw(bytes) => bytes.len() as u64,
+                    UnixFsFile::Node(node) => node.data().map_or(0, |data| data.len() as u64),
+                };
+                stat.chunks += 1;
+                stat.min_chunk_size = stat.min_chunk_size.min(size);
+                stat.max_chunk_size = stat.max_chunk_size.max(size);
+                total_chunk_size += size;
+            }
+
+            queue.extend(links.into_iter().map(|link| (link.cid, depth + 1)));
+            next = match queue.pop_front() {
+                Some((cid, depth)) => Some((UnixFsFile::load(&cid, store).await?, depth)),
+                None => None,
+            };
+        }
+
+        stat.average_chunk_size = total_chunk_size / stat.chunks as u64;
+
+        Ok(stat)
+    }
+
+    /// Whether this file has the canonical empty content, without having to
+    /// read it from the store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{public::PublicFile, common::MemoryBlockStore};
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///
+    ///     assert!(PublicFile::with_content(Utc::now(), vec![], store).await?.is_empty());
+    ///     assert!(!PublicFile::with_content(Utc::now(), vec![1], store).await?.is_empty());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        if let Some(UnixFsFile::Raw(bytes)) = self.userland.get_value() {
+            return bytes.is_empty();
+        }
+
+        self.userland.get_cid().is_some_and(is_empty_content_cid)
+    }
+
     /// Gets the entire content of a file.
     ///
     /// # Examples
@@ -440,18 +681,327 @@ impl PublicFile {
         time: DateTime<Utc>,
         store: &impl BlockStore,
     ) -> Result<()> {
-        let content_cid = FileBuilder::new()
-            .content_bytes(content)
-            .build()?
-            .store(store)
-            .await?;
+        self.set_content_with_config(content, time, &StoreConfig::default(), store)
+            .await
+    }
 
-        self.metadata.upsert_mtime(time);
-        self.userland = Link::from_cid(content_cid);
+    /// Writes a new content cid to the file like `set_content` does,
+    /// laying out the content as configured.
+    pub async fn set_content_with_config(
+        &mut self,
+        content: Vec<u8>,
+        time: DateTime<Utc>,
+        config: &StoreConfig,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let userland = store_content_bytes(content, config, store).await?;
+
+        self.content_changed(time);
+        self.userland = userland;
+
+        Ok(())
+    }
+
+    /// Appends given bytes to the content of this file.
+    ///
+    /// The bytes become new chunks of the existing content DAG, so the existing
+    /// content isn't read or stored again, apart from a last chunk that isn't full.
+    /// The resulting content DAG is the same as the one `set_content` creates for
+    /// the concatenated content, so it deduplicates with it.
+    ///
+    /// Files with a content encoding are rejected with `FsError::UnsupportedEncoding`.
+    /// The content digest is removed, since it can't be updated without reading the
+    /// whole content.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicFile,
+    ///     common::MemoryBlockStore,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let mut file = PublicFile::with_content(Utc::now(), b"Hello, ".to_vec(), store).await?;
+    ///
+    ///     file.append_content(b"World!", Utc::now(), store).await?;
+    ///
+    ///     assert_eq!(file.get_content(store).await?, b"Hello, World!");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn append_content(
+        &mut self,
+        content: &[u8],
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        if let Some(encoding) = self.metadata.get_content_encoding() {
+            bail!(FsError::UnsupportedEncoding(encoding.into()));
+        }
+
+        let config = StoreConfig::default();
+        let content_cid = balanced_tree::append_to_tree(
+            &self.userland.resolve_cid(store).await?,
+            Bytes::copy_from_slice(content),
+            config.chunk_size,
+            config.content_fanout,
+            store,
+        )
+        .await?;
+        let userland = canonicalize_content(content_cid, store).await?;
+
+        self.content_changed(time);
+        self.userland = userland;
+
+        Ok(())
+    }
+
+    /// Cuts off the content of this file after `len` bytes, or extends it with
+    /// zero bytes up to `len` bytes.
+    ///
+    /// Like with `append_content`, only the chunk `len` falls into gets rewritten,
+    /// and the resulting content DAG is the same as the one `set_content` creates
+    /// for the new content. Files with a content encoding are rejected with
+    /// `FsError::UnsupportedEncoding`, and the content digest is removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicFile,
+    ///     common::MemoryBlockStore,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let mut file = PublicFile::with_content(Utc::now(), b"Hello, World!".to_vec(), store).await?;
+    ///
+    ///     file.truncate(5, Utc::now(), store).await?;
+    ///     assert_eq!(file.get_content(store).await?, b"Hello");
+    ///
+    ///     file.truncate(7, Utc::now(), store).await?;
+    ///     assert_eq!(file.get_content(store).await?, b"Hello\0\0");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn truncate(
+        &mut self,
+        len: u64,
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        if let Some(encoding) = self.metadata.get_content_encoding() {
+            bail!(FsError::UnsupportedEncoding(encoding.into()));
+        }
+
+        let config = StoreConfig::default();
+        let content_cid = balanced_tree::truncate_tree(
+            &self.userland.resolve_cid(store).await?,
+            len,
+            config.chunk_size,
+            config.content_fanout,
+            store,
+        )
+        .await?;
+        let userland = canonicalize_content(content_cid, store).await?;
+
+        self.content_changed(time);
+        self.userland = userland;
 
         Ok(())
     }
 
+    /// Sets the content of this file to an already stored content DAG,
+    /// e.g. one that was created using `FileBuilder` directly.
+    ///
+    /// The content root is loaded from the store first, to make sure it
+    /// actually decodes as UnixFS file content that records its size.
+    /// Returns `FsError::InvalidContent` if it doesn't.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicFile,
+    ///     common::MemoryBlockStore,
+    /// };
+    /// use wnfs_unixfs_file::builder::FileBuilder;
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let blob_root = FileBuilder::new()
+    ///         .content_bytes(b"Hello, World!".to_vec())
+    ///         .build()?
+    ///         .store(store)
+    ///         .await?;
+    ///
+    ///     let mut file = PublicFile::new(Utc::now());
+    ///     file.set_content_from_blob_cid(blob_root, Utc::now(), store).await?;
+    ///
+    ///     assert_eq!(file.get_content(store).await?, b"Hello, World!");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn set_content_from_blob_cid(
+        &mut self,
+        blob_root: Cid,
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let block = store.get_block(&blob_root).await?;
+        let content = UnixFsFile::decode(&blob_root, block)
+            .map_err(|_| FsError::InvalidContent(blob_root))?;
+
+        let userland = match content.filesize() {
+            None => bail!(FsError::InvalidContent(blob_root)),
+            Some(0) => Link::from(UnixFsFile::empty()),
+            Some(_) => Link::from_cid(blob_root),
+        };
+
+        self.content_changed(time);
+        self.userland = userland;
+
+        Ok(())
+    }
+
+    /// Records a change of this file's content made in place: Updates the
+    /// modification time, drops the content digest and forgets the stored CID,
+    /// making the stored revision the previous one.
+    fn content_changed(&mut self, time: DateTime<Utc>) {
+        if let Some(previous_cid) = self.persisted_as.take() {
+            self.previous = BTreeSet::from([previous_cid]);
+        }
+        self.metadata.upsert_mtime(time);
+        self.metadata.delete_content_digest();
+    }
+
+    /// Computes the blake3 digest of this file's content by reading all of it.
+    ///
+    /// The content DAG is streamed through the hasher chunk by chunk, so only a
+    /// bounded amount of content is held in memory at any time, no matter how
+    /// large the file is.
+    pub async fn compute_content_digest(&self, store: &impl BlockStore) -> Result<[u8; 32]> {
+        digest_content(self.stream_content(0, store).await?).await
+    }
+
+    /// Computes the digest of this file's content and records it in its metadata,
+    /// so `verify_content` and `content_bytes_eq` can use it later.
+    ///
+    /// Digests are opt-in: They're part of the stored metadata, so recording one
+    /// changes the file's CID. Changing the content via `set_content`, `append` or
+    /// `truncate` drops the recorded digest again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{public::PublicFile, common::MemoryBlockStore};
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let mut file = PublicFile::with_content(Utc::now(), b"Hello".to_vec(), store).await?;
+    ///     assert_eq!(file.get_metadata().get_content_digest(), None);
+    ///
+    ///     let digest = file.record_content_digest(store).await?;
+    ///
+    ///     assert_eq!(file.get_metadata().get_content_digest(), Some(digest));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn record_content_digest(&mut self, store: &impl BlockStore) -> Result<[u8; 32]> {
+        let digest = self.compute_content_digest(store).await?;
+        self.get_metadata_mut().upsert_content_digest(digest);
+        Ok(digest)
+    }
+
+    /// Reads this file's entire content and checks it against the content
+    /// digest recorded in its metadata via `record_content_digest`.
+    ///
+    /// Returns `FsError::ContentDigestMismatch` if they differ. Files without
+    /// a recorded digest only get checked for their content being readable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{public::PublicFile, common::MemoryBlockStore};
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let file = PublicFile::with_content(Utc::now(), b"Hello".to_vec(), store).await?;
+    ///
+    ///     file.verify_content(store).await?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn verify_content(&self, store: &impl BlockStore) -> Result<()> {
+        let digest = self.compute_content_digest(store).await?;
+        match self.metadata.get_content_digest() {
+            Some(recorded) if recorded != digest => bail!(FsError::ContentDigestMismatch),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks whether this file and another one have the same content bytes.
+    ///
+    /// This avoids reading any content if both content DAGs are the same or
+    /// if both files have a content digest recorded via `record_content_digest`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{public::PublicFile, common::MemoryBlockStore};
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let file = PublicFile::with_content(Utc::now(), b"Hello".to_vec(), store).await?;
+    ///     let other = PublicFile::with_content(Utc::now(), b"Hello".to_vec(), store).await?;
+    ///
+    ///     assert!(file.content_bytes_eq(&other, store).await?);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn content_bytes_eq(&self, other: &Self, store: &impl BlockStore) -> Result<bool> {
+        if self.userland.resolve_cid(store).await? == other.userland.resolve_cid(store).await? {
+            return Ok(true);
+        }
+
+        let ours = match self.metadata.get_content_digest() {
+            Some(digest) => digest,
+            None => self.compute_content_digest(store).await?,
+        };
+        let theirs = match other.metadata.get_content_digest() {
+            Some(digest) => digest,
+            None => other.compute_content_digest(store).await?,
+        };
+
+        Ok(ours == theirs)
+    }
+
     /// Gets the content cid of the file.
     pub async fn get_raw_content_cid(&self, store: &impl BlockStore) -> Cid {
         let content_cid: Result<Cid> = self.userland.resolve_cid(store).await;
@@ -480,7 +1030,10 @@ impl PublicFile {
     }
 
     /// Returns a mutable reference to metadata for this file.
+    ///
+    /// This forgets the CID the file was stored as, since it's about to change.
     pub fn get_metadata_mut(&mut self) -> &mut Metadata {
+        self.persisted_as = OnceCell::new();
         &mut self.metadata
     }
 
@@ -505,6 +1058,21 @@ impl PublicFile {
         self: &mut Arc<Self>,
         other: &Arc<Self>,
         store: &impl BlockStore,
+    ) -> Result<bool> {
+        self.merge_with_policy(other, MetadataMergePolicy::default(), store)
+            .await
+    }
+
+    /// Runs the merge part of the conflict reconciliation algorithm like `merge`,
+    /// combining the metadata of files with equal content as given policy says.
+    ///
+    /// Unless the policy is `MetadataMergePolicy::TieBreak`, files that only differ
+    /// in metadata don't count as needing a tie-break.
+    pub async fn merge_with_policy(
+        self: &mut Arc<Self>,
+        other: &Arc<Self>,
+        policy: MetadataMergePolicy,
+        store: &impl BlockStore,
     ) -> Result<bool> {
         let our_cid = self.store(store).await?;
         let other_cid = other.store(store).await?;
@@ -534,7 +1102,9 @@ impl PublicFile {
                 file.metadata.clone_from(&other.metadata);
             }
             Ordering::Equal => {
-                file.metadata.tie_break_with(&other.metadata)?;
+                file.metadata.merge_with(&other.metadata, policy)?;
+                // Only the metadata differed, which the policy resolved
+                return Ok(policy == MetadataMergePolicy::TieBreak);
             }
             Ordering::Less => {
                 // We take ours
@@ -634,6 +1204,128 @@ impl Clone for PublicFile {
     }
 }
 
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Creates a file whose content is the content of given files, one after another.
+///
+/// The new content DAG links to the content DAGs of the parts, so none of their
+/// blocks get copied or even read, apart from their root nodes. This makes it cheap
+/// to assemble a file from parts that were uploaded separately.
+///
+/// Parts with a content encoding are rejected with `FsError::UnsupportedEncoding`,
+/// since their encoded contents can't be joined. The new file has no content digest.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use chrono::Utc;
+/// use wnfs::{
+///     public::{concat_files, PublicFile},
+///     common::MemoryBlockStore,
+/// };
+///
+/// #[async_std::main]
+/// async fn main() -> Result<()> {
+///     let store = &MemoryBlockStore::new();
+///     let time = Utc::now();
+///     let parts = [
+///         PublicFile::with_content_rc(time, b"Hello, ".to_vec(), store).await?,
+///         PublicFile::with_content_rc(time, b"World!".to_vec(), store).await?,
+///     ];
+///
+///     let file = concat_files(&parts, time, store).await?;
+///
+///     assert_eq!(file.get_content(store).await?, b"Hello, World!");
+///     assert_eq!(file.size(store).await?, 13);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn concat_files(
+    parts: &[Arc<PublicFile>],
+    time: DateTime<Utc>,
+    store: &impl BlockStore,
+) -> Result<PublicFile> {
+    let mut roots = Vec::with_capacity(parts.len());
+    for part in parts {
+        if let Some(encoding) = part.metadata.get_content_encoding() {
+            bail!(FsError::UnsupportedEncoding(encoding.into()));
+        }
+
+        roots.push(part.userland.resolve_cid(store).await?);
+    }
+
+    let content_cid = balanced_tree::concat_trees(&roots, DEFAULT_DEGREE, store).await?;
+
+    Ok(PublicFile {
+        persisted_as: OnceCell::new(),
+        metadata: Metadata::new(time),
+        userland: canonicalize_content(content_cid, store).await?,
+        previous: BTreeSet::new(),
+    })
+}
+
+/// Stores given bytes as file content, using the canonical empty content for no bytes.
+async fn store_content_bytes(
+    content: Vec<u8>,
+    config: &StoreConfig,
+    store: &impl BlockStore,
+) -> Result<Link<UnixFsFile>> {
+    config.validate()?;
+
+    if content.is_empty() {
+        return Ok(Link::from(UnixFsFile::empty()));
+    }
+
+    let content_cid = FileBuilder::new()
+        .fixed_chunker(config.chunk_size)
+        .degree(config.content_fanout)
+        .content_bytes(content)
+        .build()?
+        .store(store)
+        .await?;
+
+    Ok(Link::from_cid(content_cid))
+}
+
+/// Streams given content through a blake3 hasher, using a fixed size buffer.
+async fn digest_content(reader: impl AsyncRead) -> Result<[u8; 32]> {
+    let mut reader = std::pin::pin!(reader);
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher.finalize().into())
+}
+
+/// Replaces an already stored content DAG with the canonical empty content if it has no bytes.
+async fn canonicalize_content(
+    content_cid: Cid,
+    store: &impl BlockStore,
+) -> Result<Link<UnixFsFile>> {
+    Ok(
+        match UnixFsFile::load(&content_cid, store).await?.filesize() {
+            Some(0) => Link::from(UnixFsFile::empty()),
+            _ => Link::from_cid(content_cid),
+        },
+    )
+}
+
+/// Whether given CID is the CID of an empty raw block, for any supported hash function.
+fn is_empty_content_cid(cid: &Cid) -> bool {
+    cid.codec() == CODEC_RAW
+        && Code::try_from(cid.hash().code()).is_ok_and(|code| &code.digest(&[]) == cid.hash())
+}
+
 //--------------------------------------------------------------------------------------------------
 // Tests
 //--------------------------------------------------------------------------------------------------
@@ -641,7 +1333,9 @@ impl Clone for PublicFile {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use wnfs_common::MemoryBlockStore;
+    use crate::public::{golden::golden_content, PublicDirectory};
+    use testresult::TestResult;
+    use wnfs_common::{AccountingBlockStore, MemoryBlockStore};
 
     #[async_std::test]
     async fn previous_links_get_set() {
@@ -674,6 +1368,485 @@ mod tests {
             vec![previous_cid]
         );
     }
+
+    #[async_std::test]
+    async fn streamed_content_surfaces_missing_chunks() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let content = golden_content(1_000_000);
+        let file = PublicFile::with_content(time, content.clone(), store).await?;
+
+        // Reads are clamped to the end of the content
+        assert_eq!(
+            file.read_at(999_990, Some(100), store).await?,
+            content[999_990..]
+        );
+        assert!(file.read_at(2_000_000, Some(10), store).await?.is_empty());
+
+        // A copy of the store that's missing the last chunk
+        let root = file.userland.resolve_value(store).await?;
+        let last_chunk = root.links_owned()?.pop_back().unwrap().cid;
+        let partial = &MemoryBlockStore::new();
+        for cid in [file.userland.resolve_cid(store).await?]
+            .into_iter()
+            .chain(root.links_owned()?.into_iter().map(|link| link.cid))
+            .filter(|cid| cid != &last_chunk)
+        {
+            partial
+                .put_block_keyed(cid, store.get_block(&cid).await?)
+                .await?;
+        }
+
+        // Chunks before the missing one stream fine, then reading fails
+        let mut reader = file.stream_content(0, partial).await?;
+        let mut prefix = vec![0; 500_000];
+        reader.read_exact(&mut prefix).await?;
+        assert_eq!(prefix, content[..500_000]);
+        let mut rest = Vec::new();
+        assert!(reader.read_to_end(&mut rest).await.is_err());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn custom_content_fanout_round_trips_with_stable_cids() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let content = golden_content(3_000_000);
+        let config = &StoreConfig::default().with_content_fanout(4);
+
+        let file =
+            PublicFile::with_content_and_config(time, content.clone(), config, store).await?;
+        let again =
+            PublicFile::with_content_and_config(time, content.clone(), config, store).await?;
+        let default = PublicFile::with_content(time, content.clone(), store).await?;
+
+        let cid = file.userland.resolve_cid(store).await?;
+        assert_eq!(cid, again.userland.resolve_cid(store).await?);
+        assert_ne!(cid, default.userland.resolve_cid(store).await?);
+
+        let root = file.userland.resolve_value(store).await?;
+        assert!(root.links_owned()?.len() <= 4);
+        assert_eq!(file.get_content(store).await?, content);
+        assert_eq!(
+            file.read_at(1_500_000, Some(10), store).await?,
+            content[1_500_000..1_500_010]
+        );
+
+        let mut file = PublicFile::new(time);
+        let config = &StoreConfig::default().with_content_fanout(1);
+        let result = file
+            .set_content_with_config(content, time, config, store)
+            .await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::InvalidContentFanout(1))
+        ));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn custom_chunk_sizes_round_trip_streamed_content() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let content = golden_content(1024 * 1024);
+        let config = &StoreConfig::default().with_chunk_size(64 * 1024);
+
+        let file =
+            PublicFile::with_content_streaming_and_config(time, content.as_slice(), config, store)
+                .await?;
+        let again =
+            PublicFile::with_content_and_config(time, content.clone(), config, store).await?;
+        let default = PublicFile::with_content_streaming(time, content.as_slice(), store).await?;
+
+        let cid = file.userland.resolve_cid(store).await?;
+        assert_eq!(cid, again.userland.resolve_cid(store).await?);
+        assert_ne!(cid, default.userland.resolve_cid(store).await?);
+
+        let stat = file.content_stat(store).await?;
+        assert_eq!(stat.chunks, 16);
+        assert_eq!(stat.max_chunk_size, 64 * 1024);
+        assert_eq!(file.get_content(store).await?, content);
+        assert_eq!(default.get_content(store).await?, content);
+
+        for chunk_size in [0, 2 * 1024 * 1024] {
+            let config = &StoreConfig::default().with_chunk_size(chunk_size);
+            let result =
+                PublicFile::with_content_and_config(time, content.clone(), config, store).await;
+            assert!(matches!(
+                result.unwrap_err().downcast_ref(),
+                Some(FsError::InvalidChunkSize(size)) if *size == chunk_size
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn all_routes_to_empty_files_yield_the_same_content_cid() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::default();
+        let canonical = PublicFile::empty(time).userland.resolve_cid(store).await?;
+
+        let mut set_to_empty = PublicFile::with_content(time, b"Hello".to_vec(), store).await?;
+        assert!(!set_to_empty.is_empty());
+        set_to_empty.set_content(vec![], time, store).await?;
+
+        let empty_blob = FileBuilder::new()
+            .content_bytes(vec![])
+            .build()?
+            .store(store)
+            .await?;
+        let mut from_blob = PublicFile::new(time);
+        from_blob
+            .set_content_from_blob_cid(empty_blob, time, store)
+            .await?;
+
+        let root = &mut PublicDirectory::new_rc(time);
+        root.write(&["written.txt".into()], vec![], time, store)
+            .await?;
+        root.open_file_mut(&["opened.txt".into()], time, store)
+            .await?;
+        let written = root
+            .get_node(&["written.txt".into()], store)
+            .await?
+            .unwrap();
+        let opened = root.get_node(&["opened.txt".into()], store).await?.unwrap();
+
+        let files = [
+            PublicFile::new(time),
+            PublicFile::with_content(time, vec![], store).await?,
+            PublicFile::with_content_streaming(time, &b""[..], store).await?,
+            set_to_empty,
+            from_blob,
+            (*written.as_file()?).clone(),
+            (*opened.as_file()?).clone(),
+        ];
+
+        for file in files {
+            assert!(file.is_empty());
+            assert_eq!(file.userland.resolve_cid(store).await?, canonical);
+
+            // Also recognized after a round trip through the store
+            let loaded = PublicFile::load(&file.store(store).await?, store).await?;
+            assert!(loaded.is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn content_stat_reports_the_dag_shape() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::default();
+
+        let small = PublicFile::with_content(time, b"Hello".to_vec(), store).await?;
+        assert_eq!(
+            small.content_stat(store).await?,
+            ContentStat {
+                layout: ContentLayout::Single,
+                blocks: 1,
+                depth: 1,
+                chunks: 1,
+                min_chunk_size: 5,
+                max_chunk_size: 5,
+                average_chunk_size: 5,
+            }
+        );
+
+        let empty = PublicFile::new(time).content_stat(store).await?;
+        assert_eq!((empty.layout, empty.chunks), (ContentLayout::Single, 1));
+        assert_eq!(empty.max_chunk_size, 0);
+
+        // 10 chunks of 256 KiB and a smaller last one, with up to 3 links per node
+        let len = 10 * 256 * 1024 + 1000;
+        let config = &StoreConfig::default().with_content_fanout(3);
+        let large =
+            PublicFile::with_content_and_config(time, golden_content(len), config, store).await?;
+        let stat = large.content_stat(store).await?;
+        assert_eq!(stat.layout, ContentLayout::Chunked);
+        assert_eq!(stat.chunks, 11);
+        assert_eq!(stat.depth, 4);
+        assert!(stat.blocks > stat.chunks + 3);
+        assert_eq!(stat.min_chunk_size, 1000);
+        assert_eq!(stat.max_chunk_size, 256 * 1024);
+        assert_eq!(stat.average_chunk_size, len as u64 / 11);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn content_digests_stream_with_bounded_memory() -> TestResult {
+        use futures::task::{Context, Poll};
+        use std::{
+            pin::Pin,
+            sync::atomic::{AtomicU64, Ordering::SeqCst},
+        };
+        use wnfs_common::utils::InstrumentedBlockStore;
+
+        /// Tracks how many fetched bytes weren't handed to the hasher yet.
+        struct ResidentTracking<'a, R> {
+            inner: Pin<Box<R>>,
+            store: &'a InstrumentedBlockStore,
+            consumed: u64,
+            peak_resident: &'a AtomicU64,
+        }
+
+        impl<R: AsyncRead> AsyncRead for ResidentTracking<'_, R> {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                buf: &mut [u8],
+            ) -> Poll<std::io::Result<usize>> {
+                let this = self.get_mut();
+                let poll = this.inner.as_mut().poll_read(cx, buf);
+                if let Poll::Ready(Ok(read)) = poll {
+                    this.consumed += read as u64;
+                    let resident = this.store.fetched_bytes() - this.consumed;
+                    this.peak_resident.fetch_max(resident, SeqCst);
+                }
+                poll
+            }
+        }
+
+        let len = 32 * 1024 * 1024;
+        let content = golden_content(len);
+        let store = &InstrumentedBlockStore::default();
+        let file = PublicFile::with_content(Utc::now(), content.clone(), store).await?;
+        let expected: [u8; 32] = blake3::hash(&content).into();
+        drop(content);
+
+        store.reset_counts();
+        let peak_resident = &AtomicU64::new(0);
+        let reader = ResidentTracking {
+            inner: Box::pin(file.stream_content(0, store).await?),
+            store,
+            consumed: 0,
+            peak_resident,
+        };
+
+        assert_eq!(digest_content(reader).await?, expected);
+        assert_eq!(file.compute_content_digest(store).await?, expected);
+        // The file got read completely, but only ever a few chunks at a time
+        assert!(store.fetched_bytes() >= len as u64);
+        assert!(peak_resident.load(SeqCst) < 1024 * 1024);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "compress")]
+    #[async_std::test]
+    async fn decoded_content_gets_decompressed() -> TestResult {
+        use async_compression::futures::bufread::{GzipEncoder, ZstdEncoder};
+
+        let time = Utc::now();
+        let store = &MemoryBlockStore::default();
+        let content = b"Hello, World!\n".repeat(1000);
+
+        for encoding in ["gzip", "zstd", "identity"] {
+            let mut encoded = Vec::new();
+            match encoding {
+                "gzip" => {
+                    GzipEncoder::new(&content[..])
+                        .read_to_end(&mut encoded)
+                        .await?
+                }
+                "zstd" => {
+                    ZstdEncoder::new(&content[..])
+                        .read_to_end(&mut encoded)
+                        .await?
+                }
+                _ => (&content[..]).read_to_end(&mut encoded).await?,
+            };
+
+            let mut file = PublicFile::with_content(time, encoded, store).await?;
+            file.get_metadata_mut().upsert_content_encoding(encoding);
+
+            let mut decoded = Vec::new();
+            file.stream_decoded_content(store)
+                .await?
+                .read_to_end(&mut decoded)
+                .await?;
+
+            assert_eq!(decoded, content, "{encoding} content didn't decode");
+        }
+
+        let mut file = PublicFile::with_content(time, content, store).await?;
+        file.get_metadata_mut().upsert_content_encoding("br");
+        let result = file.stream_decoded_content(store).await;
+
+        assert!(matches!(
+            result.err().unwrap().downcast_ref(),
+            Some(FsError::UnsupportedEncoding(encoding)) if encoding == "br"
+        ));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn content_digests_get_recorded_and_verified() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::default();
+
+        let file = &mut PublicFile::new_rc(time);
+        file.prepare_next_revision()
+            .set_content(b"Hello, World!".to_vec(), time, store)
+            .await?;
+        assert_eq!(file.get_metadata().get_content_digest(), None);
+        let unrecorded = file.store(store).await?;
+
+        file.prepare_next_revision()
+            .record_content_digest(store)
+            .await?;
+        assert_ne!(file.store(store).await?, unrecorded);
+        assert_eq!(
+            file.get_metadata().get_content_digest(),
+            Some(*blake3::hash(b"Hello, World!").as_bytes())
+        );
+        file.verify_content(store).await?;
+
+        let other = &PublicFile::with_content(time, b"Hello, World!".to_vec(), store).await?;
+        assert!(file.content_bytes_eq(other, store).await?);
+
+        let different = &mut PublicFile::with_content(time, b"Goodbye!".to_vec(), store).await?;
+        assert!(!file.content_bytes_eq(different, store).await?);
+        different.record_content_digest(store).await?;
+        assert!(!file.content_bytes_eq(different, store).await?);
+
+        let tampered = file.prepare_next_revision();
+        tampered.get_metadata_mut().upsert_content_digest([0; 32]);
+        assert!(matches!(
+            tampered
+                .verify_content(store)
+                .await
+                .unwrap_err()
+                .downcast_ref(),
+            Some(FsError::ContentDigestMismatch)
+        ));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn set_content_from_blob_cid_rejects_non_content_dags() {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::default();
+
+        let dir_cid = crate::public::PublicDirectory::new_rc(time)
+            .store(store)
+            .await
+            .unwrap();
+
+        let mut file = PublicFile::new(time);
+        let result = file.set_content_from_blob_cid(dir_cid, time, store).await;
+
+        assert!(matches!(
+            result.unwrap_err().downcast_ref::<FsError>(),
+            Some(FsError::InvalidContent(cid)) if cid == &dir_cid
+        ));
+    }
+
+    #[async_std::test]
+    async fn content_changes_in_place_create_new_revisions() -> TestResult {
+        async fn store_next_revision(
+            file: &PublicFile,
+            previous: Cid,
+            content: &[u8],
+            store: &impl BlockStore,
+        ) -> Result<Cid> {
+            let cid = file.store(store).await?;
+            assert_ne!(cid, previous);
+            let loaded = PublicFile::load(&cid, store).await?;
+            assert_eq!(loaded.get_content(store).await?, content);
+            assert_eq!(loaded.previous, BTreeSet::from([previous]));
+            Ok(cid)
+        }
+
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let blob_root = FileBuilder::new()
+            .content_bytes(b"From blob".to_vec())
+            .build()?
+            .store(store)
+            .await?;
+
+        let mut file = PublicFile::new(time);
+        let cid = file.store(store).await?;
+
+        file.set_content_from_blob_cid(blob_root, time, store)
+            .await?;
+        let cid = store_next_revision(&file, cid, b"From blob", store).await?;
+
+        file.set_content(b"Set".to_vec(), time, store).await?;
+        let cid = store_next_revision(&file, cid, b"Set", store).await?;
+
+        file.append_content(b", appended", time, store).await?;
+        let cid = store_next_revision(&file, cid, b"Set, appended", store).await?;
+
+        file.truncate(3, time, store).await?;
+        store_next_revision(&file, cid, b"Set", store).await?;
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn concatenated_files_share_the_content_of_their_parts() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let contents = [
+            golden_content(600_000),
+            b"Small part".to_vec(),
+            Vec::new(),
+            golden_content(300_000),
+        ];
+        let mut parts = Vec::new();
+        for content in contents.iter() {
+            let part = PublicFile::with_content_rc(time, content.clone(), store).await?;
+            part.store(store).await?;
+            parts.push(part);
+        }
+
+        let accounting = &AccountingBlockStore::new(store);
+        let file = concat_files(&parts, time, accounting).await?;
+        let expected = contents.concat();
+        assert_eq!(file.get_content(store).await?, expected);
+        assert_eq!(file.size(store).await?, expected.len() as u64);
+        assert_eq!(
+            file.read_at(599_995, Some(10), store).await?,
+            expected[599_995..600_005]
+        );
+        // Only the new root node got stored
+        assert_eq!(accounting.stats().new_blocks, 1);
+
+        // More parts than fit into one node get another layer of nodes
+        let mut many = Vec::new();
+        for i in 0..(DEFAULT_DEGREE as u32 + 10) {
+            many.push(PublicFile::with_content_rc(time, i.to_be_bytes().to_vec(), store).await?);
+        }
+        let file = concat_files(&many, time, store).await?;
+        let stat = file.content_stat(store).await?;
+        assert_eq!(stat.depth, 3);
+        assert_eq!(stat.chunks, DEFAULT_DEGREE + 10);
+        assert_eq!(
+            file.read_at(4 * 180, Some(4), store).await?,
+            180u32.to_be_bytes()
+        );
+
+        assert!(concat_files(&parts[2..3], time, store).await?.is_empty());
+
+        let encoded = &mut parts[1];
+        encoded
+            .get_metadata_mut_rc()
+            .upsert_content_encoding("gzip");
+        let result = concat_files(&parts, time, store).await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::UnsupportedEncoding(_))
+        ));
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
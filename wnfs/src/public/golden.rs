@@ -0,0 +1,206 @@
+//! Canonical public file system trees with pinned root CIDs.
+//!
+//! These trees are built with a fixed clock and fixed content, so their
+//! serialization is fully deterministic. Any change to the WNFS data format
+//! that alters their CIDs should come with a deliberate `WNFS_VERSION` bump.
+//!
+//! Downstream crates can run the same check against the version of wnfs
+//! they pin by calling [`verify_golden_cids`].
+
+use super::PublicDirectory;
+use crate::WNFS_VERSION;
+use anyhow::{bail, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use libipld_core::cid::Cid;
+use wnfs_common::{utils::Arc, BlockStore, Storable};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The root CIDs each of the [`canonical_trees`] is expected to serialize to,
+/// for data format version [`WNFS_VERSION`].
+pub const GOLDEN_ROOT_CIDS: [(&str, &str); 4] = [
+    (
+        "empty",
+        "bafyr4ih3k2ipwwqryrlmtmwtolma4t772gknmx7pxqu5opjwsrr2nc27qq",
+    ),
+    (
+        "single_file",
+        "bafyr4ietnw2zs6dbxswsnsqo5hrouvdsx76db7shnjtyzfail4jrlefyce",
+    ),
+    (
+        "nested",
+        "bafyr4idrr43xqrq7gkhec27u3qeg777csucyfnev37of5yr72jnsbkujfm",
+    ),
+    (
+        "with_history",
+        "bafyr4iazyrbss3f6kbucfwsnfn4h3bdndxpmqgeijyypkaybvnhp3yu67q",
+    ),
+];
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// The fixed point in time all canonical trees are created at.
+pub fn golden_time() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 0).unwrap()
+}
+
+/// Deterministic content of given length, used for the canonical trees' files.
+pub fn golden_content(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+/// Builds the set of canonical trees, named the same way as in [`GOLDEN_ROOT_CIDS`].
+///
+/// Content blocks get written to the given store, the directories themselves
+/// are left unstored.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use wnfs::{common::{MemoryBlockStore, Storable}, public::golden};
+///
+/// #[async_std::main]
+/// async fn main() -> Result<()> {
+///     let store = &MemoryBlockStore::new();
+///     let trees = golden::canonical_trees(store).await?;
+///
+///     for (name, root) in trees {
+///         println!("{name}: {}", root.store(store).await?);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn canonical_trees(
+    store: &impl BlockStore,
+) -> Result<Vec<(&'static str, Arc<PublicDirectory>)>> {
+    let time = golden_time();
+
+    let empty = PublicDirectory::new_rc(time);
+
+    let single_file = &mut PublicDirectory::new_rc(time);
+    single_file
+        .write(
+            &["hello.txt".into()],
+            b"Hello, World!".to_vec(),
+            time,
+            store,
+        )
+        .await?;
+
+    let nested = &mut PublicDirectory::new_rc(time);
+    nested
+        .mkdir(&["pictures".into(), "cats".into()], time, store)
+        .await?;
+    nested
+        .write(
+            &["music".into(), "jazz".into(), "track.mp3".into()],
+            golden_content(1024),
+            time,
+            store,
+        )
+        .await?;
+    nested
+        .write(
+            &["videos".into(), "big.bin".into()],
+            golden_content(600_000),
+            time,
+            store,
+        )
+        .await?;
+
+    let with_history = &mut PublicDirectory::new_rc(time);
+    with_history
+        .write(&["notes.md".into()], b"# Draft".to_vec(), time, store)
+        .await?;
+    with_history.store(store).await?;
+    with_history
+        .write(&["notes.md".into()], b"# Final".to_vec(), time, store)
+        .await?;
+
+    Ok(vec![
+        ("empty", empty),
+        ("single_file", Arc::clone(single_file)),
+        ("nested", Arc::clone(nested)),
+        ("with_history", Arc::clone(with_history)),
+    ])
+}
+
+/// Builds all [`canonical_trees`], stores them and checks their root CIDs
+/// against [`GOLDEN_ROOT_CIDS`].
+///
+/// Returns an error listing every tree whose CID drifted.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use wnfs::{common::MemoryBlockStore, public::golden};
+///
+/// #[async_std::main]
+/// async fn main() -> Result<()> {
+///     golden::verify_golden_cids(&MemoryBlockStore::new()).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn verify_golden_cids(store: &impl BlockStore) -> Result<()> {
+    let mut mismatches = Vec::new();
+
+    for ((name, root), (golden_name, golden_cid)) in canonical_trees(store)
+        .await?
+        .into_iter()
+        .zip(GOLDEN_ROOT_CIDS)
+    {
+        debug_assert_eq!(name, golden_name);
+        let cid = root.store(store).await?;
+        let expected = Cid::try_from(golden_cid)?;
+        if cid != expected {
+            mismatches.push(format!("{name}: expected {expected}, got {cid}"));
+        }
+    }
+
+    if !mismatches.is_empty() {
+        bail!(
+            "Serialization of canonical trees changed for WNFS version {WNFS_VERSION}:\n{}",
+            mismatches.join("\n")
+        );
+    }
+
+    Ok(())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testresult::TestResult;
+    use wnfs_common::MemoryBlockStore;
+
+    #[async_std::test]
+    async fn canonical_trees_match_golden_cids() -> TestResult {
+        verify_golden_cids(&MemoryBlockStore::new()).await?;
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn canonical_trees_are_deterministic() -> TestResult {
+        let first = canonical_trees(&MemoryBlockStore::new()).await?;
+        let second = canonical_trees(&MemoryBlockStore::new()).await?;
+        let store = &MemoryBlockStore::new();
+
+        for ((_, a), (_, b)) in first.into_iter().zip(second) {
+            assert_eq!(a.store(store).await?, b.store(store).await?);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+//! Views of stored public directories that load nodes only as they're needed.
+
+use super::{PublicDirectory, PublicNode};
+use anyhow::Result;
+use async_once_cell::OnceCell;
+use libipld_core::cid::Cid;
+use wnfs_common::{utils::Arc, BlockStore, Metadata, Storable};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A stored public directory that's loaded from its CID only as far as it's read.
+///
+/// Nothing is loaded until the first read. From then on, each read loads just the
+/// directories along its path that weren't loaded before, so touching `a/b/c` never
+/// loads the siblings of `a`, `b` or `c`. Loaded nodes are kept, reading the same
+/// path again doesn't hit the block store. This keeps the number of round trips low
+/// for clients reading a few paths of a large tree from a high-latency store.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use chrono::Utc;
+/// use wnfs::{
+///     public::{LazyDirectory, PublicDirectory},
+///     common::{MemoryBlockStore, Storable},
+/// };
+///
+/// #[async_std::main]
+/// async fn main() -> Result<()> {
+///     let store = &MemoryBlockStore::new();
+///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+///     let path = vec!["docs".to_string(), "notes.txt".into()];
+///     dir.write(&path, b"Hello".to_vec(), Utc::now(), store).await?;
+///
+///     let lazy = LazyDirectory::new(dir.store(store).await?);
+///     let node = lazy.get(&path, store).await?.unwrap();
+///
+///     assert_eq!(node.as_file()?.get_content(store).await?, b"Hello");
+///     assert_eq!(lazy.ls(&["docs".into()], store).await?.len(), 1);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LazyDirectory {
+    cid: Cid,
+    root: OnceCell<Arc<PublicDirectory>>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl LazyDirectory {
+    /// Creates a view of the directory with given CID, without loading it yet.
+    pub fn new(cid: Cid) -> Self {
+        Self {
+            cid,
+            root: OnceCell::new(),
+        }
+    }
+
+    /// The CID of the viewed directory.
+    pub fn cid(&self) -> &Cid {
+        &self.cid
+    }
+
+    /// Returns the viewed directory, loading it on first use. Its entries get
+    /// loaded as they're read, through this view or the directory itself.
+    pub async fn root(&self, store: &impl BlockStore) -> Result<&Arc<PublicDirectory>> {
+        self.root
+            .get_or_try_init(async { Ok(Arc::new(PublicDirectory::load(&self.cid, store).await?)) })
+            .await
+    }
+
+    /// Returns the node at given path, like `PublicDirectory::get_node`, loading only
+    /// the directories along the path and the node itself.
+    pub async fn get(
+        &self,
+        path_segments: &[String],
+        store: &impl BlockStore,
+    ) -> Result<Option<&PublicNode>> {
+        self.root(store).await?.get_node(path_segments, store).await
+    }
+
+    /// Lists the directory at given path, like `PublicDirectory::ls`. Besides the
+    /// directories along the path, this loads the listed entries, which hold the
+    /// metadata that's returned, but nothing below them.
+    pub async fn ls(
+        &self,
+        path_segments: &[String],
+        store: &impl BlockStore,
+    ) -> Result<Vec<(String, Metadata)>> {
+        self.root(store).await?.ls(path_segments, store).await
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use testresult::TestResult;
+    use wnfs_common::{MemoryBlockStore, RecordingBlockStore};
+
+    #[async_std::test]
+    async fn deep_reads_load_blocks_along_the_path_only() -> TestResult {
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        for dir in ["a", "a/b", "a/b/c", "x", "y"] {
+            for i in 0..5 {
+                root.write(&path(&format!("{dir}/{i}.txt")), vec![i; 10], time, store)
+                    .await?;
+            }
+        }
+        let cid = root.store(store).await?;
+
+        let recording = &RecordingBlockStore::new(store);
+        let lazy = LazyDirectory::new(cid);
+        assert!(recording.log().is_empty());
+
+        let leaf = path("a/b/c/3.txt");
+        let node = lazy.get(&leaf, recording).await?.unwrap();
+        assert_eq!(node.as_file()?.get_content(store).await?, vec![3; 10]);
+        // The root, `a`, `b`, `c` and the file itself
+        assert_eq!(recording.take_log().len(), leaf.len() + 1);
+
+        assert!(lazy.get(&leaf, recording).await?.is_some());
+        assert!(lazy.get(&path("a/b/0.txt"), recording).await?.is_some());
+        assert_eq!(recording.take_log().len(), 1);
+
+        assert_eq!(lazy.ls(&path("a/b"), recording).await?.len(), 6);
+        // The four files not loaded yet
+        assert_eq!(recording.take_log().len(), 4);
+
+        assert!(lazy.get(&path("a/missing"), recording).await?.is_none());
+        assert!(recording.take_log().is_empty());
+
+        Ok(())
+    }
+}
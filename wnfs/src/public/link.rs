@@ -56,6 +56,18 @@ impl PublicLink {
         self.0.resolve_cid(store).await
     }
 
+    /// Gets the Cid stored in type, if it's known without storing the node.
+    #[inline]
+    pub(crate) fn get_cid(&self) -> Option<&Cid> {
+        self.0.get_cid()
+    }
+
+    /// Gets the value stored in type, if it's present without loading it.
+    #[inline]
+    pub(crate) fn get_value(&self) -> Option<&PublicNode> {
+        self.0.get_value()
+    }
+
     /// Gets the value stored in link. It attempts to get it from the store if it is not present in link.
     #[inline]
     pub async fn resolve_value(&self, store: &impl BlockStore) -> Result<&PublicNode> {
@@ -0,0 +1,220 @@
+//! Importing directory trees from the local file system.
+
+use super::{PublicDirectory, PublicFile, PublicLink};
+use crate::utils;
+use anyhow::Result;
+use async_recursion::async_recursion;
+use blocking::Unblock;
+use chrono::{DateTime, Utc};
+use std::{
+    fs::{self, File, FileType},
+    path::{Path, PathBuf},
+};
+use wnfs_common::{utils::Arc, BlockStore, Metadata};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The result of importing a local directory via `PublicDirectory::import_from_path`.
+#[derive(Debug)]
+pub struct LocalImport {
+    /// The directory containing all imported entries.
+    pub root: Arc<PublicDirectory>,
+    /// Descriptions of all local entries that were skipped during import.
+    pub warnings: Vec<String>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl PublicDirectory {
+    /// Imports the directory at given local path and everything below it as a new
+    /// public directory tree.
+    ///
+    /// Regular files and directories are imported with their modification times
+    /// from the local file system, or given time where that's not available.
+    /// File content is streamed from disk into the store in chunks. Entries that
+    /// can't be represented, like symlinks, device files or entries whose names
+    /// aren't valid UTF-8, get skipped and listed in [`LocalImport::warnings`].
+    /// Local file system calls run on a thread pool for blocking work, so they don't
+    /// block the async executor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{common::MemoryBlockStore, public::PublicDirectory};
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let import = PublicDirectory::import_from_path("./test".as_ref(), Utc::now(), store).await?;
+    ///
+    ///     assert!(!import.root.ls(&["fixtures".into()], store).await?.is_empty());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn import_from_path(
+        local: &Path,
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<LocalImport> {
+        let mut warnings = Vec::new();
+        let root = Self::import_dir(local, time, &mut warnings, store).await?;
+        Ok(LocalImport {
+            root: Arc::new(root),
+            warnings,
+        })
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), async_recursion)]
+    #[cfg_attr(target_arch = "wasm32", async_recursion(?Send))]
+    async fn import_dir(
+        local: &Path,
+        time: DateTime<Utc>,
+        warnings: &mut Vec<String>,
+        store: &impl BlockStore,
+    ) -> Result<Self> {
+        let mut dir = Self::new(time);
+        dir.metadata = Metadata::new(modified(local).await.unwrap_or(time));
+
+        for (path, file_type) in read_dir(local).await? {
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                warnings.push(format!(
+                    "{}: name isn't valid UTF-8, skipped",
+                    path.display()
+                ));
+                continue;
+            };
+            if let Err(e) = utils::validate_name(name) {
+                warnings.push(format!("{}: {e}, skipped", path.display()));
+                continue;
+            }
+
+            let name = name.to_string();
+            let link = if file_type.is_symlink() {
+                warnings.push(format!(
+                    "{}: symlinks are not supported, skipped",
+                    path.display()
+                ));
+                continue;
+            } else if file_type.is_dir() {
+                PublicLink::with_dir(Self::import_dir(&path, time, warnings, store).await?)
+            } else if file_type.is_file() {
+                let mtime = modified(&path).await.unwrap_or(time);
+                let file_path = path.clone();
+                let file = blocking::unblock(move || File::open(file_path)).await?;
+                PublicLink::with_file(
+                    PublicFile::with_content_streaming(mtime, Unblock::new(file), store).await?,
+                )
+            } else {
+                warnings.push(format!(
+                    "{}: special files are not supported, skipped",
+                    path.display()
+                ));
+                continue;
+            };
+
+            dir.userland.insert(name, link);
+        }
+
+        Ok(dir)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// The paths and types of the entries of given local directory, sorted, so warnings
+/// come in the same order on every platform.
+async fn read_dir(local: &Path) -> Result<Vec<(PathBuf, FileType)>> {
+    let local = local.to_path_buf();
+    let read = move || -> std::io::Result<_> {
+        let mut entries = fs::read_dir(local)?
+            .map(|entry| {
+                let entry = entry?;
+                Ok((entry.path(), entry.file_type()?))
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(entries)
+    };
+
+    Ok(blocking::unblock(read).await?)
+}
+
+/// The modification time of given local path, if the platform tracks it.
+async fn modified(local: &Path) -> Option<DateTime<Utc>> {
+    let local = local.to_path_buf();
+    let modified = blocking::unblock(move || fs::symlink_metadata(local)?.modified()).await;
+    Some(modified.ok()?.into())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::time::SystemTime;
+    use testresult::TestResult;
+    use wnfs_common::MemoryBlockStore;
+
+    #[async_std::test]
+    async fn local_trees_are_imported_with_their_mtimes() -> TestResult {
+        let local = tempfile::tempdir()?;
+        let last_year = Utc.with_ymd_and_hms(2023, 6, 1, 12, 0, 0).unwrap();
+        fs::create_dir_all(local.path().join("docs/drafts"))?;
+        fs::write(local.path().join("docs/notes.txt"), b"Notes")?;
+        fs::write(local.path().join("big.bin"), vec![7; 600 * 1024])?;
+        File::options()
+            .write(true)
+            .open(local.path().join("docs/notes.txt"))?
+            .set_modified(SystemTime::from(last_year))?;
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("docs/notes.txt", local.path().join("link.txt"))?;
+
+        let store = &MemoryBlockStore::new();
+        let import = PublicDirectory::import_from_path(local.path(), Utc::now(), store).await?;
+        let root = import.root;
+
+        let names = root
+            .ls(&[], store)
+            .await?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["big.bin", "docs"]);
+        let notes = &["docs".into(), "notes.txt".into()];
+        assert_eq!(root.read(notes, store).await?, b"Notes");
+        assert_eq!(
+            root.read(&["big.bin".into()], store).await?,
+            vec![7; 600 * 1024]
+        );
+        assert!(root
+            .ls(&["docs".into(), "drafts".into()], store)
+            .await?
+            .is_empty());
+
+        let node = root.get_node(notes, store).await?.unwrap();
+        assert_eq!(
+            node.as_file()?.get_metadata().get_modified(),
+            Some(last_year)
+        );
+
+        #[cfg(unix)]
+        {
+            assert_eq!(import.warnings.len(), 1);
+            assert!(import.warnings[0].contains("link.txt"));
+        }
+
+        Ok(())
+    }
+}
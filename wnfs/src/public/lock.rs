@@ -0,0 +1,339 @@
+//! Advisory in-process locks on subtrees of a public file system.
+
+use super::{PublicDirectory, PublicNode};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures::channel::oneshot;
+use parking_lot::Mutex;
+use wnfs_common::{
+    utils::{common_prefix, Arc},
+    BlockStore,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Hands out advisory locks on subtrees, so concurrent tasks modifying the same
+/// file system don't both rebuild the same directories and then have to merge.
+///
+/// A lock on a path covers the whole subtree below it. Locks on overlapping paths,
+/// i.e. where one path is a prefix of the other, wait for each other. Locks on
+/// disjoint paths can be held at the same time.
+///
+/// These locks are cooperative: Nothing stops code that doesn't take them from
+/// modifying a locked subtree. `PublicDirectory::write_locked` and its siblings
+/// take the lock covering what they modify for the duration of the operation.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs::public::SubtreeLockManager;
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let locks = SubtreeLockManager::new();
+///
+///     let pictures = locks.lock(&["pictures".into()]).await;
+///     let videos = locks.lock(&["videos".into()]).await;
+///
+///     assert!(locks.try_lock(&["pictures".into(), "cats".into()]).is_none());
+///
+///     drop(pictures);
+///
+///     assert!(locks.try_lock(&["pictures".into(), "cats".into()]).is_some());
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct SubtreeLockManager {
+    state: Mutex<LockState>,
+}
+
+/// A held lock on a subtree. The lock is released when this guard is dropped.
+#[derive(Debug)]
+pub struct SubtreeLockGuard<'a> {
+    manager: &'a SubtreeLockManager,
+    id: u64,
+    path: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct LockState {
+    next_id: u64,
+    held: Vec<(u64, Vec<String>)>,
+    waiters: Vec<oneshot::Sender<()>>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl SubtreeLockManager {
+    /// Creates a lock manager without any held locks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Locks the subtree at given path, waiting until no overlapping lock is held anymore.
+    pub async fn lock(&self, path_segments: &[String]) -> SubtreeLockGuard<'_> {
+        loop {
+            let released = {
+                let mut state = self.state.lock();
+                if let Some(guard) = self.acquire(&mut state, path_segments) {
+                    return guard;
+                }
+
+                let (sender, receiver) = oneshot::channel();
+                state.waiters.push(sender);
+                receiver
+            };
+
+            // Retry whenever any lock was released
+            let _ = released.await;
+        }
+    }
+
+    /// Locks the smallest subtree covering all given paths, i.e. the subtree at their
+    /// common parent directory. Useful before a batch of writes.
+    pub async fn lock_covering(&self, paths: &[Vec<String>]) -> SubtreeLockGuard<'_> {
+        self.lock(&common_prefix(paths)).await
+    }
+
+    /// Locks the subtree at given path if no overlapping lock is held, without waiting.
+    pub fn try_lock(&self, path_segments: &[String]) -> Option<SubtreeLockGuard<'_>> {
+        let mut state = self.state.lock();
+        self.acquire(&mut state, path_segments)
+    }
+
+    fn acquire(
+        &self,
+        state: &mut LockState,
+        path_segments: &[String],
+    ) -> Option<SubtreeLockGuard<'_>> {
+        let overlaps = state.held.iter().any(|(_, held)| {
+            held.iter()
+                .zip(path_segments)
+                .all(|(held, segment)| held == segment)
+        });
+
+        if overlaps {
+            return None;
+        }
+
+        let id = state.next_id;
+        state.next_id += 1;
+        state.held.push((id, path_segments.to_vec()));
+
+        Some(SubtreeLockGuard {
+            manager: self,
+            id,
+            path: path_segments.to_vec(),
+        })
+    }
+}
+
+impl PublicDirectory {
+    /// Writes a file like `write`, holding the lock on given path in given lock
+    /// manager while doing so.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::{PublicDirectory, SubtreeLockManager},
+    ///     common::MemoryBlockStore,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let locks = &SubtreeLockManager::new();
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let path = &["docs".into(), "notes.md".into()];
+    ///
+    ///     dir.write_locked(locks, path, b"Hello".to_vec(), Utc::now(), store)
+    ///         .await?;
+    ///
+    ///     assert_eq!(dir.read(path, store).await?, b"Hello");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn write_locked(
+        self: &mut Arc<Self>,
+        locks: &SubtreeLockManager,
+        path_segments: &[String],
+        content: Vec<u8>,
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let _guard = locks.lock(path_segments).await;
+        self.write(path_segments, content, time, store).await
+    }
+
+    /// Creates a directory like `mkdir`, holding the lock on given path in given
+    /// lock manager while doing so.
+    pub async fn mkdir_locked(
+        self: &mut Arc<Self>,
+        locks: &SubtreeLockManager,
+        path_segments: &[String],
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let _guard = locks.lock(path_segments).await;
+        self.mkdir(path_segments, time, store).await
+    }
+
+    /// Removes a node like `rm`, holding the lock on given path in given lock
+    /// manager while doing so.
+    pub async fn rm_locked(
+        self: &mut Arc<Self>,
+        locks: &SubtreeLockManager,
+        path_segments: &[String],
+        store: &impl BlockStore,
+    ) -> Result<PublicNode> {
+        let _guard = locks.lock(path_segments).await;
+        self.rm(path_segments, store).await
+    }
+
+    /// Moves a node like `basic_mv`, holding the lock covering both paths in
+    /// given lock manager while doing so.
+    pub async fn basic_mv_locked(
+        self: &mut Arc<Self>,
+        locks: &SubtreeLockManager,
+        path_segments_from: &[String],
+        path_segments_to: &[String],
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let _guard = locks
+            .lock_covering(&[path_segments_from.to_vec(), path_segments_to.to_vec()])
+            .await;
+        self.basic_mv(path_segments_from, path_segments_to, time, store)
+            .await
+    }
+}
+
+impl SubtreeLockGuard<'_> {
+    /// The path of the locked subtree.
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+}
+
+impl Drop for SubtreeLockGuard<'_> {
+    fn drop(&mut self) {
+        let mut state = self.manager.state.lock();
+        state.held.retain(|(id, _)| *id != self.id);
+        for waiter in state.waiters.drain(..) {
+            let _ = waiter.send(());
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::task;
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        time::Duration,
+    };
+    use testresult::TestResult;
+    use wnfs_common::MemoryBlockStore;
+
+    async fn write(locks: &SubtreeLockManager, path: &[&str], log: &Mutex<Vec<String>>) {
+        let path = path.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let name = path.join("/");
+        let _guard = locks.lock(&path).await;
+        log.lock().push(format!("start {name}"));
+        task::sleep(Duration::from_millis(20)).await;
+        log.lock().push(format!("end {name}"));
+    }
+
+    #[async_std::test]
+    async fn disjoint_writers_run_concurrently() {
+        let locks = &SubtreeLockManager::new();
+        let log = &Mutex::new(Vec::new());
+
+        futures::join!(
+            write(locks, &["a", "b"], log),
+            write(locks, &["a", "c"], log)
+        );
+
+        let log = log.lock();
+        assert_eq!(log[..2], ["start a/b", "start a/c"]);
+    }
+
+    #[async_std::test]
+    async fn overlapping_writers_run_one_after_another() {
+        let locks = &SubtreeLockManager::new();
+        let log = &Mutex::new(Vec::new());
+
+        futures::join!(
+            write(locks, &["a"], log),
+            write(locks, &["a", "b"], log),
+            write(locks, &["c"], log),
+        );
+
+        let log = log.lock();
+        let position = |entry: &str| log.iter().position(|e| e == entry).unwrap();
+        assert!(position("start c") < position("end a"));
+        assert!(position("end a") < position("start a/b"));
+    }
+
+    #[async_std::test]
+    async fn locked_writes_wait_for_overlapping_locks() -> TestResult {
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let locks = &SubtreeLockManager::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let written = &AtomicBool::new(false);
+
+        let guard = locks.lock(&path("docs")).await;
+        root.write_locked(locks, &path("pictures/cat.jpg"), vec![1], time, store)
+            .await?;
+
+        let (result, _) = futures::join!(
+            async {
+                let result = root
+                    .write_locked(locks, &path("docs/notes.md"), vec![2], time, store)
+                    .await;
+                written.store(true, Ordering::SeqCst);
+                result
+            },
+            async {
+                task::sleep(Duration::from_millis(20)).await;
+                assert!(!written.load(Ordering::SeqCst));
+                drop(guard);
+            }
+        );
+        result?;
+
+        assert_eq!(root.ls(&[], store).await?.len(), 2);
+        assert!(locks.try_lock(&[]).is_some());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn covering_lock_is_taken_on_the_common_parent() {
+        let locks = SubtreeLockManager::new();
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+
+        let guard = locks
+            .lock_covering(&[path("docs/a/x.md"), path("docs/b/y.md")])
+            .await;
+
+        assert_eq!(guard.path(), &["docs"]);
+        assert!(locks.try_lock(&path("docs/c")).is_none());
+        assert!(locks.try_lock(&path("pictures")).is_some());
+    }
+}
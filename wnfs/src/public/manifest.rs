@@ -0,0 +1,133 @@
+//! Human-readable JSON descriptions of public file system trees.
+
+use super::{PublicDirectory, PublicFile, PublicNode};
+use anyhow::Result;
+use async_recursion::async_recursion;
+use serde_json::{Map, Value};
+use wnfs_common::{BlockStore, Metadata, Storable};
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl PublicDirectory {
+    /// Describes this directory and every node below it as nested JSON objects,
+    /// for inspecting trees and comparing them in tests. This isn't a serialization
+    /// format, trees can't be loaded back from their manifest.
+    ///
+    /// Each node is described by its `type`, either `"dir"` or `"file"`, its `cid`
+    /// and its `mtime` as an RFC 3339 timestamp, or `null`. Directories also have
+    /// their `entries` by name, files their `content_cid` and `size` in bytes.
+    /// Keys are sorted, so manifests of the same tree are identical and can be
+    /// diffed. Nodes that weren't stored yet get stored to compute their CIDs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let store = &MemoryBlockStore::new();
+    ///     dir.write(&["notes.txt".into()], b"Hello".to_vec(), Utc::now(), store).await?;
+    ///
+    ///     let manifest = dir.to_manifest(store).await?;
+    ///
+    ///     assert_eq!(manifest["type"], "dir");
+    ///     assert_eq!(manifest["entries"]["notes.txt"]["size"], 5);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg_attr(not(target_arch = "wasm32"), async_recursion)]
+    #[cfg_attr(target_arch = "wasm32", async_recursion(?Send))]
+    pub async fn to_manifest(&self, store: &impl BlockStore) -> Result<Value> {
+        let mut entries = Map::new();
+        for (name, link) in self.userland.iter() {
+            let entry = match link.resolve_value(store).await? {
+                PublicNode::File(file) => file.to_manifest(store).await?,
+                PublicNode::Dir(dir) => dir.to_manifest(store).await?,
+            };
+            entries.insert(name.clone(), entry);
+        }
+
+        // Inserted in sorted order, in case serde_json preserves insertion order
+        let mut manifest = Map::new();
+        manifest.insert("cid".into(), self.store(store).await?.to_string().into());
+        manifest.insert("entries".into(), entries.into());
+        manifest.insert("mtime".into(), mtime(&self.metadata));
+        manifest.insert("type".into(), "dir".into());
+        Ok(manifest.into())
+    }
+}
+
+impl PublicFile {
+    /// Describes this file as a JSON object, like `PublicDirectory::to_manifest`.
+    pub async fn to_manifest(&self, store: &impl BlockStore) -> Result<Value> {
+        let mut manifest = Map::new();
+        manifest.insert("cid".into(), self.store(store).await?.to_string().into());
+        manifest.insert(
+            "content_cid".into(),
+            self.userland.resolve_cid(store).await?.to_string().into(),
+        );
+        manifest.insert("mtime".into(), mtime(&self.metadata));
+        manifest.insert("size".into(), self.size(store).await?.into());
+        manifest.insert("type".into(), "file".into());
+        Ok(manifest.into())
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+fn mtime(metadata: &Metadata) -> Value {
+    metadata
+        .get_modified()
+        .map_or(Value::Null, |time| time.to_rfc3339().into())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::public::golden::{golden_content, golden_time};
+    use testresult::TestResult;
+    use wnfs_common::MemoryBlockStore;
+
+    #[async_std::test]
+    async fn manifests_describe_the_whole_tree() -> TestResult {
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        let time = golden_time();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        root.write(&path("notes.txt"), b"Notes".to_vec(), time, store)
+            .await?;
+        root.write(
+            &path("pictures/cats/tabby.png"),
+            golden_content(1024),
+            time,
+            store,
+        )
+        .await?;
+        root.mkdir(&path("empty"), time, store).await?;
+
+        let manifest = root.to_manifest(store).await?;
+        assert_eq!(
+            serde_json::to_string(&manifest)?,
+            serde_json::to_string(&root.to_manifest(store).await?)?
+        );
+        insta::assert_json_snapshot!(manifest);
+
+        Ok(())
+    }
+}
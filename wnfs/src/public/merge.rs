@@ -0,0 +1,303 @@
+//! Merging divergent public directories by unioning their entries.
+
+use super::{PublicDirectory, PublicNode};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeSet;
+use wnfs_common::{
+    utils::{Arc, CondSync},
+    BlockStore, Metadata, MetadataMergePolicy,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Decides which node to keep when both directories merged by `PublicDirectory::merge`
+/// have different nodes under the same name, and they aren't both directories.
+///
+/// Closures taking a [`MergeConflict`] and returning a [`Resolution`] are resolvers too.
+pub trait ConflictResolver: CondSync {
+    /// Picks the node to keep for given conflict.
+    fn resolve(&self, conflict: &MergeConflict<'_>) -> Resolution;
+}
+
+/// Two different nodes under the same name, see [`ConflictResolver`].
+#[derive(Debug, Clone, Copy)]
+pub struct MergeConflict<'a> {
+    /// The path of both nodes, relative to the merged directories.
+    pub path: &'a [String],
+    /// The node in the directory `merge` was called on.
+    pub ours: &'a PublicNode,
+    /// The node in the other directory.
+    pub theirs: &'a PublicNode,
+}
+
+/// Which node a [`ConflictResolver`] keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Ours,
+    Theirs,
+}
+
+/// Settles the conflicts `reconcile` would tie-break when merging two directories
+/// via `PublicDirectory::merge_with_resolver`.
+#[derive(Clone, Copy)]
+pub(crate) struct TwoWayMerge<'a> {
+    pub(crate) resolver: &'a dyn ConflictResolver,
+    /// The modification time of the merged directories.
+    pub(crate) time: DateTime<Utc>,
+}
+
+/// The default [`ConflictResolver`], keeping the node modified most recently.
+/// If both were modified at the same time, our node is kept.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeepNewer;
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<F> ConflictResolver for F
+where
+    F: Fn(&MergeConflict<'_>) -> Resolution + CondSync,
+{
+    fn resolve(&self, conflict: &MergeConflict<'_>) -> Resolution {
+        self(conflict)
+    }
+}
+
+impl ConflictResolver for KeepNewer {
+    fn resolve(&self, conflict: &MergeConflict<'_>) -> Resolution {
+        if modified(conflict.theirs) > modified(conflict.ours) {
+            Resolution::Theirs
+        } else {
+            Resolution::Ours
+        }
+    }
+}
+
+impl PublicDirectory {
+    /// Merges this directory with another one, like one that was changed on another
+    /// replica concurrently, by unioning their entries recursively. Conflicts get
+    /// resolved by keeping the node modified most recently, see [`KeepNewer`].
+    ///
+    /// See `merge_with_resolver` for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::{MemoryBlockStore, Storable},
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let ours = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let theirs = &mut PublicDirectory::new_rc(Utc::now());
+    ///     ours.write(&["ours.txt".into()], b"Ours".to_vec(), Utc::now(), store).await?;
+    ///     theirs.write(&["theirs.txt".into()], b"Theirs".to_vec(), Utc::now(), store).await?;
+    ///
+    ///     let merged = ours.merge(theirs, Utc::now(), store).await?;
+    ///
+    ///     assert_eq!(merged.ls(&[], store).await?.len(), 2);
+    ///     assert_eq!(merged.get_previous().len(), 2);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn merge(
+        &self,
+        other: &Self,
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<Arc<Self>> {
+        self.merge_with_resolver(other, time, &KeepNewer, store)
+            .await
+    }
+
+    /// Merges this directory with another one by unioning their entries recursively,
+    /// asking given resolver which node to keep where both have different nodes under
+    /// the same name.
+    ///
+    /// Entries only one of the directories has are kept. Where one node is a newer
+    /// revision of the other, the newer one is kept. Directories both have under the
+    /// same name get merged the same way, all other nodes that differ, like files
+    /// with different content, are a conflict for the resolver. The merged directory
+    /// and all merged directories below it get both merged revisions as their previous
+    /// revisions, metadata combined like `reconcile` does, without snapshot labels,
+    /// and given time as their modification time.
+    ///
+    /// This is a two-way merge: It doesn't look for a common ancestor of the merged
+    /// directories, so an entry that one side removed since they diverged is brought
+    /// back by the other side. Use `reconcile` to merge revisions of the same history.
+    pub async fn merge_with_resolver(
+        &self,
+        other: &Self,
+        time: DateTime<Utc>,
+        resolver: &impl ConflictResolver,
+        store: &impl BlockStore,
+    ) -> Result<Arc<Self>> {
+        let merged = &mut Arc::new(self.clone());
+        let two_way = TwoWayMerge { resolver, time };
+        merged
+            .reconcile_helper(
+                &Arc::new(other.clone()),
+                MetadataMergePolicy::default(),
+                Some(two_way),
+                store,
+                &[],
+                &mut BTreeSet::new(),
+            )
+            .await?;
+
+        Ok(Arc::clone(merged))
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+fn modified(node: &PublicNode) -> Option<DateTime<Utc>> {
+    let metadata: &Metadata = match node {
+        PublicNode::File(file) => &file.metadata,
+        PublicNode::Dir(dir) => &dir.metadata,
+    };
+
+    metadata.get_modified()
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use testresult::TestResult;
+    use wnfs_common::{MemoryBlockStore, Storable};
+
+    #[async_std::test]
+    async fn disjoint_directories_merge_into_their_union() -> TestResult {
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let ours = &mut PublicDirectory::new_rc(time);
+        let theirs = &mut PublicDirectory::new_rc(time);
+        ours.write(&path("docs/a.txt"), b"a".to_vec(), time, store)
+            .await?;
+        ours.write(&path("shared.txt"), b"same".to_vec(), time, store)
+            .await?;
+        theirs
+            .write(&path("docs/b.txt"), b"b".to_vec(), time, store)
+            .await?;
+        theirs
+            .write(&path("pictures/cat.png"), b"cat".to_vec(), time, store)
+            .await?;
+        theirs
+            .write(&path("shared.txt"), b"same".to_vec(), time, store)
+            .await?;
+
+        let later = time + Duration::hours(1);
+        let merged = ours.merge(theirs, later, store).await?;
+
+        let names = |entries: Vec<(String, Metadata)>| {
+            entries
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(merged.ls(&[], store).await?),
+            ["docs", "pictures", "shared.txt"]
+        );
+        assert_eq!(
+            names(merged.ls(&path("docs"), store).await?),
+            ["a.txt", "b.txt"]
+        );
+        assert_eq!(merged.read(&path("pictures/cat.png"), store).await?, b"cat");
+        assert_eq!(
+            merged.get_previous(),
+            &BTreeSet::from([ours.store(store).await?, theirs.store(store).await?])
+        );
+        let modified = merged.get_metadata().get_modified().unwrap();
+        assert_eq!(modified.timestamp(), later.timestamp());
+        let docs = merged.get_node(&path("docs"), store).await?.unwrap();
+        assert_eq!(docs.get_previous().len(), 2);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn conflicting_files_are_decided_by_the_resolver() -> TestResult {
+        let notes = &["notes.txt".into()];
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let ours = &mut PublicDirectory::new_rc(time);
+        let theirs = &mut PublicDirectory::new_rc(time);
+        ours.write(notes, b"Ours".to_vec(), time, store).await?;
+        theirs
+            .write(notes, b"Theirs".to_vec(), time + Duration::hours(1), store)
+            .await?;
+
+        // By default, the newer file wins
+        let merged = ours.merge(theirs, time, store).await?;
+        assert_eq!(merged.read(notes, store).await?, b"Theirs");
+
+        let mut conflicts = Vec::new();
+        let seen = std::sync::Mutex::new(&mut conflicts);
+        let keep_ours = |conflict: &MergeConflict<'_>| {
+            seen.lock().unwrap().push(conflict.path.to_vec());
+            Resolution::Ours
+        };
+        let merged = ours
+            .merge_with_resolver(theirs, time, &keep_ours, store)
+            .await?;
+        assert_eq!(merged.read(notes, store).await?, b"Ours");
+        assert_eq!(conflicts, vec![notes.to_vec()]);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn merged_directories_carry_no_snapshot_labels() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let ours = &mut PublicDirectory::new_rc(time);
+        let theirs = &mut PublicDirectory::new_rc(time + Duration::hours(1));
+        ours.write(&["ours.txt".into()], b"Ours".to_vec(), time, store)
+            .await?;
+        theirs
+            .write(&["theirs.txt".into()], b"Theirs".to_vec(), time, store)
+            .await?;
+        ours.snapshot_labeled("ours", store).await?;
+        theirs.snapshot_labeled("theirs", store).await?;
+
+        let later = time + Duration::hours(2);
+        let merged = ours.merge(theirs, later, store).await?;
+        let metadata = merged.get_metadata();
+        assert_eq!(metadata.get_snapshot_label(), None);
+        assert_eq!(
+            metadata.get_modified().unwrap().timestamp(),
+            later.timestamp()
+        );
+
+        // Both sides stay findable by their labels
+        assert_eq!(
+            merged.find_snapshot("theirs", store).await?,
+            Some(theirs.store(store).await?)
+        );
+        assert_eq!(
+            merged.find_snapshot("ours", store).await?,
+            Some(ours.store(store).await?)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+//! Copying public file system trees from one block store to another.
+
+use super::{
+    verify::{block_links, BlockKind},
+    PublicDirectory,
+};
+use anyhow::Result;
+use std::collections::{BTreeSet, VecDeque};
+use wnfs_common::{
+    utils::{Arc, CondSend},
+    BlockStore, Storable,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// How far a `PublicDirectory::deep_clone_to_store_with_progress` call got.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CloneProgress {
+    /// The number of distinct blocks of the tree that were looked at so far.
+    pub visited: usize,
+    /// The number of blocks that were missing in the destination store and got copied.
+    pub copied: usize,
+    /// The total size of the copied blocks in bytes.
+    pub copied_bytes: u64,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl PublicDirectory {
+    /// Copies all blocks reachable from this directory from the source block store,
+    /// i.e. the store this directory was loaded from or written to, into the
+    /// destination block store. Returns this directory loaded from the destination store.
+    ///
+    /// This includes file content, file sidecars, tombstones of removed entries and
+    /// previous revisions. Blocks that already exist in the destination store aren't
+    /// copied again. Changes that weren't stored yet get stored into the source store
+    /// first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::MemoryBlockStore,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let old_store = &MemoryBlockStore::new();
+    ///     let new_store = &MemoryBlockStore::new();
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let path = &["notes.txt".into()];
+    ///     dir.write(path, b"Hello".to_vec(), Utc::now(), old_store).await?;
+    ///
+    ///     let migrated = dir.deep_clone_to_store(old_store, new_store).await?;
+    ///
+    ///     assert_eq!(migrated.read(path, new_store).await?, b"Hello");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn deep_clone_to_store(
+        &self,
+        src: &impl BlockStore,
+        dst: &impl BlockStore,
+    ) -> Result<Arc<Self>> {
+        self.deep_clone_to_store_with_progress(src, dst, |_| {})
+            .await
+    }
+
+    /// Copies all blocks reachable from this directory into the destination block store
+    /// like `deep_clone_to_store` does, calling given function after each visited block.
+    pub async fn deep_clone_to_store_with_progress(
+        &self,
+        src: &impl BlockStore,
+        dst: &impl BlockStore,
+        mut on_progress: impl FnMut(&CloneProgress) + CondSend,
+    ) -> Result<Arc<Self>> {
+        let root = self.store(src).await?;
+
+        let mut progress = CloneProgress::default();
+        let mut visited = BTreeSet::new();
+        let mut queue = VecDeque::from([(root, BlockKind::Node)]);
+        while let Some((cid, kind)) = queue.pop_front() {
+            if !visited.insert(cid) {
+                continue;
+            }
+
+            let bytes = src.get_block(&cid).await?;
+            progress.visited += 1;
+
+            if !dst.has_block(&cid).await? {
+                progress.copied += 1;
+                progress.copied_bytes += bytes.len() as u64;
+                dst.put_block_keyed(cid, bytes.clone()).await?;
+            }
+
+            queue.extend(block_links(&cid, bytes, kind, true)?);
+            on_progress(&progress);
+        }
+
+        Ok(Arc::new(Self::load(&root, dst).await?))
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::public::{golden::golden_content, verify_import};
+    use chrono::Utc;
+    use testresult::TestResult;
+    use wnfs_common::MemoryBlockStore;
+
+    #[async_std::test]
+    async fn deep_clones_copy_everything_once() -> TestResult {
+        let time = Utc::now();
+        let src = &MemoryBlockStore::new();
+        let dst = &MemoryBlockStore::new();
+        let dir = &mut PublicDirectory::new_rc(time);
+        let big = &["pictures".into(), "big.bin".into()];
+        dir.write(big, golden_content(1_000_000), time, src).await?;
+        dir.store(src).await?;
+        dir.write(&["notes.txt".into()], b"Hello".to_vec(), time, src)
+            .await?;
+
+        let mut updates = Vec::new();
+        let migrated = dir
+            .deep_clone_to_store_with_progress(src, dst, |progress| updates.push(*progress))
+            .await?;
+
+        let root = migrated.store(dst).await?;
+        assert_eq!(root, dir.store(src).await?);
+        assert_eq!(migrated.read(big, dst).await?, golden_content(1_000_000));
+
+        let report = verify_import(&root, dst).await?;
+        assert!(report.is_ok());
+        let last = updates.last().unwrap();
+        assert_eq!(last.visited, report.visited);
+        assert_eq!(last.copied, report.visited);
+        assert_eq!(updates.len(), report.visited);
+
+        let mut updates = Vec::new();
+        dir.deep_clone_to_store_with_progress(src, dst, |progress| updates.push(*progress))
+            .await?;
+        assert_eq!(updates.last().unwrap().copied, 0);
+
+        Ok(())
+    }
+}
@@ -1,11 +1,51 @@
 //! Primitives for working with the public file system.
 
+mod audit;
+mod bound;
+mod compare;
+mod config;
+mod diff;
 mod directory;
 mod file;
+pub mod golden;
+mod lazy;
 mod link;
+#[cfg(all(feature = "disk", not(target_arch = "wasm32")))]
+mod local;
+mod lock;
+mod manifest;
+mod merge;
+mod migrate;
 mod node;
+mod normalization;
+mod ops;
+mod proof;
+mod selector;
+mod tar;
+mod tombstone;
+mod unixfs;
+mod verify;
 
+pub use audit::*;
+pub use bound::*;
+pub use compare::*;
+pub use config::*;
+pub use diff::*;
 pub use directory::*;
 pub use file::*;
+pub use lazy::*;
 pub use link::*;
+#[cfg(all(feature = "disk", not(target_arch = "wasm32")))]
+pub use local::*;
+pub use lock::*;
+pub use merge::*;
+pub use migrate::*;
 pub use node::*;
+pub use normalization::*;
+pub use ops::*;
+pub use proof::*;
+pub use selector::*;
+pub use tar::*;
+pub use tombstone::*;
+pub use unixfs::*;
+pub use verify::*;
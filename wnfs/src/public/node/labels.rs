@@ -0,0 +1,238 @@
+use super::PublicNode;
+use anyhow::Result;
+use libipld_core::cid::Cid;
+#[cfg(feature = "custom-link-labels")]
+use libipld_core::ipld::Ipld;
+use wnfs_common::BlockStore;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The labels used for the links of a public node to its content and to its previous revisions.
+///
+/// The default labels are the field names of the WNFS data format, `userland` and `previous`.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs::public::LinkLabels;
+///
+/// let labels = LinkLabels::default();
+///
+/// assert_eq!(labels.content, "userland");
+/// assert_eq!(labels.previous, "previous");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkLabels {
+    /// The label for links to file content or directory entries.
+    pub content: String,
+    /// The label for links to previous revisions.
+    pub previous: String,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl LinkLabels {
+    /// Creates link labels with custom names for content and previous links.
+    pub fn new(content: impl Into<String>, previous: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            previous: previous.into(),
+        }
+    }
+
+    /// Whether these are the labels of the WNFS data format.
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+impl Default for LinkLabels {
+    fn default() -> Self {
+        Self::new("userland", "previous")
+    }
+}
+
+impl PublicNode {
+    /// Returns all links of this node together with their labels.
+    ///
+    /// Files have a single content link labeled `<content>`, directories have
+    /// one link labeled `<content>/<name>` per entry. Previous revisions are
+    /// labeled `<previous>/<index>`.
+    /// With the default labels, these are the IPLD paths of the links within
+    /// the serialized node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::{LinkLabels, PublicDirectory, PublicNode},
+    ///     common::MemoryBlockStore,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     dir.mkdir(&["pictures".into()], Utc::now(), store).await?;
+    ///
+    ///     let node = PublicNode::Dir(dir.clone());
+    ///     let links = node.links(&LinkLabels::new("entries", "parents"), store).await?;
+    ///
+    ///     assert_eq!(links[0].0, "entries/pictures");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn links(
+        &self,
+        labels: &LinkLabels,
+        store: &impl BlockStore,
+    ) -> Result<Vec<(String, Cid)>> {
+        let mut links = Vec::new();
+        match self {
+            Self::File(file) => {
+                links.push((
+                    labels.content.clone(),
+                    file.userland.resolve_cid(store).await?,
+                ));
+            }
+            Self::Dir(dir) => {
+                for (name, link) in dir.userland.iter() {
+                    let label = format!("{}/{name}", labels.content);
+                    links.push((label, link.resolve_cid(store).await?));
+                }
+            }
+        }
+
+        for (index, cid) in self.get_previous().iter().enumerate() {
+            links.push((format!("{}/{index}", labels.previous), *cid));
+        }
+
+        Ok(links)
+    }
+
+    /// Stores this node with its content and previous links stored under given labels
+    /// instead of the field names of the WNFS data format.
+    ///
+    /// Nodes stored with non-default labels are **not** compatible with WNFS:
+    /// They have different CIDs than the same node stored via `store` and can't be
+    /// loaded again via `PublicNode::load`. This is only meant for handing DAGs to tools
+    /// that expect specific link names. Only this node is relabeled, its children
+    /// are referenced by their regular CIDs.
+    ///
+    /// With the default labels, this is equivalent to `store`.
+    #[cfg(feature = "custom-link-labels")]
+    pub async fn store_with_labels(
+        &self,
+        labels: &LinkLabels,
+        store: &impl BlockStore,
+    ) -> Result<Cid> {
+        use wnfs_common::{encode, libipld::cbor::DagCborCodec, Storable};
+
+        if labels.is_default() {
+            return self.store(store).await;
+        }
+
+        let mut ipld = libipld_core::serde::to_ipld(self.to_serializable(store).await?)?;
+        if let Ipld::Map(node) = &mut ipld {
+            for fields in node.values_mut() {
+                if let Ipld::Map(fields) = fields {
+                    relabel(fields, "userland", &labels.content);
+                    relabel(fields, "previous", &labels.previous);
+                }
+            }
+        }
+
+        let bytes = encode(&ipld, DagCborCodec)?;
+        Ok(store.put_block(bytes, DagCborCodec.into()).await?)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(feature = "custom-link-labels")]
+fn relabel(fields: &mut std::collections::BTreeMap<String, Ipld>, from: &str, to: &str) {
+    if let Some(value) = fields.remove(from) {
+        fields.insert(to.to_string(), value);
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::public::{PublicDirectory, PublicFile};
+    use chrono::Utc;
+    use testresult::TestResult;
+    use wnfs_common::{utils::Arc, MemoryBlockStore, Storable};
+
+    #[async_std::test]
+    async fn links_are_labeled_by_given_scheme() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let file = &mut PublicFile::with_content_rc(time, b"Hello".to_vec(), store).await?;
+        let previous = file.store(store).await?;
+        file.prepare_next_revision()
+            .set_content(b"World".to_vec(), time, store)
+            .await?;
+        let content = file.userland.resolve_cid(store).await?;
+
+        let node = PublicNode::File(Arc::clone(file));
+        let labels = LinkLabels::new("content", "parents");
+
+        assert_eq!(
+            node.links(&labels, store).await?,
+            vec![("content".into(), content), ("parents/0".into(), previous)]
+        );
+
+        let dir = &mut PublicDirectory::new_rc(time);
+        dir.write(&["a.txt".into()], b"a".to_vec(), time, store)
+            .await?;
+        let node = PublicNode::Dir(Arc::clone(dir));
+        let links = node.links(&LinkLabels::default(), store).await?;
+
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].0, "userland/a.txt");
+
+        Ok(())
+    }
+
+    #[cfg(feature = "custom-link-labels")]
+    #[async_std::test]
+    async fn custom_labels_change_the_stored_block() -> TestResult {
+        use wnfs_common::{decode, libipld::cbor::DagCborCodec};
+
+        let store = &MemoryBlockStore::new();
+        let node = PublicNode::File(PublicFile::new_rc(Utc::now()));
+
+        let default_cid = node
+            .store_with_labels(&LinkLabels::default(), store)
+            .await?;
+        assert_eq!(default_cid, node.store(store).await?);
+
+        let labels = LinkLabels::new("content", "parents");
+        let custom_cid = node.store_with_labels(&labels, store).await?;
+        assert_ne!(default_cid, custom_cid);
+
+        let ipld: Ipld = decode(&store.get_block(&custom_cid).await?, DagCborCodec)?;
+        let Ipld::Map(fields) = ipld.get("wnfs/pub/file")? else {
+            panic!("relabeled node isn't a map");
+        };
+
+        assert!(fields.contains_key("content") && fields.contains_key("parents"));
+        assert!(!fields.contains_key("userland") && !fields.contains_key("previous"));
+
+        Ok(())
+    }
+}
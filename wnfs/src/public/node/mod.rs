@@ -1,6 +1,8 @@
+mod labels;
 #[allow(clippy::module_inception)]
 mod node;
 mod serializable;
 
+pub use labels::*;
 pub use node::*;
 pub(crate) use serializable::*;
@@ -3,15 +3,31 @@
 use super::PublicNodeSerializable;
 use crate::{
     error::FsError,
-    public::{PublicDirectory, PublicFile},
+    public::{
+        verify::{block_links, BlockKind},
+        PublicDirectory, PublicFile,
+    },
     traits::Id,
+    NodeKind,
 };
 use anyhow::{bail, Result};
 use async_once_cell::OnceCell;
+use async_recursion::async_recursion;
+use async_stream::try_stream;
+use bytes::Bytes;
 use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt, TryStreamExt};
 use libipld_core::cid::Cid;
-use std::{cmp::Ordering, collections::BTreeSet};
-use wnfs_common::{utils::Arc, BlockStore, Storable};
+use parking_lot::Mutex;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, VecDeque},
+};
+use wnfs_common::{
+    libipld::Ipld,
+    utils::{boxed_fut, Arc, CondSend},
+    AccountingBlockStore, BlockStore, BlockStoreError, Storable, CODEC_RAW,
+};
 
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
@@ -36,6 +52,29 @@ pub enum PublicNode {
     Dir(Arc<PublicDirectory>),
 }
 
+/// Progress of `PublicNode::store_with_progress`, reported after each new block
+/// written and each node skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreProgress {
+    /// The path of the node, relative to the node being stored.
+    pub path: Vec<String>,
+    /// Whether the node was skipped because it was already stored.
+    pub skipped: bool,
+    /// The number of new blocks written so far.
+    pub blocks_written: u64,
+    /// The total size in bytes of the new blocks written so far.
+    pub bytes_written: u64,
+}
+
+/// Reports the progress of `PublicNode::store_with_progress` from within the block
+/// store, so storing a node with many content blocks is reported block by block.
+struct ProgressBlockStore<B, F> {
+    accounting: AccountingBlockStore<B>,
+    /// The path of the node currently being stored.
+    path: Mutex<Vec<String>>,
+    progress: Mutex<F>,
+}
+
 //--------------------------------------------------------------------------------------------------
 // Implementations
 //--------------------------------------------------------------------------------------------------
@@ -67,14 +106,104 @@ impl PublicNode {
     pub fn upsert_mtime(&mut self, time: DateTime<Utc>) {
         match self {
             Self::File(file) => {
-                Arc::make_mut(file).metadata.upsert_mtime(time);
+                Arc::make_mut(file).get_metadata_mut().upsert_mtime(time);
             }
             Self::Dir(dir) => {
-                Arc::make_mut(dir).metadata.upsert_mtime(time);
+                Arc::make_mut(dir).get_metadata_mut().upsert_mtime(time);
             }
         }
     }
 
+    /// Upserts the modified time like `upsert_mtime`, but in a new revision of the
+    /// node if it was stored before, so its previous revision is the stored one.
+    pub(crate) fn upsert_mtime_in_next_revision(&mut self, time: DateTime<Utc>) {
+        match self {
+            Self::File(file) => file.prepare_next_revision().metadata.upsert_mtime(time),
+            Self::Dir(dir) => dir.prepare_next_revision().metadata.upsert_mtime(time),
+        }
+    }
+
+    /// Sets an attribute in the metadata of this node, see `Metadata::set_attribute`,
+    /// and returns the value it had before. The node moves on to its next revision.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::public::{PublicFile, PublicNode};
+    /// use chrono::Utc;
+    /// use libipld_core::ipld::Ipld;
+    ///
+    /// let node = &mut PublicNode::File(PublicFile::new_rc(Utc::now()));
+    /// node.set_attribute("content-type", Ipld::String("text/plain".into()));
+    ///
+    /// assert_eq!(
+    ///     node.get_attribute("content-type"),
+    ///     Some(&Ipld::String("text/plain".into()))
+    /// );
+    /// ```
+    pub fn set_attribute(&mut self, key: &str, value: Ipld) -> Option<Ipld> {
+        match self {
+            Self::File(file) => file
+                .prepare_next_revision()
+                .metadata
+                .set_attribute(key, value),
+            Self::Dir(dir) => dir
+                .prepare_next_revision()
+                .metadata
+                .set_attribute(key, value),
+        }
+    }
+
+    /// Returns the attribute with given key from the metadata of this node.
+    pub fn get_attribute(&self, key: &str) -> Option<&Ipld> {
+        match self {
+            Self::File(file) => file.metadata.get_attribute(key),
+            Self::Dir(dir) => dir.metadata.get_attribute(key),
+        }
+    }
+
+    /// Removes the attribute with given key from the metadata of this node and returns
+    /// its value. The node moves on to its next revision if it had the attribute.
+    pub fn remove_attribute(&mut self, key: &str) -> Option<Ipld> {
+        self.get_attribute(key)?;
+        match self {
+            Self::File(file) => file.prepare_next_revision().metadata.remove_attribute(key),
+            Self::Dir(dir) => dir.prepare_next_revision().metadata.remove_attribute(key),
+        }
+    }
+
+    /// Whether this node has changes that weren't stored yet, i.e. whether it
+    /// wasn't stored since it was created or last changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::{PublicDirectory, PublicNode},
+    ///     common::{MemoryBlockStore, Storable},
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let node = &mut PublicNode::Dir(PublicDirectory::new_rc(Utc::now()));
+    ///     assert!(node.is_dirty());
+    ///
+    ///     node.store(store).await?;
+    ///     assert!(!node.is_dirty());
+    ///
+    ///     node.upsert_mtime(Utc::now());
+    ///     assert!(node.is_dirty());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn is_dirty(&self) -> bool {
+        self.persisted_as().and_then(OnceCell::get).is_none()
+    }
+
     /// Creates node with updated previous pointer value.
     ///
     /// # Examples
@@ -138,6 +267,291 @@ impl PublicNode {
         }
     }
 
+    /// Streams the previous revisions of this node, newest first, by following
+    /// the previous links back until there are none left.
+    ///
+    /// The revisions are loaded breadth-first: First all previous revisions of this
+    /// node in the order of their CIDs, then all of their previous revisions, and so
+    /// on. Revisions that multiple merge nodes link to are yielded only once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use futures::TryStreamExt;
+    /// use wnfs::{
+    ///     public::{PublicDirectory, PublicNode},
+    ///     common::{MemoryBlockStore, Storable},
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     for content in ["One", "Two", "Three"] {
+    ///         dir.write(&["notes.txt".into()], content.into(), Utc::now(), store).await?;
+    ///         dir.store(store).await?;
+    ///     }
+    ///
+    ///     let node = PublicNode::Dir(dir.clone());
+    ///     let history: Vec<PublicNode> = node.history(store).try_collect().await?;
+    ///
+    ///     assert_eq!(history.len(), 2);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn history<'a>(
+        &'a self,
+        store: &'a impl BlockStore,
+    ) -> impl Stream<Item = Result<PublicNode>> + 'a {
+        Self::history_from(self.get_previous(), store)
+    }
+
+    /// Streams the revisions reachable from given previous links, like `history`.
+    /// Revisions are loaded from the store, so they know their own CIDs.
+    pub(crate) fn history_from<'a>(
+        previous: &BTreeSet<Cid>,
+        store: &'a impl BlockStore,
+    ) -> impl Stream<Item = Result<PublicNode>> + 'a {
+        let mut queue = VecDeque::from_iter(previous.iter().copied());
+        try_stream! {
+            let mut visited = BTreeSet::new();
+            while let Some(cid) = queue.pop_front() {
+                if !visited.insert(cid) {
+                    continue;
+                }
+
+                let node = PublicNode::load(&cid, store).await?;
+                queue.extend(node.get_previous().iter().copied());
+                yield node;
+            }
+        }
+    }
+
+    /// Stores this node like `store` does, but stores up to `concurrency` nodes at
+    /// the same time, which is a lot faster with block stores that have latency.
+    ///
+    /// Nodes that weren't stored yet are stored one level of the tree at a time,
+    /// starting with the deepest, so every node's children are stored before it.
+    /// Nodes that are already stored are skipped, along with everything below them.
+    /// The resulting CID is the same `store` returns. A `concurrency` of zero is
+    /// treated as one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::{PublicDirectory, PublicNode},
+    ///     common::{MemoryBlockStore, Storable},
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     dir.write(&["a".into(), "notes.txt".into()], b"A".to_vec(), Utc::now(), store).await?;
+    ///     dir.write(&["b".into(), "notes.txt".into()], b"B".to_vec(), Utc::now(), store).await?;
+    ///
+    ///     let node = PublicNode::Dir(dir.clone());
+    ///     let cid = node.store_parallel(store, 8).await?;
+    ///
+    ///     assert_eq!(cid, dir.store(store).await?);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn store_parallel(&self, store: &impl BlockStore, concurrency: usize) -> Result<Cid> {
+        let mut levels = Vec::new();
+        let mut level = vec![self];
+        while !level.is_empty() {
+            let below = level
+                .iter()
+                .filter_map(|node| match node {
+                    Self::Dir(dir) => Some(dir.userland.values()),
+                    Self::File(_) => None,
+                })
+                .flatten()
+                .filter(|link| link.get_cid().is_none())
+                .filter_map(|link| link.get_value())
+                .collect::<Vec<_>>();
+
+            levels.push(level);
+            level = below;
+        }
+
+        // The root is the only node of the first level
+        for level in levels.iter().skip(1).rev() {
+            futures::stream::iter(level)
+                .map(|node| node.store(store))
+                .buffer_unordered(concurrency.max(1))
+                .try_collect::<Vec<_>>()
+                .await?;
+        }
+
+        self.store(store).await
+    }
+
+    /// Stores this node like `store` does, calling `progress` after each block that
+    /// was new to the block store, e.g. for rendering a progress bar.
+    ///
+    /// Nodes are stored children first, in order of their paths, and blocks are
+    /// reported with the path of the node they're stored for. That includes file
+    /// content blocks that only get stored along with their file, so a big file
+    /// reports progress chunk by chunk. Nodes that are already stored are reported
+    /// once as skipped, and nothing below them is visited, as are nodes storing
+    /// which didn't write any new blocks. After the last report, progress adds up
+    /// to exactly what was written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::{PublicDirectory, PublicNode},
+    ///     common::MemoryBlockStore,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     dir.write(&["docs".into(), "notes.txt".into()], b"Hello".to_vec(), Utc::now(), store)
+    ///         .await?;
+    ///
+    ///     let mut reports = Vec::new();
+    ///     let node = PublicNode::Dir(dir.clone());
+    ///     node.store_with_progress(store, |progress| reports.push(progress)).await?;
+    ///
+    ///     let paths = reports.iter().map(|p| p.path.join("/")).collect::<Vec<_>>();
+    ///     assert_eq!(paths, ["docs/notes.txt", "docs", ""]);
+    ///     assert_eq!(reports.last().unwrap().blocks_written, 3);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn store_with_progress(
+        &self,
+        store: &impl BlockStore,
+        progress: impl FnMut(StoreProgress) + CondSend,
+    ) -> Result<Cid> {
+        // Pre-order with the last child first, so reversed it's post-order by path
+        let mut pending = Vec::new();
+        let mut stack = vec![(Vec::new(), Some(self))];
+        while let Some((path, node)) = stack.pop() {
+            let is_stored = match node {
+                Some(node) => node.persisted_as().and_then(OnceCell::get).is_some(),
+                None => true,
+            };
+            if let (false, Some(Self::Dir(dir))) = (is_stored, node) {
+                for (name, link) in dir.userland.iter() {
+                    let mut child_path = path.clone();
+                    child_path.push(name.clone());
+                    let child = link.get_cid().is_none().then(|| link.get_value()).flatten();
+                    stack.push((child_path, child));
+                }
+            }
+            pending.push((path, node.filter(|_| !is_stored)));
+        }
+
+        let reporting = ProgressBlockStore {
+            accounting: AccountingBlockStore::new(store),
+            path: Mutex::default(),
+            progress: Mutex::new(progress),
+        };
+        for (path, node) in pending.into_iter().rev() {
+            let Some(node) = node else {
+                reporting.report(path, true);
+                continue;
+            };
+
+            let before = reporting.accounting.stats();
+            *reporting.path.lock() = path;
+            // Boxing the future, since storing nodes makes for large futures
+            boxed_fut(node.store(&reporting)).await?;
+            if reporting.accounting.stats() == before {
+                let path = std::mem::take(&mut *reporting.path.lock());
+                reporting.report(path, false);
+            }
+        }
+
+        self.store(store).await
+    }
+
+    /// Returns the CIDs of all blocks this node transitively links to, including
+    /// the block of this node itself, e.g. to find out which blocks a garbage
+    /// collector needs to keep.
+    ///
+    /// That covers the blocks of all nodes below this one, file content DAGs and
+    /// anything else that's linked to, like sidecars. Previous revisions aren't
+    /// included, see `reachable_cids_with_history` for that. The node gets stored
+    /// first, if it wasn't already.
+    ///
+    /// Blocks are decoded as a public node or file content, whichever they're linked
+    /// to as. Raw blocks can't link to anything, so they aren't loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::{PublicDirectory, PublicNode},
+    ///     common::{MemoryBlockStore, Storable},
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     dir.write(&["notes.txt".into()], b"Hello".to_vec(), Utc::now(), store).await?;
+    ///
+    ///     let node = PublicNode::Dir(dir.clone());
+    ///     let reachable = node.reachable_cids(store).await?;
+    ///
+    ///     // The directory, the file and its content
+    ///     assert_eq!(reachable.len(), 3);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn reachable_cids(&self, store: &impl BlockStore) -> Result<BTreeSet<Cid>> {
+        self.collect_reachable_cids(false, store).await
+    }
+
+    /// Like `reachable_cids`, but also includes previous revisions of this node
+    /// and of all nodes below it, and everything they link to.
+    pub async fn reachable_cids_with_history(
+        &self,
+        store: &impl BlockStore,
+    ) -> Result<BTreeSet<Cid>> {
+        self.collect_reachable_cids(true, store).await
+    }
+
+    async fn collect_reachable_cids(
+        &self,
+        include_history: bool,
+        store: &impl BlockStore,
+    ) -> Result<BTreeSet<Cid>> {
+        let mut reachable = BTreeSet::new();
+        let mut queue = VecDeque::from([(self.store(store).await?, BlockKind::Node)]);
+        while let Some((cid, kind)) = queue.pop_front() {
+            // Also guards against cycles in malformed DAGs
+            if !reachable.insert(cid) || cid.codec() == CODEC_RAW {
+                continue;
+            }
+
+            let bytes = store.get_block(&cid).await?;
+            queue.extend(block_links(&cid, bytes, kind, include_history)?);
+        }
+
+        Ok(reachable)
+    }
+
     /// Casts a node to a directory.
     ///
     /// # Examples
@@ -230,6 +644,91 @@ impl PublicNode {
         matches!(self, Self::File(_))
     }
 
+    /// Returns the kind of the underlying node, for matching exhaustively.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{public::{PublicFile, PublicNode}, NodeKind};
+    /// use chrono::Utc;
+    ///
+    /// let node = PublicNode::File(PublicFile::new_rc(Utc::now()));
+    ///
+    /// match node.kind() {
+    ///     NodeKind::File => println!("A file"),
+    ///     NodeKind::Dir => println!("A directory"),
+    /// }
+    /// ```
+    pub fn kind(&self) -> NodeKind {
+        match self {
+            Self::File(_) => NodeKind::File,
+            Self::Dir(_) => NodeKind::Dir,
+        }
+    }
+
+    /// Estimates the total content size in bytes of all files in this subtree.
+    ///
+    /// This is an estimate, not an exact figure: Directories with more than `sample`
+    /// entries only have `sample` evenly spread entries visited, and their total is
+    /// extrapolated from those. Subtrees in which no directory has more than `sample`
+    /// entries are computed exactly.
+    ///
+    /// This doesn't load any content blocks except the content DAG roots of visited files.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::{PublicDirectory, PublicNode},
+    ///     common::MemoryBlockStore,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     for i in 0..100 {
+    ///         dir.write(&[format!("{i}.txt")], vec![0; 10], Utc::now(), store)
+    ///             .await?;
+    ///     }
+    ///
+    ///     let node = PublicNode::Dir(dir.clone());
+    ///
+    ///     assert_eq!(node.estimate_subtree_size(store, 10).await?, 1000);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg_attr(not(target_arch = "wasm32"), async_recursion)]
+    #[cfg_attr(target_arch = "wasm32", async_recursion(?Send))]
+    pub async fn estimate_subtree_size(
+        &self,
+        store: &impl BlockStore,
+        sample: usize,
+    ) -> Result<u64> {
+        let dir = match self {
+            Self::File(file) => return file.size(store).await,
+            Self::Dir(dir) => dir,
+        };
+
+        let entries = dir.userland.len();
+        let visited = sample.clamp(1, entries.max(1));
+
+        let mut total = 0;
+        for index in 0..visited {
+            // Spread the sampled entries evenly across all entries
+            let link = dir.userland.values().nth(index * entries / visited);
+            if let Some(link) = link {
+                let node = link.resolve_value(store).await?;
+                total += node.estimate_subtree_size(store, sample).await?;
+            }
+        }
+
+        Ok(total * entries as u64 / visited as u64)
+    }
+
     /// Comparing the merkle clocks of this node to the other node.
     ///
     /// This gives you information about which node is "ahead" of which other node
@@ -344,6 +843,46 @@ impl From<PublicDirectory> for PublicNode {
     }
 }
 
+impl<B: BlockStore, F: FnMut(StoreProgress) + CondSend> ProgressBlockStore<B, F> {
+    fn report(&self, path: Vec<String>, skipped: bool) {
+        let stats = self.accounting.stats();
+        (self.progress.lock())(StoreProgress {
+            path,
+            skipped,
+            blocks_written: stats.new_blocks,
+            bytes_written: stats.new_bytes,
+        });
+    }
+}
+
+impl<B: BlockStore, F: FnMut(StoreProgress) + CondSend> BlockStore for ProgressBlockStore<B, F> {
+    async fn get_block(&self, cid: &Cid) -> Result<Bytes, BlockStoreError> {
+        self.accounting.get_block(cid).await
+    }
+
+    async fn put_block_keyed(
+        &self,
+        cid: Cid,
+        bytes: impl Into<Bytes> + CondSend,
+    ) -> Result<(), BlockStoreError> {
+        let before = self.accounting.stats();
+        self.accounting.put_block_keyed(cid, bytes).await?;
+        if self.accounting.stats() != before {
+            self.report(self.path.lock().clone(), false);
+        }
+
+        Ok(())
+    }
+
+    async fn has_block(&self, cid: &Cid) -> Result<bool, BlockStoreError> {
+        self.accounting.has_block(cid).await
+    }
+
+    fn create_cid(&self, bytes: &[u8], codec: u64) -> Result<Cid, BlockStoreError> {
+        self.accounting.create_cid(bytes, codec)
+    }
+}
+
 impl Storable for PublicNode {
     type Serializable = PublicNodeSerializable;
 
@@ -385,8 +924,166 @@ impl Storable for PublicNode {
 mod tests {
     use crate::public::{PublicDirectory, PublicFile, PublicNode};
     use chrono::Utc;
+    use futures::TryStreamExt;
+    use libipld_core::ipld::Ipld;
+    use std::time::Duration;
     use testresult::TestResult;
-    use wnfs_common::{MemoryBlockStore, Storable};
+    use wnfs_common::{
+        utils::{Arc, InstrumentedBlockStore},
+        AccountingBlockStore, BlockStore, MemoryBlockStore, RecordingBlockStore, Storable,
+        CODEC_RAW,
+    };
+
+    #[async_std::test]
+    async fn attributes_round_trip_through_the_store() -> TestResult {
+        let store = &MemoryBlockStore::new();
+        let node = &mut PublicNode::File(PublicFile::new_rc(Utc::now()));
+        let before = node.store(store).await?;
+
+        let author = Ipld::String("alice".into());
+        assert_eq!(node.set_attribute("author", author.clone()), None);
+        assert_eq!(node.get_previous().iter().collect::<Vec<_>>(), [&before]);
+
+        let loaded = PublicNode::load(&node.store(store).await?, store).await?;
+        assert_eq!(loaded.get_attribute("author"), Some(&author));
+        assert_eq!(loaded.get_attribute("created"), None);
+
+        // Nodes stored without attributes have none
+        let loaded = PublicNode::load(&before, store).await?;
+        assert_eq!(loaded.get_attribute("author"), None);
+
+        let dir = &mut PublicNode::Dir(PublicDirectory::new_rc(Utc::now()));
+        dir.set_attribute("color", Ipld::String("blue".into()));
+        assert_eq!(
+            dir.remove_attribute("color"),
+            Some(Ipld::String("blue".into()))
+        );
+        assert_eq!(dir.remove_attribute("color"), None);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn store_parallel_matches_store_with_bounded_concurrency() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let build = || async move {
+            let root = &mut PublicDirectory::new_rc(time);
+            for i in 0..8 {
+                for j in 0..4 {
+                    let path = [format!("dir-{i}"), format!("sub-{j}"), "file.txt".into()];
+                    root.write(&path, format!("{i}/{j}").into(), time, store)
+                        .await?;
+                }
+            }
+            anyhow::Ok(PublicNode::Dir(Arc::clone(root)))
+        };
+
+        let sequential = build().await?.store(store).await?;
+
+        let slow = &InstrumentedBlockStore::default().with_delay(Duration::from_millis(2));
+        let node = build().await?;
+        assert_eq!(node.store_parallel(slow, 4).await?, sequential);
+        assert_eq!(slow.max_in_flight(), 4);
+
+        // Everything's stored now, so there's nothing left to put
+        slow.reset_counts();
+        assert_eq!(node.store_parallel(slow, 4).await?, sequential);
+        assert_eq!(slow.puts(), 0);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn store_progress_adds_up_to_the_blocks_written() -> TestResult {
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        root.write(&path("docs/a.txt"), b"a".to_vec(), time, store)
+            .await?;
+        root.write(&path("pictures/cat.png"), b"cat".to_vec(), time, store)
+            .await?;
+        root.store(store).await?;
+        root.write(&path("docs/b.txt"), b"b".to_vec(), time, store)
+            .await?;
+        // Empty content only gets stored along with its file
+        root.write(&path("docs/empty.txt"), Vec::new(), time, store)
+            .await?;
+
+        let accounting = &AccountingBlockStore::new(store);
+        let mut reports = Vec::new();
+        let node = PublicNode::Dir(Arc::clone(root));
+        let cid = node
+            .store_with_progress(accounting, |progress| reports.push(progress))
+            .await?;
+        assert_eq!(cid, root.store(store).await?);
+
+        let events = reports
+            .iter()
+            .map(|p| (p.path.join("/"), p.skipped))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                ("docs/a.txt".into(), true),
+                ("docs/b.txt".into(), false),
+                ("docs/empty.txt".into(), false),
+                ("docs/empty.txt".into(), false),
+                ("docs".into(), false),
+                ("pictures".into(), true),
+                ("".into(), false),
+            ]
+        );
+        let last = reports.last().unwrap();
+        let stats = accounting.stats();
+        assert_eq!(last.blocks_written, stats.new_blocks);
+        assert_eq!(last.bytes_written, stats.new_bytes);
+        assert_eq!(last.blocks_written, 5);
+        assert!(reports
+            .windows(2)
+            .all(|w| w[0].blocks_written <= w[1].blocks_written));
+
+        // Storing again skips everything
+        let mut reports = Vec::new();
+        node.store_with_progress(store, |progress| reports.push(progress))
+            .await?;
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].skipped);
+        assert_eq!(reports[0].blocks_written, 0);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn history_visits_merged_revisions_breadth_first_once() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let path = &["notes.txt".into()];
+        let base = &mut PublicDirectory::new_rc(time);
+        base.write(path, b"Base".to_vec(), time, store).await?;
+        let base_cid = base.store(store).await?;
+
+        let mut branches = Vec::new();
+        for content in ["Left", "Right"] {
+            let branch = &mut Arc::clone(base);
+            branch.write(path, content.into(), time, store).await?;
+            branches.push(branch.store(store).await?);
+        }
+        branches.sort();
+
+        let merge = PublicNode::Dir(Arc::clone(base)).update_previous(branches.clone());
+        let history: Vec<PublicNode> = merge.history(store).try_collect().await?;
+
+        let mut cids = Vec::new();
+        for node in &history {
+            cids.push(node.store(store).await?);
+        }
+        assert_eq!(cids, [branches[0], branches[1], base_cid]);
+        assert!(history[2].get_previous().is_empty());
+
+        Ok(())
+    }
 
     #[async_std::test]
     async fn serialized_public_node_can_be_deserialized() -> TestResult {
@@ -408,6 +1105,181 @@ mod tests {
 
         Ok(())
     }
+
+    #[async_std::test]
+    async fn nodes_under_other_codecs_are_reported_as_unsupported() -> TestResult {
+        use wnfs_common::{BlockStore, BlockStoreError, CODEC_DAG_PB};
+
+        let store = &MemoryBlockStore::new();
+        let node: PublicNode = PublicFile::new(Utc::now()).into();
+        let cid = node.store(store).await?;
+        let bytes = store.get_block(&cid).await?;
+        let cid = store.put_block(bytes, CODEC_DAG_PB).await?;
+
+        let err = PublicNode::load(&cid, store).await.unwrap_err();
+        assert_eq!(err.to_string(), "Unsupported codec dag-pb");
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(BlockStoreError::UnsupportedCodec(CODEC_DAG_PB))
+        ));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn nodes_with_unknown_versions_or_kinds_are_rejected() -> TestResult {
+        use crate::{error::FsError, public::PublicNodeSerializable};
+        use wnfs_common::{
+            encode,
+            libipld::{cbor::DagCborCodec, Ipld},
+            BlockStore, CODEC_DAG_CBOR,
+        };
+
+        let store = &MemoryBlockStore::new();
+        let node: PublicNode = PublicFile::new(Utc::now()).into();
+
+        let PublicNodeSerializable::File(mut file) = node.to_serializable(store).await? else {
+            unreachable!();
+        };
+        file.version = semver::Version::new(2, 0, 0);
+        let bytes = encode(&PublicNodeSerializable::File(file), DagCborCodec)?;
+        let cid = store.put_block(bytes, CODEC_DAG_CBOR).await?;
+
+        let result = PublicNode::load(&cid, store).await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::UnexpectedVersion(version)) if version.major == 2
+        ));
+
+        let unknown_kind = Ipld::Map([("wnfs/pub/symlink".into(), Ipld::Null)].into());
+        let bytes = encode(&unknown_kind, DagCborCodec)?;
+        let cid = store.put_block(bytes, CODEC_DAG_CBOR).await?;
+
+        assert!(PublicNode::load(&cid, store).await.is_err());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn subtree_size_estimates_are_exact_for_small_trees() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let dir = &mut PublicDirectory::new_rc(time);
+        dir.write(&["a".into(), "b.txt".into()], vec![0; 100], time, store)
+            .await?;
+        dir.write(&["c.txt".into()], vec![0; 23], time, store)
+            .await?;
+        for i in 0..40 {
+            let size = if i % 2 == 0 { 10 } else { 30 };
+            dir.write(
+                &["many".into(), format!("{i:02}")],
+                vec![0; size],
+                time,
+                store,
+            )
+            .await?;
+        }
+
+        let node = PublicNode::Dir(dir.clone());
+
+        assert_eq!(node.estimate_subtree_size(store, 100).await?, 123 + 800);
+
+        // Sampling every second entry of "many" only visits the 10 byte files
+        let estimate = node.estimate_subtree_size(store, 20).await?;
+        assert_eq!(estimate, 123 + 400);
+
+        let file = dir.get_node(&["c.txt".into()], store).await?.unwrap();
+        assert_eq!(file.estimate_subtree_size(store, 1).await?, 23);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn reachable_cids_cover_content_and_optionally_history() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let path = &["pictures".into(), "big.png".into()];
+        let dir = &mut PublicDirectory::new_rc(time);
+        dir.write(path, vec![1; 1_000_000], time, store).await?;
+        let old_root = dir.store(store).await?;
+        let old_content = dir
+            .get_node(path, store)
+            .await?
+            .unwrap()
+            .as_file()?
+            .userland
+            .resolve_cid(store)
+            .await?;
+
+        dir.write(path, vec![2; 1_000_000], time, store).await?;
+        let thumbnail = store.put_block(b"Tiny".to_vec(), CODEC_RAW).await?;
+        dir.set_sidecar(path, "thumbnail", Some(thumbnail), store)
+            .await?;
+        store.put_block(b"Unrelated".to_vec(), CODEC_RAW).await?;
+
+        let node = PublicNode::Dir(Arc::clone(dir));
+        let root = node.store(store).await?;
+        let file = dir.get_node(path, store).await?.unwrap().as_file()?;
+        let content = file.userland.resolve_cid(store).await?;
+
+        let recording = &RecordingBlockStore::new(store);
+        let reachable = node.reachable_cids(recording).await?;
+        // Raw content blocks don't link anywhere, so they don't get loaded
+        assert!(recording
+            .log()
+            .iter()
+            .all(|access| access.cid.codec() != CODEC_RAW));
+        for cid in [root, file.store(store).await?, content, thumbnail] {
+            assert!(reachable.contains(&cid));
+        }
+        // The content DAG is more than its root
+        let content_blocks = reachable.iter().filter(|cid| cid.codec() == CODEC_RAW);
+        assert!(content_blocks.count() > 1);
+        assert!(!reachable.contains(&old_root));
+        assert!(!reachable.contains(&old_content));
+
+        let with_history = node.reachable_cids_with_history(store).await?;
+        assert!(with_history.is_superset(&reachable));
+        assert!(with_history.contains(&old_root));
+        assert!(with_history.contains(&old_content));
+
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[async_std::test]
+    async fn trees_can_be_changed_on_other_threads() -> TestResult {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<PublicNode>();
+        assert_send_sync::<Arc<PublicDirectory>>();
+        assert_send_sync::<Arc<PublicFile>>();
+
+        let time = Utc::now();
+        let store = Arc::new(MemoryBlockStore::new());
+        let base = PublicDirectory::new_rc(time);
+
+        let tasks = (0..4).map(|i| {
+            let store = Arc::clone(&store);
+            let mut dir = Arc::clone(&base);
+            async_std::task::spawn(async move {
+                dir.write(&[format!("{i}.txt")], vec![i], time, store.as_ref())
+                    .await?;
+                anyhow::Ok(dir)
+            })
+        });
+
+        for (i, dir) in futures::future::join_all(tasks)
+            .await
+            .into_iter()
+            .enumerate()
+        {
+            let content = dir?.read(&[format!("{i}.txt")], store.as_ref()).await?;
+            assert_eq!(content, vec![i as u8]);
+        }
+        assert!(base.ls(&[], store.as_ref()).await?.is_empty());
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -8,6 +8,15 @@ use wnfs_common::Metadata;
 // Type Definitions
 //--------------------------------------------------------------------------------------------------
 
+/// The serialized form of a public node.
+///
+/// This is a self-describing envelope: The single map key tags the node kind,
+/// and each node carries the data format version it was written with. Loading
+/// a node fails on unknown node kinds, and on versions this library can't read
+/// with `FsError::UnexpectedVersion`. See `is_readable_wnfs_version`.
+///
+/// This layout is defined by the WNFS specification, so changing it breaks
+/// compatibility with other implementations and changes all CIDs.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PublicNodeSerializable {
     #[serde(rename = "wnfs/pub/file")]
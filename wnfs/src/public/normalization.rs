@@ -0,0 +1,333 @@
+//! Matching directory entry names that only differ in case or Unicode normalization.
+
+use super::PublicDirectory;
+use chrono::{DateTime, Utc};
+use libipld_core::ipld::Ipld;
+use std::borrow::Cow;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+use wnfs_common::{utils::Arc, Metadata};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+const NAME_NORMALIZATION_KEY: &str = "nameNormalization";
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// How a directory matches path segments against the names of its entries, see
+/// `PublicDirectory::set_name_normalization`.
+///
+/// File systems disagree on which names are the same: Some are case-insensitive,
+/// and some store `é` as one code point (NFC), while others store it as `e` followed
+/// by a combining accent (NFD). Normalizing names avoids ending up with entries that
+/// look identical, but can't be found by their name when it's typed on another
+/// platform.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum NameNormalization {
+    /// Names only match if they're the same bytes.
+    #[default]
+    CaseSensitive,
+    /// Names match if they're the same ignoring case. Entries keep the case they
+    /// were created with.
+    CaseInsensitive,
+    /// Names match if they're the same after Unicode NFC normalization. Entries
+    /// are created with their NFC-normalized names.
+    Nfc,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl NameNormalization {
+    /// Returns the form of given name that matching names have in common.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::public::NameNormalization;
+    ///
+    /// let nfd = "Cafe\u{301}";
+    ///
+    /// assert_eq!(NameNormalization::Nfc.normalize(nfd), "Caf\u{e9}");
+    /// assert_eq!(NameNormalization::CaseInsensitive.normalize("Café"), "café");
+    /// assert_eq!(NameNormalization::CaseSensitive.normalize(nfd), nfd);
+    /// ```
+    pub fn normalize<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        match self {
+            Self::CaseSensitive => Cow::Borrowed(name),
+            Self::CaseInsensitive => Cow::Owned(name.to_lowercase()),
+            Self::Nfc if is_nfc(name) => Cow::Borrowed(name),
+            Self::Nfc => Cow::Owned(name.nfc().collect()),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::CaseSensitive => "caseSensitive",
+            Self::CaseInsensitive => "caseInsensitive",
+            Self::Nfc => "nfc",
+        }
+    }
+
+    fn from_metadata(metadata: &Metadata) -> Self {
+        match metadata.get(NAME_NORMALIZATION_KEY) {
+            Some(Ipld::String(name)) if name == Self::CaseInsensitive.name() => {
+                Self::CaseInsensitive
+            }
+            Some(Ipld::String(name)) if name == Self::Nfc.name() => Self::Nfc,
+            _ => Self::CaseSensitive,
+        }
+    }
+}
+
+impl PublicDirectory {
+    /// Sets how path segments are matched against the names of this directory's
+    /// entries, when looking up, writing or removing them.
+    ///
+    /// The normalization is stored in the directory's metadata, and directories
+    /// created below this one by `write` or `mkdir` get the same normalization.
+    /// Existing entries keep their names. If several of them match the same path
+    /// segment, e.g. `FILE` and `file` that were added before the directory became
+    /// case-insensitive, an entry with exactly that name is picked, otherwise the
+    /// first of them in byte order. Writing a name that matches an existing entry
+    /// writes to that entry, instead of adding one next to it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::{NameNormalization, PublicDirectory},
+    ///     common::MemoryBlockStore,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     dir.set_name_normalization(NameNormalization::CaseInsensitive);
+    ///     dir.write(&["docs".into(), "README.md".into()], b"Hello".to_vec(), Utc::now(), store)
+    ///         .await?;
+    ///
+    ///     let content = dir.read(&["DOCS".into(), "readme.md".into()], store).await?;
+    ///
+    ///     assert_eq!(content, b"Hello");
+    ///     assert_eq!(dir.ls(&["docs".into()], store).await?[0].0, "README.md");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn set_name_normalization(self: &mut Arc<Self>, normalization: NameNormalization) {
+        let metadata = self.get_metadata_mut_rc();
+        match normalization {
+            NameNormalization::CaseSensitive => metadata.delete(NAME_NORMALIZATION_KEY),
+            _ => metadata.put(NAME_NORMALIZATION_KEY, normalization.name().into()),
+        };
+    }
+
+    /// Returns how path segments are matched against the names of this directory's
+    /// entries, see `set_name_normalization`.
+    pub fn get_name_normalization(&self) -> NameNormalization {
+        NameNormalization::from_metadata(&self.metadata)
+    }
+
+    /// Returns the name under which given path segment is stored in this directory:
+    /// The name of an existing entry it matches, or otherwise the name a new entry
+    /// for it gets.
+    pub(crate) fn entry_name<'a>(&self, segment: &'a str) -> Cow<'a, str> {
+        let normalization = self.get_name_normalization();
+        if normalization == NameNormalization::CaseSensitive || self.userland.contains_key(segment)
+        {
+            return Cow::Borrowed(segment);
+        }
+
+        let normalized = normalization.normalize(segment);
+        match self
+            .userland
+            .keys()
+            .find(|name| normalization.normalize(name) == normalized)
+        {
+            Some(name) => Cow::Owned(name.clone()),
+            None if normalization == NameNormalization::Nfc => normalized,
+            None => Cow::Borrowed(segment),
+        }
+    }
+
+    /// Creates an empty directory to be added to this one, with the same name
+    /// normalization.
+    pub(crate) fn new_child_dir(&self, time: DateTime<Utc>) -> Self {
+        let mut dir = Self::new(time);
+        if let Some(normalization) = self.metadata.get(NAME_NORMALIZATION_KEY) {
+            dir.metadata
+                .put(NAME_NORMALIZATION_KEY, normalization.clone());
+        }
+        dir
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::FsError;
+    use testresult::TestResult;
+    use wnfs_common::{MemoryBlockStore, Storable};
+
+    #[async_std::test]
+    async fn nfc_and_nfd_spellings_resolve_to_one_entry() -> TestResult {
+        let nfc = vec!["Caf\u{e9}".to_string()];
+        let nfd = vec!["Cafe\u{301}".to_string()];
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+
+        // By default, both spellings are different entries
+        let dir = &mut PublicDirectory::new_rc(time);
+        dir.write(&nfd, b"NFD".to_vec(), time, store).await?;
+        assert!(dir.get_node(&nfc, store).await?.is_none());
+
+        let dir = &mut PublicDirectory::new_rc(time);
+        dir.set_name_normalization(NameNormalization::Nfc);
+        dir.write(&nfd, b"NFD".to_vec(), time, store).await?;
+        assert_eq!(dir.read(&nfc, store).await?, b"NFD");
+        dir.write(&nfc, b"NFC".to_vec(), time, store).await?;
+        assert_eq!(dir.read(&nfd, store).await?, b"NFC");
+
+        let names = dir.ls(&[], store).await?;
+        assert_eq!(names.len(), 1);
+        assert_eq!(names[0].0, nfc[0]);
+
+        // Survives storing, and gets inherited by new directories
+        let dir = &mut Arc::new(PublicDirectory::load(&dir.store(store).await?, store).await?);
+        assert_eq!(dir.get_name_normalization(), NameNormalization::Nfc);
+        dir.rm(&nfd, store).await?;
+        assert!(dir.ls(&[], store).await?.is_empty());
+
+        dir.write(
+            &[nfd.clone(), nfd].concat(),
+            b"Nested".to_vec(),
+            time,
+            store,
+        )
+        .await?;
+        let nested = dir.get_node(&nfc, store).await?.unwrap().as_dir()?;
+        assert_eq!(nested.get_name_normalization(), NameNormalization::Nfc);
+        assert_eq!(
+            dir.read(&[nfc.clone(), nfc].concat(), store).await?,
+            b"Nested"
+        );
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn case_insensitive_lookups_prefer_exact_matches() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let dir = &mut PublicDirectory::new_rc(time);
+        dir.write(&["file".into()], b"lower".to_vec(), time, store)
+            .await?;
+        dir.write(&["FILE".into()], b"upper".to_vec(), time, store)
+            .await?;
+        dir.set_name_normalization(NameNormalization::CaseInsensitive);
+
+        assert_eq!(dir.read(&["file".into()], store).await?, b"lower");
+        assert_eq!(dir.read(&["FILE".into()], store).await?, b"upper");
+        // "FILE" comes first in byte order
+        assert_eq!(dir.read(&["File".into()], store).await?, b"upper");
+
+        dir.write(&["File".into()], b"mixed".to_vec(), time, store)
+            .await?;
+        assert_eq!(dir.read(&["FILE".into()], store).await?, b"mixed");
+        assert_eq!(dir.ls(&[], store).await?.len(), 2);
+
+        // Copies and moves don't create entries clashing with existing ones
+        let clashes = dir
+            .cp(&["file".into()], &["fILE".into()], time, store)
+            .await;
+        assert!(matches!(
+            clashes.unwrap_err().downcast_ref(),
+            Some(FsError::FileAlreadyExists)
+        ));
+        dir.cp(&["file".into()], &["Copy".into()], time, store)
+            .await?;
+        let clashes = dir
+            .mv(&["FILE".into()], &["COPY".into()], time, store)
+            .await;
+        assert!(matches!(
+            clashes.unwrap_err().downcast_ref(),
+            Some(FsError::FileAlreadyExists)
+        ));
+        dir.mv(&["copy".into()], &["Moved".into()], time, store)
+            .await?;
+        assert_eq!(dir.read(&["MOVED".into()], store).await?, b"lower");
+        dir.open_file_mut(&["moved".into()], time, store).await?;
+        let names = dir
+            .ls(&[], store)
+            .await?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["FILE", "Moved", "file"]);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn moves_into_own_subtree_are_rejected_however_spelled() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        let dir = &mut PublicDirectory::new_rc(time);
+        dir.set_name_normalization(NameNormalization::CaseInsensitive);
+        dir.write(&path("Docs/sub/a.txt"), b"data".to_vec(), time, store)
+            .await?;
+
+        let result = dir
+            .mv(&path("Docs"), &path("docs/sub/moved"), time, store)
+            .await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::InvalidPath)
+        ));
+        let result = dir
+            .basic_mv(&path("DOCS"), &path("docs/SUB/moved"), time, store)
+            .await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::InvalidPath)
+        ));
+
+        assert_eq!(dir.read(&path("Docs/sub/a.txt"), store).await?, b"data");
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn changing_normalization_of_stored_directory_is_persisted() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let dir = &mut PublicDirectory::new_rc(time);
+        let cid = dir.store(store).await?;
+
+        dir.set_name_normalization(NameNormalization::CaseInsensitive);
+        let new_cid = dir.store(store).await?;
+        assert_ne!(cid, new_cid);
+
+        let loaded = PublicDirectory::load(&new_cid, store).await?;
+        assert_eq!(
+            loaded.get_name_normalization(),
+            NameNormalization::CaseInsensitive
+        );
+        assert_eq!(loaded.previous, [cid].into());
+
+        Ok(())
+    }
+}
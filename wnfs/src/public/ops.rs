@@ -0,0 +1,518 @@
+//! Recording and replaying logical operations on public directories.
+
+use super::{PublicDirectory, PublicNode};
+use crate::error::FsError;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use libipld_core::cid::Cid;
+use libipld_core::ipld::Ipld;
+use serde::{Deserialize, Serialize};
+use wnfs_common::{utils::Arc, BlockStore, Storable};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A logical operation on a public directory.
+///
+/// Operations carry everything needed to apply them, including timestamps,
+/// so applying the same operations to the same directory always results
+/// in the same root CID.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Op {
+    /// Writes a file, see `PublicDirectory::write`.
+    Write {
+        path: Vec<String>,
+        #[serde(with = "serde_bytes")]
+        content: Vec<u8>,
+        time: DateTime<Utc>,
+    },
+    /// Creates a directory, see `PublicDirectory::mkdir`.
+    Mkdir {
+        path: Vec<String>,
+        time: DateTime<Utc>,
+    },
+    /// Removes a node, see `PublicDirectory::rm`.
+    Rm { path: Vec<String> },
+    /// Moves a node, see `PublicDirectory::basic_mv`.
+    Mv {
+        from: Vec<String>,
+        to: Vec<String>,
+        time: DateTime<Utc>,
+    },
+    /// Sets a metadata entry of the node at given path.
+    /// An empty path sets it on the directory itself.
+    SetMetadata {
+        path: Vec<String>,
+        key: String,
+        value: Ipld,
+    },
+}
+
+/// Applies operations to public directories and records the ones that succeeded,
+/// so they can be shipped to and replayed on another replica.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use chrono::Utc;
+/// use wnfs::{
+///     public::{Op, OpRecorder, PublicDirectory},
+///     common::{MemoryBlockStore, Storable},
+/// };
+///
+/// #[async_std::main]
+/// async fn main() -> Result<()> {
+///     let store = &MemoryBlockStore::new();
+///     let base = PublicDirectory::new_rc(Utc::now());
+///
+///     let mut recorder = OpRecorder::new();
+///     let dir = &mut base.clone();
+///     recorder
+///         .apply(dir, Op::Mkdir { path: vec!["pictures".into()], time: Utc::now() }, store)
+///         .await?;
+///
+///     let replica = &mut base.clone();
+///     Op::replay(replica, recorder.ops(), store).await?;
+///
+///     assert_eq!(dir.store(store).await?, replica.store(store).await?);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OpRecorder {
+    ops: Vec<Op>,
+}
+
+/// The result of rebasing recorded operations onto a new head via `PublicDirectory::rebase`.
+#[derive(Debug, Clone)]
+pub struct RebaseOutcome {
+    /// The new head with all non-conflicting operations applied.
+    pub dir: Arc<PublicDirectory>,
+    /// The operations that weren't applied, in order.
+    pub conflicts: Vec<RebaseConflict>,
+}
+
+/// A recorded operation that couldn't be rebased.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RebaseConflict {
+    /// The index of the operation in the rebased operations.
+    pub index: usize,
+    /// Why the operation wasn't applied.
+    pub kind: RebaseConflictKind,
+}
+
+/// Why a recorded operation couldn't be rebased.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RebaseConflictKind {
+    /// The node at given path, which the operation touches or writes into,
+    /// was removed in the new head.
+    Removed(Vec<String>),
+    /// The node at given path, which the operation replaces, removes or changes,
+    /// was changed or created in the new head too.
+    Modified(Vec<String>),
+    /// The operation failed when applied to the new head.
+    Failed(String),
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl Op {
+    /// Applies this operation to given directory. If it fails, the directory
+    /// is left unchanged.
+    pub async fn apply(
+        &self,
+        dir: &mut Arc<PublicDirectory>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let mut applied = Arc::clone(dir);
+        self.apply_in_place(&mut applied, store).await?;
+        *dir = applied;
+        Ok(())
+    }
+
+    /// Applies given operations in order to given directory. If any of them
+    /// fails, none of them are applied and the directory is left unchanged.
+    pub async fn replay(
+        dir: &mut Arc<PublicDirectory>,
+        ops: &[Op],
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        let mut replayed = Arc::clone(dir);
+        for op in ops {
+            op.apply_in_place(&mut replayed, store).await?;
+        }
+
+        *dir = replayed;
+        Ok(())
+    }
+
+    /// Applies this operation to given directory, which may be left partially
+    /// changed if applying it fails.
+    async fn apply_in_place(
+        &self,
+        dir: &mut Arc<PublicDirectory>,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        match self {
+            Self::Write {
+                path,
+                content,
+                time,
+            } => dir.write(path, content.clone(), *time, store).await,
+            Self::Mkdir { path, time } => dir.mkdir(path, *time, store).await,
+            Self::Rm { path } => dir.rm(path, store).await.map(|_| ()),
+            Self::Mv { from, to, time } => dir.basic_mv(from, to, *time, store).await,
+            Self::SetMetadata { path, key, value } => {
+                let Some((name, parent_path)) = path.split_last() else {
+                    dir.get_metadata_mut_rc().put(key, value.clone());
+                    return Ok(());
+                };
+
+                let parent = dir
+                    .get_leaf_dir_mut(parent_path, store)
+                    .await?
+                    .into_found(path)?;
+
+                let metadata = match parent.lookup_node_mut(name, store).await? {
+                    Some(PublicNode::File(file)) => file.get_metadata_mut_rc(),
+                    Some(PublicNode::Dir(dir)) => dir.get_metadata_mut_rc(),
+                    None => bail!(FsError::not_found(path)),
+                };
+
+                metadata.put(key, value.clone());
+                Ok(())
+            }
+        }
+    }
+}
+
+impl PublicDirectory {
+    /// Replays operations that were recorded on this directory onto a newer head,
+    /// e.g. after a commit failed because another writer advanced the head.
+    ///
+    /// Each operation is checked against what changed between this directory and
+    /// the new head first. Operations that write into or touch nodes that were removed
+    /// in the new head, or that would overwrite, remove or change nodes that were
+    /// changed in the new head, aren't applied and get reported as conflicts instead.
+    /// So are operations that fail when applied. All other operations are applied in order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::{Op, PublicDirectory, RebaseConflictKind},
+    ///     common::MemoryBlockStore,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let base = &mut PublicDirectory::new_rc(Utc::now());
+    ///     base.mkdir(&["drafts".into()], Utc::now(), store).await?;
+    ///
+    ///     // Another writer removed the drafts directory in the meantime
+    ///     let head = &mut base.clone();
+    ///     head.rm(&["drafts".into()], store).await?;
+    ///
+    ///     let ops = [
+    ///         Op::Write {
+    ///             path: vec!["drafts".into(), "a.md".into()],
+    ///             content: b"# A".to_vec(),
+    ///             time: Utc::now(),
+    ///         },
+    ///         Op::Mkdir { path: vec!["pictures".into()], time: Utc::now() },
+    ///     ];
+    ///     let outcome = base.rebase(head, &ops, store).await?;
+    ///
+    ///     assert_eq!(outcome.conflicts.len(), 1);
+    ///     assert_eq!(outcome.conflicts[0].kind, RebaseConflictKind::Removed(vec!["drafts".into()]));
+    ///     assert_eq!(outcome.dir.ls(&[], store).await?.len(), 1);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn rebase(
+        &self,
+        onto: &Arc<PublicDirectory>,
+        ops: &[Op],
+        store: &impl BlockStore,
+    ) -> Result<RebaseOutcome> {
+        let mut dir = Arc::clone(onto);
+        let mut conflicts = Vec::new();
+        for (index, op) in ops.iter().enumerate() {
+            let kind = match self.rebase_conflict(onto, op, store).await? {
+                Some(kind) => kind,
+                None => match op.apply(&mut dir, store).await {
+                    Ok(()) => continue,
+                    Err(e) => RebaseConflictKind::Failed(e.to_string()),
+                },
+            };
+
+            conflicts.push(RebaseConflict { index, kind });
+        }
+
+        Ok(RebaseOutcome { dir, conflicts })
+    }
+
+    async fn rebase_conflict(
+        &self,
+        onto: &PublicDirectory,
+        op: &Op,
+        store: &impl BlockStore,
+    ) -> Result<Option<RebaseConflictKind>> {
+        let (touched, replaced): (&[&Vec<String>], &[&Vec<String>]) = match op {
+            Op::Mkdir { path, .. } => (&[path], &[]),
+            Op::Write { path, .. } | Op::Rm { path } | Op::SetMetadata { path, .. } => {
+                (&[path], &[path])
+            }
+            Op::Mv { from, to, .. } => (&[from, to], &[from, to]),
+        };
+
+        for path in touched {
+            for len in 1..=path.len() {
+                let prefix = &path[..len];
+                let before = self.get_node(prefix, store).await?;
+                let after = onto.get_node(prefix, store).await?;
+                let is_replaced = len == path.len() && replaced.contains(path);
+                match (before, after) {
+                    (Some(_), None) => {
+                        return Ok(Some(RebaseConflictKind::Removed(prefix.to_vec())))
+                    }
+                    (before, Some(after))
+                        if is_replaced
+                            && !before.is_some_and(|before| same_node(before, after)) =>
+                    {
+                        return Ok(Some(RebaseConflictKind::Modified(prefix.to_vec())))
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub(crate) async fn node_cid(
+        &self,
+        path_segments: &[String],
+        store: &impl BlockStore,
+    ) -> Result<Option<Cid>> {
+        match self.get_node(path_segments, store).await? {
+            Some(node) => Ok(Some(node.store(store).await?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl OpRecorder {
+    /// Creates a recorder without any recorded operations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies given operation to given directory and records it if it succeeded.
+    pub async fn apply(
+        &mut self,
+        dir: &mut Arc<PublicDirectory>,
+        op: Op,
+        store: &impl BlockStore,
+    ) -> Result<()> {
+        op.apply(dir, store).await?;
+        self.ops.push(op);
+        Ok(())
+    }
+
+    /// The operations recorded so far, in the order they were applied.
+    pub fn ops(&self) -> &[Op] {
+        &self.ops
+    }
+
+    /// Returns the operations recorded so far and clears the recording.
+    pub fn take_ops(&mut self) -> Vec<Op> {
+        std::mem::take(&mut self.ops)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Whether given nodes are the same, told without storing them: by the CIDs they
+/// were persisted as if both were, otherwise by comparing them structurally.
+fn same_node(a: &PublicNode, b: &PublicNode) -> bool {
+    let persisted_as = |node: &PublicNode| node.persisted_as().and_then(|cid| cid.get()).copied();
+    match (persisted_as(a), persisted_as(b)) {
+        (Some(a_cid), Some(b_cid)) => a_cid == b_cid,
+        _ => a == b,
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use testresult::TestResult;
+    use wnfs_common::{
+        decode, encode, libipld::cbor::DagCborCodec, BlockOperation, MemoryBlockStore,
+        RecordingBlockStore, Storable, CODEC_DAG_CBOR,
+    };
+
+    #[async_std::test]
+    async fn replaying_recorded_ops_reproduces_the_root_cid() -> TestResult {
+        let time = Utc.timestamp_opt(1_000_000, 0).unwrap();
+        let store = &MemoryBlockStore::new();
+        let base = &mut PublicDirectory::new_rc(time);
+        base.write(&["existing.txt".into()], b"old".to_vec(), time, store)
+            .await?;
+        base.store(store).await?;
+
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        let ops = vec![
+            Op::Mkdir {
+                path: path("docs/drafts"),
+                time,
+            },
+            Op::Write {
+                path: path("docs/drafts/a.md"),
+                content: b"# A".to_vec(),
+                time,
+            },
+            Op::Mv {
+                from: path("docs/drafts/a.md"),
+                to: path("docs/a.md"),
+                time,
+            },
+            Op::SetMetadata {
+                path: path("docs/a.md"),
+                key: "author".into(),
+                value: Ipld::String("someone".into()),
+            },
+            Op::SetMetadata {
+                path: vec![],
+                key: "label".into(),
+                value: Ipld::Integer(42),
+            },
+            Op::Rm {
+                path: path("existing.txt"),
+            },
+        ];
+
+        let mut recorder = OpRecorder::new();
+        let dir = &mut Arc::clone(base);
+        for op in ops.clone() {
+            recorder.apply(dir, op, store).await?;
+        }
+
+        let failing = Op::Rm {
+            path: path("missing"),
+        };
+        assert!(recorder.apply(dir, failing.clone(), store).await.is_err());
+        assert_eq!(recorder.ops(), ops.as_slice());
+
+        let shipped = encode(&recorder.take_ops(), DagCborCodec)?;
+        let received: Vec<Op> = decode(&shipped, DagCborCodec)?;
+
+        // Batches that fail midway don't change the directory
+        let replica = &mut Arc::clone(base);
+        let failing_batch = [received.clone(), vec![failing]].concat();
+        assert!(Op::replay(replica, &failing_batch, store).await.is_err());
+        assert_eq!(replica.store(store).await?, base.store(store).await?);
+
+        Op::replay(replica, &received, store).await?;
+
+        assert_eq!(dir.store(store).await?, replica.store(store).await?);
+        assert!(recorder.ops().is_empty());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn rebasing_skips_ops_that_conflict_with_the_new_head() -> TestResult {
+        let time = Utc.timestamp_opt(1_000_000, 0).unwrap();
+        let store = &MemoryBlockStore::new();
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        let base = &mut PublicDirectory::new_rc(time);
+        base.write(&path("docs/a.md"), b"a".to_vec(), time, store)
+            .await?;
+        base.write(&path("docs/b.md"), b"b".to_vec(), time, store)
+            .await?;
+        base.write(&path("notes.txt"), b"notes".to_vec(), time, store)
+            .await?;
+        base.store(store).await?;
+
+        let head = &mut Arc::clone(base);
+        head.write(&path("notes.txt"), b"their notes".to_vec(), time, store)
+            .await?;
+        head.rm(&path("docs/b.md"), store).await?;
+        head.mkdir(&path("pictures"), time, store).await?;
+
+        let ops = vec![
+            Op::Write {
+                path: path("docs/a.md"),
+                content: b"my a".to_vec(),
+                time,
+            },
+            Op::Write {
+                path: path("notes.txt"),
+                content: b"my notes".to_vec(),
+                time,
+            },
+            Op::SetMetadata {
+                path: path("docs/b.md"),
+                key: "author".into(),
+                value: Ipld::String("me".into()),
+            },
+            Op::Mkdir {
+                path: path("music"),
+                time,
+            },
+            Op::Rm {
+                path: path("pictures"),
+            },
+            Op::Rm {
+                path: path("missing.txt"),
+            },
+        ];
+
+        let recording = &RecordingBlockStore::new(store);
+        let outcome = base.rebase(head, &ops, recording).await?;
+        assert!(!recording.log().iter().any(|access| matches!(
+            access.operation,
+            BlockOperation::Put(_) if access.cid.codec() == CODEC_DAG_CBOR
+        )));
+
+        let kinds = outcome
+            .conflicts
+            .iter()
+            .map(|conflict| (conflict.index, &conflict.kind))
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            kinds.as_slice(),
+            [
+                (1, RebaseConflictKind::Modified(notes)),
+                (2, RebaseConflictKind::Removed(b)),
+                (4, RebaseConflictKind::Modified(pictures)),
+                (5, RebaseConflictKind::Failed(_)),
+            ] if notes == &path("notes.txt") && b == &path("docs/b.md") && pictures == &path("pictures")
+        ));
+
+        let dir = &outcome.dir;
+        assert_eq!(dir.read(&path("docs/a.md"), store).await?, b"my a");
+        assert_eq!(dir.read(&path("notes.txt"), store).await?, b"their notes");
+        assert!(dir.get_node(&path("music"), store).await?.is_some());
+        assert!(dir.get_node(&path("pictures"), store).await?.is_some());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+//! Proofs that a path in a public file system tree leads to a given node.
+
+use super::{PublicDirectory, PublicNode, PublicNodeSerializable};
+use crate::{error::FsError, SearchResult};
+use anyhow::{bail, Result};
+use bytes::Bytes;
+use libipld_core::cid::Cid;
+use wnfs_common::{
+    libipld::multihash::{Code, MultihashDigest},
+    BlockStore, LoadIpld, Storable,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A proof that a path leads from a root directory to a node, see
+/// `PublicDirectory::prove_path`.
+///
+/// It consists of the blocks of the directories along the path, which is the least
+/// that's needed to follow the path from the root CID. The node at the end of the
+/// path itself isn't included, only its CID follows from the proof.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathProof {
+    /// The path that's proven, relative to the root directory.
+    pub path: Vec<String>,
+    /// The serialized directories along the path, starting with the root directory.
+    pub blocks: Vec<Bytes>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl PublicDirectory {
+    /// Creates a proof that given path leads from this directory to the node that's
+    /// at the path now, so clients that only know this directory's CID can verify
+    /// which node is at the path without having the tree.
+    ///
+    /// This directory and the directories along the path get stored. Fails with
+    /// `FsError::NotFound` if there's no node at the path, `FsError::ParentNotFound`
+    /// if a directory on the way is missing, and `FsError::NotADirectory` if the
+    /// path runs into a file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::{MemoryBlockStore, Storable},
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     let path = vec!["docs".to_string(), "notes.txt".into()];
+    ///     dir.write(&path, b"Hello".to_vec(), Utc::now(), store).await?;
+    ///
+    ///     let proof = dir.prove_path(&path, store).await?;
+    ///     let root = dir.store(store).await?;
+    ///     let leaf = dir.get_node(&path, store).await?.unwrap().store(store).await?;
+    ///
+    ///     assert_eq!(proof.verify(&root)?, leaf);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn prove_path(
+        &self,
+        path_segments: &[String],
+        store: &impl BlockStore,
+    ) -> Result<PathProof> {
+        let blocks = self
+            .collect_proof_blocks(path_segments, store)
+            .await?
+            .into_found(path_segments)?;
+
+        Ok(PathProof {
+            path: path_segments.to_vec(),
+            blocks,
+        })
+    }
+
+    /// Collects the blocks of the directories along given path. Entries are looked
+    /// up by their exact names, like `PathProof::verify` does.
+    async fn collect_proof_blocks(
+        &self,
+        path_segments: &[String],
+        store: &impl BlockStore,
+    ) -> Result<SearchResult<Vec<Bytes>>> {
+        let mut blocks = Vec::new();
+        let mut dir = self;
+        for (depth, segment) in path_segments.iter().enumerate() {
+            blocks.push(store.get_block(&dir.store(store).await?).await?);
+
+            let Some(link) = dir.userland.get(segment) else {
+                return Ok(SearchResult::Missing(blocks, depth));
+            };
+            if depth + 1 == path_segments.len() {
+                link.resolve_cid(store).await?;
+                break;
+            }
+
+            dir = match link.resolve_value(store).await? {
+                PublicNode::Dir(dir) => dir,
+                PublicNode::File(_) => return Ok(SearchResult::NotADir(blocks, depth)),
+            };
+        }
+
+        Ok(SearchResult::Found(blocks))
+    }
+}
+
+impl PathProof {
+    /// Follows the path of this proof from given root CID through the included
+    /// blocks, without a block store, and returns the CID of the node at the end
+    /// of the path. For the empty path, that's the root CID itself.
+    ///
+    /// Fails with `FsError::InvalidPathProof` if a block doesn't hash to the CID
+    /// it's linked as, isn't a directory, or doesn't have the next path segment.
+    pub fn verify(&self, root: &Cid) -> Result<Cid> {
+        if self.blocks.len() != self.path.len() {
+            bail!(FsError::InvalidPathProof(format!(
+                "expected {} blocks for a path of that length, got {}",
+                self.path.len(),
+                self.blocks.len()
+            )));
+        }
+
+        let mut cid = *root;
+        for (segment, bytes) in self.path.iter().zip(&self.blocks) {
+            let Ok(code) = Code::try_from(cid.hash().code()) else {
+                bail!(FsError::InvalidPathProof(format!(
+                    "unsupported hash function in CID {cid}"
+                )));
+            };
+            if &code.digest(bytes) != cid.hash() {
+                bail!(FsError::InvalidPathProof(format!(
+                    "block doesn't match its CID {cid}"
+                )));
+            }
+
+            let Ok(PublicNodeSerializable::Dir(dir)) =
+                PublicNodeSerializable::decode_ipld(&cid, bytes.clone())
+            else {
+                bail!(FsError::InvalidPathProof(format!(
+                    "block {cid} isn't a directory"
+                )));
+            };
+
+            cid = match dir.userland.get(segment) {
+                Some(cid) => *cid,
+                None => bail!(FsError::InvalidPathProof(format!(
+                    "directory {cid} has no entry {segment:?}"
+                ))),
+            };
+        }
+
+        Ok(cid)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use testresult::TestResult;
+    use wnfs_common::MemoryBlockStore;
+
+    #[async_std::test]
+    async fn tampered_proofs_fail_verification() -> TestResult {
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let leaf_path = path("pictures/cats/tabby.png");
+        root.write(&leaf_path, b"Tabby".to_vec(), time, store)
+            .await?;
+        root.write(&path("pictures/dogs.png"), b"Dogs".to_vec(), time, store)
+            .await?;
+
+        let proof = root.prove_path(&leaf_path, store).await?;
+        let root_cid = root.store(store).await?;
+        let leaf = root.get_node(&leaf_path, store).await?.unwrap();
+        assert_eq!(proof.blocks.len(), 3);
+        assert_eq!(proof.verify(&root_cid)?, leaf.store(store).await?);
+
+        let is_invalid = |proof: &PathProof| {
+            matches!(
+                proof.verify(&root_cid).unwrap_err().downcast_ref(),
+                Some(FsError::InvalidPathProof(_))
+            )
+        };
+
+        for i in 0..proof.blocks.len() {
+            let mut tampered = proof.clone();
+            let mut block = tampered.blocks[i].to_vec();
+            let last = block.len() - 1;
+            block[last] ^= 1;
+            tampered.blocks[i] = block.into();
+            assert!(is_invalid(&tampered));
+        }
+
+        // Swapping in a validly hashed, but unrelated block doesn't work either
+        let mut tampered = proof.clone();
+        tampered.blocks.swap(1, 2);
+        assert!(is_invalid(&tampered));
+
+        let mut tampered = proof.clone();
+        tampered.path = path("pictures/dogs.png/tabby.png");
+        assert!(is_invalid(&tampered));
+
+        let mut tampered = proof.clone();
+        tampered.blocks.pop();
+        assert!(is_invalid(&tampered));
+
+        let other_root = PublicDirectory::new_rc(time).store(store).await?;
+        assert!(proof.verify(&other_root).is_err());
+
+        let err = root.prove_path(&path("pictures/birds.png"), store).await;
+        assert!(matches!(
+            err.unwrap_err().downcast_ref(),
+            Some(FsError::NotFound { .. })
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,272 @@
+//! IPLD selectors describing parts of public file system trees.
+
+use libipld_core::ipld::Ipld;
+use std::collections::BTreeMap;
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Returns an IPLD selector, in its IPLD representation, that matches the subtree at
+/// given path when applied to the root block of a public file system tree.
+///
+/// The selector explores the directories along the path and then all nodes below it,
+/// including file content DAGs and file sidecars, and matches each explored block of
+/// the subtree. Previous revisions aren't included. It can be handed to a graphsync
+/// implementation to fetch exactly that subtree.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs::public::subtree_selector;
+/// use libipld_core::ipld::Ipld;
+///
+/// let selector = subtree_selector(&["pictures".into()]);
+///
+/// assert!(matches!(selector, Ipld::Map(_)));
+/// ```
+pub fn subtree_selector(path_segments: &[String]) -> Ipld {
+    path_segments
+        .iter()
+        .rev()
+        .fold(descendants_selector(), |next, segment| {
+            explore_fields([(
+                "wnfs/pub/dir",
+                explore_fields([("userland", explore_fields([(segment.as_str(), next)]))]),
+            )])
+        })
+}
+
+/// Matches a node and recursively everything it links to as part of the current tree.
+fn descendants_selector() -> Ipld {
+    let edge = || single("@", Ipld::Map(BTreeMap::new()));
+    let explore_all = |next| single("a", single(">", next));
+
+    let node = explore_fields([
+        (
+            "wnfs/pub/dir",
+            explore_fields([("userland", explore_all(edge()))]),
+        ),
+        (
+            "wnfs/pub/file",
+            explore_fields([
+                ("userland", edge()),
+                (
+                    "metadata",
+                    explore_fields([("sidecars", explore_all(edge()))]),
+                ),
+            ]),
+        ),
+        // File content DAG nodes in dag-pb
+        ("Links", explore_all(explore_fields([("Hash", edge())]))),
+    ]);
+
+    let matcher = single(".", Ipld::Map(BTreeMap::new()));
+    let limit = single("none", Ipld::Map(BTreeMap::new()));
+
+    single(
+        "R",
+        Ipld::Map(BTreeMap::from([
+            ("l".into(), limit),
+            (":>".into(), single("|", Ipld::List(vec![matcher, node]))),
+        ])),
+    )
+}
+
+fn explore_fields<'a>(fields: impl IntoIterator<Item = (&'a str, Ipld)>) -> Ipld {
+    let fields = fields
+        .into_iter()
+        .map(|(name, next)| (name.to_string(), next))
+        .collect();
+
+    single("f", single("f>", Ipld::Map(fields)))
+}
+
+fn single(key: &str, value: Ipld) -> Ipld {
+    Ipld::Map(BTreeMap::from([(key.to_string(), value)]))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::public::{golden::golden_content, PublicDirectory};
+    use anyhow::{bail, Result};
+    use chrono::Utc;
+    use libipld_core::cid::Cid;
+    use std::collections::BTreeSet;
+    use testresult::TestResult;
+    use wnfs_common::{
+        decode,
+        libipld::{cbor::DagCborCodec, pb::DagPbCodec},
+        BlockStore, MemoryBlockStore, Storable, CODEC_DAG_CBOR, CODEC_DAG_PB, CODEC_RAW,
+    };
+
+    /// A minimal selector interpreter, covering the selectors `subtree_selector` emits.
+    /// Returns the CIDs of all matched blocks.
+    async fn select(root: Cid, selector: &Ipld, store: &impl BlockStore) -> Result<BTreeSet<Cid>> {
+        let mut matched = BTreeSet::new();
+        let mut stack = vec![(Ipld::Link(root), root, selector.clone(), None::<Ipld>)];
+        while let Some((ipld, block, selector, recursive)) = stack.pop() {
+            let ipld = match ipld {
+                Ipld::Link(cid) => {
+                    let bytes = store.get_block(&cid).await?;
+                    let node = match cid.codec() {
+                        CODEC_DAG_CBOR => decode(&bytes, DagCborCodec)?,
+                        CODEC_DAG_PB => decode(&bytes, DagPbCodec)?,
+                        CODEC_RAW => Ipld::Bytes(bytes.to_vec()),
+                        codec => bail!("Unexpected codec {codec}"),
+                    };
+                    stack.push((node, cid, selector, recursive));
+                    continue;
+                }
+                ipld => ipld,
+            };
+
+            let Ipld::Map(selector_map) = &selector else {
+                bail!("Selector isn't a map");
+            };
+            let Some((kind, body)) = selector_map.iter().next() else {
+                bail!("Empty selector");
+            };
+
+            match (kind.as_str(), body) {
+                (".", _) => {
+                    matched.insert(block);
+                }
+                ("|", Ipld::List(selectors)) => stack.extend(
+                    selectors
+                        .iter()
+                        .map(|s| (ipld.clone(), block, s.clone(), recursive.clone())),
+                ),
+                ("R", Ipld::Map(body)) => {
+                    let inner = body[":>"].clone();
+                    stack.push((ipld, block, inner.clone(), Some(inner)));
+                }
+                ("@", _) => {
+                    let inner = recursive.clone().expect("edge outside of recursion");
+                    stack.push((ipld, block, inner, recursive));
+                }
+                ("a", Ipld::Map(body)) => {
+                    let children: Vec<Ipld> = match &ipld {
+                        Ipld::Map(map) => map.values().cloned().collect(),
+                        Ipld::List(list) => list.clone(),
+                        _ => vec![],
+                    };
+                    for child in children {
+                        stack.push((child, block, body[">"].clone(), recursive.clone()));
+                    }
+                }
+                ("f", Ipld::Map(body)) => {
+                    let Ipld::Map(fields) = &body["f>"] else {
+                        bail!("Fields aren't a map");
+                    };
+                    for (field, next) in fields {
+                        if let Ok(child) = ipld.get(field.as_str()) {
+                            stack.push((child.clone(), block, next.clone(), recursive.clone()));
+                        }
+                    }
+                }
+                (kind, _) => bail!("Unsupported selector {kind}"),
+            }
+        }
+
+        Ok(matched)
+    }
+
+    #[async_std::test]
+    async fn subtree_selectors_match_exactly_the_subtree() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let big = &["pictures".into(), "cats".into(), "big.png".into()];
+        root.write(big, golden_content(2_000_000), time, store)
+            .await?;
+        root.write(
+            &["pictures".into(), "cats".into(), "tabby.png".into()],
+            b"Tabby".to_vec(),
+            time,
+            store,
+        )
+        .await?;
+        root.write(
+            &["pictures".into(), "dog.png".into()],
+            b"Dog".to_vec(),
+            time,
+            store,
+        )
+        .await?;
+        root.write(&["notes.txt".into()], b"Notes".to_vec(), time, store)
+            .await?;
+        let thumbnail = store.put_block(b"Tiny cat".to_vec(), CODEC_RAW).await?;
+        root.set_sidecar(big, "thumbnail", Some(thumbnail), store)
+            .await?;
+        // A previous revision that isn't part of the current subtree
+        root.store(store).await?;
+        root.write(
+            &["pictures".into(), "cats".into(), "tabby.png".into()],
+            b"New tabby".to_vec(),
+            time,
+            store,
+        )
+        .await?;
+        let root_cid = root.store(store).await?;
+
+        let cats = root
+            .get_node(&["pictures".into(), "cats".into()], store)
+            .await?
+            .unwrap()
+            .as_dir()?;
+        let tabby = cats
+            .lookup_node("tabby.png", store)
+            .await?
+            .unwrap()
+            .as_file()?;
+        let big_file = cats
+            .lookup_node("big.png", store)
+            .await?
+            .unwrap()
+            .as_file()?;
+
+        let mut expected = BTreeSet::from([
+            cats.store(store).await?,
+            tabby.store(store).await?,
+            tabby.userland.resolve_cid(store).await?,
+            big_file.store(store).await?,
+            thumbnail,
+        ]);
+        let mut content = vec![big_file.userland.resolve_cid(store).await?];
+        while let Some(cid) = content.pop() {
+            expected.insert(cid);
+            if cid.codec() == CODEC_DAG_PB {
+                let node: Ipld = decode(&store.get_block(&cid).await?, DagPbCodec)?;
+                let Ipld::List(links) = node.get("Links")? else {
+                    panic!("Links aren't a list");
+                };
+                for link in links {
+                    let Ipld::Link(cid) = link.get("Hash")? else {
+                        panic!("Hash isn't a link");
+                    };
+                    content.push(*cid);
+                }
+            }
+        }
+        assert!(expected.len() > 10);
+
+        let selector = subtree_selector(&["pictures".into(), "cats".into()]);
+        assert_eq!(select(root_cid, &selector, store).await?, expected);
+
+        let whole = select(root_cid, &subtree_selector(&[]), store).await?;
+        assert!(whole.is_superset(&expected));
+        assert!(whole.contains(&root_cid));
+        assert!(!whole.contains(&tabby.previous.iter().next().copied().unwrap()));
+
+        let missing = subtree_selector(&["videos".into()]);
+        assert!(select(root_cid, &missing, store).await?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,765 @@
+//! Importing and exporting tar archives to and from the public file system.
+
+use super::{tombstone, PublicDirectory, PublicFile, PublicLink, PublicNode};
+use crate::{error::FsError, utils};
+use anyhow::{bail, Result};
+use async_recursion::async_recursion;
+use chrono::{DateTime, TimeZone, Utc};
+use futures::{io, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use std::collections::BTreeMap;
+use wnfs_common::{
+    utils::{Arc, CondSend},
+    BlockStore, Metadata,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+const BLOCK_SIZE: usize = 512;
+
+/// The largest GNU long name or pax extended header that gets read into memory.
+const MAX_EXTENSION_SIZE: u64 = 1024 * 1024;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The result of importing a tar archive via [`import_tar`].
+#[derive(Debug)]
+pub struct TarImport {
+    /// The directory containing all imported entries.
+    pub root: Arc<PublicDirectory>,
+    /// Descriptions of all archive entries that were skipped during import.
+    pub warnings: Vec<String>,
+}
+
+/// The parts of a tar header we care about.
+#[derive(Debug)]
+struct TarHeader {
+    path: String,
+    link_path: String,
+    size: u64,
+    mtime: i64,
+    kind: u8,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Imports a tar archive as a new public directory tree.
+///
+/// Regular files and directories are imported with their modification
+/// times from the tar headers. File content is streamed into the store.
+/// Hard links are imported as copies of the file they link to.
+///
+/// Both the ustar and GNU variants are supported, including GNU long names
+/// and pax extended headers of up to 1 MiB. Entries that can't be represented,
+/// like symlinks, device files or entries with invalid names, get skipped and
+/// listed in [`TarImport::warnings`]. Entries for the archive root itself, like
+/// `./`, get skipped silently.
+///
+/// Given time is used for the root directory and for any intermediate
+/// directories that don't have an entry of their own.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use chrono::Utc;
+/// use futures::io::Cursor;
+/// use wnfs::{common::MemoryBlockStore, public::import_tar};
+///
+/// #[async_std::main]
+/// async fn main() -> Result<()> {
+///     let store = &MemoryBlockStore::new();
+///
+///     // An empty archive is just two zero blocks.
+///     let archive = Cursor::new(vec![0; 1024]);
+///     let import = import_tar(archive, Utc::now(), store).await?;
+///
+///     assert!(import.root.ls(&[], store).await?.is_empty());
+///     assert!(import.warnings.is_empty());
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn import_tar(
+    mut reader: impl AsyncRead + Unpin + CondSend,
+    time: DateTime<Utc>,
+    store: &impl BlockStore,
+) -> Result<TarImport> {
+    let mut root = PublicDirectory::new_rc(time);
+    let mut warnings = Vec::new();
+
+    let mut long_name = None;
+    let mut long_link = None;
+    let mut pax = BTreeMap::new();
+
+    let mut block = [0; BLOCK_SIZE];
+    while read_block(&mut reader, &mut block).await? {
+        if block.iter().all(|byte| *byte == 0) {
+            break;
+        }
+
+        let mut header = parse_header(&block)?;
+        match header.kind {
+            b'L' => {
+                long_name = Some(read_string(&mut reader, header.size).await?);
+                continue;
+            }
+            b'K' => {
+                long_link = Some(read_string(&mut reader, header.size).await?);
+                continue;
+            }
+            b'x' => {
+                pax = parse_pax_records(&read_string(&mut reader, header.size).await?);
+                continue;
+            }
+            b'g' => {
+                skip_data(&mut reader, header.size).await?;
+                continue;
+            }
+            _ => {}
+        }
+
+        // Extension headers override the fields of the entry that follows them
+        if let Some(name) = long_name.take() {
+            header.path = name;
+        }
+        if let Some(name) = long_link.take() {
+            header.link_path = name;
+        }
+        let mut pax = std::mem::take(&mut pax);
+        if let Some(path) = pax.remove("path") {
+            header.path = path;
+        }
+        if let Some(path) = pax.remove("linkpath") {
+            header.link_path = path;
+        }
+        if let Some(mtime) = pax
+            .get("mtime")
+            .and_then(|m| m.split('.').next()?.parse().ok())
+        {
+            header.mtime = mtime;
+        }
+        if let Some(size) = pax.get("size").and_then(|s| s.parse().ok()) {
+            header.size = size;
+        }
+
+        let mtime = Utc.timestamp_opt(header.mtime, 0).single().unwrap_or(time);
+        let Some(path) = tar_path_segments(&header.path) else {
+            warnings.push(format!("{}: path escapes the archive root", header.path));
+            skip_data(&mut reader, header.size).await?;
+            continue;
+        };
+        if let Some(name) = path.iter().find(|name| utils::validate_name(name).is_err()) {
+            warnings.push(format!("{}: invalid name {name:?}, skipped", header.path));
+            skip_data(&mut reader, header.size).await?;
+            continue;
+        }
+        if path.is_empty() {
+            if header.kind != b'5' {
+                warnings.push(format!("{:?}: empty path, skipped", header.path));
+            }
+            skip_data(&mut reader, header.size).await?;
+            continue;
+        }
+
+        match header.kind {
+            b'0' | b'\0' | b'7' => {
+                let (dir_path, filename) = utils::split_last(&path)?;
+                let content = (&mut reader).take(header.size);
+                let file = PublicFile::with_content_streaming(mtime, content, store).await?;
+                skip_bytes(&mut reader, padding(header.size)).await?;
+
+                let dir = root
+                    .get_or_create_leaf_dir_mut(dir_path, time, store)
+                    .await?;
+                let name = dir.entry_name(filename).into_owned();
+                tombstone::clear_tombstone(&mut dir.metadata, &name);
+                dir.userland.insert(name, PublicLink::with_file(file));
+            }
+            b'5' => {
+                skip_data(&mut reader, header.size).await?;
+                let dir = root.get_or_create_leaf_dir_mut(&path, mtime, store).await?;
+                dir.metadata = Metadata::new(mtime);
+            }
+            b'1' => {
+                skip_data(&mut reader, header.size).await?;
+                let copied = match tar_path_segments(&header.link_path) {
+                    Some(target) => root.cp(&target, &path, mtime, store).await.is_ok(),
+                    None => false,
+                };
+                if !copied {
+                    warnings.push(format!(
+                        "{}: hard link target {} not found",
+                        header.path, header.link_path
+                    ));
+                }
+            }
+            b'2' => {
+                skip_data(&mut reader, header.size).await?;
+                warnings.push(format!(
+                    "{}: symlinks are not supported, skipped link to {}",
+                    header.path, header.link_path
+                ));
+            }
+            kind => {
+                skip_data(&mut reader, header.size).await?;
+                warnings.push(format!(
+                    "{}: unsupported entry type {:?}, skipped",
+                    header.path, kind as char
+                ));
+            }
+        }
+    }
+
+    Ok(TarImport { root, warnings })
+}
+
+/// Exports a public directory tree as a tar archive into given writer.
+///
+/// Entries are written in sorted order, so exporting the same tree always
+/// results in the same archive. File content is streamed from the store
+/// without buffering whole files. Modification times get taken from each
+/// node's metadata.
+///
+/// Paths that don't fit into a tar header are written using GNU long names.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use chrono::Utc;
+/// use wnfs::{
+///     common::MemoryBlockStore,
+///     public::{export_tar, import_tar, PublicDirectory},
+/// };
+///
+/// #[async_std::main]
+/// async fn main() -> Result<()> {
+///     let store = &MemoryBlockStore::new();
+///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+///     dir.write(&["notes".into(), "todo.md".into()], b"- [ ] Export".to_vec(), Utc::now(), store)
+///         .await?;
+///
+///     let mut archive = Vec::new();
+///     export_tar(dir, store, &mut archive).await?;
+///
+///     let import = import_tar(archive.as_slice(), Utc::now(), store).await?;
+///     assert_eq!(
+///         import.root.read(&["notes".into(), "todo.md".into()], store).await?,
+///         b"- [ ] Export"
+///     );
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn export_tar(
+    root: &PublicDirectory,
+    store: &impl BlockStore,
+    mut writer: impl AsyncWrite + Unpin + CondSend,
+) -> Result<()> {
+    export_dir(root, "", store, &mut writer).await?;
+    writer.write_all(&[0; 2 * BLOCK_SIZE]).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_recursion)]
+#[cfg_attr(target_arch = "wasm32", async_recursion(?Send))]
+async fn export_dir(
+    dir: &PublicDirectory,
+    prefix: &str,
+    store: &impl BlockStore,
+    writer: &mut (impl AsyncWrite + Unpin + CondSend),
+) -> Result<()> {
+    for (name, link) in dir.userland.iter() {
+        match link.resolve_value(store).await? {
+            PublicNode::File(file) => {
+                let path = format!("{prefix}{name}");
+                let size = file.size(store).await?;
+                let mtime = mtime_of(file.get_metadata());
+                write_header(writer, &path, b'0', size, mtime, 0o644).await?;
+                let copied = io::copy(file.stream_content(0, store).await?, writer).await?;
+                if copied != size {
+                    bail!(FsError::InvalidContent(
+                        file.userland.resolve_cid(store).await?
+                    ));
+                }
+                writer.write_all(&vec![0; padding(size) as usize]).await?;
+            }
+            PublicNode::Dir(subdir) => {
+                let path = format!("{prefix}{name}/");
+                let mtime = mtime_of(subdir.get_metadata());
+                write_header(writer, &path, b'5', 0, mtime, 0o755).await?;
+                export_dir(subdir, &path, store, writer).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn mtime_of(metadata: &Metadata) -> u64 {
+    metadata
+        .get_modified()
+        .map(|time| time.timestamp().max(0) as u64)
+        .unwrap_or_default()
+}
+
+/// Writes a GNU tar header, preceded by a long name entry if the path doesn't fit.
+async fn write_header(
+    writer: &mut (impl AsyncWrite + Unpin),
+    path: &str,
+    kind: u8,
+    size: u64,
+    mtime: u64,
+    mode: u64,
+) -> Result<()> {
+    if path.len() > 100 {
+        let mut name = path.as_bytes().to_vec();
+        name.push(0);
+        let length = name.len() as u64;
+        write_header_block(writer, "././@LongLink", b'L', length, 0, 0o644).await?;
+        name.resize(name.len() + padding(length) as usize, 0);
+        writer.write_all(&name).await?;
+    }
+
+    write_header_block(writer, path, kind, size, mtime, mode).await
+}
+
+async fn write_header_block(
+    writer: &mut (impl AsyncWrite + Unpin),
+    path: &str,
+    kind: u8,
+    size: u64,
+    mtime: u64,
+    mode: u64,
+) -> Result<()> {
+    let mut block = [0; BLOCK_SIZE];
+    let name = &path.as_bytes()[..path.len().min(100)];
+    block[..name.len()].copy_from_slice(name);
+    write_numeric(&mut block[100..108], mode);
+    write_numeric(&mut block[108..116], 0);
+    write_numeric(&mut block[116..124], 0);
+    write_numeric(&mut block[124..136], size);
+    write_numeric(&mut block[136..148], mtime);
+    block[148..156].fill(b' ');
+    block[156] = kind;
+    block[257..265].copy_from_slice(b"ustar  \0");
+
+    let checksum: u64 = block.iter().map(|byte| *byte as u64).sum();
+    block[148..155].copy_from_slice(format!("{checksum:06o}\0").as_bytes());
+
+    writer.write_all(&block).await?;
+    Ok(())
+}
+
+/// Writes a numeric header field, in octal if it fits, otherwise GNU's base-256 encoding.
+fn write_numeric(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+    if value < 1 << (3 * digits) {
+        field.copy_from_slice(format!("{value:0digits$o}\0").as_bytes());
+    } else {
+        field.fill(0);
+        field[0] = 0x80;
+        let bytes = value.to_be_bytes();
+        let len = field.len();
+        field[len - bytes.len()..].copy_from_slice(&bytes);
+    }
+}
+
+/// Splits a path from a tar header into path segments.
+///
+/// Returns `None` for paths that would escape the archive root.
+pub(crate) fn tar_path_segments(path: &str) -> Option<Vec<String>> {
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => return None,
+            segment => segments.push(segment.to_string()),
+        }
+    }
+
+    Some(segments)
+}
+
+/// The amount of zero bytes that pad an entry of given size to a full block.
+pub(crate) fn padding(size: u64) -> u64 {
+    let block_size = BLOCK_SIZE as u64;
+    (block_size - size % block_size) % block_size
+}
+
+fn parse_header(block: &[u8; BLOCK_SIZE]) -> Result<TarHeader> {
+    let checksum = parse_numeric(&block[148..156])?;
+    let actual: u64 = block
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| match i {
+            148..=155 => b' ' as u64,
+            _ => *byte as u64,
+        })
+        .sum();
+    if checksum != actual {
+        bail!(FsError::InvalidTarArchive(
+            "header checksum mismatch".into()
+        ));
+    }
+
+    let is_ustar = &block[257..263] == b"ustar\0";
+    let mut path = parse_string(&block[0..100]);
+    if is_ustar {
+        let prefix = parse_string(&block[345..500]);
+        if !prefix.is_empty() {
+            path = format!("{prefix}/{path}");
+        }
+    }
+
+    Ok(TarHeader {
+        path,
+        link_path: parse_string(&block[157..257]),
+        size: parse_numeric(&block[124..136])?,
+        mtime: parse_numeric(&block[136..148])? as i64,
+        kind: block[156],
+    })
+}
+
+/// Parses a numeric header field, either octal or GNU's base-256 encoding.
+fn parse_numeric(field: &[u8]) -> Result<u64> {
+    if field[0] & 0x80 != 0 {
+        return Ok(field[1..]
+            .iter()
+            .fold(0, |acc, byte| (acc << 8) | *byte as u64));
+    }
+
+    let digits = String::from_utf8_lossy(field);
+    let digits = digits.trim_matches(|c: char| c == '\0' || c == ' ');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+
+    u64::from_str_radix(digits, 8)
+        .map_err(|_| FsError::InvalidTarArchive(format!("invalid numeric field {digits:?}")).into())
+}
+
+fn parse_string(field: &[u8]) -> String {
+    let end = field
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// Parses pax records of the form `"<length> <key>=<value>\n"`.
+fn parse_pax_records(records: &str) -> BTreeMap<String, String> {
+    records
+        .lines()
+        .filter_map(|record| {
+            let (_, key_value) = record.split_once(' ')?;
+            let (key, value) = key_value.split_once('=')?;
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Reads a full block. Returns `false` if the reader was already at its end.
+async fn read_block(
+    reader: &mut (impl AsyncRead + Unpin),
+    block: &mut [u8; BLOCK_SIZE],
+) -> Result<bool> {
+    let mut read = 0;
+    while read < BLOCK_SIZE {
+        match reader.read(&mut block[read..]).await? {
+            0 if read == 0 => return Ok(false),
+            0 => bail!(FsError::InvalidTarArchive("truncated header".into())),
+            n => read += n,
+        }
+    }
+
+    Ok(true)
+}
+
+async fn read_string(reader: &mut (impl AsyncRead + Unpin), size: u64) -> Result<String> {
+    if size > MAX_EXTENSION_SIZE {
+        bail!(FsError::InvalidTarArchive(format!(
+            "extension header of {size} bytes exceeds the limit of {MAX_EXTENSION_SIZE}"
+        )));
+    }
+
+    let mut buffer = Vec::new();
+    reader.take(size).read_to_end(&mut buffer).await?;
+    skip_bytes(reader, padding(size)).await?;
+    Ok(parse_string(&buffer))
+}
+
+/// Skips an entry's data of given size, including the padding up to the next block.
+async fn skip_data(reader: &mut (impl AsyncRead + Unpin), size: u64) -> Result<()> {
+    skip_bytes(reader, size + padding(size)).await
+}
+
+async fn skip_bytes(reader: &mut (impl AsyncRead + Unpin), count: u64) -> Result<()> {
+    let skipped = io::copy(reader.take(count), &mut io::sink()).await?;
+    if skipped != count {
+        bail!(FsError::InvalidTarArchive("truncated entry".into()));
+    }
+
+    Ok(())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MAX_NAME_LENGTH;
+    use futures::io::Cursor;
+    use testresult::TestResult;
+    use wnfs_common::MemoryBlockStore;
+
+    fn append(
+        builder: &mut tar::Builder<Vec<u8>>,
+        mut header: tar::Header,
+        kind: tar::EntryType,
+        path: &str,
+        data: &[u8],
+    ) {
+        header.set_entry_type(kind);
+        header.set_size(data.len() as u64);
+        header.set_mtime(1_700_000_000);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, path, data).unwrap();
+    }
+
+    #[async_std::test]
+    async fn import_tar_imports_files_and_directories() -> TestResult {
+        let store = &MemoryBlockStore::new();
+        let long_name = format!("docs/{}.txt", "a".repeat(120));
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let gnu = tar::Header::new_gnu;
+        let ustar = tar::Header::new_ustar;
+        append(
+            &mut builder,
+            ustar(),
+            tar::EntryType::Directory,
+            "docs/",
+            b"",
+        );
+        append(
+            &mut builder,
+            gnu(),
+            tar::EntryType::Regular,
+            "docs/hello.txt",
+            b"Hello",
+        );
+        append(
+            &mut builder,
+            gnu(),
+            tar::EntryType::Regular,
+            &long_name,
+            b"Long",
+        );
+        append(
+            &mut builder,
+            ustar(),
+            tar::EntryType::Regular,
+            "./top.bin",
+            &[7; 1000],
+        );
+        let mut header = gnu();
+        header.set_link_name("docs/hello.txt")?;
+        append(
+            &mut builder,
+            header.clone(),
+            tar::EntryType::Link,
+            "copy.txt",
+            b"",
+        );
+        append(&mut builder, header, tar::EntryType::Symlink, "link", b"");
+        append(&mut builder, gnu(), tar::EntryType::Fifo, "pipe", b"");
+        let archive = builder.into_inner()?;
+
+        let TarImport { root, warnings } =
+            import_tar(Cursor::new(archive), Utc::now(), store).await?;
+
+        assert_eq!(
+            root.read(&["docs".into(), "hello.txt".into()], store)
+                .await?,
+            b"Hello"
+        );
+        assert_eq!(
+            root.read(&tar_path_segments(&long_name).unwrap(), store)
+                .await?,
+            b"Long"
+        );
+        assert_eq!(root.read(&["top.bin".into()], store).await?, vec![7; 1000]);
+        assert_eq!(root.read(&["copy.txt".into()], store).await?, b"Hello");
+
+        let mtime = Utc.timestamp_opt(1_700_000_000, 0).single();
+        let docs = root.get_node(&["docs".into()], store).await?.unwrap();
+        assert_eq!(docs.as_dir()?.get_metadata().get_modified(), mtime);
+        let hello = root
+            .get_node(&["docs".into(), "hello.txt".into()], store)
+            .await?;
+        assert_eq!(
+            hello.unwrap().as_file()?.get_metadata().get_modified(),
+            mtime
+        );
+
+        assert_eq!(warnings.len(), 2);
+        assert!(root.get_node(&["link".into()], store).await?.is_none());
+        assert!(root.get_node(&["pipe".into()], store).await?.is_none());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn export_tar_writes_sorted_reproducible_archives() -> TestResult {
+        let store = &MemoryBlockStore::new();
+        let time = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let long_name = "x".repeat(150);
+        let dir = &mut PublicDirectory::new_rc(time);
+        dir.write(&["b.txt".into()], b"B".to_vec(), time, store)
+            .await?;
+        dir.write(&["a".into(), long_name.clone()], vec![1; 600], time, store)
+            .await?;
+        dir.mkdir(&["c".into()], time, store).await?;
+
+        let mut archive = Vec::new();
+        export_tar(dir, store, &mut archive).await?;
+        let mut again = Vec::new();
+        export_tar(dir, store, &mut again).await?;
+        assert_eq!(archive, again);
+
+        let mut entries = Vec::new();
+        for entry in tar::Archive::new(archive.as_slice()).entries()? {
+            let entry = entry?;
+            let header = entry.header();
+            entries.push((
+                entry.path()?.to_string_lossy().into_owned(),
+                header.size()?,
+                header.mtime()?,
+            ));
+        }
+
+        assert_eq!(
+            entries,
+            vec![
+                ("a/".into(), 0, 1_700_000_000),
+                (format!("a/{long_name}"), 600, 1_700_000_000),
+                ("b.txt".into(), 1, 1_700_000_000),
+                ("c/".into(), 0, 1_700_000_000),
+            ]
+        );
+
+        let import = import_tar(archive.as_slice(), time, store).await?;
+        assert!(import.warnings.is_empty());
+        let root = import.root;
+        assert_eq!(
+            root.read(&["a".into(), long_name], store).await?,
+            vec![1; 600]
+        );
+        assert_eq!(root.read(&["b.txt".into()], store).await?, b"B");
+        assert!(root.get_node(&["c".into()], store).await?.is_some());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn import_tar_rejects_corrupted_headers() {
+        let store = &MemoryBlockStore::new();
+        let mut builder = tar::Builder::new(Vec::new());
+        append(
+            &mut builder,
+            tar::Header::new_gnu(),
+            tar::EntryType::Regular,
+            "file.txt",
+            b"Hello",
+        );
+        let mut archive = builder.into_inner().unwrap();
+        archive[0] = b'F';
+
+        let result = import_tar(Cursor::new(archive), Utc::now(), store).await;
+
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::InvalidTarArchive(_))
+        ));
+    }
+
+    #[async_std::test]
+    async fn import_tar_skips_root_entries_and_invalid_names() -> TestResult {
+        let store = &MemoryBlockStore::new();
+        let long_name = "a".repeat(MAX_NAME_LENGTH + 1);
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let header = tar::Header::new_gnu;
+        append(&mut builder, header(), tar::EntryType::Directory, "./", &[]);
+        append(
+            &mut builder,
+            header(),
+            tar::EntryType::Regular,
+            "./",
+            b"Root",
+        );
+        append(
+            &mut builder,
+            header(),
+            tar::EntryType::Regular,
+            &format!("docs/{long_name}"),
+            b"Long",
+        );
+        append(
+            &mut builder,
+            header(),
+            tar::EntryType::Regular,
+            "./docs/kept.txt",
+            b"Kept",
+        );
+        let archive = builder.into_inner()?;
+
+        let import = import_tar(Cursor::new(archive), Utc::now(), store).await?;
+
+        let docs = import.root.ls(&["docs".into()], store).await?;
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].0, "kept.txt");
+        assert_eq!(import.warnings.len(), 2);
+        assert!(import.warnings[1].contains("invalid name"));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn import_tar_bounds_extension_headers() -> TestResult {
+        let store = &MemoryBlockStore::new();
+        let mut header = tar::Header::new_gnu();
+        header.set_path("././@LongLink")?;
+        header.set_entry_type(tar::EntryType::GNULongName);
+        header.set_size(1 << 40);
+        header.set_cksum();
+
+        let result = import_tar(Cursor::new(header.as_bytes().to_vec()), Utc::now(), store).await;
+
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(FsError::InvalidTarArchive(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn tar_path_segments_rejects_escaping_paths() {
+        assert_eq!(
+            tar_path_segments("./a//b/"),
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(tar_path_segments("a/../../b"), None);
+    }
+}
@@ -0,0 +1,357 @@
+//! Tombstones that keep removed entries and their history discoverable.
+
+use super::{PublicDirectory, PublicNode};
+use crate::{error::FsError, utils};
+use anyhow::{bail, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use libipld_core::{cid::Cid, ipld::Ipld};
+use std::collections::BTreeMap;
+use wnfs_common::{utils::Arc, BlockStore, Metadata, Storable};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+const TOMBSTONES_KEY: &str = "tombstones";
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A record of a directory entry that was removed via `PublicDirectory::rm_with_tombstone`.
+///
+/// Tombstones are ordered by their deletion time first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Tombstone {
+    /// When the node was removed, in seconds precision.
+    pub deleted: DateTime<Utc>,
+    /// The CID of the last revision of the removed node.
+    pub cid: Cid,
+}
+
+/// An entry listed by `PublicDirectory::ls_with_deleted`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DirEntry {
+    /// A node that's in the directory, with its metadata.
+    Node(Metadata),
+    /// A node that was removed from the directory.
+    Deleted(Tombstone),
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl PublicDirectory {
+    /// Removes the node at given path like `rm` does, but leaves a tombstone in its
+    /// directory that records the removed node's CID and given deletion time.
+    ///
+    /// The tombstone links to the removed node, so its last revision and all its
+    /// history stay reachable from the directory, and can be found via
+    /// `ls_with_deleted`. Adding a node under the same name again removes the
+    /// tombstone.
+    ///
+    /// Tombstones are stored in the directory's metadata, so they don't change
+    /// the directory's format, and implementations that don't know about them
+    /// just ignore them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::{DirEntry, PublicDirectory},
+    ///     common::MemoryBlockStore,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     dir.write(&["notes.txt".into()], b"Hello".to_vec(), Utc::now(), store).await?;
+    ///
+    ///     dir.rm_with_tombstone(&["notes.txt".into()], Utc::now(), store).await?;
+    ///
+    ///     assert!(dir.ls(&[], store).await?.is_empty());
+    ///     let entries = dir.ls_with_deleted(&[], true, store).await?;
+    ///     assert!(matches!(&entries[..], [(name, DirEntry::Deleted(_))] if name == "notes.txt"));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn rm_with_tombstone(
+        self: &mut Arc<Self>,
+        path_segments: &[String],
+        time: DateTime<Utc>,
+        store: &impl BlockStore,
+    ) -> Result<PublicNode> {
+        let (path, _) = utils::split_last(path_segments)?;
+        let dir = self
+            .get_leaf_dir_mut(path, store)
+            .await?
+            .into_found(path_segments)?;
+
+        // Normalized like `rm` does it, so the tombstone has the removed entry's name
+        let name = dir.entry_name(&path_segments[path.len()]).into_owned();
+        let Some(link) = dir.userland.remove(&name) else {
+            bail!(FsError::not_found(path_segments));
+        };
+
+        let node = link.resolve_owned_value(store).await?;
+        let tombstone = Tombstone {
+            cid: node.store(store).await?,
+            deleted: time,
+        };
+
+        let mut tombstones = tombstones(&dir.metadata);
+        tombstones.insert(name, tombstone);
+        put_tombstones(&mut dir.metadata, tombstones);
+
+        Ok(node)
+    }
+
+    /// Lists the directory at given path like `ls`, and if `include_deleted` is set,
+    /// also the entries removed via `rm_with_tombstone`, sorted by name.
+    ///
+    /// A node added under the name of a tombstone removes that tombstone, so every
+    /// name is listed at most once.
+    pub async fn ls_with_deleted(
+        &self,
+        path_segments: &[String],
+        include_deleted: bool,
+        store: &impl BlockStore,
+    ) -> Result<Vec<(String, DirEntry)>> {
+        let dir = self
+            .get_leaf_dir(path_segments, store)
+            .await?
+            .into_found(path_segments)?;
+
+        let mut entries = BTreeMap::new();
+        if include_deleted {
+            for (name, tombstone) in dir.get_tombstones() {
+                entries.insert(name, DirEntry::Deleted(tombstone));
+            }
+        }
+        for (name, metadata) in dir.ls(&[], store).await? {
+            entries.insert(name, DirEntry::Node(metadata));
+        }
+
+        Ok(entries.into_iter().collect())
+    }
+
+    /// Returns the tombstones of entries removed from this directory via
+    /// `rm_with_tombstone`, by name.
+    pub fn get_tombstones(&self) -> BTreeMap<String, Tombstone> {
+        tombstones(&self.metadata)
+    }
+
+    /// Combines the tombstones of this directory from before a merge, given as
+    /// `ours`, with those in `theirs`, name by name. Of two tombstones for one name,
+    /// the later removal wins. Tombstones of names the merged directory has an entry
+    /// for are dropped.
+    pub(crate) fn merge_tombstones(
+        &mut self,
+        ours: BTreeMap<String, Tombstone>,
+        theirs: &Metadata,
+    ) {
+        let mut merged = ours;
+        for (name, tombstone) in tombstones(theirs) {
+            let ours = merged.entry(name).or_insert(tombstone);
+            *ours = (*ours).max(tombstone);
+        }
+
+        merged.retain(|name, _| !self.userland.contains_key(name));
+        put_tombstones(&mut self.metadata, merged);
+    }
+}
+
+impl Tombstone {
+    fn to_ipld(self) -> Ipld {
+        Ipld::Map(BTreeMap::from([
+            ("cid".into(), Ipld::Link(self.cid)),
+            ("deleted".into(), self.deleted.timestamp().into()),
+        ]))
+    }
+
+    fn from_ipld(ipld: &Ipld) -> Option<Self> {
+        let Ipld::Map(fields) = ipld else {
+            return None;
+        };
+        let (Some(Ipld::Link(cid)), Some(Ipld::Integer(deleted))) =
+            (fields.get("cid"), fields.get("deleted"))
+        else {
+            return None;
+        };
+
+        Some(Self {
+            cid: *cid,
+            deleted: Utc
+                .timestamp_opt(i64::try_from(*deleted).ok()?, 0)
+                .single()?,
+        })
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Removes the tombstone for given name, if any, from given directory metadata.
+/// Called whenever a node gets added under a name.
+pub(crate) fn clear_tombstone(metadata: &mut Metadata, name: &str) {
+    let mut tombstones = tombstones(metadata);
+    if tombstones.remove(name).is_some() {
+        put_tombstones(metadata, tombstones);
+    }
+}
+
+/// Writes given tombstones to given directory metadata, removing the key if there
+/// aren't any.
+fn put_tombstones(metadata: &mut Metadata, tombstones: BTreeMap<String, Tombstone>) {
+    if tombstones.is_empty() {
+        metadata.delete(TOMBSTONES_KEY);
+        return;
+    }
+
+    let tombstones = tombstones
+        .into_iter()
+        .map(|(name, tombstone)| (name, tombstone.to_ipld()))
+        .collect();
+    metadata.put(TOMBSTONES_KEY, Ipld::Map(tombstones));
+}
+
+/// Reads the tombstones from given directory metadata, skipping malformed ones.
+pub(crate) fn tombstones(metadata: &Metadata) -> BTreeMap<String, Tombstone> {
+    let Some(Ipld::Map(tombstones)) = metadata.get(TOMBSTONES_KEY) else {
+        return BTreeMap::new();
+    };
+
+    tombstones
+        .iter()
+        .filter_map(|(name, ipld)| Some((name.clone(), Tombstone::from_ipld(ipld)?)))
+        .collect()
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use testresult::TestResult;
+    use wnfs_common::MemoryBlockStore;
+
+    #[async_std::test]
+    async fn deleted_files_stay_reachable_through_their_tombstones() -> TestResult {
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        let time = Utc::now();
+        let later = time + Duration::hours(1);
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let notes = path("docs/notes.txt");
+        root.write(&notes, b"First".to_vec(), time, store).await?;
+        root.store(store).await?;
+        root.write(&notes, b"Last".to_vec(), time, store).await?;
+        root.write(&path("docs/todo.txt"), b"Todo".to_vec(), time, store)
+            .await?;
+
+        let removed = root.rm_with_tombstone(&notes, later, store).await?;
+        let root_cid = root.store(store).await?;
+
+        let root = PublicDirectory::load(&root_cid, store).await?;
+        let entries = root.ls_with_deleted(&path("docs"), true, store).await?;
+        let [(deleted, DirEntry::Deleted(tombstone)), (kept, DirEntry::Node(_))] = &entries[..]
+        else {
+            panic!("unexpected entries {entries:?}");
+        };
+        assert_eq!((deleted.as_str(), kept.as_str()), ("notes.txt", "todo.txt"));
+        assert_eq!(tombstone.cid, removed.store(store).await?);
+        assert_eq!(tombstone.deleted.timestamp(), later.timestamp());
+
+        let last = PublicNode::load(&tombstone.cid, store).await?;
+        assert_eq!(last.as_file()?.get_content(store).await?, b"Last");
+        let first = PublicNode::load(last.get_previous().first().unwrap(), store).await?;
+        assert_eq!(first.as_file()?.get_content(store).await?, b"First");
+
+        let reachable = PublicNode::Dir(Arc::new(root.clone()))
+            .reachable_cids(store)
+            .await?;
+        assert!(reachable.contains(&tombstone.cid));
+        assert_eq!(
+            root.ls_with_deleted(&path("docs"), false, store)
+                .await?
+                .len(),
+            1
+        );
+
+        // Entries added again under the same name remove the tombstone
+        let root = &mut Arc::new(root);
+        root.write(&notes, b"Again".to_vec(), later, store).await?;
+        let entries = root.ls_with_deleted(&path("docs"), true, store).await?;
+        assert!(matches!(entries[0], (_, DirEntry::Node(_))));
+        root.rm(&notes, store).await?;
+        let entries = root.ls_with_deleted(&path("docs"), true, store).await?;
+        assert!(matches!(&entries[..], [(name, DirEntry::Node(_))] if name == "todo.txt"));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn reconciling_merges_tombstones_by_name() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let base = &mut PublicDirectory::new_rc(time);
+        for name in ["a.txt", "b.txt", "c.txt", "d.txt"] {
+            base.write(&[name.into()], name.into(), time, store).await?;
+        }
+        base.store(store).await?;
+
+        let ours = &mut Arc::clone(base);
+        let theirs = &mut Arc::clone(base);
+        let hour = |h| time + Duration::hours(h);
+        // Each side removed a.txt and b.txt, but only one left a tombstone
+        ours.rm_with_tombstone(&["a.txt".into()], hour(1), store)
+            .await?;
+        theirs.rm(&["a.txt".into()], store).await?;
+        ours.rm(&["b.txt".into()], store).await?;
+        theirs
+            .rm_with_tombstone(&["b.txt".into()], hour(1), store)
+            .await?;
+        ours.rm_with_tombstone(&["c.txt".into()], hour(3), store)
+            .await?;
+        ours.rm_with_tombstone(&["d.txt".into()], hour(1), store)
+            .await?;
+        theirs
+            .rm_with_tombstone(&["c.txt".into()], hour(2), store)
+            .await?;
+        // They deleted d.txt too, but then added it again
+        theirs
+            .rm_with_tombstone(&["d.txt".into()], hour(2), store)
+            .await?;
+        theirs
+            .write(&["d.txt".into()], b"Again".to_vec(), hour(3), store)
+            .await?;
+
+        ours.reconcile(theirs, store).await?;
+
+        let deleted = ours
+            .get_tombstones()
+            .into_iter()
+            .map(|(name, tombstone)| (name, tombstone.deleted.timestamp()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            deleted,
+            [
+                ("a.txt".into(), hour(1).timestamp()),
+                ("b.txt".into(), hour(1).timestamp()),
+                ("c.txt".into(), hour(3).timestamp()),
+            ]
+        );
+        assert_eq!(ours.read(&["d.txt".into()], store).await?, b"Again");
+
+        Ok(())
+    }
+}
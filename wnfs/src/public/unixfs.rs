@@ -0,0 +1,237 @@
+//! Exporting public file system trees as UnixFS, so plain IPFS nodes can resolve them.
+
+use super::{PublicDirectory, PublicNode};
+use anyhow::Result;
+use async_recursion::async_recursion;
+use bytes::Bytes;
+use libipld_core::cid::Cid;
+use wnfs_common::{
+    libipld::pb::{PbLink, PbNode},
+    BlockStore, Metadata, CODEC_DAG_PB, CODEC_RAW,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The protobuf encoding of the UnixFS `Data` message `{ Type: Directory }`.
+const UNIXFS_DIRECTORY_DATA: &[u8] = &[0x08, 0x01];
+
+/// Metadata entries that get dropped without a warning, since they're either on
+/// every node or derived from the content.
+const SILENTLY_DROPPED_KEYS: &[&str] = &["created", "modified", "contentDigest"];
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The result of exporting a public directory via [`export_unixfs`].
+#[derive(Debug)]
+pub struct UnixFsExport {
+    /// The CID of the UnixFS directory corresponding to the exported directory.
+    pub root: Cid,
+    /// Descriptions of all metadata that couldn't be represented in UnixFS.
+    pub warnings: Vec<String>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Re-encodes given directory tree as UnixFS, so standard IPFS nodes and
+/// gateways can resolve paths in it.
+///
+/// Directories get stored as new dag-pb UnixFS directory blocks. File content
+/// already is a UnixFS file DAG, so files link to their existing content
+/// without copying it.
+///
+/// UnixFS can't represent WNFS metadata. Timestamps, content digests and previous
+/// revisions get dropped silently. All other metadata entries, like sidecars or
+/// content encodings, get dropped and listed in [`UnixFsExport::warnings`].
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use chrono::Utc;
+/// use wnfs::{
+///     common::MemoryBlockStore,
+///     public::{export_unixfs, PublicDirectory},
+/// };
+///
+/// #[async_std::main]
+/// async fn main() -> Result<()> {
+///     let store = &MemoryBlockStore::new();
+///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+///     dir.write(&["notes".into(), "todo.md".into()], b"- [ ] Export".to_vec(), Utc::now(), store)
+///         .await?;
+///
+///     let export = export_unixfs(dir, store).await?;
+///
+///     assert_eq!(export.root.codec(), 0x70);
+///     assert!(export.warnings.is_empty());
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn export_unixfs(
+    root: &PublicDirectory,
+    store: &impl BlockStore,
+) -> Result<UnixFsExport> {
+    let mut warnings = Vec::new();
+    let (root, _) = export_dir(root, "", store, &mut warnings).await?;
+    Ok(UnixFsExport { root, warnings })
+}
+
+/// Stores given directory as a UnixFS directory and returns its CID and cumulative size.
+#[cfg_attr(not(target_arch = "wasm32"), async_recursion)]
+#[cfg_attr(target_arch = "wasm32", async_recursion(?Send))]
+async fn export_dir(
+    dir: &PublicDirectory,
+    path: &str,
+    store: &impl BlockStore,
+    warnings: &mut Vec<String>,
+) -> Result<(Cid, u64)> {
+    warn_about_metadata(&dir.metadata, path, warnings);
+
+    let mut links = Vec::new();
+    let mut total_size = 0;
+    for (name, link) in dir.userland.iter() {
+        let child_path = format!("{path}/{name}");
+        let (cid, size) = match link.resolve_value(store).await? {
+            PublicNode::File(file) => {
+                warn_about_metadata(file.get_metadata(), &child_path, warnings);
+                let content = file.userland.resolve_cid(store).await?;
+                (content, content_size(&content, store).await?)
+            }
+            PublicNode::Dir(subdir) => export_dir(subdir, &child_path, store, warnings).await?,
+        };
+
+        total_size += size;
+        links.push(PbLink {
+            cid,
+            name: Some(name.clone()),
+            size: Some(size),
+        });
+    }
+
+    let bytes = PbNode {
+        links,
+        data: Some(Bytes::from_static(UNIXFS_DIRECTORY_DATA)),
+    }
+    .into_bytes();
+
+    total_size += bytes.len() as u64;
+    let cid = store.put_block(bytes.to_vec(), CODEC_DAG_PB).await?;
+    Ok((cid, total_size))
+}
+
+/// The cumulative size of a UnixFS file DAG, i.e. its root block
+/// plus the sizes its links record.
+async fn content_size(cid: &Cid, store: &impl BlockStore) -> Result<u64> {
+    let bytes = store.get_block(cid).await?;
+    if cid.codec() == CODEC_RAW {
+        return Ok(bytes.len() as u64);
+    }
+
+    let size = bytes.len() as u64;
+    let node = PbNode::from_bytes(bytes)?;
+    Ok(size + node.links.iter().filter_map(|link| link.size).sum::<u64>())
+}
+
+fn warn_about_metadata(metadata: &Metadata, path: &str, warnings: &mut Vec<String>) {
+    let path = if path.is_empty() { "/" } else { path };
+    for key in metadata.0.keys() {
+        if !SILENTLY_DROPPED_KEYS.contains(&key.as_str()) {
+            warnings.push(format!("Dropped metadata entry {key:?} of {path}"));
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::public::golden::golden_content;
+    use chrono::Utc;
+    use libipld_core::ipld::Ipld;
+    use testresult::TestResult;
+    use tokio::io::AsyncReadExt;
+    use wnfs_common::{decode, libipld::pb::DagPbCodec, MemoryBlockStore};
+    use wnfs_unixfs_file::unixfs::UnixFsFile;
+
+    /// Resolves a path in a UnixFS directory tree the way an IPFS node would.
+    async fn resolve(root: Cid, path: &[&str], store: &impl BlockStore) -> Result<Option<Cid>> {
+        let mut cid = root;
+        for segment in path {
+            let node: Ipld = decode(&store.get_block(&cid).await?, DagPbCodec)?;
+            assert_eq!(
+                node.get("Data")?,
+                &Ipld::Bytes(UNIXFS_DIRECTORY_DATA.to_vec())
+            );
+            let Ipld::List(links) = node.get("Links")? else {
+                panic!("Links aren't a list");
+            };
+            let link = links
+                .iter()
+                .find(|link| link.get("Name").ok() == Some(&Ipld::String(segment.to_string())));
+            match link.map(|link| link.get("Hash")) {
+                Some(Ok(Ipld::Link(next))) => cid = *next,
+                _ => return Ok(None),
+            }
+        }
+
+        Ok(Some(cid))
+    }
+
+    #[async_std::test]
+    async fn exported_trees_resolve_as_unixfs() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let root = &mut PublicDirectory::new_rc(time);
+        let big = &["pictures".into(), "big.png".into()];
+        root.write(big, golden_content(1_000_000), time, store)
+            .await?;
+        root.write(&["notes.txt".into()], b"Notes".to_vec(), time, store)
+            .await?;
+        root.mkdir(&["empty".into()], time, store).await?;
+        root.set_content_encoding(big, Some("identity".into()), store)
+            .await?;
+
+        let export = export_unixfs(root, store).await?;
+
+        let cid = resolve(export.root, &["pictures", "big.png"], store)
+            .await?
+            .unwrap();
+        let mut bytes = Vec::new();
+        UnixFsFile::load(&cid, store)
+            .await?
+            .into_content_reader(store, None)?
+            .read_to_end(&mut bytes)
+            .await?;
+        assert_eq!(bytes, golden_content(1_000_000));
+
+        let notes = resolve(export.root, &["notes.txt"], store).await?.unwrap();
+        assert_eq!(store.get_block(&notes).await?, b"Notes".to_vec());
+        assert!(resolve(export.root, &["empty"], store).await?.is_some());
+        assert!(resolve(export.root, &["missing"], store).await?.is_none());
+
+        assert_eq!(
+            export.warnings,
+            vec![r#"Dropped metadata entry "contentEncoding" of /pictures/big.png"#]
+        );
+
+        // The recorded sizes cover all blocks below each link
+        let node = PbNode::from_bytes(store.get_block(&export.root).await?)?;
+        let pictures = node
+            .links
+            .iter()
+            .find(|l| l.name.as_deref() == Some("pictures"));
+        assert!(pictures.unwrap().size.unwrap() > 1_000_000);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,361 @@
+//! Verifying that all blocks of a public file system tree are present and intact.
+
+use super::{tombstone::tombstones, PublicDirectory, PublicNodeSerializable};
+use anyhow::Result;
+use bytes::Bytes;
+use libipld_core::cid::Cid;
+use std::collections::{BTreeSet, VecDeque};
+use wnfs_common::{
+    libipld::multihash::{Code, MultihashDigest},
+    BlockStore, BlockStoreError, LoadIpld,
+};
+use wnfs_unixfs_file::unixfs::UnixFsFile;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The outcome of verifying a public file system tree via `verify_import`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// The number of distinct blocks that were checked.
+    pub visited: usize,
+    /// Blocks that are referenced in the tree, but not in the block store.
+    pub missing: Vec<Cid>,
+    /// Blocks that don't match their CID's hash or don't decode as what they're referenced as.
+    pub corrupt: Vec<Cid>,
+}
+
+/// The outcome of checking a public file system tree via `PublicDirectory::fsck`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FsckReport {
+    /// The number of distinct blocks that were checked.
+    pub visited: usize,
+    /// Blocks that are referenced in the tree, but not in the block store.
+    pub missing: Vec<Cid>,
+    /// Blocks whose bytes don't hash to their CID.
+    pub hash_mismatches: Vec<Cid>,
+    /// Blocks that don't decode as what they're referenced as, a public node
+    /// or a file content block.
+    pub undeserializable: Vec<Cid>,
+}
+
+/// What a block is referenced as, and thus what it needs to decode as.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum BlockKind {
+    Node,
+    Content,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl VerifyReport {
+    /// Whether all blocks of the tree are present and intact.
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty()
+    }
+}
+
+impl FsckReport {
+    /// Whether all blocks of the tree are present and intact.
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty()
+            && self.hash_mismatches.is_empty()
+            && self.undeserializable.is_empty()
+    }
+}
+
+impl PublicDirectory {
+    /// Checks the integrity of the public file system tree at given root CID,
+    /// like one pulled from an untrusted peer, and reports all problems found.
+    ///
+    /// This walks all blocks reachable from the root: directories, files, file
+    /// content, file sidecars, tombstones of removed entries and previous revisions.
+    /// Each block is checked to be present in the store, to hash to its CID and to
+    /// decode as a public node or a file content block respectively, so content DAGs
+    /// are checked to be complete.
+    /// Blocks below missing or broken blocks can't be discovered, so they're not
+    /// reported. Only errors of the store itself make this fail.
+    ///
+    /// Hashes are only checked for hash functions that `libipld` supports.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use anyhow::Result;
+    /// use chrono::Utc;
+    /// use wnfs::{
+    ///     public::PublicDirectory,
+    ///     common::{BlockStore, MemoryBlockStore, Storable, CODEC_DAG_CBOR},
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<()> {
+    ///     let store = &MemoryBlockStore::new();
+    ///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+    ///     dir.write(&["notes.txt".into()], b"Hello".to_vec(), Utc::now(), store).await?;
+    ///     let root = dir.store(store).await?;
+    ///
+    ///     assert!(PublicDirectory::fsck(&root, store).await?.is_ok());
+    ///
+    ///     let not_a_node = store.put_block(b"Hello".to_vec(), CODEC_DAG_CBOR).await?;
+    ///     let report = PublicDirectory::fsck(&not_a_node, store).await?;
+    ///
+    ///     assert_eq!(report.undeserializable, vec![not_a_node]);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn fsck(root: &Cid, store: &impl BlockStore) -> Result<FsckReport> {
+        let mut report = FsckReport::default();
+        let mut visited = BTreeSet::new();
+        let mut queue = VecDeque::from([(*root, BlockKind::Node)]);
+
+        while let Some((cid, kind)) = queue.pop_front() {
+            if !visited.insert(cid) {
+                continue;
+            }
+
+            report.visited += 1;
+
+            let bytes = match store.get_block(&cid).await {
+                Ok(bytes) => bytes,
+                Err(BlockStoreError::CIDNotFound(_)) => {
+                    report.missing.push(cid);
+                    continue;
+                }
+                Err(BlockStoreError::CorruptBlock(_)) => {
+                    report.hash_mismatches.push(cid);
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            if let Ok(code) = Code::try_from(cid.hash().code()) {
+                if &code.digest(&bytes) != cid.hash() {
+                    report.hash_mismatches.push(cid);
+                    continue;
+                }
+            }
+
+            match block_links(&cid, bytes, kind, true) {
+                Ok(links) => queue.extend(links),
+                Err(_) => report.undeserializable.push(cid),
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Checks that the public file system tree at given root CID is complete,
+/// without having to load the root directory first.
+///
+/// This checks the same blocks as `PublicDirectory::fsck` does, reporting
+/// blocks with mismatching hashes and undeserializable ones as corrupt alike.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use chrono::Utc;
+/// use wnfs::{
+///     public::{verify_import, PublicDirectory},
+///     common::{MemoryBlockStore, Storable},
+/// };
+///
+/// #[async_std::main]
+/// async fn main() -> Result<()> {
+///     let store = &MemoryBlockStore::new();
+///     let dir = &mut PublicDirectory::new_rc(Utc::now());
+///     dir.write(&["notes.txt".into()], b"Hello".to_vec(), Utc::now(), store)
+///         .await?;
+///     let root = dir.store(store).await?;
+///
+///     let report = verify_import(&root, store).await?;
+///
+///     assert!(report.is_ok());
+///     assert_eq!(report.visited, 3);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn verify_import(expected_root: &Cid, store: &impl BlockStore) -> Result<VerifyReport> {
+    let report = PublicDirectory::fsck(expected_root, store).await?;
+    Ok(VerifyReport {
+        visited: report.visited,
+        missing: report.missing,
+        corrupt: [report.hash_mismatches, report.undeserializable].concat(),
+    })
+}
+
+/// Decodes given block as what it's referenced as and returns the blocks it links to.
+/// Links to previous revisions are only included if `include_history` is set.
+pub(crate) fn block_links(
+    cid: &Cid,
+    bytes: Bytes,
+    kind: BlockKind,
+    include_history: bool,
+) -> Result<Vec<(Cid, BlockKind)>> {
+    let mut links = Vec::new();
+    match kind {
+        BlockKind::Node => match PublicNodeSerializable::decode_ipld(cid, bytes)? {
+            PublicNodeSerializable::File(file) => {
+                links.push((file.userland, BlockKind::Content));
+                links.extend(
+                    file.metadata
+                        .get_sidecars()
+                        .into_values()
+                        .map(|cid| (cid, BlockKind::Content)),
+                );
+                if include_history {
+                    links.extend(file.previous.into_iter().map(|cid| (cid, BlockKind::Node)));
+                }
+            }
+            PublicNodeSerializable::Dir(dir) => {
+                links.extend(dir.userland.into_values().map(|cid| (cid, BlockKind::Node)));
+                links.extend(
+                    tombstones(&dir.metadata)
+                        .into_values()
+                        .map(|tombstone| (tombstone.cid, BlockKind::Node)),
+                );
+                if include_history {
+                    links.extend(dir.previous.into_iter().map(|cid| (cid, BlockKind::Node)));
+                }
+            }
+        },
+        BlockKind::Content => {
+            let content = UnixFsFile::decode(cid, bytes)?;
+            links.extend(
+                content
+                    .links_owned()?
+                    .into_iter()
+                    .map(|link| (link.cid, BlockKind::Content)),
+            );
+        }
+    }
+
+    Ok(links)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::public::{golden::golden_content, PublicDirectory};
+    use chrono::Utc;
+    use testresult::TestResult;
+    use wnfs_common::{utils::InstrumentedBlockStore, MemoryBlockStore, Storable, CODEC_DAG_CBOR};
+
+    #[async_std::test]
+    async fn verify_import_reports_missing_and_corrupt_blocks() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let dir = &mut PublicDirectory::new_rc(time);
+        dir.write(&["big.bin".into()], golden_content(1_000_000), time, store)
+            .await?;
+        dir.write(&["small.txt".into()], b"Hello".to_vec(), time, store)
+            .await?;
+        let root = dir.store(store).await?;
+
+        let report = verify_import(&root, store).await?;
+        assert!(report.is_ok());
+
+        // Drop one content chunk of the big file
+        let big = dir
+            .get_node(&["big.bin".into()], store)
+            .await?
+            .unwrap()
+            .as_file()?;
+        let content = big.userland.resolve_value(store).await?;
+        let dropped = content.links_owned()?[1].cid;
+        let tampered = &InstrumentedBlockStore::new(store);
+        tampered.drop_block(dropped);
+
+        let report = verify_import(&root, tampered).await?;
+        assert_eq!(report.missing, vec![dropped]);
+        assert!(report.corrupt.is_empty());
+
+        // Replace the small file's node with bytes not matching its hash
+        let small = dir.get_node(&["small.txt".into()], store).await?.unwrap();
+        let small_cid = small.store(store).await?;
+        tampered.replace_block(small_cid, b"garbage".to_vec());
+
+        let report = verify_import(&root, tampered).await?;
+        assert_eq!(report.corrupt, vec![small_cid]);
+
+        // A validly hashed block that isn't a public node
+        let not_a_node = store
+            .put_block(b"not a node".to_vec(), CODEC_DAG_CBOR)
+            .await?;
+        let report = verify_import(&not_a_node, store).await?;
+        assert_eq!(report.corrupt, vec![not_a_node]);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn fsck_reports_all_problems_by_kind() -> TestResult {
+        let time = Utc::now();
+        let store = &MemoryBlockStore::new();
+        let dir = &mut PublicDirectory::new_rc(time);
+        dir.write(&["big.bin".into()], golden_content(1_000_000), time, store)
+            .await?;
+        dir.write(&["docs".into(), "a.txt".into()], b"A".to_vec(), time, store)
+            .await?;
+        dir.write(&["docs".into(), "b.txt".into()], b"B".to_vec(), time, store)
+            .await?;
+        let root = dir.store(store).await?;
+        let healthy = PublicDirectory::fsck(&root, store).await?;
+        assert!(healthy.is_ok());
+
+        let big = dir.get_node(&["big.bin".into()], store).await?.unwrap();
+        let chunk = big
+            .as_file()?
+            .userland
+            .resolve_value(store)
+            .await?
+            .links_owned()?[0]
+            .cid;
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        let a = dir.get_node(&path("docs/a.txt"), store).await?.unwrap();
+        let a = a.store(store).await?;
+        let b = dir.get_node(&path("docs/b.txt"), store).await?.unwrap();
+        let b = b.store(store).await?;
+
+        let tampered = &InstrumentedBlockStore::new(store);
+        tampered.drop_block(chunk);
+        tampered.replace_block(a, b"garbage".to_vec());
+        tampered.drop_block(b);
+
+        // All problems get reported, not just the first one
+        let report = PublicDirectory::fsck(&root, tampered).await?;
+        assert!(!report.is_ok());
+        assert_eq!(
+            BTreeSet::from_iter(report.missing),
+            BTreeSet::from([chunk, b])
+        );
+        assert_eq!(report.hash_mismatches, vec![a]);
+        assert!(report.undeserializable.is_empty());
+        // The content blocks below the two broken file nodes can't be discovered
+        assert_eq!(report.visited, healthy.visited - 2);
+
+        // Stores rejecting corrupt blocks themselves don't make this fail
+        let verifying = &InstrumentedBlockStore::new(store).with_verification();
+        verifying.replace_block(a, b"garbage".to_vec());
+        let report = PublicDirectory::fsck(&root, verifying).await?;
+        assert_eq!(report.hash_mismatches, vec![a]);
+        assert!(report.missing.is_empty());
+
+        Ok(())
+    }
+}
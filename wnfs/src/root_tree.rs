@@ -163,7 +163,7 @@ impl<B: BlockStore> RootTree<B> {
         let node = dir
             .get_node(path, true, &forest, &self.store)
             .await?
-            .ok_or(FsError::NotFound)?;
+            .ok_or_else(|| FsError::not_found(path))?;
         let access_key = node.store(&mut forest, &self.store, rng).await?;
 
         Ok(access_key)
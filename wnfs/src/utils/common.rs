@@ -1,4 +1,4 @@
-use crate::error::FsError;
+use crate::{error::FsError, NameViolation, MAX_NAME_LENGTH};
 use anyhow::Result;
 use std::fmt::Debug;
 use wnfs_common::utils::error;
@@ -22,6 +22,95 @@ pub(crate) fn split_last(path_segments: &[String]) -> Result<(&[String], &String
     }
 }
 
+/// Checks that given name can be used for a new directory entry.
+///
+/// Names must be non-empty, must not be `.` or `..`, must not contain `/` or NUL
+/// and must be at most `MAX_NAME_LENGTH` bytes long. As strings, they're valid UTF-8 already.
+pub(crate) fn validate_name(name: &str) -> Result<()> {
+    if name_violation(name).is_some() {
+        return error(FsError::InvalidName(name.to_string()));
+    }
+
+    Ok(())
+}
+
+/// Returns the first reason why given name can't be used for a directory entry,
+/// if there's any. See `validate_name`.
+pub(crate) fn name_violation(name: &str) -> Option<NameViolation> {
+    if name.is_empty() {
+        return Some(NameViolation::Empty);
+    }
+
+    if name == "." || name == ".." {
+        return Some(NameViolation::Reserved);
+    }
+
+    if let Some(c) = name.chars().find(|c| ['/', '\0'].contains(c)) {
+        return Some(NameViolation::InvalidCharacter(c));
+    }
+
+    if name.len() > MAX_NAME_LENGTH {
+        return Some(NameViolation::TooLong(name.len()));
+    }
+
+    None
+}
+
+/// Whether given name matches given shell-style pattern, where `*` stands for
+/// any sequence of characters and `?` for any single character.
+pub(crate) fn matches_pattern(name: &str, pattern: &str) -> bool {
+    let name = name.chars().collect::<Vec<_>>();
+    let pattern = pattern.chars().collect::<Vec<_>>();
+
+    let (mut n, mut p) = (0, 0);
+    // Position of the last `*` in the pattern and of the name when we hit it
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                n += 1;
+                p += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` swallow one more character
+                Some((star, start)) => {
+                    backtrack = Some((star, start + 1));
+                    p = star + 1;
+                    n = start + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Resolves `.` and `..` segments in given path segments.
+/// Fails with `FsError::InvalidPath` when `..` would go above the root.
+pub(crate) fn normalize_path<'a>(
+    path_segments: impl IntoIterator<Item = &'a String>,
+) -> Result<Vec<String>> {
+    let mut normalized = Vec::new();
+    for segment in path_segments {
+        match segment.as_str() {
+            "." => {}
+            ".." => {
+                if normalized.pop().is_none() {
+                    return error(FsError::InvalidPath);
+                }
+            }
+            _ => normalized.push(segment.clone()),
+        }
+    }
+
+    Ok(normalized)
+}
+
 //--------------------------------------------------------------------------------------------------
 // Tests
 //--------------------------------------------------------------------------------------------------
@@ -37,4 +126,56 @@ mod tests {
         assert_eq!(rest, &["a", "b"]);
         assert_eq!(last, &"c");
     }
+
+    #[test]
+    fn validate_name_rejects_unusable_names() {
+        assert!(validate_name("cats.png").is_ok());
+        assert!(validate_name(&"a".repeat(MAX_NAME_LENGTH)).is_ok());
+
+        assert!(validate_name("...").is_ok());
+        assert!(validate_name(".hidden").is_ok());
+
+        for name in [
+            "",
+            ".",
+            "..",
+            "a/b",
+            "a\0b",
+            &"a".repeat(MAX_NAME_LENGTH + 1),
+        ] {
+            let err = validate_name(name).unwrap_err();
+            assert!(matches!(err.downcast_ref(), Some(FsError::InvalidName(_))));
+        }
+
+        assert_eq!(name_violation(""), Some(NameViolation::Empty));
+        assert_eq!(name_violation(".."), Some(NameViolation::Reserved));
+        assert_eq!(
+            name_violation("a\0b"),
+            Some(NameViolation::InvalidCharacter('\0'))
+        );
+        assert_eq!(
+            name_violation(&"ä".repeat(128)),
+            Some(NameViolation::TooLong(256))
+        );
+    }
+
+    #[test]
+    fn matches_pattern_supports_wildcards() {
+        assert!(matches_pattern("config.toml", "config.toml"));
+        assert!(matches_pattern("config.toml", "*.toml"));
+        assert!(matches_pattern("config.toml", "c*g.*"));
+        assert!(matches_pattern("config.toml", "config.to?l"));
+        assert!(matches_pattern("", "*"));
+        assert!(!matches_pattern("config.toml", "*.json"));
+        assert!(!matches_pattern("config.toml", "config"));
+        assert!(!matches_pattern("config", "config?"));
+    }
+
+    #[test]
+    fn normalize_path_resolves_relative_segments() {
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        assert_eq!(normalize_path(&path("a/./b/../c")).unwrap(), path("a/c"));
+        assert_eq!(normalize_path(&path("a/..")).unwrap(), Vec::<String>::new());
+        assert!(normalize_path(&path("a/../..")).is_err());
+    }
 }